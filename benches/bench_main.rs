@@ -2264,6 +2264,7 @@ criterion_group!(
     bench_decompress_offset33_micro,
     bench_decompress_offset15_micro,
     bench_decompress_large_synthetic,
+    bench_fused_checksum,
 );
 criterion_main!(benches);
 
@@ -2781,3 +2782,71 @@ fn bench_decompress_large_synthetic(c: &mut Criterion) {
 
     group.finish();
 }
+
+fn bench_fused_checksum(c: &mut Criterion) {
+    // Large enough that the output does not stay in cache between two passes.
+    let size = 64 * 1024 * 1024;
+    let mut state = 0x9e3779b9u32;
+    let original_data: Vec<u8> = (0..size)
+        .map(|i| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            if i % 4096 < 3072 {
+                b"fused checksum benchmark "[i % 25]
+            } else {
+                state as u8
+            }
+        })
+        .collect();
+
+    let mut compressor = Compressor::new(6).unwrap();
+    let deflate = compressor.compress_deflate(&original_data).unwrap();
+    let gzip = compressor.compress_gzip(&original_data).unwrap();
+    let zlib = compressor.compress_zlib(&original_data).unwrap();
+    let mut out_buf = vec![0u8; size];
+
+    let mut group = c.benchmark_group("Fused Checksum");
+    group.throughput(Throughput::Bytes(size as u64));
+    group.sample_size(10);
+
+    group.bench_function("gzip two-pass (deflate + crc32)", |b| {
+        let mut decompressor = Decompressor::new();
+        b.iter(|| {
+            let n = decompressor
+                .decompress_deflate_into(&deflate, &mut out_buf)
+                .unwrap();
+            crc32(0, &out_buf[..n])
+        });
+    });
+
+    group.bench_function("gzip fused", |b| {
+        let mut decompressor = Decompressor::new();
+        b.iter(|| {
+            decompressor
+                .decompress_gzip_into(&gzip, &mut out_buf)
+                .unwrap()
+        });
+    });
+
+    group.bench_function("zlib two-pass (deflate + adler32)", |b| {
+        let mut decompressor = Decompressor::new();
+        b.iter(|| {
+            let n = decompressor
+                .decompress_deflate_into(&deflate, &mut out_buf)
+                .unwrap();
+            adler32(1, &out_buf[..n])
+        });
+    });
+
+    group.bench_function("zlib fused", |b| {
+        let mut decompressor = Decompressor::new();
+        b.iter(|| {
+            decompressor
+                .decompress_zlib_into(&zlib, &mut out_buf)
+                .unwrap()
+        });
+    });
+
+    group.finish();
+}
@@ -0,0 +1,49 @@
+//! With the `capi` feature, generates `include/libdeflate.h` from `src/capi.rs`.
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "capi")]
+    capi_header::generate();
+}
+
+#[cfg(feature = "capi")]
+mod capi_header {
+    use std::path::PathBuf;
+    use std::{env, fs};
+
+    const HEADER: &str = "include/libdeflate.h";
+
+    pub fn generate() {
+        for input in ["cbindgen.toml", "src/capi.rs"] {
+            println!("cargo:rerun-if-changed={input}");
+        }
+
+        // cbindgen only declares `no_mangle` functions and does not evaluate `cfg_attr`,
+        // so it reads a copy with the exports unconditional.
+        let capi = fs::read_to_string("src/capi.rs")
+            .expect("cannot read src/capi.rs")
+            .replace(
+                "#[cfg_attr(feature = \"capi\", unsafe(no_mangle))]",
+                "#[unsafe(no_mangle)]",
+            );
+        let capi_path = PathBuf::from(env::var_os("OUT_DIR").unwrap()).join("capi.rs");
+        fs::write(&capi_path, capi).expect("cannot write the cbindgen input");
+
+        let config = cbindgen::Config::from_file("cbindgen.toml").expect("bad cbindgen.toml");
+        let bindings = cbindgen::Builder::new()
+            .with_config(config)
+            .with_src(capi_path)
+            .generate()
+            .expect("cannot generate the C header");
+        let mut header = Vec::new();
+        bindings.write(&mut header);
+        let header = String::from_utf8(header)
+            .unwrap()
+            .replace("LIBDEFLATE_RESULT_", "LIBDEFLATE_");
+
+        // Leave the file alone when nothing changed, so that C builds are not redone.
+        if fs::read_to_string(HEADER).ok().as_deref() != Some(header.as_str()) {
+            fs::write(HEADER, header).expect("cannot write include/libdeflate.h");
+        }
+    }
+}
@@ -0,0 +1,329 @@
+/*
+ * libdeflate.h - C API of libdeflate-rs
+ *
+ * Source compatible with the upstream libdeflate 1.25 header, so the library built with
+ * `cargo build --release --features capi` can replace libdeflate for existing consumers.
+ */
+
+#ifndef LIBDEFLATE_H
+#define LIBDEFLATE_H
+
+/* Generated from src/capi.rs by build.rs with cbindgen; do not edit. */
+
+#include <stddef.h>
+#include <stdint.h>
+
+#define LIBDEFLATE_VERSION_MAJOR 1
+#define LIBDEFLATE_VERSION_MINOR 25
+#define LIBDEFLATE_VERSION_STRING "1.25"
+
+#ifndef LIBDEFLATEAPI
+#  if defined(LIBDEFLATE_DLL) && (defined(_WIN32) || defined(__CYGWIN__))
+#    define LIBDEFLATEAPI __declspec(dllimport)
+#  else
+#    define LIBDEFLATEAPI
+#  endif
+#endif
+
+/**
+ * `enum libdeflate_result`: the outcome of a decompression.
+ */
+enum libdeflate_result {
+  /**
+   * The data decompressed successfully.
+   */
+  LIBDEFLATE_SUCCESS = 0,
+  /**
+   * The data is invalid, corrupt or truncated.
+   */
+  LIBDEFLATE_BAD_DATA = 1,
+  /**
+   * `actual_out_nbytes_ret` was null, and the data decompressed to fewer than
+   * `out_nbytes_avail` bytes.
+   */
+  LIBDEFLATE_SHORT_OUTPUT = 2,
+  /**
+   * The data decompresses to more than `out_nbytes_avail` bytes.
+   */
+  LIBDEFLATE_INSUFFICIENT_SPACE = 3,
+};
+
+/**
+ * `struct libdeflate_compressor`, opaque to C.
+ */
+struct libdeflate_compressor;
+
+/**
+ * `struct libdeflate_decompressor`, opaque to C.
+ */
+struct libdeflate_decompressor;
+
+/**
+ * `struct libdeflate_options`: optional settings for a compressor or decompressor.
+ */
+struct libdeflate_options {
+  /**
+   * Must be `sizeof(struct libdeflate_options)`.
+   */
+  size_t sizeof_options;
+  /**
+   * An allocator for this handle, used instead of the global one. Set both functions
+   * or neither.
+   */
+  void *(*malloc_func)(size_t);
+  void (*free_func)(void*);
+};
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Installs the allocator used for handles created without their own.
+ *
+ * # Safety
+ *
+ * `malloc_func` and `free_func` must behave like `malloc()` and `free()`. Existing handles
+ * keep the `free_func` they were allocated with.
+ */
+LIBDEFLATEAPI
+void libdeflate_set_memory_allocator(void *(*malloc_func)(size_t),
+                                     void (*free_func)(void*));
+
+/**
+ * Returns a compressor for `compression_level` (0 to [`MAX_COMPRESSION_LEVEL`]), or null
+ * if the level is out of range or memory runs out.
+ *
+ * # Safety
+ *
+ * The result must be released with [`libdeflate_free_compressor`].
+ */
+LIBDEFLATEAPI struct libdeflate_compressor *libdeflate_alloc_compressor(int compression_level);
+
+/**
+ * # Safety
+ *
+ * `options` must be null or point to a `LibdeflateOptions`. The result must be released
+ * with [`libdeflate_free_compressor`].
+ */
+LIBDEFLATEAPI
+struct libdeflate_compressor *libdeflate_alloc_compressor_ex(int compression_level,
+                                                             const struct libdeflate_options *options);
+
+/**
+ * Compresses to a raw deflate stream. Returns the compressed size, or 0 if it does not fit
+ * or the compressor's allocator runs out of memory.
+ *
+ * # Safety
+ *
+ * `compressor` must come from [`libdeflate_alloc_compressor`]; `input` and `output` must be
+ * valid for `in_nbytes` and `out_nbytes_avail` bytes.
+ */
+LIBDEFLATEAPI
+size_t libdeflate_deflate_compress(struct libdeflate_compressor *compressor,
+                                   const void *input,
+                                   size_t in_nbytes,
+                                   void *output,
+                                   size_t out_nbytes_avail);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_compress`].
+ */
+LIBDEFLATEAPI
+size_t libdeflate_zlib_compress(struct libdeflate_compressor *compressor,
+                                const void *input,
+                                size_t in_nbytes,
+                                void *output,
+                                size_t out_nbytes_avail);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_compress`].
+ */
+LIBDEFLATEAPI
+size_t libdeflate_gzip_compress(struct libdeflate_compressor *compressor,
+                                const void *input,
+                                size_t in_nbytes,
+                                void *output,
+                                size_t out_nbytes_avail);
+
+/**
+ * The largest raw deflate output for `in_nbytes` bytes of input. A null `compressor` gives
+ * a bound that holds for every compressor.
+ *
+ * # Safety
+ *
+ * `compressor` must be null or come from [`libdeflate_alloc_compressor`].
+ */
+LIBDEFLATEAPI
+size_t libdeflate_deflate_compress_bound(struct libdeflate_compressor *compressor,
+                                         size_t in_nbytes);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_compress_bound`].
+ */
+LIBDEFLATEAPI
+size_t libdeflate_zlib_compress_bound(struct libdeflate_compressor *compressor,
+                                      size_t in_nbytes);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_compress_bound`].
+ */
+LIBDEFLATEAPI
+size_t libdeflate_gzip_compress_bound(struct libdeflate_compressor *compressor,
+                                      size_t in_nbytes);
+
+/**
+ * # Safety
+ *
+ * `compressor` must be null or come from [`libdeflate_alloc_compressor`], and must not be
+ * used afterwards.
+ */
+LIBDEFLATEAPI void libdeflate_free_compressor(struct libdeflate_compressor *compressor);
+
+/**
+ * Returns a decompressor, or null.
+ *
+ * # Safety
+ *
+ * The result must be released with [`libdeflate_free_decompressor`].
+ */
+LIBDEFLATEAPI struct libdeflate_decompressor *libdeflate_alloc_decompressor(void);
+
+/**
+ * # Safety
+ *
+ * `options` must be null or point to a `LibdeflateOptions`. The result must be released
+ * with [`libdeflate_free_decompressor`].
+ */
+LIBDEFLATEAPI
+struct libdeflate_decompressor *libdeflate_alloc_decompressor_ex(const struct libdeflate_options *options);
+
+/**
+ * Decompresses a raw deflate stream. With a null `actual_out_nbytes_ret`, the stream must
+ * fill `out_nbytes_avail` bytes exactly.
+ *
+ * # Safety
+ *
+ * `decompressor` must come from [`libdeflate_alloc_decompressor`]; `input` and `output`
+ * must be valid for `in_nbytes` and `out_nbytes_avail` bytes; the result pointers must be
+ * null or writable.
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_deflate_decompress(struct libdeflate_decompressor *decompressor,
+                                                     const void *input,
+                                                     size_t in_nbytes,
+                                                     void *output,
+                                                     size_t out_nbytes_avail,
+                                                     size_t *actual_out_nbytes_ret);
+
+/**
+ * Like [`libdeflate_deflate_decompress`], also reporting the compressed size.
+ *
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_decompress`].
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_deflate_decompress_ex(struct libdeflate_decompressor *decompressor,
+                                                        const void *input,
+                                                        size_t in_nbytes,
+                                                        void *output,
+                                                        size_t out_nbytes_avail,
+                                                        size_t *actual_in_nbytes_ret,
+                                                        size_t *actual_out_nbytes_ret);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_decompress`].
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_zlib_decompress(struct libdeflate_decompressor *decompressor,
+                                                  const void *input,
+                                                  size_t in_nbytes,
+                                                  void *output,
+                                                  size_t out_nbytes_avail,
+                                                  size_t *actual_out_nbytes_ret);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_decompress`].
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_zlib_decompress_ex(struct libdeflate_decompressor *decompressor,
+                                                     const void *input,
+                                                     size_t in_nbytes,
+                                                     void *output,
+                                                     size_t out_nbytes_avail,
+                                                     size_t *actual_in_nbytes_ret,
+                                                     size_t *actual_out_nbytes_ret);
+
+/**
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_decompress`].
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_gzip_decompress(struct libdeflate_decompressor *decompressor,
+                                                  const void *input,
+                                                  size_t in_nbytes,
+                                                  void *output,
+                                                  size_t out_nbytes_avail,
+                                                  size_t *actual_out_nbytes_ret);
+
+/**
+ * Decompresses the first gzip member of `input`.
+ *
+ * # Safety
+ *
+ * As for [`libdeflate_deflate_decompress`].
+ */
+LIBDEFLATEAPI
+enum libdeflate_result libdeflate_gzip_decompress_ex(struct libdeflate_decompressor *decompressor,
+                                                     const void *input,
+                                                     size_t in_nbytes,
+                                                     void *output,
+                                                     size_t out_nbytes_avail,
+                                                     size_t *actual_in_nbytes_ret,
+                                                     size_t *actual_out_nbytes_ret);
+
+/**
+ * # Safety
+ *
+ * `decompressor` must be null or come from [`libdeflate_alloc_decompressor`], and must not
+ * be used afterwards.
+ */
+LIBDEFLATEAPI void libdeflate_free_decompressor(struct libdeflate_decompressor *decompressor);
+
+/**
+ * Updates the Adler-32 checksum `adler` with `len` bytes; start from 1.
+ *
+ * # Safety
+ *
+ * `buffer` must be valid for `len` bytes.
+ */
+LIBDEFLATEAPI uint32_t libdeflate_adler32(uint32_t adler, const void *buffer, size_t len);
+
+/**
+ * Updates the CRC-32 `crc` with `len` bytes; start from 0.
+ *
+ * # Safety
+ *
+ * `buffer` must be valid for `len` bytes.
+ */
+LIBDEFLATEAPI uint32_t libdeflate_crc32(uint32_t crc, const void *buffer, size_t len);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* LIBDEFLATE_H */
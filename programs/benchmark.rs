@@ -0,0 +1,533 @@
+//! `libdeflate-benchmark`: measures compression ratio and throughput, and checksum speed.
+//!
+//! `libdeflate-benchmark [options] FILE...` benchmarks compression and decompression.
+//! `libdeflate-benchmark checksum [options] FILE...` computes CRC-32 or Adler-32 checksums.
+
+mod prog_util;
+
+use libdeflate::{Compressor, Decompressor, adler32, crc32};
+use prog_util::*;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Deflate,
+    Zlib,
+    Gzip,
+}
+
+impl Format {
+    fn name(self) -> &'static str {
+        match self {
+            Format::Deflate => "deflate",
+            Format::Zlib => "zlib",
+            Format::Gzip => "gzip",
+        }
+    }
+}
+
+/// A compression engine to benchmark. Each chunk is compressed independently.
+trait Engine {
+    fn name(&self) -> &'static str;
+    fn bound(&mut self, format: Format, len: usize) -> usize;
+    fn compress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize>;
+    fn decompress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize>;
+}
+
+struct RustEngine {
+    compressor: Compressor,
+    decompressor: Decompressor,
+}
+
+impl RustEngine {
+    fn new(level: i32) -> io::Result<Self> {
+        Ok(Self {
+            compressor: Compressor::new(level)?,
+            decompressor: Decompressor::new(),
+        })
+    }
+}
+
+impl Engine for RustEngine {
+    fn name(&self) -> &'static str {
+        "libdeflate-rs"
+    }
+
+    fn bound(&mut self, format: Format, len: usize) -> usize {
+        match format {
+            Format::Deflate => self.compressor.deflate_compress_bound(len),
+            Format::Zlib => self.compressor.zlib_compress_bound(len),
+            Format::Gzip => self.compressor.gzip_compress_bound(len),
+        }
+    }
+
+    fn compress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match format {
+            Format::Deflate => self.compressor.compress_deflate_into(input, output),
+            Format::Zlib => self.compressor.compress_zlib_into(input, output),
+            Format::Gzip => self.compressor.compress_gzip_into(input, output),
+        }
+    }
+
+    fn decompress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match format {
+            Format::Deflate => self.decompressor.decompress_deflate_into(input, output),
+            Format::Zlib => self.decompressor.decompress_zlib_into(input, output),
+            Format::Gzip => self.decompressor.decompress_gzip_into(input, output),
+        }
+    }
+}
+
+#[cfg(all(feature = "libdeflater", not(feature = "capi")))]
+struct CEngine {
+    compressor: libdeflater::Compressor,
+    decompressor: libdeflater::Decompressor,
+}
+
+#[cfg(all(feature = "libdeflater", not(feature = "capi")))]
+impl CEngine {
+    fn new(level: i32) -> io::Result<Self> {
+        let level = libdeflater::CompressionLvl::new(level)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "invalid level"))?;
+        Ok(Self {
+            compressor: libdeflater::Compressor::new(level),
+            decompressor: libdeflater::Decompressor::new(),
+        })
+    }
+}
+
+#[cfg(all(feature = "libdeflater", not(feature = "capi")))]
+impl Engine for CEngine {
+    fn name(&self) -> &'static str {
+        "libdeflate-c"
+    }
+
+    fn bound(&mut self, format: Format, len: usize) -> usize {
+        match format {
+            Format::Deflate => self.compressor.deflate_compress_bound(len),
+            Format::Zlib => self.compressor.zlib_compress_bound(len),
+            Format::Gzip => self.compressor.gzip_compress_bound(len),
+        }
+    }
+
+    fn compress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match format {
+            Format::Deflate => self.compressor.deflate_compress(input, output),
+            Format::Zlib => self.compressor.zlib_compress(input, output),
+            Format::Gzip => self.compressor.gzip_compress(input, output),
+        }
+        .map_err(io::Error::other)
+    }
+
+    fn decompress(&mut self, format: Format, input: &[u8], output: &mut [u8]) -> io::Result<usize> {
+        match format {
+            Format::Deflate => self.decompressor.deflate_decompress(input, output),
+            Format::Zlib => self.decompressor.zlib_decompress(input, output),
+            Format::Gzip => self.decompressor.gzip_decompress(input, output),
+        }
+        .map_err(io::Error::other)
+    }
+}
+
+struct Options {
+    levels: Vec<i32>,
+    formats: Vec<Format>,
+    chunk_size: usize,
+    runs: usize,
+    compare: bool,
+}
+
+struct Measurement {
+    compressed_size: usize,
+    compress_time: Duration,
+    decompress_time: Duration,
+}
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [-LEVEL]... [-a] [-C] [-f FORMAT[,FORMAT]...] [-r RUNS] [-s SIZE] FILE...\n\
+         \x20      {program} checksum [-A] [-s SIZE] [-t] FILE...\n\
+         Benchmark compression and decompression of the specified FILEs.\n\
+         \n\
+         Options:\n\
+         \x20 -LEVEL    compression level to benchmark; may be repeated (default 6)\n\
+         \x20 -a        benchmark all levels, 0 through 12\n\
+         \x20 -C        also benchmark the C libdeflate (requires the `libdeflater` feature)\n\
+         \x20 -f FMT    formats to benchmark: deflate, zlib, gzip or all (default deflate)\n\
+         \x20 -h        print this help\n\
+         \x20 -r RUNS   number of runs; the fastest is reported (default 3)\n\
+         \x20 -s SIZE   chunk size in bytes; each chunk is compressed independently (default 1048576)\n\
+         \x20 -V        show version information\n\
+         \n\
+         Checksum options:\n\
+         \x20 -A        compute Adler-32 instead of CRC-32\n\
+         \x20 -s SIZE   feed the checksum SIZE bytes at a time (default whole file)\n\
+         \x20 -t        print the time taken and throughput\n"
+    )
+}
+
+fn parse_number(opt: char, value: Option<String>) -> Result<usize, String> {
+    let value = value.ok_or_else(|| format!("option requires an argument -- '{opt}'"))?;
+    match value.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(n),
+        _ => Err(format!("invalid argument to -{opt}: \"{value}\"")),
+    }
+}
+
+/// Splits `args` into flags and file names. Flags listed in `with_value` take a value, either the
+/// rest of the argument or the next argument. A run of digits is returned as flag `'0'`.
+fn parse_flags(args: Vec<String>, with_value: &str) -> (Vec<(char, Option<String>)>, Vec<String>) {
+    let mut flags = Vec::new();
+    let mut files = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if !arg.starts_with('-') || arg == "-" {
+            files.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            files.extend(args.by_ref());
+            break;
+        }
+        let chars: Vec<char> = arg[1..].chars().collect();
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            i += 1;
+            if c.is_ascii_digit() {
+                let start = i - 1;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                flags.push(('0', Some(chars[start..i].iter().collect())));
+            } else if with_value.contains(c) {
+                let value = if i < chars.len() {
+                    Some(chars[i..].iter().collect())
+                } else {
+                    args.next()
+                };
+                flags.push((c, value));
+                break;
+            } else {
+                flags.push((c, None));
+            }
+        }
+    }
+    (flags, files)
+}
+
+fn parse_formats(value: &str) -> Result<Vec<Format>, String> {
+    let mut formats = Vec::new();
+    for name in value.split(',') {
+        match name {
+            "deflate" => formats.push(Format::Deflate),
+            "zlib" => formats.push(Format::Zlib),
+            "gzip" => formats.push(Format::Gzip),
+            "all" => formats.extend([Format::Deflate, Format::Zlib, Format::Gzip]),
+            _ => return Err(format!("unknown format \"{name}\"")),
+        }
+    }
+    Ok(formats)
+}
+
+fn run_engine(
+    engine: &mut dyn Engine,
+    format: Format,
+    data: &[u8],
+    chunk_size: usize,
+    runs: usize,
+) -> io::Result<Measurement> {
+    let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+    let mut compressed: Vec<Vec<u8>> = chunks
+        .iter()
+        .map(|chunk| vec![0u8; engine.bound(format, chunk.len())])
+        .collect();
+    let mut compressed_lens = vec![0usize; chunks.len()];
+    let mut decompressed = vec![0u8; chunk_size.min(data.len())];
+
+    let mut best = Measurement {
+        compressed_size: 0,
+        compress_time: Duration::MAX,
+        decompress_time: Duration::MAX,
+    };
+    for _ in 0..runs {
+        let start = Instant::now();
+        for (i, chunk) in chunks.iter().enumerate() {
+            compressed_lens[i] = engine.compress(format, chunk, &mut compressed[i])?;
+        }
+        best.compress_time = best.compress_time.min(start.elapsed());
+
+        let start = Instant::now();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let out = &mut decompressed[..chunk.len()];
+            let n = engine.decompress(format, &compressed[i][..compressed_lens[i]], out)?;
+            if n != chunk.len() {
+                return Err(io::Error::other("decompressed size mismatch"));
+            }
+        }
+        best.decompress_time = best.decompress_time.min(start.elapsed());
+    }
+
+    // Verify outside of the timed loop.
+    for (i, chunk) in chunks.iter().enumerate() {
+        let out = &mut decompressed[..chunk.len()];
+        engine.decompress(format, &compressed[i][..compressed_lens[i]], out)?;
+        if out != *chunk {
+            return Err(io::Error::other(format!(
+                "{}: data did not round-trip",
+                engine.name()
+            )));
+        }
+    }
+    best.compressed_size = compressed_lens.iter().sum();
+    Ok(best)
+}
+
+fn throughput(bytes: usize, time: Duration) -> f64 {
+    let secs = time.as_secs_f64();
+    if secs == 0.0 {
+        0.0
+    } else {
+        bytes as f64 / secs / 1_000_000.0
+    }
+}
+
+#[cfg_attr(
+    not(all(feature = "libdeflater", not(feature = "capi"))),
+    allow(unused_variables)
+)]
+fn new_engines(opts: &Options, level: i32) -> io::Result<Vec<Box<dyn Engine>>> {
+    #[cfg(all(feature = "libdeflater", not(feature = "capi")))]
+    if opts.compare {
+        return Ok(vec![
+            Box::new(RustEngine::new(level)?),
+            Box::new(CEngine::new(level)?),
+        ]);
+    }
+    Ok(vec![Box::new(RustEngine::new(level)?)])
+}
+
+fn benchmark_file(opts: &Options, path: &str) -> Result<(), String> {
+    let data = read_input(if path == "-" {
+        None
+    } else {
+        Some(Path::new(path))
+    })
+    .map_err(|e| e.to_string())?;
+
+    println!("File: {path} ({} bytes)", data.len());
+    println!(
+        "{:<14} {:<8} {:>5} {:>12} {:>7} {:>12} {:>12}",
+        "Engine", "Format", "Level", "Compressed", "Ratio", "Comp MB/s", "Decomp MB/s"
+    );
+    if data.is_empty() {
+        return Ok(());
+    }
+
+    for &format in &opts.formats {
+        for &level in &opts.levels {
+            for mut engine in new_engines(opts, level).map_err(|e| e.to_string())? {
+                let m = run_engine(engine.as_mut(), format, &data, opts.chunk_size, opts.runs)
+                    .map_err(|e| e.to_string())?;
+                println!(
+                    "{:<14} {:<8} {:>5} {:>12} {:>6.2}% {:>12.1} {:>12.1}",
+                    engine.name(),
+                    format.name(),
+                    level,
+                    m.compressed_size,
+                    100.0 * m.compressed_size as f64 / data.len() as f64,
+                    throughput(data.len(), m.compress_time),
+                    throughput(data.len(), m.decompress_time),
+                );
+            }
+        }
+    }
+    println!();
+    Ok(())
+}
+
+fn benchmark_main(program: &str, args: Vec<String>) -> i32 {
+    let mut opts = Options {
+        levels: Vec::new(),
+        formats: Vec::new(),
+        chunk_size: DEFAULT_CHUNK_SIZE,
+        runs: 3,
+        compare: false,
+    };
+
+    let (flags, files) = parse_flags(args, "frs");
+    let files = (|| {
+        for (c, value) in flags {
+            match c {
+                '0' => {
+                    let digits = value.unwrap_or_default();
+                    match digits.parse::<i32>() {
+                        Ok(level) if level <= 12 => opts.levels.push(level),
+                        _ => return Err(format!("invalid compression level: \"{digits}\"")),
+                    }
+                }
+                'a' => opts.levels.extend(0..=12),
+                'C' => {
+                    // The C libdeflate's symbols clash with the `capi` exports.
+                    if !cfg!(all(feature = "libdeflater", not(feature = "capi"))) {
+                        return Err(
+                            "-C requires building with the `libdeflater` feature and without `capi`"
+                                .to_string(),
+                        );
+                    }
+                    opts.compare = true;
+                }
+                'f' => {
+                    let v = value.ok_or("option requires an argument -- 'f'")?;
+                    opts.formats.extend(parse_formats(&v)?);
+                }
+                'h' => {
+                    print!("{}", usage(program));
+                    std::process::exit(0);
+                }
+                'r' => opts.runs = parse_number('r', value)?,
+                's' => opts.chunk_size = parse_number('s', value)?,
+                'V' => {
+                    println!("{}", version_string(program));
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("invalid option -- '{c}'\n{}", usage(program))),
+            }
+        }
+        Ok(files)
+    })();
+    let files = match files {
+        Ok(files) if !files.is_empty() => files,
+        Ok(_) => {
+            eprint!("{}", usage(program));
+            return 1;
+        }
+        Err(msg) => {
+            eprintln!("{program}: {msg}");
+            return 1;
+        }
+    };
+    if opts.levels.is_empty() {
+        opts.levels.push(6);
+    }
+    if opts.formats.is_empty() {
+        opts.formats.push(Format::Deflate);
+    }
+
+    println!("{}", version_string(program));
+    println!(
+        "CRC-32: {}, Adler-32: {}, chunk size: {}, runs: {}",
+        crc32::implementation_name(),
+        adler32::implementation_name(),
+        opts.chunk_size,
+        opts.runs
+    );
+    println!();
+
+    let mut status = 0;
+    for file in &files {
+        if let Err(msg) = benchmark_file(&opts, file) {
+            eprintln!("{program}: {file}: {msg}");
+            status = 1;
+        }
+    }
+    status
+}
+
+fn checksum_main(program: &str, args: Vec<String>) -> i32 {
+    let mut use_adler32 = false;
+    let mut chunk_size = usize::MAX;
+    let mut timed = false;
+
+    let (flags, files) = parse_flags(args, "s");
+    let files = (|| {
+        for (c, value) in flags {
+            match c {
+                'A' => use_adler32 = true,
+                'h' => {
+                    print!("{}", usage(program));
+                    std::process::exit(0);
+                }
+                's' => chunk_size = parse_number('s', value)?,
+                't' => timed = true,
+                'V' => {
+                    println!("{}", version_string(program));
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("invalid option -- '{c}'\n{}", usage(program))),
+            }
+        }
+        Ok(files)
+    })();
+    let mut files = match files {
+        Ok(files) => files,
+        Err(msg) => {
+            eprintln!("{program}: {msg}");
+            return 1;
+        }
+    };
+    if files.is_empty() {
+        files.push("-".to_string());
+    }
+
+    let (name, implementation) = if use_adler32 {
+        ("Adler-32", adler32::implementation_name())
+    } else {
+        ("CRC-32", crc32::implementation_name())
+    };
+    eprintln!("{name} implementation: {implementation}");
+
+    let mut status = 0;
+    for file in &files {
+        let data = match read_input(if file == "-" {
+            None
+        } else {
+            Some(Path::new(file))
+        }) {
+            Ok(data) => data,
+            Err(e) => {
+                eprintln!("{program}: {file}: {e}");
+                status = 1;
+                continue;
+            }
+        };
+
+        let start = Instant::now();
+        let mut sum = if use_adler32 { 1 } else { 0 };
+        for chunk in data.chunks(chunk_size.min(data.len().max(1))) {
+            sum = if use_adler32 {
+                adler32(sum, chunk)
+            } else {
+                crc32(sum, chunk)
+            };
+        }
+        let elapsed = start.elapsed();
+
+        if timed {
+            println!(
+                "{sum:08x}\t{file}\t{} ms\t{:.1} MB/s",
+                elapsed.as_millis(),
+                throughput(data.len(), elapsed)
+            );
+        } else {
+            println!("{sum:08x}\t{file}");
+        }
+    }
+    status
+}
+
+fn main() {
+    let program = program_invocation_name();
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let status = if args.first().map(String::as_str) == Some("checksum") {
+        args.remove(0);
+        checksum_main(&program, args)
+    } else {
+        benchmark_main(&program, args)
+    };
+    std::process::exit(status);
+}
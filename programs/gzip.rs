@@ -0,0 +1,486 @@
+//! `libdeflate-gzip`: a gzip-compatible compression program built on this crate.
+//!
+//! When invoked under a name containing `gunzip` (for example through a `libdeflate-gunzip`
+//! link), decompression is the default, as with the C `libdeflate-gunzip`.
+
+mod prog_util;
+
+use libdeflate::common::{GZIP_FOOTER_SIZE, GZIP_ID1, GZIP_ID2, GzipHeader};
+use libdeflate::decompress::{DecompressResult, Decompressor, parse_gzip_header};
+use prog_util::*;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+const DEFAULT_LEVEL: i32 = 6;
+const DEFAULT_SUFFIX: &str = ".gz";
+
+struct Options {
+    to_stdout: bool,
+    decompress: bool,
+    force: bool,
+    keep: bool,
+    list: bool,
+    test: bool,
+    quiet: bool,
+    verbose: bool,
+    /// `Some(true)` for `-N`, `Some(false)` for `-n`. When unset, names and timestamps are saved
+    /// when compressing but not restored when decompressing, like GNU gzip.
+    name: Option<bool>,
+    level: i32,
+    suffix: String,
+}
+
+/// The result of decompressing every member of a gzip file.
+struct Decoded {
+    data: Vec<u8>,
+    header: GzipHeader,
+    trailing_garbage: bool,
+}
+
+fn usage(program: &str) -> String {
+    format!(
+        "Usage: {program} [-LEVEL] [-cdfhklnNqtvV] [-S SUF] FILE...\n\
+         Compress or decompress the specified FILEs.\n\
+         \n\
+         Options:\n\
+         \x20 -1        fastest (worst) compression\n\
+         \x20 -6        medium compression (default)\n\
+         \x20 -12       slowest (best) compression\n\
+         \x20 -c        write to standard output\n\
+         \x20 -d        decompress\n\
+         \x20 -f        overwrite existing output files; (de)compress hard-linked files\n\
+         \x20 -h        print this help\n\
+         \x20 -k        don't delete input files\n\
+         \x20 -l        list information about compressed files\n\
+         \x20 -n        don't save or restore the original name and timestamp\n\
+         \x20 -N        save or restore the original name and timestamp\n\
+         \x20 -q        suppress warnings\n\
+         \x20 -S SUF    use suffix SUF instead of .gz\n\
+         \x20 -t        test file integrity\n\
+         \x20 -v        print the compression ratio of each file\n\
+         \x20 -V        show version and legal information\n"
+    )
+}
+
+fn parse_args(
+    program: &str,
+    args: Vec<OsString>,
+    opts: &mut Options,
+) -> Result<Vec<OsString>, String> {
+    let mut files = Vec::new();
+    let mut args = args.into_iter();
+    let mut only_files = false;
+
+    while let Some(arg) = args.next() {
+        let text = arg.to_string_lossy();
+        if only_files || !text.starts_with('-') || text == "-" {
+            files.push(arg);
+            continue;
+        }
+        if text == "--" {
+            only_files = true;
+            continue;
+        }
+
+        let flags: Vec<char> = text[1..].chars().collect();
+        let mut i = 0;
+        while i < flags.len() {
+            let c = flags[i];
+            i += 1;
+            match c {
+                '0'..='9' => {
+                    let mut level = c.to_digit(10).unwrap() as i32;
+                    while i < flags.len() && flags[i].is_ascii_digit() {
+                        level = level * 10 + flags[i].to_digit(10).unwrap() as i32;
+                        i += 1;
+                    }
+                    if !(1..=12).contains(&level) {
+                        return Err(format!("invalid compression level: \"{level}\""));
+                    }
+                    opts.level = level;
+                }
+                'c' => opts.to_stdout = true,
+                'd' => opts.decompress = true,
+                'f' => opts.force = true,
+                'h' => {
+                    print!("{}", usage(program));
+                    std::process::exit(0);
+                }
+                'k' => opts.keep = true,
+                'l' => opts.list = true,
+                'n' => opts.name = Some(false),
+                'N' => opts.name = Some(true),
+                'q' => opts.quiet = true,
+                'S' => {
+                    let suffix: String = flags[i..].iter().collect();
+                    let suffix = if suffix.is_empty() {
+                        match args.next() {
+                            Some(next) => next.to_string_lossy().into_owned(),
+                            None => return Err("option requires an argument -- 'S'".to_string()),
+                        }
+                    } else {
+                        suffix
+                    };
+                    if suffix.is_empty() || suffix.contains('/') {
+                        return Err(format!("invalid suffix '{suffix}'"));
+                    }
+                    opts.suffix = suffix;
+                    i = flags.len();
+                }
+                't' => opts.test = true,
+                'v' => opts.verbose = true,
+                'V' => {
+                    println!("{}", version_string(program));
+                    std::process::exit(0);
+                }
+                _ => return Err(format!("invalid option -- '{c}'\n{}", usage(program))),
+            }
+        }
+    }
+
+    if files.is_empty() {
+        files.push(OsString::from("-"));
+    }
+    Ok(files)
+}
+
+fn compress_data(opts: &Options, data: &[u8], header: &GzipHeader) -> io::Result<Vec<u8>> {
+    let mut compressor = libdeflate::Compressor::new(opts.level)?;
+    compressor.compress_gzip_with_header(data, header)
+}
+
+/// Decompresses all gzip members in `data`, as gzip does for concatenated files.
+fn decompress_data(data: &[u8]) -> Result<Decoded, String> {
+    const CORRUPT: &str = "file corrupt or not in gzip format";
+
+    let (header, _) = parse_gzip_header(data).map_err(|_| "not in gzip format".to_string())?;
+    let mut decompressor = Decompressor::new();
+    let mut out: Vec<u8> = Vec::new();
+
+    // The ISIZE field of the last member is a good first guess of the total size.
+    let mut guess = if data.len() >= GZIP_FOOTER_SIZE {
+        u32::from_le_bytes(data[data.len() - 4..].try_into().unwrap()) as usize
+    } else {
+        0
+    };
+
+    let mut pos = 0;
+    let mut trailing_garbage = false;
+    while pos < data.len() {
+        let member = &data[pos..];
+        if member.len() < 2 || member[0] != GZIP_ID1 || member[1] != GZIP_ID2 {
+            trailing_garbage = member.iter().any(|&b| b != 0);
+            break;
+        }
+
+        let mut capacity = if guess != 0 {
+            guess
+        } else {
+            member.len().max(64) * 2
+        };
+        loop {
+            out.try_reserve(capacity).map_err(|e| e.to_string())?;
+            let spare = &mut out.spare_capacity_mut()[..capacity];
+            let (res, in_consumed, out_produced) =
+                unsafe { decompressor.decompress_gzip_uninit(member, spare) };
+            match res {
+                DecompressResult::Success => {
+                    unsafe { out.set_len(out.len() + out_produced) };
+                    pos += in_consumed;
+                    break;
+                }
+                DecompressResult::InsufficientSpace => {
+                    capacity = capacity.checked_mul(2).ok_or(CORRUPT)?;
+                }
+                _ => return Err(CORRUPT.to_string()),
+            }
+        }
+        guess = 0;
+    }
+
+    Ok(Decoded {
+        data: out,
+        header,
+        trailing_garbage,
+    })
+}
+
+fn has_suffix(path: &Path, suffix: &str) -> bool {
+    path.file_name()
+        .map(|name| {
+            let name = name.to_string_lossy();
+            name.len() > suffix.len() && name.ends_with(suffix)
+        })
+        .unwrap_or(false)
+}
+
+fn strip_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap().to_string_lossy();
+    path.with_file_name(&name[..name.len() - suffix.len()])
+}
+
+fn append_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Copies the permissions and modification time from the input to the output, as gzip does.
+fn restore_metadata(out_path: &Path, in_meta: &fs::Metadata, mtime: Option<u32>) -> io::Result<()> {
+    let file = fs::File::options().write(true).open(out_path)?;
+    match mtime {
+        Some(secs) => file.set_modified(from_unix_seconds(secs))?,
+        None => file.set_modified(in_meta.modified()?)?,
+    }
+    fs::set_permissions(out_path, in_meta.permissions())
+}
+
+fn list_header(opts: &Options) {
+    if !opts.quiet {
+        println!("         compressed        uncompressed  ratio uncompressed_name");
+    }
+}
+
+fn list_entry(compressed: usize, uncompressed: usize, name: &str) {
+    let ratio = if uncompressed == 0 {
+        0.0
+    } else {
+        100.0 * (1.0 - compressed as f64 / uncompressed as f64)
+    };
+    println!("{compressed:>19} {uncompressed:>19} {ratio:>5.1}% {name}");
+}
+
+fn process_stdin(opts: &Options) -> Result<(), String> {
+    if !opts.decompress && !opts.force && io::stdout().is_terminal() {
+        return Err(
+            "compressed data not written to a terminal. Use -f to force compression.".to_string(),
+        );
+    }
+    let data = read_input(None).map_err(|e| e.to_string())?;
+
+    if !opts.decompress {
+        let out = compress_data(opts, &data, &GzipHeader::default()).map_err(|e| e.to_string())?;
+        return write_stdout(&out).map_err(|e| e.to_string());
+    }
+
+    let decoded = decompress_data(&data)?;
+    if decoded.trailing_garbage && !opts.quiet {
+        eprintln!(
+            "{}: stdin: trailing garbage ignored",
+            program_invocation_name()
+        );
+    }
+    if opts.list {
+        list_entry(data.len(), decoded.data.len(), "stdout");
+    } else if !opts.test {
+        write_stdout(&decoded.data).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn process_file(opts: &Options, path: &Path) -> Result<(), String> {
+    let meta = fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+    if meta.is_dir() {
+        return Err("is a directory -- ignored".to_string());
+    }
+    if !meta.is_file() {
+        return Err("not a regular file -- ignored".to_string());
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let writes_output = !opts.to_stdout && !opts.test && !opts.list;
+        if writes_output && !opts.keep && !opts.force && meta.nlink() > 1 {
+            return Err(format!("has {} other links -- unchanged", meta.nlink() - 1));
+        }
+    }
+
+    if opts.decompress {
+        decompress_file(opts, path, &meta)
+    } else {
+        compress_file(opts, path, &meta)
+    }
+}
+
+fn compress_file(opts: &Options, path: &Path, meta: &fs::Metadata) -> Result<(), String> {
+    if !opts.to_stdout && has_suffix(path, &opts.suffix) {
+        return Err(format!("already has {} suffix -- unchanged", opts.suffix));
+    }
+
+    let data = read_input(Some(path)).map_err(|e| e.to_string())?;
+    let header = if opts.name == Some(false) {
+        GzipHeader::default()
+    } else {
+        GzipHeader {
+            mtime: meta.modified().map(to_unix_seconds).unwrap_or(0),
+            filename: path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned().into_bytes()),
+        }
+    };
+    let out = compress_data(opts, &data, &header).map_err(|e| e.to_string())?;
+
+    if opts.to_stdout {
+        return write_stdout(&out).map_err(|e| e.to_string());
+    }
+
+    let out_path = append_suffix(path, &opts.suffix);
+    write_output(opts, path, &out_path, &out, meta, None)?;
+    if opts.verbose {
+        let saved = if data.is_empty() {
+            0.0
+        } else {
+            100.0 * (1.0 - out.len() as f64 / data.len() as f64)
+        };
+        eprintln!(
+            "{}:\t{saved:.1}% -- replaced with {}",
+            path.display(),
+            out_path.display()
+        );
+    }
+    Ok(())
+}
+
+fn decompress_file(opts: &Options, path: &Path, meta: &fs::Metadata) -> Result<(), String> {
+    let writes_output = !opts.to_stdout && !opts.test && !opts.list;
+    if writes_output && !has_suffix(path, &opts.suffix) {
+        return Err(format!(
+            "unknown suffix -- ignored (expected {})",
+            opts.suffix
+        ));
+    }
+
+    let data = read_input(Some(path)).map_err(|e| e.to_string())?;
+    let decoded = decompress_data(&data)?;
+    if decoded.trailing_garbage && !opts.quiet {
+        eprintln!(
+            "{}: {}: trailing garbage ignored",
+            program_invocation_name(),
+            path.display()
+        );
+    }
+
+    let restore = opts.name == Some(true);
+    let stored_name = decoded
+        .header
+        .filename
+        .as_deref()
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        // Only the final component is honored so a crafted header can't escape the directory.
+        .and_then(|name| {
+            Path::new(&name)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+        });
+
+    if opts.list {
+        let name = match (&stored_name, restore) {
+            (Some(name), true) => path.with_file_name(name),
+            _ if has_suffix(path, &opts.suffix) => strip_suffix(path, &opts.suffix),
+            _ => path.to_path_buf(),
+        };
+        list_entry(data.len(), decoded.data.len(), &name.display().to_string());
+        return Ok(());
+    }
+    if opts.test {
+        if opts.verbose {
+            eprintln!("{}:\tOK", path.display());
+        }
+        return Ok(());
+    }
+    if opts.to_stdout {
+        return write_stdout(&decoded.data).map_err(|e| e.to_string());
+    }
+
+    let out_path = match (&stored_name, restore) {
+        (Some(name), true) => path.with_file_name(name),
+        _ => strip_suffix(path, &opts.suffix),
+    };
+    let mtime = if restore && decoded.header.mtime != 0 {
+        Some(decoded.header.mtime)
+    } else {
+        None
+    };
+    write_output(opts, path, &out_path, &decoded.data, meta, mtime)
+}
+
+fn write_output(
+    opts: &Options,
+    in_path: &Path,
+    out_path: &Path,
+    data: &[u8],
+    in_meta: &fs::Metadata,
+    mtime: Option<u32>,
+) -> Result<(), String> {
+    let mut file = create_output(out_path, opts.force).map_err(|e| {
+        if e.kind() == io::ErrorKind::AlreadyExists {
+            format!("{} already exists", out_path.display())
+        } else {
+            format!("{}: {}", out_path.display(), e)
+        }
+    })?;
+    let res = file
+        .write_all(data)
+        .and_then(|_| file.sync_all())
+        .and_then(|_| restore_metadata(out_path, in_meta, mtime));
+    if let Err(e) = res {
+        let _ = fs::remove_file(out_path);
+        return Err(format!("{}: {}", out_path.display(), e));
+    }
+    if !opts.keep {
+        fs::remove_file(in_path).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let program = program_invocation_name();
+    let mut opts = Options {
+        to_stdout: false,
+        decompress: program.contains("gunzip"),
+        force: false,
+        keep: false,
+        list: false,
+        test: false,
+        quiet: false,
+        verbose: false,
+        name: None,
+        level: DEFAULT_LEVEL,
+        suffix: DEFAULT_SUFFIX.to_string(),
+    };
+
+    let files = match parse_args(&program, std::env::args_os().skip(1).collect(), &mut opts) {
+        Ok(files) => files,
+        Err(msg) => {
+            eprintln!("{program}: {msg}");
+            std::process::exit(1);
+        }
+    };
+    if opts.list || opts.test {
+        opts.decompress = true;
+    }
+    if opts.list {
+        list_header(&opts);
+    }
+
+    let mut status = 0;
+    for file in &files {
+        let res = if file == "-" {
+            process_stdin(&opts)
+        } else {
+            process_file(&opts, Path::new(file))
+        };
+        if let Err(msg) = res {
+            let name = if file == "-" {
+                "stdin".to_string()
+            } else {
+                Path::new(file).display().to_string()
+            };
+            eprintln!("{program}: {name}: {msg}");
+            status = 1;
+        }
+    }
+    std::process::exit(status);
+}
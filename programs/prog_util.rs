@@ -0,0 +1,82 @@
+//! Helpers shared by the command-line programs.
+
+// Not every program uses every helper.
+#![allow(dead_code)]
+
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name the program was invoked as, for error messages.
+pub fn program_invocation_name() -> String {
+    std::env::args_os()
+        .next()
+        .and_then(|arg0| {
+            Path::new(&arg0)
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+        })
+        .unwrap_or_else(|| "libdeflate".to_string())
+}
+
+pub fn version_string(program: &str) -> String {
+    format!(
+        "{} (libdeflate-rs {}, libdeflate {})",
+        program,
+        env!("CARGO_PKG_VERSION"),
+        libdeflate::common::LIBDEFLATE_VERSION_STRING
+    )
+}
+
+/// Reads all of `path`, or all of standard input if `path` is `None`.
+pub fn read_input(path: Option<&Path>) -> io::Result<Vec<u8>> {
+    let mut data = Vec::new();
+    match path {
+        Some(path) => {
+            let mut file = File::open(path)?;
+            if let Ok(meta) = file.metadata() {
+                data.reserve(meta.len() as usize);
+            }
+            file.read_to_end(&mut data)?;
+        }
+        None => {
+            io::stdin().lock().read_to_end(&mut data)?;
+        }
+    }
+    Ok(data)
+}
+
+pub fn write_stdout(data: &[u8]) -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    stdout.write_all(data)?;
+    stdout.flush()
+}
+
+/// Creates `path` for writing. Refuses to replace an existing file unless `overwrite` is set.
+pub fn create_output(path: &Path, overwrite: bool) -> io::Result<File> {
+    if overwrite {
+        match fs::symlink_metadata(path) {
+            Ok(meta) if meta.is_dir() => {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "is a directory",
+                ));
+            }
+            Ok(_) => fs::remove_file(path)?,
+            Err(_) => {}
+        }
+    }
+    File::options().write(true).create_new(true).open(path)
+}
+
+/// Converts a file time into the 32-bit seconds used by the gzip MTIME field.
+pub fn to_unix_seconds(time: SystemTime) -> u32 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs().min(u32::MAX as u64) as u32)
+        .unwrap_or(0)
+}
+
+pub fn from_unix_seconds(secs: u32) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs as u64)
+}
@@ -1,5 +1,13 @@
-use std::cmp::min;
-use std::sync::OnceLock;
+#[cfg(target_arch = "aarch64")]
+use crate::cpu_features::aarch64_feature_enabled;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::cpu_features::x86_feature_enabled;
+#[cfg(feature = "std")]
+use crate::crc32::PARALLEL_CHECKSUM_CHUNK_SIZE;
+use crate::sync::OnceLock;
+use core::cmp::min;
+#[cfg(feature = "std")]
+use rayon::prelude::*;
 
 const DIVISOR: u32 = 65521;
 const MAX_CHUNK_LEN: usize = 4096;
@@ -111,42 +119,91 @@ mod arm;
 
 type Adler32Fn = unsafe fn(u32, &[u8]) -> u32;
 
-#[inline]
-pub fn adler32(adler: u32, slice: &[u8]) -> u32 {
-    static IMPL: OnceLock<Adler32Fn> = OnceLock::new();
-    let func = IMPL.get_or_init(|| {
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+static IMPL: OnceLock<(Adler32Fn, &'static str)> = OnceLock::new();
+
+fn select_impl() -> (Adler32Fn, &'static str) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if x86_feature_enabled!("avx512vl")
+            && x86_feature_enabled!("avx512vnni")
+            && x86_feature_enabled!("avx512bw")
+            && x86_feature_enabled!("avx512f")
         {
-            if is_x86_feature_detected!("avx512vl")
-                && is_x86_feature_detected!("avx512vnni")
-                && is_x86_feature_detected!("avx512bw")
-                && is_x86_feature_detected!("avx512f")
-            {
-                return x86::adler32_x86_avx512_vnni;
-            }
-            if is_x86_feature_detected!("avxvnni") {
-                return x86::adler32_x86_avx2_vnni;
-            }
-            if is_x86_feature_detected!("avx2") {
-                return x86::adler32_x86_avx2;
-            }
-            if is_x86_feature_detected!("sse2") {
-                return x86::adler32_x86_sse2;
-            }
+            return (x86::adler32_x86_avx512_vnni, "avx512_vnni");
+        }
+        if x86_feature_enabled!("avxvnni") {
+            return (x86::adler32_x86_avx2_vnni, "avx2_vnni");
+        }
+        if x86_feature_enabled!("avx2") {
+            return (x86::adler32_x86_avx2, "avx2");
+        }
+        if x86_feature_enabled!("sse2") {
+            return (x86::adler32_x86_sse2, "sse2");
         }
+    }
 
-        #[cfg(target_arch = "aarch64")]
-        {
-            if std::arch::is_aarch64_feature_detected!("dotprod") {
-                return arm::adler32_arm_neon_dotprod;
-            }
-            if std::arch::is_aarch64_feature_detected!("neon") {
-                return arm::adler32_arm_neon;
-            }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if aarch64_feature_enabled!("dotprod") {
+            return (arm::adler32_arm_neon_dotprod, "neon_dotprod");
+        }
+        if aarch64_feature_enabled!("neon") {
+            return (arm::adler32_arm_neon, "neon");
         }
+    }
 
-        adler32_generic
-    });
+    (adler32_generic, "generic")
+}
 
+/// Name of the Adler-32 implementation selected for this CPU.
+pub fn implementation_name() -> &'static str {
+    IMPL.get_or_init(select_impl).1
+}
+
+#[inline]
+pub fn adler32(adler: u32, slice: &[u8]) -> u32 {
+    let func = IMPL.get_or_init(select_impl).0;
     unsafe { func(adler, slice) }
 }
+
+/// Combines `adler1 = adler32(1, a)` and `adler2 = adler32(1, b)` into
+/// `adler32(1, a ++ b)`, where `len2` is the length of `b`.
+pub fn adler32_combine(adler1: u32, adler2: u32, len2: usize) -> u32 {
+    let rem = (len2 % DIVISOR as usize) as u32;
+    let mut s1 = adler1 & 0xFFFF;
+    let mut s2 = (rem * s1) % DIVISOR;
+    s1 += (adler2 & 0xFFFF) + DIVISOR - 1;
+    s2 += (adler1 >> 16) + (adler2 >> 16) + DIVISOR - rem;
+    if s1 >= DIVISOR {
+        s1 -= DIVISOR;
+    }
+    if s1 >= DIVISOR {
+        s1 -= DIVISOR;
+    }
+    if s2 >= DIVISOR << 1 {
+        s2 -= DIVISOR << 1;
+    }
+    if s2 >= DIVISOR {
+        s2 -= DIVISOR;
+    }
+    s2 << 16 | s1
+}
+
+/// Same result as [`adler32`], but splits large inputs across the rayon thread pool
+/// and merges the per-chunk checksums with [`adler32_combine`].
+#[cfg(feature = "std")]
+pub fn adler32_parallel(adler: u32, slice: &[u8]) -> u32 {
+    if slice.len() <= PARALLEL_CHECKSUM_CHUNK_SIZE {
+        return adler32(adler, slice);
+    }
+    let sums: Vec<u32> = slice
+        .par_chunks(PARALLEL_CHECKSUM_CHUNK_SIZE)
+        .map(|chunk| adler32(1, chunk))
+        .collect();
+    slice
+        .chunks(PARALLEL_CHECKSUM_CHUNK_SIZE)
+        .zip(sums)
+        .fold(adler, |acc, (chunk, a)| {
+            adler32_combine(acc, a, chunk.len())
+        })
+}
@@ -0,0 +1,342 @@
+//! Pluggable allocators for the working memory of
+//! [`Compressor`](crate::compress::Compressor).
+//!
+//! A compressor made with
+//! [`Compressor::with_allocator`](crate::compress::Compressor::with_allocator) takes its
+//! match-finder tables and parsing buffers from the given [`Allocator`] instead of the Rust
+//! global allocator. [`CallbackAllocator`] adapts a C-style `malloc`/`free` pair, as
+//! `libdeflate_set_memory_allocator` takes.
+//!
+//! When the allocator runs out, [`Compressor::try_with_allocator`] returns an
+//! [`AllocError`] and compression returns
+//! [`CompressResult::OutOfMemory`](crate::compress::CompressResult::OutOfMemory).
+//!
+//! [`Compressor::try_with_allocator`]: crate::compress::Compressor::try_with_allocator
+
+use alloc::alloc::{Layout, handle_alloc_error};
+use alloc::sync::Arc;
+use core::ffi::c_void;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem::{align_of, size_of};
+use core::ops::{Deref, DerefMut};
+use core::ptr::{self, NonNull};
+
+pub type MallocFunc = unsafe extern "C" fn(usize) -> *mut c_void;
+pub type FreeFunc = unsafe extern "C" fn(*mut c_void);
+
+/// A source of memory for a compressor.
+///
+/// Requests are never zero-sized. An allocator is shared by a compressor and the
+/// compressors it creates for parallel chunks, so it must be usable from any thread.
+pub trait Allocator: Send + Sync {
+    /// Returns memory for `layout`, or null if there is none.
+    fn allocate(&self, layout: Layout) -> *mut u8;
+
+    /// Releases memory.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must have come from [`allocate`](Allocator::allocate) on this allocator with
+    /// the same `layout`, and not have been released since.
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout);
+}
+
+/// An [`Allocator`] returned null for a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AllocError {
+    layout: Layout,
+}
+
+impl AllocError {
+    /// The request that failed.
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+}
+
+impl fmt::Display for AllocError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to allocate {} bytes", self.layout.size())
+    }
+}
+
+impl core::error::Error for AllocError {}
+
+/// The Rust global allocator.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Global;
+
+impl Allocator for Global {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        unsafe { alloc::alloc::alloc(layout) }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        alloc::alloc::dealloc(ptr, layout)
+    }
+}
+
+/// An [`Allocator`] calling a `malloc`-like and a `free`-like function.
+///
+/// `malloc_func` must return memory aligned for any of the fundamental C types, as
+/// `malloc()` does; requests for stricter alignment fail.
+#[derive(Clone, Copy, Debug)]
+pub struct CallbackAllocator {
+    malloc_func: MallocFunc,
+    free_func: FreeFunc,
+}
+
+impl CallbackAllocator {
+    /// # Safety
+    ///
+    /// `malloc_func` and `free_func` must behave like `malloc()` and `free()` and be safe
+    /// to call from any thread.
+    pub unsafe fn new(malloc_func: MallocFunc, free_func: FreeFunc) -> Self {
+        Self {
+            malloc_func,
+            free_func,
+        }
+    }
+}
+
+impl Allocator for CallbackAllocator {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        if layout.align() > align_of::<u64>() {
+            return ptr::null_mut();
+        }
+        unsafe { (self.malloc_func)(layout.size()) as *mut u8 }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, _layout: Layout) {
+        (self.free_func)(ptr as *mut c_void)
+    }
+}
+
+/// The allocator a compressor was made with; `None` stands for [`Global`], so that
+/// compressors without one carry no `Arc`.
+#[derive(Clone, Default)]
+pub(crate) struct AllocatorRef(Option<Arc<dyn Allocator>>);
+
+impl AllocatorRef {
+    pub(crate) fn new(allocator: Arc<dyn Allocator>) -> Self {
+        Self(Some(allocator))
+    }
+
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        match &self.0 {
+            Some(allocator) => allocator.allocate(layout),
+            None => Global.allocate(layout),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        match &self.0 {
+            Some(allocator) => allocator.deallocate(ptr, layout),
+            None => Global.deallocate(ptr, layout),
+        }
+    }
+}
+
+/// A growable array of `Copy` elements in memory from an [`AllocatorRef`]: the parts of
+/// `Vec` the compressor uses.
+pub(crate) struct AllocVec<T: Copy> {
+    ptr: NonNull<T>,
+    len: usize,
+    cap: usize,
+    allocator: AllocatorRef,
+    _marker: PhantomData<T>,
+}
+
+// SAFETY: `AllocVec` owns its elements like `Vec`, and allocators are `Send + Sync`.
+unsafe impl<T: Copy + Send> Send for AllocVec<T> {}
+unsafe impl<T: Copy + Sync> Sync for AllocVec<T> {}
+
+impl<T: Copy> Default for AllocVec<T> {
+    fn default() -> Self {
+        Self::new_in(&AllocatorRef::default())
+    }
+}
+
+impl<T: Copy> AllocVec<T> {
+    pub(crate) fn new_in(allocator: &AllocatorRef) -> Self {
+        const { assert!(size_of::<T>() > 0) };
+        Self {
+            ptr: NonNull::dangling(),
+            len: 0,
+            cap: 0,
+            allocator: allocator.clone(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub(crate) fn try_with_capacity_in(
+        capacity: usize,
+        allocator: &AllocatorRef,
+    ) -> Result<Self, AllocError> {
+        let mut v = Self::new_in(allocator);
+        v.try_reserve_exact(capacity)?;
+        Ok(v)
+    }
+
+    /// `len` copies of `value`, like `vec![value; len]`.
+    pub(crate) fn from_elem_in(value: T, len: usize, allocator: &AllocatorRef) -> Self {
+        Self::try_from_elem_in(value, len, allocator)
+            .unwrap_or_else(|e| handle_alloc_error(e.layout))
+    }
+
+    pub(crate) fn try_from_elem_in(
+        value: T,
+        len: usize,
+        allocator: &AllocatorRef,
+    ) -> Result<Self, AllocError> {
+        let mut v = Self::try_with_capacity_in(len, allocator)?;
+        v.resize(len, value);
+        Ok(v)
+    }
+
+    /// The bytes [`try_with_capacity_in`](Self::try_with_capacity_in) and
+    /// [`try_from_elem_in`](Self::try_from_elem_in) request for `capacity` elements.
+    pub(crate) const fn footprint(capacity: usize) -> usize {
+        capacity * size_of::<T>()
+    }
+
+    pub(crate) fn capacity(&self) -> usize {
+        self.cap
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// # Safety
+    ///
+    /// `len` must be at most the capacity, and the elements up to it initialized.
+    pub(crate) unsafe fn set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    pub(crate) fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional)
+            .unwrap_or_else(|e| handle_alloc_error(e.layout))
+    }
+
+    fn try_reserve(&mut self, additional: usize) -> Result<(), AllocError> {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed > self.cap {
+            self.grow_to(needed.max(self.cap * 2).max(8))?;
+        }
+        Ok(())
+    }
+
+    fn try_reserve_exact(&mut self, additional: usize) -> Result<(), AllocError> {
+        let needed = self.len.checked_add(additional).expect("capacity overflow");
+        if needed > self.cap {
+            self.grow_to(needed)?;
+        }
+        Ok(())
+    }
+
+    /// Makes room for `capacity` elements in all, whatever the length, so that filling
+    /// the vector up to that from empty cannot fail.
+    pub(crate) fn try_reserve_total(&mut self, capacity: usize) -> Result<(), AllocError> {
+        if capacity > self.cap {
+            self.grow_to(capacity)?;
+        }
+        Ok(())
+    }
+
+    /// Moves the elements to a new allocation of `cap` elements. On failure the vector is
+    /// left as it was.
+    fn grow_to(&mut self, cap: usize) -> Result<(), AllocError> {
+        let layout = Layout::array::<T>(cap).expect("capacity overflow");
+        let new = self.allocator.allocate(layout) as *mut T;
+        let new = NonNull::new(new).ok_or(AllocError { layout })?;
+        unsafe {
+            ptr::copy_nonoverlapping(self.ptr.as_ptr(), new.as_ptr(), self.len);
+            self.release();
+        }
+        self.ptr = new;
+        self.cap = cap;
+        Ok(())
+    }
+
+    unsafe fn release(&mut self) {
+        if self.cap > 0 {
+            let layout = Layout::array::<T>(self.cap).unwrap();
+            self.allocator
+                .deallocate(self.ptr.as_ptr() as *mut u8, layout);
+        }
+    }
+
+    #[inline(always)]
+    pub(crate) fn push(&mut self, value: T) {
+        if self.len == self.cap {
+            self.reserve(1);
+        }
+        unsafe { self.ptr.as_ptr().add(self.len).write(value) };
+        self.len += 1;
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        self.len = self.len.checked_sub(1)?;
+        Some(unsafe { self.ptr.as_ptr().add(self.len).read() })
+    }
+
+    pub(crate) fn resize(&mut self, len: usize, value: T) {
+        if len > self.len {
+            self.reserve(len - self.len);
+            for i in self.len..len {
+                unsafe { self.ptr.as_ptr().add(i).write(value) };
+            }
+        }
+        self.len = len;
+    }
+
+    pub(crate) fn extend_from_slice(&mut self, values: &[T]) {
+        self.reserve(values.len());
+        unsafe {
+            ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                self.ptr.as_ptr().add(self.len),
+                values.len(),
+            );
+        }
+        self.len += values.len();
+    }
+}
+
+impl<T: Copy> Drop for AllocVec<T> {
+    fn drop(&mut self) {
+        unsafe { self.release() }
+    }
+}
+
+impl<T: Copy> Deref for AllocVec<T> {
+    type Target = [T];
+
+    #[inline(always)]
+    fn deref(&self) -> &[T] {
+        unsafe { core::slice::from_raw_parts(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<T: Copy> DerefMut for AllocVec<T> {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut [T] {
+        unsafe { core::slice::from_raw_parts_mut(self.ptr.as_ptr(), self.len) }
+    }
+}
+
+impl<'a, T: Copy> IntoIterator for &'a AllocVec<T> {
+    type Item = &'a T;
+    type IntoIter = core::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
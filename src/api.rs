@@ -1,22 +1,97 @@
-use crate::compress::{CompressResult, Compressor as InternalCompressor, FlushMode};
-use crate::decompress::Decompressor as InternalDecompressor;
+use crate::allocator::Allocator;
+use crate::common::{GZIP_MIN_HEADER_SIZE, GZIP_MIN_OVERHEAD, GzipHeader, ZLIB_MIN_OVERHEAD};
+use crate::compress::{
+    AutoLevel, AutoLevelState, CompressResult, CompressionStats, Compressor as InternalCompressor,
+    FlushMode, Format, MAX_COMPRESSION_LEVEL, Parallelism,
+};
+use crate::decompress::{
+    ChecksumVerification, Decompressor as InternalDecompressor, DeflateVariant,
+};
 use std::io::{self};
+use std::sync::Arc;
 
 pub struct Compressor {
     inner: InternalCompressor,
+    auto_level: Option<AutoLevelState>,
 }
 
 impl Compressor {
     pub fn new(level: i32) -> io::Result<Self> {
-        if !(0..=12).contains(&level) {
+        Ok(Self {
+            inner: InternalCompressor::new(Self::check_level(level)?),
+            auto_level: None,
+        })
+    }
+
+    /// Like [`new`](Self::new), with working memory from `allocator`; see
+    /// [`crate::compress::Compressor::with_allocator`]. Fails with
+    /// [`io::ErrorKind::OutOfMemory`] if `allocator` cannot provide it.
+    pub fn with_allocator(level: i32, allocator: Arc<dyn Allocator>) -> io::Result<Self> {
+        let level = Self::check_level(level)?;
+        Ok(Self {
+            inner: InternalCompressor::try_with_allocator(level, allocator)
+                .map_err(|e| io::Error::new(io::ErrorKind::OutOfMemory, e))?,
+            auto_level: None,
+        })
+    }
+
+    fn check_level(level: i32) -> io::Result<usize> {
+        if !(0..=MAX_COMPRESSION_LEVEL as i32).contains(&level) {
             return Err(io::Error::new(
                 io::ErrorKind::InvalidInput,
-                "Compression level must be between 0 and 12",
+                format!(
+                    "Compression level must be between 0 and {}",
+                    MAX_COMPRESSION_LEVEL
+                ),
             ));
         }
-        Ok(Self {
-            inner: InternalCompressor::new(level as usize),
-        })
+        Ok(level as usize)
+    }
+
+    /// Sets how large inputs are split over threads; see [`Parallelism`].
+    pub fn set_parallelism(&mut self, parallelism: Parallelism) {
+        self.inner.set_parallelism(parallelism);
+    }
+
+    /// See [`crate::compress::Compressor::set_chunk_priming`]: on by default; turn it off when the
+    /// parallel chunks of large inputs must decode independently.
+    pub fn set_chunk_priming(&mut self, enabled: bool) {
+        self.inner.set_chunk_priming(enabled);
+    }
+
+    /// See [`crate::compress::Compressor::set_incompressible_probe`]: on by default from
+    /// level 6; turn it off to always search for matches.
+    pub fn set_incompressible_probe(&mut self, enabled: bool) {
+        self.inner.set_incompressible_probe(enabled);
+    }
+
+    /// Sets whether each compression call gathers [`CompressionStats`]; see
+    /// [`crate::compress::Compressor::set_collect_stats`]. Off by default.
+    pub fn set_collect_stats(&mut self, enabled: bool) {
+        self.inner.set_collect_stats(enabled);
+    }
+
+    /// The stats of the last compression call, if enabled.
+    pub fn stats(&self) -> Option<&CompressionStats> {
+        self.inner.stats()
+    }
+
+    /// Chooses the level from the data with `auto` (see [`AutoLevel`]): on the first call
+    /// that compresses, and again once `auto.recheck_interval()` bytes have been compressed
+    /// since the last choice. The level given to [`new`](Self::new) is replaced.
+    pub fn set_auto_level(&mut self, auto: AutoLevel) {
+        self.auto_level = Some(AutoLevelState::new(auto));
+    }
+
+    /// The level the next call compresses at, unless an [`AutoLevel`] chooses another.
+    pub fn level(&self) -> usize {
+        self.inner.compression_level
+    }
+
+    fn update_auto_level(&mut self, data: &[u8]) {
+        if let Some(level) = self.auto_level.as_mut().and_then(|a| a.level_for(data)) {
+            self.inner.set_level(level);
+        }
     }
 
     pub fn compress_deflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
@@ -56,16 +131,46 @@ impl Compressor {
         })
     }
 
+    /// Compresses `data` into a gzip member whose header records `header.mtime` and
+    /// `header.filename`.
+    pub fn compress_gzip_with_header(
+        &mut self,
+        data: &[u8],
+        header: &GzipHeader,
+    ) -> io::Result<Vec<u8>> {
+        let bound = self
+            .gzip_compress_bound(data.len())
+            .saturating_add(header.encoded_len() - GZIP_MIN_HEADER_SIZE);
+        self.compress_helper(data, bound, |c, data, out| {
+            c.compress_gzip_with_header(data, out, header)
+        })
+    }
+
+    /// The exact length of what [`compress_deflate`](Self::compress_deflate),
+    /// [`compress_zlib`](Self::compress_zlib) or [`compress_gzip`](Self::compress_gzip)
+    /// return for `data` at [`level`](Self::level), without allocating the output. It
+    /// costs about as much as compressing; see
+    /// [`crate::compress::Compressor::estimate_compressed_size`].
+    pub fn estimate_compressed_size(&mut self, data: &[u8], format: Format) -> usize {
+        self.inner.estimate_compressed_size(data, format)
+    }
+
+    /// A cheaper approximation of [`estimate_compressed_size`](Self::estimate_compressed_size),
+    /// typically within a few percent.
+    pub fn approximate_compressed_size(&mut self, data: &[u8], format: Format) -> usize {
+        self.inner.approximate_compressed_size(data, format)
+    }
+
     pub fn deflate_compress_bound(&mut self, size: usize) -> usize {
-        InternalCompressor::deflate_compress_bound(size)
+        self.inner.deflate_bound(size)
     }
 
     pub fn zlib_compress_bound(&mut self, size: usize) -> usize {
-        InternalCompressor::zlib_compress_bound(size)
+        ZLIB_MIN_OVERHEAD.saturating_add(self.inner.deflate_bound(size))
     }
 
     pub fn gzip_compress_bound(&mut self, size: usize) -> usize {
-        InternalCompressor::gzip_compress_bound(size)
+        GZIP_MIN_OVERHEAD.saturating_add(self.inner.deflate_bound(size))
     }
 
     fn compress_helper<F>(&mut self, data: &[u8], bound: usize, f: F) -> io::Result<Vec<u8>>
@@ -76,6 +181,7 @@ impl Compressor {
             &mut [std::mem::MaybeUninit<u8>],
         ) -> (CompressResult, usize),
     {
+        self.update_auto_level(data);
         let mut output = Vec::new();
         output.try_reserve_exact(bound).map_err(io::Error::other)?;
 
@@ -94,6 +200,7 @@ impl Compressor {
                 Ok(output)
             }
             CompressResult::InsufficientSpace => Err(io::Error::other("Insufficient space")),
+            CompressResult::OutOfMemory => Err(io::ErrorKind::OutOfMemory.into()),
         }
     }
 
@@ -117,6 +224,7 @@ impl Compressor {
                 "Input and output buffers overlap",
             ));
         }
+        self.update_auto_level(data);
         let out_uninit = unsafe {
             std::slice::from_raw_parts_mut(
                 output.as_mut_ptr() as *mut std::mem::MaybeUninit<u8>,
@@ -124,10 +232,10 @@ impl Compressor {
             )
         };
         let (res, size) = f(&mut self.inner, data, out_uninit);
-        if res == CompressResult::Success {
-            Ok(size)
-        } else {
-            Err(io::Error::other(error_msg))
+        match res {
+            CompressResult::Success => Ok(size),
+            CompressResult::InsufficientSpace => Err(io::Error::other(error_msg)),
+            CompressResult::OutOfMemory => Err(io::ErrorKind::OutOfMemory.into()),
         }
     }
 }
@@ -161,6 +269,20 @@ impl Decompressor {
         self.limit_ratio = ratio;
     }
 
+    /// Sets how much of gzip and zlib trailers to verify; see
+    /// [`ChecksumVerification`]. Anything but the default `Full` is unsafe for
+    /// untrusted input.
+    pub fn set_checksum_verification(&mut self, verification: ChecksumVerification) {
+        self.inner.set_checksum_verification(verification);
+    }
+
+    /// Selects standard deflate or Deflate64; see [`DeflateVariant`]. Deflate64 streams
+    /// can expand far beyond the default size-to-input limit, which
+    /// [`set_limit_ratio`](Self::set_limit_ratio) may need to raise.
+    pub fn set_variant(&mut self, variant: DeflateVariant) {
+        self.inner.set_variant(variant);
+    }
+
     pub fn decompress_deflate(&mut self, data: &[u8], expected_size: usize) -> io::Result<Vec<u8>> {
         self.decompress_helper(data, expected_size, |d, data, out| unsafe {
             d.decompress_uninit(data, out)
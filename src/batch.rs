@@ -1,9 +1,10 @@
-use crate::compress::{CompressResult, Compressor};
-use crate::decompress::{DecompressResult, Decompressor};
+use crate::compress::{CompressResult, Compressor, FlushMode, Parallelism};
+use crate::decompress::{ChecksumVerification, DecompressResult, Decompressor};
 use rayon::prelude::*;
 
 pub struct BatchCompressor {
     level: usize,
+    parallelism: Parallelism,
     #[cfg(feature = "cuda")]
     cuda_compressor: std::sync::OnceLock<Option<crate::batch_cuda::CudaBatchCompressor>>,
 }
@@ -12,11 +13,43 @@ impl BatchCompressor {
     pub fn new(level: usize) -> Self {
         Self {
             level,
+            parallelism: Parallelism::default(),
             #[cfg(feature = "cuda")]
             cuda_compressor: std::sync::OnceLock::new(),
         }
     }
 
+    /// Sets the thread pool the batch runs on, and how each input is split; see
+    /// [`Parallelism`]. A single-threaded configuration compresses the inputs one after
+    /// another on the calling thread.
+    pub fn set_parallelism(&mut self, parallelism: Parallelism) {
+        self.parallelism = parallelism;
+    }
+
+    fn new_compressor(&self) -> Compressor {
+        let mut compressor = Compressor::new(self.level);
+        compressor.set_parallelism(self.parallelism.clone());
+        compressor
+    }
+
+    fn compress_one(compressor: &mut Compressor, buffer: &mut Vec<u8>, input: &[u8]) -> Vec<u8> {
+        let bound = compressor.deflate_bound(input.len());
+        buffer.clear();
+        buffer.reserve(bound);
+        let buf_uninit = buffer.spare_capacity_mut();
+        let buf_slice = &mut buf_uninit[..bound];
+
+        let (res, size, _) = compressor.compress(input, buf_slice, FlushMode::Finish);
+        if res == CompressResult::Success {
+            unsafe {
+                buffer.set_len(size);
+            }
+            buffer.to_vec()
+        } else {
+            Vec::new()
+        }
+    }
+
     pub fn compress_batch(&self, inputs: &[&[u8]]) -> Vec<Vec<u8>> {
         #[cfg(feature = "cuda")]
         {
@@ -31,34 +64,30 @@ impl BatchCompressor {
             }
         }
 
-        inputs
-            .par_iter()
-            .map_init(
-                || (Compressor::new(self.level), Vec::new()),
-                |(compressor, buffer), &input| {
-                    let bound = Compressor::deflate_compress_bound(input.len());
-                    buffer.clear();
-                    buffer.reserve(bound);
-                    let buf_uninit = buffer.spare_capacity_mut();
-                    let buf_slice = &mut buf_uninit[..bound];
-
-                    let (res, size, _) =
-                        compressor.compress(input, buf_slice, crate::compress::FlushMode::Finish);
-                    if res == CompressResult::Success {
-                        unsafe {
-                            buffer.set_len(size);
-                        }
-                        buffer.to_vec()
-                    } else {
-                        Vec::new()
-                    }
-                },
-            )
-            .collect()
+        if self.parallelism.is_single_threaded() {
+            let mut compressor = self.new_compressor();
+            let mut buffer = Vec::new();
+            return inputs
+                .iter()
+                .map(|input| Self::compress_one(&mut compressor, &mut buffer, input))
+                .collect();
+        }
+
+        self.parallelism.install(|| {
+            inputs
+                .par_iter()
+                .map_init(
+                    || (self.new_compressor(), Vec::new()),
+                    |(compressor, buffer), input| Self::compress_one(compressor, buffer, input),
+                )
+                .collect()
+        })
     }
 }
 
-pub struct BatchDecompressor;
+pub struct BatchDecompressor {
+    verification: ChecksumVerification,
+}
 
 impl Default for BatchDecompressor {
     fn default() -> Self {
@@ -68,7 +97,16 @@ impl Default for BatchDecompressor {
 
 impl BatchDecompressor {
     pub fn new() -> Self {
-        Self
+        Self {
+            verification: ChecksumVerification::Full,
+        }
+    }
+
+    /// Sets how much of gzip and zlib trailers to verify; see
+    /// [`ChecksumVerification`]. Anything but the default `Full` is unsafe for
+    /// untrusted input.
+    pub fn set_checksum_verification(&mut self, verification: ChecksumVerification) {
+        self.verification = verification;
     }
 
     pub fn decompress_batch(
@@ -76,11 +114,49 @@ impl BatchDecompressor {
         inputs: &[&[u8]],
         max_out_sizes: &[usize],
     ) -> Vec<Option<Vec<u8>>> {
+        self.decompress_batch_with(inputs, max_out_sizes, |d, input, out| {
+            d.decompress(input, out)
+        })
+    }
+
+    pub fn decompress_batch_zlib(
+        &self,
+        inputs: &[&[u8]],
+        max_out_sizes: &[usize],
+    ) -> Vec<Option<Vec<u8>>> {
+        self.decompress_batch_with(inputs, max_out_sizes, |d, input, out| {
+            d.decompress_zlib(input, out)
+        })
+    }
+
+    pub fn decompress_batch_gzip(
+        &self,
+        inputs: &[&[u8]],
+        max_out_sizes: &[usize],
+    ) -> Vec<Option<Vec<u8>>> {
+        self.decompress_batch_with(inputs, max_out_sizes, |d, input, out| {
+            d.decompress_gzip(input, out)
+        })
+    }
+
+    fn decompress_batch_with<F>(
+        &self,
+        inputs: &[&[u8]],
+        max_out_sizes: &[usize],
+        f: F,
+    ) -> Vec<Option<Vec<u8>>>
+    where
+        F: Fn(&mut Decompressor, &[u8], &mut [u8]) -> (DecompressResult, usize, usize) + Sync,
+    {
         inputs
             .par_iter()
             .zip(max_out_sizes.par_iter())
             .map_init(
-                || (Decompressor::new(), Vec::new()),
+                || {
+                    let mut decompressor = Decompressor::new();
+                    decompressor.set_checksum_verification(self.verification);
+                    (decompressor, Vec::new())
+                },
                 |(decompressor, buffer), (&input, &max_size)| {
                     if buffer.capacity() < max_size {
                         buffer.reserve(max_size.saturating_sub(buffer.len()));
@@ -89,7 +165,7 @@ impl BatchDecompressor {
                         buffer.set_len(max_size);
                     }
 
-                    let (res, _, size) = decompressor.decompress(input, buffer);
+                    let (res, _, size) = f(decompressor, input, buffer);
                     if res == DecompressResult::Success {
                         Some(buffer[..size].to_vec())
                     } else {
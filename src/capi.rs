@@ -0,0 +1,569 @@
+//! The upstream libdeflate C API, declared in `include/libdeflate.h`, which `build.rs`
+//! generates from this file with cbindgen when the `capi` feature is on.
+//!
+//! The functions are always compiled so that Rust code and tests can call them; they are
+//! exported under their C names only with the `capi` feature, because the C libdeflate that
+//! the `libdeflater` comparison builds link defines the same symbols.
+//!
+//! The compressor and decompressor handles are allocated with the installed `malloc_func`
+//! and released with the matching `free_func`. A compressor's working buffers come from the
+//! same pair; a decompressor has none.
+
+use crate::allocator::CallbackAllocator;
+use crate::common::{GZIP_MIN_OVERHEAD, ZLIB_MIN_OVERHEAD};
+use crate::compress::{CompressResult, Compressor, FlushMode, MAX_COMPRESSION_LEVEL};
+use crate::decompress::{DecompressResult, Decompressor};
+use std::ffi::{c_int, c_void};
+use std::mem::{MaybeUninit, align_of, size_of};
+use std::ptr;
+use std::sync::{Arc, RwLock};
+
+pub use crate::allocator::{FreeFunc, MallocFunc};
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+static ALLOCATOR: RwLock<(MallocFunc, FreeFunc)> = RwLock::new((malloc, free));
+
+/// `struct libdeflate_options`: optional settings for a compressor or decompressor.
+#[repr(C)]
+pub struct LibdeflateOptions {
+    /// Must be `sizeof(struct libdeflate_options)`.
+    pub sizeof_options: usize,
+    /// An allocator for this handle, used instead of the global one. Set both functions
+    /// or neither.
+    // Spelled out rather than `MallocFunc` and `FreeFunc`, which cbindgen would not see
+    // through inside an `Option`.
+    pub malloc_func: Option<unsafe extern "C" fn(usize) -> *mut c_void>,
+    pub free_func: Option<unsafe extern "C" fn(*mut c_void)>,
+}
+
+/// `enum libdeflate_result`: the outcome of a decompression.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LibdeflateResult {
+    /// The data decompressed successfully.
+    Success = 0,
+    /// The data is invalid, corrupt or truncated.
+    BadData = 1,
+    /// `actual_out_nbytes_ret` was null, and the data decompressed to fewer than
+    /// `out_nbytes_avail` bytes.
+    ShortOutput = 2,
+    /// The data decompresses to more than `out_nbytes_avail` bytes.
+    InsufficientSpace = 3,
+}
+
+/// `struct libdeflate_compressor`, opaque to C.
+pub struct LibdeflateCompressor {
+    inner: Compressor,
+    free_func: FreeFunc,
+}
+
+/// `struct libdeflate_decompressor`, opaque to C.
+pub struct LibdeflateDecompressor {
+    inner: Decompressor,
+    free_func: FreeFunc,
+}
+
+// malloc() only promises alignment for the fundamental types.
+const _: () = assert!(align_of::<LibdeflateCompressor>() <= align_of::<u64>());
+const _: () = assert!(align_of::<LibdeflateDecompressor>() <= align_of::<u64>());
+
+/// Picks the per-handle allocator from `options`, or the global one. `None` if `options`
+/// has the wrong size or names only one of the two functions.
+unsafe fn allocator(options: *const LibdeflateOptions) -> Option<(MallocFunc, FreeFunc)> {
+    if !options.is_null() {
+        let options = &*options;
+        if options.sizeof_options != size_of::<LibdeflateOptions>() {
+            return None;
+        }
+        match (options.malloc_func, options.free_func) {
+            (Some(m), Some(f)) => return Some((m, f)),
+            (None, None) => {}
+            _ => return None,
+        }
+    }
+    Some(*ALLOCATOR.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Allocates a handle with `malloc_func`, then fills it with `make`. Null if either runs
+/// out of memory.
+unsafe fn alloc_handle<T>(
+    malloc_func: MallocFunc,
+    free_func: FreeFunc,
+    make: impl FnOnce() -> Option<T>,
+) -> *mut T {
+    let p = malloc_func(size_of::<T>()) as *mut T;
+    if p.is_null() {
+        return p;
+    }
+    match make() {
+        Some(value) => {
+            p.write(value);
+            p
+        }
+        None => {
+            free_func(p as *mut c_void);
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn in_slice<'a>(input: *const c_void, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(input as *const u8, len)
+    }
+}
+
+unsafe fn out_slice<'a>(output: *mut c_void, len: usize) -> &'a mut [MaybeUninit<u8>] {
+    if len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(output as *mut MaybeUninit<u8>, len)
+    }
+}
+
+/// Installs the allocator used for handles created without their own.
+///
+/// # Safety
+///
+/// `malloc_func` and `free_func` must behave like `malloc()` and `free()`. Existing handles
+/// keep the `free_func` they were allocated with.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_set_memory_allocator(
+    malloc_func: unsafe extern "C" fn(usize) -> *mut c_void,
+    free_func: unsafe extern "C" fn(*mut c_void),
+) {
+    *ALLOCATOR.write().unwrap_or_else(|e| e.into_inner()) = (malloc_func, free_func);
+}
+
+/// Returns a compressor for `compression_level` (0 to [`MAX_COMPRESSION_LEVEL`]), or null
+/// if the level is out of range or memory runs out.
+///
+/// # Safety
+///
+/// The result must be released with [`libdeflate_free_compressor`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_alloc_compressor(
+    compression_level: c_int,
+) -> *mut LibdeflateCompressor {
+    libdeflate_alloc_compressor_ex(compression_level, ptr::null())
+}
+
+/// # Safety
+///
+/// `options` must be null or point to a `LibdeflateOptions`. The result must be released
+/// with [`libdeflate_free_compressor`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_alloc_compressor_ex(
+    compression_level: c_int,
+    options: *const LibdeflateOptions,
+) -> *mut LibdeflateCompressor {
+    if !(0..=MAX_COMPRESSION_LEVEL as c_int).contains(&compression_level) {
+        return ptr::null_mut();
+    }
+    let Some((malloc_func, free_func)) = allocator(options) else {
+        return ptr::null_mut();
+    };
+    alloc_handle(malloc_func, free_func, || {
+        let inner = Compressor::try_with_allocator(
+            compression_level as usize,
+            Arc::new(CallbackAllocator::new(malloc_func, free_func)),
+        );
+        Some(LibdeflateCompressor {
+            inner: inner.ok()?,
+            free_func,
+        })
+    })
+}
+
+unsafe fn compress_with(
+    compressor: *mut LibdeflateCompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    f: impl FnOnce(&mut Compressor, &[u8], &mut [MaybeUninit<u8>]) -> (CompressResult, usize),
+) -> usize {
+    let compressor = &mut (*compressor).inner;
+    let input = in_slice(input, in_nbytes);
+    let output = out_slice(output, out_nbytes_avail);
+    match f(compressor, input, output) {
+        (CompressResult::Success, size) => size,
+        _ => 0,
+    }
+}
+
+/// Compresses to a raw deflate stream. Returns the compressed size, or 0 if it does not fit
+/// or the compressor's allocator runs out of memory.
+///
+/// # Safety
+///
+/// `compressor` must come from [`libdeflate_alloc_compressor`]; `input` and `output` must be
+/// valid for `in_nbytes` and `out_nbytes_avail` bytes.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_deflate_compress(
+    compressor: *mut LibdeflateCompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+) -> usize {
+    compress_with(
+        compressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        |c, input, output| {
+            let (res, size, _) = c.compress(input, output, FlushMode::Finish);
+            (res, size)
+        },
+    )
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_compress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_zlib_compress(
+    compressor: *mut LibdeflateCompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+) -> usize {
+    compress_with(
+        compressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        |c, input, output| c.compress_zlib(input, output),
+    )
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_compress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_gzip_compress(
+    compressor: *mut LibdeflateCompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+) -> usize {
+    compress_with(
+        compressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        |c, input, output| c.compress_gzip(input, output),
+    )
+}
+
+/// The largest raw deflate output for `in_nbytes` bytes of input. A null `compressor` gives
+/// a bound that holds for every compressor.
+///
+/// # Safety
+///
+/// `compressor` must be null or come from [`libdeflate_alloc_compressor`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_deflate_compress_bound(
+    compressor: *mut LibdeflateCompressor,
+    in_nbytes: usize,
+) -> usize {
+    match compressor.as_ref() {
+        Some(c) => c.inner.deflate_bound(in_nbytes),
+        None => Compressor::deflate_compress_bound(in_nbytes),
+    }
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_compress_bound`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_zlib_compress_bound(
+    compressor: *mut LibdeflateCompressor,
+    in_nbytes: usize,
+) -> usize {
+    ZLIB_MIN_OVERHEAD.saturating_add(libdeflate_deflate_compress_bound(compressor, in_nbytes))
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_compress_bound`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_gzip_compress_bound(
+    compressor: *mut LibdeflateCompressor,
+    in_nbytes: usize,
+) -> usize {
+    GZIP_MIN_OVERHEAD.saturating_add(libdeflate_deflate_compress_bound(compressor, in_nbytes))
+}
+
+/// # Safety
+///
+/// `compressor` must be null or come from [`libdeflate_alloc_compressor`], and must not be
+/// used afterwards.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_free_compressor(compressor: *mut LibdeflateCompressor) {
+    if !compressor.is_null() {
+        let free_func = (*compressor).free_func;
+        ptr::drop_in_place(compressor);
+        free_func(compressor as *mut c_void);
+    }
+}
+
+/// Returns a decompressor, or null.
+///
+/// # Safety
+///
+/// The result must be released with [`libdeflate_free_decompressor`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_alloc_decompressor() -> *mut LibdeflateDecompressor {
+    libdeflate_alloc_decompressor_ex(ptr::null())
+}
+
+/// # Safety
+///
+/// `options` must be null or point to a `LibdeflateOptions`. The result must be released
+/// with [`libdeflate_free_decompressor`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_alloc_decompressor_ex(
+    options: *const LibdeflateOptions,
+) -> *mut LibdeflateDecompressor {
+    let Some((malloc_func, free_func)) = allocator(options) else {
+        return ptr::null_mut();
+    };
+    alloc_handle(malloc_func, free_func, || {
+        Some(LibdeflateDecompressor {
+            inner: Decompressor::new(),
+            free_func,
+        })
+    })
+}
+
+#[allow(clippy::too_many_arguments)]
+unsafe fn decompress_with(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_in_nbytes_ret: *mut usize,
+    actual_out_nbytes_ret: *mut usize,
+    f: impl FnOnce(&mut Decompressor, &[u8], &mut [MaybeUninit<u8>]) -> (DecompressResult, usize, usize),
+) -> LibdeflateResult {
+    let decompressor = &mut (*decompressor).inner;
+    let input = in_slice(input, in_nbytes);
+    let output = out_slice(output, out_nbytes_avail);
+    let (res, in_consumed, out_produced) = f(decompressor, input, output);
+    match res {
+        DecompressResult::Success => {
+            if !actual_in_nbytes_ret.is_null() {
+                *actual_in_nbytes_ret = in_consumed;
+            }
+            if !actual_out_nbytes_ret.is_null() {
+                *actual_out_nbytes_ret = out_produced;
+            } else if out_produced != out_nbytes_avail {
+                return LibdeflateResult::ShortOutput;
+            }
+            LibdeflateResult::Success
+        }
+        DecompressResult::ShortOutput => LibdeflateResult::ShortOutput,
+        DecompressResult::InsufficientSpace => LibdeflateResult::InsufficientSpace,
+        DecompressResult::BadData | DecompressResult::ShortInput => LibdeflateResult::BadData,
+    }
+}
+
+/// Decompresses a raw deflate stream. With a null `actual_out_nbytes_ret`, the stream must
+/// fill `out_nbytes_avail` bytes exactly.
+///
+/// # Safety
+///
+/// `decompressor` must come from [`libdeflate_alloc_decompressor`]; `input` and `output`
+/// must be valid for `in_nbytes` and `out_nbytes_avail` bytes; the result pointers must be
+/// null or writable.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_deflate_decompress(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    libdeflate_deflate_decompress_ex(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        ptr::null_mut(),
+        actual_out_nbytes_ret,
+    )
+}
+
+/// Like [`libdeflate_deflate_decompress`], also reporting the compressed size.
+///
+/// # Safety
+///
+/// As for [`libdeflate_deflate_decompress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_deflate_decompress_ex(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_in_nbytes_ret: *mut usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    decompress_with(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        actual_in_nbytes_ret,
+        actual_out_nbytes_ret,
+        |d, input, output| d.decompress_uninit(input, output),
+    )
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_decompress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_zlib_decompress(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    libdeflate_zlib_decompress_ex(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        ptr::null_mut(),
+        actual_out_nbytes_ret,
+    )
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_decompress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_zlib_decompress_ex(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_in_nbytes_ret: *mut usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    decompress_with(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        actual_in_nbytes_ret,
+        actual_out_nbytes_ret,
+        |d, input, output| d.decompress_zlib_uninit(input, output),
+    )
+}
+
+/// # Safety
+///
+/// As for [`libdeflate_deflate_decompress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_gzip_decompress(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    libdeflate_gzip_decompress_ex(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        ptr::null_mut(),
+        actual_out_nbytes_ret,
+    )
+}
+
+/// Decompresses the first gzip member of `input`.
+///
+/// # Safety
+///
+/// As for [`libdeflate_deflate_decompress`].
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_gzip_decompress_ex(
+    decompressor: *mut LibdeflateDecompressor,
+    input: *const c_void,
+    in_nbytes: usize,
+    output: *mut c_void,
+    out_nbytes_avail: usize,
+    actual_in_nbytes_ret: *mut usize,
+    actual_out_nbytes_ret: *mut usize,
+) -> LibdeflateResult {
+    decompress_with(
+        decompressor,
+        input,
+        in_nbytes,
+        output,
+        out_nbytes_avail,
+        actual_in_nbytes_ret,
+        actual_out_nbytes_ret,
+        |d, input, output| d.decompress_gzip_uninit(input, output),
+    )
+}
+
+/// # Safety
+///
+/// `decompressor` must be null or come from [`libdeflate_alloc_decompressor`], and must not
+/// be used afterwards.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_free_decompressor(decompressor: *mut LibdeflateDecompressor) {
+    if !decompressor.is_null() {
+        let free_func = (*decompressor).free_func;
+        ptr::drop_in_place(decompressor);
+        free_func(decompressor as *mut c_void);
+    }
+}
+
+/// Updates the Adler-32 checksum `adler` with `len` bytes; start from 1.
+///
+/// # Safety
+///
+/// `buffer` must be valid for `len` bytes.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_adler32(adler: u32, buffer: *const c_void, len: usize) -> u32 {
+    crate::adler32(adler, in_slice(buffer, len))
+}
+
+/// Updates the CRC-32 `crc` with `len` bytes; start from 0.
+///
+/// # Safety
+///
+/// `buffer` must be valid for `len` bytes.
+#[cfg_attr(feature = "capi", unsafe(no_mangle))]
+pub unsafe extern "C" fn libdeflate_crc32(crc: u32, buffer: *const c_void, len: usize) -> u32 {
+    crate::crc32(crc, in_slice(buffer, len))
+}
@@ -0,0 +1,219 @@
+use crate::adler32::adler32;
+use crate::crc32::crc32;
+use std::hash::Hasher;
+use std::io::{self, Read, Write};
+
+/// A running 32-bit checksum that can be fed incrementally.
+pub trait Checksum: Default {
+    /// Feeds more data into the checksum.
+    fn update(&mut self, data: &[u8]);
+    /// Returns the checksum of all data fed so far.
+    fn finalize(&self) -> u32;
+    /// Restarts the checksum as if no data had been fed.
+    fn reset(&mut self);
+}
+
+/// Incremental gzip CRC-32. Handles the pre- and post-inversion internally.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Crc32Hasher {
+    crc: u32,
+}
+
+impl Crc32Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resumes from the CRC-32 of some earlier data.
+    pub fn with_initial(crc: u32) -> Self {
+        Self { crc }
+    }
+
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) {
+        self.crc = crc32(self.crc, data);
+    }
+
+    #[inline]
+    pub fn finalize(&self) -> u32 {
+        self.crc
+    }
+
+    pub fn reset(&mut self) {
+        self.crc = 0;
+    }
+}
+
+/// Incremental zlib Adler-32.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Adler32Hasher {
+    adler: u32,
+}
+
+impl Default for Adler32Hasher {
+    fn default() -> Self {
+        Self { adler: 1 }
+    }
+}
+
+impl Adler32Hasher {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Resumes from the Adler-32 of some earlier data.
+    pub fn with_initial(adler: u32) -> Self {
+        Self { adler }
+    }
+
+    #[inline]
+    pub fn update(&mut self, data: &[u8]) {
+        self.adler = adler32(self.adler, data);
+    }
+
+    #[inline]
+    pub fn finalize(&self) -> u32 {
+        self.adler
+    }
+
+    pub fn reset(&mut self) {
+        self.adler = 1;
+    }
+}
+
+macro_rules! impl_checksum {
+    ($ty:ty) => {
+        impl Checksum for $ty {
+            #[inline]
+            fn update(&mut self, data: &[u8]) {
+                <$ty>::update(self, data)
+            }
+
+            #[inline]
+            fn finalize(&self) -> u32 {
+                <$ty>::finalize(self)
+            }
+
+            fn reset(&mut self) {
+                <$ty>::reset(self)
+            }
+        }
+
+        impl Hasher for $ty {
+            #[inline]
+            fn write(&mut self, bytes: &[u8]) {
+                self.update(bytes);
+            }
+
+            #[inline]
+            fn finish(&self) -> u64 {
+                self.finalize() as u64
+            }
+        }
+    };
+}
+
+impl_checksum!(Crc32Hasher);
+impl_checksum!(Adler32Hasher);
+
+/// Wraps a reader and checksums every byte read through it.
+pub struct ChecksumReader<R: Read, C: Checksum = Crc32Hasher> {
+    inner: R,
+    checksum: C,
+}
+
+impl<R: Read, C: Checksum> ChecksumReader<R, C> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            checksum: C::default(),
+        }
+    }
+
+    /// Returns the checksum of the data read so far.
+    pub fn checksum(&self) -> u32 {
+        self.checksum.finalize()
+    }
+
+    pub fn hasher_mut(&mut self) -> &mut C {
+        &mut self.checksum
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Reading directly from the inner reader bypasses the checksum.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    pub fn into_parts(self) -> (R, C) {
+        (self.inner, self.checksum)
+    }
+}
+
+impl<R: Read, C: Checksum> Read for ChecksumReader<R, C> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+/// Wraps a writer and checksums every byte the writer accepts.
+pub struct ChecksumWriter<W: Write, C: Checksum = Crc32Hasher> {
+    inner: W,
+    checksum: C,
+}
+
+impl<W: Write, C: Checksum> ChecksumWriter<W, C> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            checksum: C::default(),
+        }
+    }
+
+    /// Returns the checksum of the data written so far.
+    pub fn checksum(&self) -> u32 {
+        self.checksum.finalize()
+    }
+
+    pub fn hasher_mut(&mut self) -> &mut C {
+        &mut self.checksum
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Writing directly to the inner writer bypasses the checksum.
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    pub fn into_parts(self) -> (W, C) {
+        (self.inner, self.checksum)
+    }
+}
+
+impl<W: Write, C: Checksum> Write for ChecksumWriter<W, C> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.checksum.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
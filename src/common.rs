@@ -1,3 +1,5 @@
+use alloc::vec::Vec;
+
 pub const LIBDEFLATE_VERSION_MAJOR: u32 = 1;
 pub const LIBDEFLATE_VERSION_MINOR: u32 = 25;
 pub const LIBDEFLATE_VERSION_STRING: &str = "1.25";
@@ -8,6 +10,7 @@ pub const DEFLATE_BLOCKTYPE_DYNAMIC_HUFFMAN: u8 = 2;
 
 pub const DEFLATE_MIN_MATCH_LEN: usize = 3;
 pub const DEFLATE_MAX_MATCH_LEN: usize = 258;
+pub const DEFLATE64_MAX_MATCH_LEN: usize = 65538;
 
 pub const DEFLATE_MAX_MATCH_OFFSET: usize = 32768;
 pub const DEFLATE_WINDOW_ORDER: usize = 15;
@@ -65,6 +68,31 @@ pub const GZIP_XFL_SLOWEST_COMPRESSION: u8 = 0x02;
 pub const GZIP_XFL_FASTEST_COMPRESSION: u8 = 0x04;
 pub const GZIP_OS_UNKNOWN: u8 = 255;
 
+/// Optional metadata stored in a gzip member header.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct GzipHeader {
+    /// Modification time of the original file in seconds since the Unix epoch, or
+    /// [`GZIP_MTIME_UNAVAILABLE`].
+    pub mtime: u32,
+    /// Original file name (FNAME), without the terminating NUL byte.
+    pub filename: Option<Vec<u8>>,
+}
+
+impl GzipHeader {
+    /// Number of bytes the header occupies when written by the compressor.
+    pub fn encoded_len(&self) -> usize {
+        GZIP_MIN_HEADER_SIZE + self.filename_bytes().map_or(0, |name| name.len() + 1)
+    }
+
+    /// The file name as it is written: truncated at the first NUL byte, since FNAME is
+    /// NUL-terminated.
+    pub(crate) fn filename_bytes(&self) -> Option<&[u8]> {
+        self.filename
+            .as_deref()
+            .map(|name| name.split(|&b| b == 0).next().unwrap_or(name))
+    }
+}
+
 pub const MIN_BLOCK_LENGTH: usize = 5000;
 pub const SOFT_MAX_BLOCK_LENGTH: usize = 300000;
 pub const SEQ_STORE_LENGTH: usize = 50000;
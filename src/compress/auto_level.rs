@@ -0,0 +1,233 @@
+use super::{Compressor, MAX_COMPRESSION_LEVEL};
+use std::time::{Duration, Instant};
+
+/// Default candidates of [`AutoLevel`], from fastest to strongest.
+const DEFAULT_LEVELS: [usize; 5] = [1, 3, 6, 9, 12];
+/// Default number of input bytes trial-compressed at each candidate level.
+const DEFAULT_SAMPLE_SIZE: usize = 256 * 1024;
+/// Default number of input bytes a stream compresses between re-checks.
+const DEFAULT_RECHECK_INTERVAL: usize = 16 * 1024 * 1024;
+/// Inputs larger than the sample size are sampled at this many evenly spaced places.
+const SAMPLE_PIECES: usize = 4;
+
+/// Picks a compression level for the data at hand by trial-compressing a sample of it at
+/// each candidate level with [`Compressor::compress_to_size`], which computes the size
+/// without writing output.
+///
+/// The chosen level is the lowest candidate that reaches the ratio goal at the required
+/// throughput, or, failing that or without a ratio goal, the highest candidate that runs
+/// at the required throughput. When no candidate is fast enough, the lowest is chosen.
+/// Throughput is measured on the calling thread, so it is per core.
+///
+/// [`api::Compressor::set_auto_level`](crate::api::Compressor::set_auto_level) and
+/// [`DeflateEncoder::with_auto_level`](crate::stream::DeflateEncoder::with_auto_level)
+/// choose again after every [`recheck_interval`](Self::recheck_interval) bytes.
+#[derive(Clone, Debug)]
+pub struct AutoLevel {
+    levels: Vec<usize>,
+    min_throughput: Option<f64>,
+    target_ratio: Option<f64>,
+    sample_size: usize,
+    recheck_interval: usize,
+}
+
+/// How one candidate level did on a sample; see [`AutoLevel::evaluate`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LevelTrial {
+    pub level: usize,
+    /// Bytes of input in the sample.
+    pub input_size: usize,
+    /// Bytes of raw DEFLATE output for the sample.
+    pub compressed_size: usize,
+    pub elapsed: Duration,
+}
+
+impl LevelTrial {
+    /// Compressed size over input size; 1.0 for an empty sample.
+    pub fn ratio(&self) -> f64 {
+        if self.input_size == 0 {
+            1.0
+        } else {
+            self.compressed_size as f64 / self.input_size as f64
+        }
+    }
+
+    /// Input bytes per second.
+    pub fn throughput(&self) -> f64 {
+        self.input_size as f64 / self.elapsed.as_secs_f64().max(1e-9)
+    }
+}
+
+impl Default for AutoLevel {
+    fn default() -> Self {
+        Self {
+            levels: DEFAULT_LEVELS.to_vec(),
+            min_throughput: None,
+            target_ratio: None,
+            sample_size: DEFAULT_SAMPLE_SIZE,
+            recheck_interval: DEFAULT_RECHECK_INTERVAL,
+        }
+    }
+}
+
+impl AutoLevel {
+    /// Candidates 1, 3, 6, 9 and 12 with no goals, which picks level 12; set a
+    /// throughput target, a ratio goal or both.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only chooses levels that compress at least `bytes_per_second` of input per core.
+    pub fn with_min_throughput(mut self, bytes_per_second: f64) -> Self {
+        self.min_throughput = Some(bytes_per_second);
+        self
+    }
+
+    /// Stops at the lowest level whose compressed size is at most `ratio` times the input
+    /// size.
+    pub fn with_target_ratio(mut self, ratio: f64) -> Self {
+        self.target_ratio = Some(ratio);
+        self
+    }
+
+    /// Sets the candidate levels. They are sorted, and levels above
+    /// [`MAX_COMPRESSION_LEVEL`] are clamped to it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `levels` is empty.
+    pub fn with_levels(mut self, levels: &[usize]) -> Self {
+        assert!(
+            !levels.is_empty(),
+            "AutoLevel needs at least one candidate level"
+        );
+        self.levels = levels
+            .iter()
+            .map(|&level| level.min(MAX_COMPRESSION_LEVEL))
+            .collect();
+        self.levels.sort_unstable();
+        self.levels.dedup();
+        self
+    }
+
+    /// Sets how many bytes of input are trial-compressed at each level (at least 1).
+    pub fn with_sample_size(mut self, size: usize) -> Self {
+        self.sample_size = size.max(1);
+        self
+    }
+
+    /// Sets how many bytes a stream compresses before the level is chosen again.
+    pub fn with_recheck_interval(mut self, bytes: usize) -> Self {
+        self.recheck_interval = bytes;
+        self
+    }
+
+    pub fn levels(&self) -> &[usize] {
+        &self.levels
+    }
+
+    pub fn min_throughput(&self) -> Option<f64> {
+        self.min_throughput
+    }
+
+    pub fn target_ratio(&self) -> Option<f64> {
+        self.target_ratio
+    }
+
+    pub fn sample_size(&self) -> usize {
+        self.sample_size
+    }
+
+    pub fn recheck_interval(&self) -> usize {
+        self.recheck_interval
+    }
+
+    /// Trial-compresses a sample of `data` at each candidate level, in the order of
+    /// [`levels`](Self::levels). Inputs up to the sample size are compressed whole; larger
+    /// ones are sampled at a few evenly spaced places, each compressed on its own.
+    pub fn evaluate(&self, data: &[u8]) -> Vec<LevelTrial> {
+        let pieces = self.sample(data);
+        self.levels
+            .iter()
+            .map(|&level| {
+                let mut compressor = Compressor::new(level);
+                let mut trial = LevelTrial {
+                    level,
+                    input_size: 0,
+                    compressed_size: 0,
+                    elapsed: Duration::ZERO,
+                };
+                for piece in &pieces {
+                    let start = Instant::now();
+                    trial.compressed_size += compressor.compress_to_size(piece, true);
+                    trial.elapsed += start.elapsed();
+                    trial.input_size += piece.len();
+                }
+                trial
+            })
+            .collect()
+    }
+
+    /// The level to compress `data` at; see [`AutoLevel`]. An empty `data` gets the highest
+    /// candidate without any trials.
+    pub fn choose(&self, data: &[u8]) -> usize {
+        if data.is_empty() {
+            return *self.levels.last().unwrap();
+        }
+        let trials = self.evaluate(data);
+        let fast_enough: Vec<&LevelTrial> = trials
+            .iter()
+            .filter(|t| self.min_throughput.is_none_or(|min| t.throughput() >= min))
+            .collect();
+        if let Some(target) = self.target_ratio
+            && let Some(t) = fast_enough.iter().find(|t| t.ratio() <= target)
+        {
+            return t.level;
+        }
+        fast_enough.last().map_or(self.levels[0], |t| t.level)
+    }
+
+    fn sample<'a>(&self, data: &'a [u8]) -> Vec<&'a [u8]> {
+        if data.len() <= self.sample_size {
+            return vec![data];
+        }
+        let piece_len = self.sample_size.div_ceil(SAMPLE_PIECES);
+        let stride = (data.len() - piece_len) / (SAMPLE_PIECES - 1);
+        (0..SAMPLE_PIECES)
+            .map(|i| &data[i * stride..i * stride + piece_len])
+            .collect()
+    }
+}
+
+/// An [`AutoLevel`] in use by a compressor that is fed input over time.
+#[derive(Debug)]
+pub(crate) struct AutoLevelState {
+    auto: AutoLevel,
+    /// Bytes compressed since the level was last chosen; `None` before the first choice.
+    since_check: Option<usize>,
+}
+
+impl AutoLevelState {
+    pub(crate) fn new(auto: AutoLevel) -> Self {
+        Self {
+            auto,
+            since_check: None,
+        }
+    }
+
+    /// Called with each input about to be compressed; returns the level to switch to when
+    /// a choice is due.
+    pub(crate) fn level_for(&mut self, data: &[u8]) -> Option<usize> {
+        let due = self
+            .since_check
+            .is_none_or(|n| n >= self.auto.recheck_interval);
+        let level = due.then(|| {
+            self.since_check = Some(0);
+            self.auto.choose(data)
+        });
+        if let Some(n) = &mut self.since_check {
+            *n += data.len();
+        }
+        level
+    }
+}
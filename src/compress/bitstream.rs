@@ -1,10 +1,14 @@
-use std::mem::MaybeUninit;
+use core::mem::MaybeUninit;
 
 pub struct Bitstream<'a> {
     pub output: &'a mut [MaybeUninit<u8>],
     pub out_idx: usize,
     pub bitbuf: u64,
     pub bitcount: u32,
+    /// Bytes already dropped from the front of `output` by [`recycle`](Self::recycle).
+    pub discarded: usize,
+    /// Total capacity of a counting bitstream; see [`counting`](Self::counting).
+    limit: Option<usize>,
 }
 
 impl<'a> Bitstream<'a> {
@@ -14,9 +18,46 @@ impl<'a> Bitstream<'a> {
             out_idx: 0,
             bitbuf: 0,
             bitcount: 0,
+            discarded: 0,
+            limit: None,
         }
     }
 
+    /// A bitstream that behaves as if writing into `limit` bytes, but only keeps what was
+    /// written since the last [`recycle`](Self::recycle) in `scratch`, so that the size of
+    /// a long stream can be measured without room for all of it.
+    pub fn counting(scratch: &'a mut [MaybeUninit<u8>], limit: usize) -> Self {
+        let room = limit.min(scratch.len());
+        Self {
+            limit: Some(limit),
+            ..Self::new(&mut scratch[..room])
+        }
+    }
+
+    /// For a counting bitstream, drops the whole bytes written so far and starts over at
+    /// the front of `output`, which shrinks when less than its length remains of the limit.
+    /// Nothing written before may be read or rewritten afterwards. A no-op otherwise.
+    #[inline]
+    pub fn recycle(&mut self) {
+        if let Some(limit) = self.limit {
+            self.discarded += self.out_idx;
+            self.out_idx = 0;
+            let room = (limit - self.discarded).min(self.output.len());
+            let output = core::mem::take(&mut self.output);
+            self.output = &mut output[..room];
+        }
+    }
+
+    /// Bytes written in all, including those dropped by [`recycle`](Self::recycle).
+    pub fn total_out(&self) -> usize {
+        self.discarded + self.out_idx
+    }
+
+    /// Bits written in all, including those still in `bitbuf`.
+    pub fn bits_written(&self) -> usize {
+        self.total_out() * 8 + self.bitcount as usize
+    }
+
     #[inline(always)]
     pub fn write_bits(&mut self, bits: u32, count: u32) -> bool {
         if count == 0 {
@@ -56,7 +97,7 @@ impl<'a> Bitstream<'a> {
 
         if new_bitcount >= 32 {
             let bitbuf = self.bitbuf | ((bits as u64) << bitcount);
-            std::ptr::write_unaligned(
+            core::ptr::write_unaligned(
                 self.output.as_mut_ptr().add(self.out_idx) as *mut u64,
                 bitbuf.to_le(),
             );
@@ -89,7 +130,7 @@ impl<'a> Bitstream<'a> {
             let bitbuf_low = self.bitbuf | (bits << bitcount);
             let bitbuf_high = bits >> (64 - bitcount);
 
-            std::ptr::write_unaligned(
+            core::ptr::write_unaligned(
                 self.output.as_mut_ptr().add(self.out_idx) as *mut u64,
                 bitbuf_low.to_le(),
             );
@@ -99,7 +140,7 @@ impl<'a> Bitstream<'a> {
         } else {
             let bitbuf = self.bitbuf | (bits << bitcount);
             if new_bitcount >= 32 {
-                std::ptr::write_unaligned(
+                core::ptr::write_unaligned(
                     self.output.as_mut_ptr().add(self.out_idx) as *mut u64,
                     bitbuf.to_le(),
                 );
@@ -142,7 +183,7 @@ impl<'a> Bitstream<'a> {
             // This avoids truncation to u32 and allows using full register width stores on 64-bit systems.
             if self.out_idx + 8 <= self.output.len() {
                 unsafe {
-                    std::ptr::write_unaligned(
+                    core::ptr::write_unaligned(
                         self.output.as_mut_ptr().add(self.out_idx) as *mut u64,
                         bitbuf.to_le(),
                     );
@@ -157,7 +198,7 @@ impl<'a> Bitstream<'a> {
             // Using u32 write avoids 8-byte boundary check and reduces memory bandwidth compared to u64 blind write.
             if self.out_idx + 4 <= self.output.len() {
                 unsafe {
-                    std::ptr::write_unaligned(
+                    core::ptr::write_unaligned(
                         self.output.as_mut_ptr().add(self.out_idx) as *mut u32,
                         (bitbuf as u32).to_le(),
                     );
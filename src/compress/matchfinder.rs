@@ -1,10 +1,16 @@
+use crate::allocator::{AllocError, AllocVec, AllocatorRef};
 use crate::common::*;
-use std::cmp::min;
+#[cfg(target_arch = "aarch64")]
+use crate::cpu_features::aarch64_feature_enabled;
+#[cfg(target_arch = "x86_64")]
+use crate::cpu_features::x86_feature_enabled;
+use crate::sync::OnceLock;
+use core::cmp::min;
 
 #[cfg(target_arch = "aarch64")]
-use std::arch::aarch64::*;
+use core::arch::aarch64::*;
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use core::arch::x86_64::*;
 
 pub const MATCHFINDER_HASH_ORDER: usize = 15;
 pub const MATCHFINDER_HASH_SIZE: usize = 1 << MATCHFINDER_HASH_ORDER;
@@ -144,7 +150,7 @@ macro_rules! impl_matchfinder_trait {
                 $fmp: usize,
                 $fmmd: usize,
                 $fmnl: usize,
-                $fmm: &mut Vec<(u16, u16)>,
+                $fmm: &mut AllocVec<(u16, u16)>,
             ) -> (usize, usize) {
                 $fm_matches_body
             }
@@ -182,7 +188,7 @@ pub trait MatchFinderTrait {
         pos: usize,
         max_depth: usize,
         nice_len: usize,
-        matches: &mut Vec<(u16, u16)>,
+        matches: &mut AllocVec<(u16, u16)>,
     ) -> (usize, usize);
 }
 
@@ -693,25 +699,54 @@ unsafe fn match_len_neon(a: *const u8, b: *const u8, max_len: usize) -> usize {
     len + match_len_sw(a.add(len), b.add(len), max_len - len)
 }
 
+impl MatchLenStrategy {
+    pub fn name(self) -> &'static str {
+        match self {
+            MatchLenStrategy::Scalar => "scalar",
+            #[cfg(target_arch = "x86_64")]
+            MatchLenStrategy::Sse2 => "sse2",
+            #[cfg(target_arch = "x86_64")]
+            MatchLenStrategy::Avx2 => "avx2",
+            #[cfg(target_arch = "x86_64")]
+            MatchLenStrategy::Avx512 => "avx512",
+            #[cfg(target_arch = "x86_64")]
+            MatchLenStrategy::Avx10 => "avx10",
+            #[cfg(target_arch = "aarch64")]
+            MatchLenStrategy::Neon => "neon",
+        }
+    }
+}
+
+static MATCH_LEN_STRATEGY: OnceLock<MatchLenStrategy> = OnceLock::new();
+
 fn get_match_len_strategy() -> MatchLenStrategy {
+    *MATCH_LEN_STRATEGY.get_or_init(select_match_len_strategy)
+}
+
+/// Name of the match length implementation selected for this CPU.
+pub(crate) fn match_len_implementation_name() -> &'static str {
+    get_match_len_strategy().name()
+}
+
+fn select_match_len_strategy() -> MatchLenStrategy {
     #[cfg(target_arch = "x86_64")]
     {
-        if is_x86_feature_detected!("avx512vl") && is_x86_feature_detected!("avx512bw") {
+        if x86_feature_enabled!("avx512vl") && x86_feature_enabled!("avx512bw") {
             return MatchLenStrategy::Avx10;
         }
-        if is_x86_feature_detected!("avx512bw") {
+        if x86_feature_enabled!("avx512bw") {
             return MatchLenStrategy::Avx512;
         }
-        if is_x86_feature_detected!("avx2") {
+        if x86_feature_enabled!("avx2") {
             return MatchLenStrategy::Avx2;
         }
-        if is_x86_feature_detected!("sse2") {
+        if x86_feature_enabled!("sse2") {
             return MatchLenStrategy::Sse2;
         }
     }
     #[cfg(target_arch = "aarch64")]
     {
-        if std::arch::is_aarch64_feature_detected!("neon") {
+        if aarch64_feature_enabled!("neon") {
             return MatchLenStrategy::Neon;
         }
     }
@@ -719,20 +754,29 @@ fn get_match_len_strategy() -> MatchLenStrategy {
 }
 
 pub struct MatchFinder {
-    pub hash_tab: Vec<i32>,
-    pub prev_tab: Vec<u16>,
+    pub hash_tab: AllocVec<i32>,
+    pub prev_tab: AllocVec<u16>,
     pub base_offset: usize,
     match_len: MatchLenStrategy,
 }
 
 impl MatchFinder {
+    /// The bytes [`try_new_in`](Self::try_new_in) allocates.
+    pub const FOOTPRINT: usize = AllocVec::<i32>::footprint(MATCHFINDER_HASH_SIZE)
+        + AllocVec::<u16>::footprint(MATCHFINDER_WINDOW_SIZE);
+
+    #[cfg(test)]
     pub fn new() -> Self {
-        Self {
-            hash_tab: vec![-1; MATCHFINDER_HASH_SIZE],
-            prev_tab: vec![0; MATCHFINDER_WINDOW_SIZE],
+        Self::try_new_in(&AllocatorRef::default()).unwrap()
+    }
+
+    pub(crate) fn try_new_in(allocator: &AllocatorRef) -> Result<Self, AllocError> {
+        Ok(Self {
+            hash_tab: AllocVec::try_from_elem_in(-1, MATCHFINDER_HASH_SIZE, allocator)?,
+            prev_tab: AllocVec::try_from_elem_in(0, MATCHFINDER_WINDOW_SIZE, allocator)?,
             base_offset: 0,
             match_len: get_match_len_strategy(),
-        }
+        })
     }
 
     pub fn reset(&mut self) {
@@ -809,7 +853,9 @@ impl MatchFinder {
         let mut depth = 0;
         let mut cur_pos_i32 = cur_pos;
 
-        while cur_pos_i32 != -1 && depth < max_depth {
+        // A candidate exactly a window back shares its prev_tab slot with the current position,
+        // so following it can step past the start of the buffer.
+        while cur_pos_i32 >= 0 && depth < max_depth {
             let p_abs = cur_pos_i32 as usize;
             if p_abs < self.base_offset {
                 break;
@@ -896,7 +942,7 @@ impl MatchFinder {
         pos: usize,
         max_depth: usize,
         nice_len: usize,
-        matches: &mut Vec<(u16, u16)>,
+        matches: &mut AllocVec<(u16, u16)>,
     ) -> (usize, usize) {
         matches.clear();
         let mut on_match = |len: usize, offset: usize| {
@@ -1107,18 +1153,26 @@ impl MatchFinder {
 }
 
 pub struct HtMatchFinder {
-    pub hash_tab: Vec<i32>,
+    pub hash_tab: AllocVec<i32>,
     pub base_offset: usize,
     match_len: MatchLenStrategy,
 }
 
 impl HtMatchFinder {
+    /// The bytes [`try_new_in`](Self::try_new_in) allocates.
+    pub const FOOTPRINT: usize = AllocVec::<i32>::footprint(MATCHFINDER_HASH_SIZE);
+
+    #[cfg(test)]
     pub fn new() -> Self {
-        Self {
-            hash_tab: vec![-1; MATCHFINDER_HASH_SIZE],
+        Self::try_new_in(&AllocatorRef::default()).unwrap()
+    }
+
+    pub(crate) fn try_new_in(allocator: &AllocatorRef) -> Result<Self, AllocError> {
+        Ok(Self {
+            hash_tab: AllocVec::try_from_elem_in(-1, MATCHFINDER_HASH_SIZE, allocator)?,
             base_offset: 0,
             match_len: get_match_len_strategy(),
-        }
+        })
     }
 
     pub fn reset(&mut self) {
@@ -1267,12 +1321,12 @@ impl MatchVisitor for BestMatchVisitor {
 }
 
 struct AllMatchesVisitor<'a> {
-    matches: &'a mut Vec<(u16, u16)>,
+    matches: &'a mut AllocVec<(u16, u16)>,
     best_len: usize,
 }
 
 impl<'a> AllMatchesVisitor<'a> {
-    fn new(matches: &'a mut Vec<(u16, u16)>) -> Self {
+    fn new(matches: &'a mut AllocVec<(u16, u16)>) -> Self {
         Self {
             matches,
             best_len: 3,
@@ -1306,22 +1360,32 @@ impl MatchVisitor for NoOpVisitor {
 }
 
 pub struct BtMatchFinder {
-    pub hash3_tab: Vec<[i32; 2]>,
-    pub hash4_tab: Vec<i32>,
-    pub child_tab: Vec<[i32; 2]>,
+    pub hash3_tab: AllocVec<[i32; 2]>,
+    pub hash4_tab: AllocVec<i32>,
+    pub child_tab: AllocVec<[i32; 2]>,
     pub base_offset: usize,
     match_len: MatchLenStrategy,
 }
 
 impl BtMatchFinder {
+    /// The bytes [`try_new_in`](Self::try_new_in) allocates.
+    pub const FOOTPRINT: usize = AllocVec::<[i32; 2]>::footprint(1 << 16)
+        + AllocVec::<i32>::footprint(1 << 16)
+        + AllocVec::<[i32; 2]>::footprint(MATCHFINDER_WINDOW_SIZE);
+
+    #[cfg(test)]
     pub fn new() -> Self {
-        Self {
-            hash3_tab: vec![[-1; 2]; 1 << 16],
-            hash4_tab: vec![-1; 1 << 16],
-            child_tab: vec![[0; 2]; MATCHFINDER_WINDOW_SIZE],
+        Self::try_new_in(&AllocatorRef::default()).unwrap()
+    }
+
+    pub(crate) fn try_new_in(allocator: &AllocatorRef) -> Result<Self, AllocError> {
+        Ok(Self {
+            hash3_tab: AllocVec::try_from_elem_in([-1; 2], 1 << 16, allocator)?,
+            hash4_tab: AllocVec::try_from_elem_in(-1, 1 << 16, allocator)?,
+            child_tab: AllocVec::try_from_elem_in([0; 2], MATCHFINDER_WINDOW_SIZE, allocator)?,
             base_offset: 0,
             match_len: get_match_len_strategy(),
-        }
+        })
     }
 
     pub fn reset(&mut self) {
@@ -1706,7 +1770,7 @@ impl BtMatchFinder {
         pos: usize,
         max_depth: usize,
         nice_len: usize,
-        matches: &mut Vec<(u16, u16)>,
+        matches: &mut AllocVec<(u16, u16)>,
     ) -> (usize, usize) {
         matches.clear();
         unsafe {
@@ -1788,7 +1852,7 @@ mod tests {
         mf2.prepare(data.len());
 
         let max_depth = 10;
-        let mut matches = Vec::new();
+        let mut matches = AllocVec::default();
 
         for i in 0..5 {
             mf1.find_match(data, i, max_depth, 258);
@@ -1,17 +1,43 @@
 pub mod bitstream;
 
+#[cfg(feature = "std")]
+mod auto_level;
 mod huffman_comp;
 mod matchfinder;
-
+mod optimal;
+mod parallel;
+mod probe;
+mod size;
+mod stats;
+mod tokens;
+
+#[cfg(feature = "std")]
+pub(crate) use self::auto_level::AutoLevelState;
+#[cfg(feature = "std")]
+pub use self::auto_level::{AutoLevel, LevelTrial};
 use self::bitstream::Bitstream;
 use self::huffman_comp::make_huffman_code;
+pub(crate) use self::matchfinder::match_len_implementation_name;
 use self::matchfinder::{BtMatchFinder, HtMatchFinder, MatchFinder, MatchFinderTrait};
+use self::optimal::{
+    BlockType, MATCH_CACHE_INDEX_CAPACITY, PATH_CAPACITY, PLANNED_BLOCKS_CAPACITY, PlannedBlock,
+    SEGMENT_SCRATCH_BOUND,
+};
+pub use self::parallel::Parallelism;
+use self::probe::{PROBE_TABLE_SIZE, ProbeCursor};
+pub use self::size::Format;
+pub use self::stats::CompressionStats;
+pub use self::tokens::{Token, encode_tokens, parse};
+use crate::allocator::{AllocError, AllocVec, Allocator, AllocatorRef};
 use crate::common::*;
+use crate::sync::OnceLock;
+use alloc::alloc::handle_alloc_error;
+use alloc::sync::Arc;
+use alloc::vec::Vec;
+use core::cmp::min;
+use core::mem::MaybeUninit;
+#[cfg(feature = "std")]
 use rayon::prelude::*;
-use std::cmp::min;
-use std::io;
-use std::mem::MaybeUninit;
-use std::sync::OnceLock;
 
 const LENGTH_WRITE_TABLE: [u32; 260] = [
     3, 3, 3, 3, 16777220, 33554437, 50331654, 67108871, 83886088, 100663305, 117440522, 134283275,
@@ -127,6 +153,17 @@ const SLOT_TO_OBS_IDX: [u8; 32] = [
 pub const MAX_LITLEN_CODEWORD_LEN: usize = 14;
 pub const MAX_OFFSET_CODEWORD_LEN: usize = 15;
 pub const MAX_PRE_CODEWORD_LEN: usize = 7;
+// Parse costs, in bits, of symbols that the code a block is parsed against has no codeword
+// for. Counting them as free would make the parse prefer exactly the symbols the first pass
+// never saw, such as the literals a primed chunk could match from its history.
+const LITERAL_NOSTAT_BITS: u32 = 13;
+const LENGTH_NOSTAT_BITS: u32 = 13;
+const OFFSET_NOSTAT_BITS: u32 = 10;
+/// Inputs larger than this are split into chunks of this size and compressed in parallel.
+pub const PARALLEL_CHUNK_SIZE: usize = 256 * 1024;
+/// Highest supported compression level. Levels 13 and up iterate the parse to convergence
+/// and search block boundaries exhaustively; they are much slower than level 12.
+pub const MAX_COMPRESSION_LEVEL: usize = 16;
 
 fn gen_codewords_from_lens(lens: &[u8], codewords: &mut [u32], max_len: usize) {
     let mut len_counts = [0u32; 16];
@@ -238,6 +275,8 @@ fn compute_static_tables() -> StaticTables {
 pub enum CompressResult {
     Success,
     InsufficientSpace,
+    /// The compressor's [`Allocator`] could not provide a working buffer.
+    OutOfMemory,
 }
 
 #[derive(Clone, Copy)]
@@ -421,6 +460,43 @@ enum MatchFinderEnum {
     Bt(BtMatchFinder),
 }
 
+/// Checksum that the chunked compression path computes alongside each chunk.
+#[derive(Clone, Copy)]
+enum ChunkChecksum {
+    #[cfg(feature = "std")]
+    None,
+    Crc32,
+    Adler32,
+}
+
+impl ChunkChecksum {
+    #[cfg(feature = "std")]
+    fn initial(self) -> u32 {
+        match self {
+            ChunkChecksum::Adler32 => 1,
+            _ => 0,
+        }
+    }
+
+    fn of(self, data: &[u8]) -> u32 {
+        match self {
+            #[cfg(feature = "std")]
+            ChunkChecksum::None => 0,
+            ChunkChecksum::Crc32 => crate::crc32::crc32(0, data),
+            ChunkChecksum::Adler32 => crate::adler32::adler32(1, data),
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn combine(self, sum1: u32, sum2: u32, len2: usize) -> u32 {
+        match self {
+            ChunkChecksum::None => 0,
+            ChunkChecksum::Crc32 => crate::crc32::crc32_combine(sum1, sum2, len2),
+            ChunkChecksum::Adler32 => crate::adler32::adler32_combine(sum1, sum2, len2),
+        }
+    }
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum FlushMode {
     None,
@@ -448,15 +524,169 @@ pub struct Compressor {
     pub offset_slot_costs: [u32; 32],
 
     mf: Option<MatchFinderEnum>,
-    sequences: Vec<Sequence>,
-    dp_costs: Vec<u32>,
-    dp_path: Vec<u32>,
+    sequences: AllocVec<Sequence>,
+    dp_costs: AllocVec<u32>,
+    dp_path: AllocVec<u32>,
     split_stats: BlockSplitStats,
-    matches: Vec<(u16, u16)>,
+    matches: AllocVec<(u16, u16)>,
+    match_cache: AllocVec<(u16, u16)>,
+    match_cache_index: AllocVec<u32>,
+    optimal_path: AllocVec<Token>,
+    scratch_path: AllocVec<Token>,
+    optimal_blocks: AllocVec<PlannedBlock>,
+    /// Scratch for [`probe::probe`]; empty at the levels that do not probe.
+    probe_table: AllocVec<u32>,
+    allocator: AllocatorRef,
+    /// Compressors lent to the tasks of `compress_chunks`, kept for the next large input.
+    #[cfg(feature = "std")]
+    chunk_pool: Vec<Compressor>,
+    prime_chunks: bool,
+    /// Set by [`set_incompressible_probe`](Self::set_incompressible_probe); `None` leaves it
+    /// to the level.
+    probe_blocks: Option<bool>,
+    parallelism: Parallelism,
+    stats: Option<CompressionStats>,
+}
+
+/// What a chunk task of `compress_chunks` yields: its size or output, its checksum and
+/// its stats, or why it failed.
+#[cfg(feature = "std")]
+type ChunkOutput<T> = Result<(T, u32, Option<CompressionStats>), CompressResult>;
+
+/// A compressor borrowed from a `chunk_pool` for one rayon task, returned when dropped.
+/// `None` if the pool was empty and the allocator had no room for a new one.
+#[cfg(feature = "std")]
+struct PooledCompressor<'a> {
+    pool: &'a std::sync::Mutex<Vec<Compressor>>,
+    compressor: Option<Compressor>,
+}
+
+#[cfg(feature = "std")]
+impl<'a> PooledCompressor<'a> {
+    fn take(
+        pool: &'a std::sync::Mutex<Vec<Compressor>>,
+        level: usize,
+        allocator: &AllocatorRef,
+    ) -> Self {
+        let pooled = pool.lock().ok().and_then(|mut p| p.pop());
+        Self {
+            pool,
+            compressor: pooled
+                .or_else(|| Compressor::try_with_allocator_ref(level, allocator.clone()).ok()),
+        }
+    }
+
+    fn get(&mut self) -> Option<&mut Compressor> {
+        self.compressor.as_mut()
+    }
 }
 
+#[cfg(feature = "std")]
+impl Drop for PooledCompressor<'_> {
+    fn drop(&mut self) {
+        if let (Some(compressor), Ok(mut pool)) = (self.compressor.take(), self.pool.lock()) {
+            pool.push(compressor);
+        }
+    }
+}
+
+/// Capacity of `sequences`: every sequence of a block but the last ends in a match of at
+/// least 3 bytes.
+const SEQUENCES_CAPACITY: usize = MAX_BLOCK_LENGTH / DEFLATE_MIN_MATCH_LEN + 1;
+/// Capacity of `dp_costs` and `dp_path`: an entry per byte of a block, and one for its end.
+const DP_CAPACITY: usize = MAX_BLOCK_LENGTH + 1;
+/// Capacity of `matches`: the lengths `find_matches` reports only increase, so it never
+/// grows.
+const MATCHES_CAPACITY: usize = DEFLATE_MAX_MATCH_LEN;
+/// Longest block the levels below 13 write: `should_end_block` ends a block once it
+/// reaches [`SOFT_MAX_BLOCK_LENGTH`] unless at most [`MIN_BLOCK_LENGTH`] bytes would be
+/// left, and a match may run past either. Segments of levels 13 and up are shorter.
+const MAX_BLOCK_LENGTH: usize = SOFT_MAX_BLOCK_LENGTH + MIN_BLOCK_LENGTH + DEFLATE_MAX_MATCH_LEN;
+
 impl Compressor {
     pub fn new(level: usize) -> Self {
+        Self::with_allocator_ref(level, AllocatorRef::default())
+    }
+
+    /// Like [`new`](Self::new), taking the match-finder tables and parsing buffers from
+    /// `allocator`; [`memory_footprint`](Self::memory_footprint) says how much.
+    ///
+    /// The match cache of levels 13 and up grows through `allocator` too, as do the
+    /// compressors made for the chunks of large inputs. The bookkeeping of chunked
+    /// compression still uses the global allocator; with
+    /// [`Parallelism::single_threaded`] everything comes from `allocator`.
+    pub fn with_allocator(level: usize, allocator: Arc<dyn Allocator>) -> Self {
+        Self::with_allocator_ref(level, AllocatorRef::new(allocator))
+    }
+
+    /// Like [`with_allocator`](Self::with_allocator), but returns an error instead of
+    /// aborting when `allocator` cannot provide the working memory.
+    pub fn try_with_allocator(
+        level: usize,
+        allocator: Arc<dyn Allocator>,
+    ) -> Result<Self, AllocError> {
+        Self::try_with_allocator_ref(level, AllocatorRef::new(allocator))
+    }
+
+    /// The bytes a compressor for `level` takes up front: the `Compressor` itself plus
+    /// what [`with_allocator`](Self::with_allocator) requests from its allocator.
+    ///
+    /// Below level 13 this is all that compressing takes; the buffers are sized for the
+    /// longest block. Levels 13 and up also allocate up to
+    /// [`match_cache_footprint`](Self::match_cache_footprint) while compressing. Inputs
+    /// split by [`Parallelism`] take a compressor like this one per thread.
+    pub fn memory_footprint(level: usize) -> usize {
+        let mf = if level == 1 {
+            HtMatchFinder::FOOTPRINT
+        } else if level >= 10 {
+            BtMatchFinder::FOOTPRINT
+        } else {
+            MatchFinder::FOOTPRINT
+        };
+        let sequences = if level == 0 {
+            0
+        } else {
+            AllocVec::<Sequence>::footprint(SEQUENCES_CAPACITY)
+        };
+        let near_optimal = if level >= 10 {
+            AllocVec::<u32>::footprint(DP_CAPACITY) * 2
+                + AllocVec::<(u16, u16)>::footprint(MATCHES_CAPACITY)
+        } else {
+            0
+        };
+        let probe = if (1..13).contains(&level) {
+            AllocVec::<u32>::footprint(PROBE_TABLE_SIZE)
+        } else {
+            0
+        };
+        let exhaustive = if level >= 13 {
+            AllocVec::<u32>::footprint(MATCH_CACHE_INDEX_CAPACITY)
+                + AllocVec::<Token>::footprint(PATH_CAPACITY) * 2
+                + AllocVec::<PlannedBlock>::footprint(PLANNED_BLOCKS_CAPACITY)
+        } else {
+            0
+        };
+        size_of::<Self>() + mf + sequences + near_optimal + probe + exhaustive
+    }
+
+    /// An upper bound on what a compressor for `level` allocates while compressing, on top
+    /// of [`memory_footprint`](Self::memory_footprint): at levels 13 and up, a cache of the
+    /// matches in up to 256 KiB of input, whose size depends on the data, and the scratch
+    /// of parsing it. 0 below level 13.
+    pub fn match_cache_footprint(level: usize) -> usize {
+        if level >= 13 {
+            SEGMENT_SCRATCH_BOUND
+        } else {
+            0
+        }
+    }
+
+    fn with_allocator_ref(level: usize, allocator: AllocatorRef) -> Self {
+        Self::try_with_allocator_ref(level, allocator)
+            .unwrap_or_else(|e| handle_alloc_error(e.layout()))
+    }
+
+    fn try_with_allocator_ref(level: usize, allocator: AllocatorRef) -> Result<Self, AllocError> {
         let mut c = Self {
             compression_level: level,
             max_search_depth: 0,
@@ -474,36 +704,69 @@ impl Compressor {
             length_costs: [0; DEFLATE_MAX_MATCH_LEN + 1],
             offset_slot_costs: [0; 32],
             mf: Some(if level == 1 {
-                MatchFinderEnum::Table(HtMatchFinder::new())
+                MatchFinderEnum::Table(HtMatchFinder::try_new_in(&allocator)?)
             } else if level >= 10 {
-                MatchFinderEnum::Bt(BtMatchFinder::new())
+                MatchFinderEnum::Bt(BtMatchFinder::try_new_in(&allocator)?)
             } else {
-                MatchFinderEnum::Chain(MatchFinder::new())
+                MatchFinderEnum::Chain(MatchFinder::try_new_in(&allocator)?)
             }),
             sequences: if level == 0 {
-                Vec::new()
+                AllocVec::new_in(&allocator)
             } else {
-                Vec::with_capacity(50000)
+                AllocVec::try_with_capacity_in(SEQUENCES_CAPACITY, &allocator)?
             },
             dp_costs: if level >= 10 {
-                Vec::with_capacity(300000)
+                AllocVec::try_with_capacity_in(DP_CAPACITY, &allocator)?
             } else {
-                Vec::new()
+                AllocVec::new_in(&allocator)
             },
             dp_path: if level >= 10 {
-                Vec::with_capacity(300000)
+                AllocVec::try_with_capacity_in(DP_CAPACITY, &allocator)?
             } else {
-                Vec::new()
+                AllocVec::new_in(&allocator)
             },
             split_stats: BlockSplitStats::new(),
             matches: if level >= 10 {
-                Vec::with_capacity(32)
+                AllocVec::try_with_capacity_in(MATCHES_CAPACITY, &allocator)?
+            } else {
+                AllocVec::new_in(&allocator)
+            },
+            match_cache: AllocVec::new_in(&allocator),
+            match_cache_index: if level >= 13 {
+                AllocVec::try_with_capacity_in(MATCH_CACHE_INDEX_CAPACITY, &allocator)?
             } else {
-                Vec::new()
+                AllocVec::new_in(&allocator)
             },
+            optimal_path: if level >= 13 {
+                AllocVec::try_with_capacity_in(PATH_CAPACITY, &allocator)?
+            } else {
+                AllocVec::new_in(&allocator)
+            },
+            scratch_path: if level >= 13 {
+                AllocVec::try_with_capacity_in(PATH_CAPACITY, &allocator)?
+            } else {
+                AllocVec::new_in(&allocator)
+            },
+            optimal_blocks: if level >= 13 {
+                AllocVec::try_with_capacity_in(PLANNED_BLOCKS_CAPACITY, &allocator)?
+            } else {
+                AllocVec::new_in(&allocator)
+            },
+            probe_table: if (1..13).contains(&level) {
+                AllocVec::try_from_elem_in(0, PROBE_TABLE_SIZE, &allocator)?
+            } else {
+                AllocVec::new_in(&allocator)
+            },
+            allocator,
+            #[cfg(feature = "std")]
+            chunk_pool: Vec::new(),
+            prime_chunks: true,
+            probe_blocks: None,
+            parallelism: Parallelism::default(),
+            stats: None,
         };
         c.init_params();
-        c
+        Ok(c)
     }
 
     fn update_huffman_tables(&mut self) {
@@ -590,68 +853,115 @@ impl Compressor {
                 self.max_search_depth = 100;
                 self.nice_match_length = 150;
             }
-            12 => {
+            12 | 13 => {
                 self.max_search_depth = 300;
                 self.nice_match_length = 258;
             }
+            14 => {
+                self.max_search_depth = 600;
+                self.nice_match_length = 258;
+            }
             _ => {
-                self.max_search_depth = 300;
+                self.max_search_depth = 1000;
                 self.nice_match_length = 258;
             }
         }
     }
 
+    /// How many positions ahead the greedy levels look for a longer match.
+    fn lazy_depth(&self) -> u32 {
+        if self.compression_level >= 8 {
+            2
+        } else if self.compression_level >= 5 {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Compresses the next block, or the next segment at levels 13 and up, and returns the
+    /// number of input bytes consumed, or 0 if the output is full.
+    fn compress_next<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        in_idx: usize,
+        bs: &mut Bitstream,
+        final_block: bool,
+        cursor: &mut ProbeCursor,
+    ) -> Result<usize, AllocError> {
+        if self.compression_level >= 13 {
+            return self.compress_exhaustive_segment(mf, input, in_idx, bs, final_block);
+        }
+        Ok(
+            if let Some(processed) =
+                self.compress_probed_window(mf, input, in_idx, bs, final_block, cursor)
+            {
+                processed
+            } else if self.compression_level >= 10 {
+                self.compress_near_optimal_block(mf, input, in_idx, bs, final_block)
+            } else {
+                self.compress_greedy_block(mf, input, in_idx, bs, self.lazy_depth(), final_block)
+            },
+        )
+    }
+
     fn compress_loop<T: MatchFinderTrait>(
         &mut self,
         mf: &mut T,
         input: &[u8],
+        start: usize,
         bs: &mut Bitstream,
         flush_mode: FlushMode,
     ) -> (CompressResult, usize, u32) {
-        let mut in_idx = 0;
+        let mut in_idx = start;
+        let mut cursor = ProbeCursor::new(start);
         mf.prepare(input.len());
+        if self.compression_level < 13 {
+            // The exhaustive levels load their own history in `cache_matches`. This inserts
+            // every position, even where `skip_positions` would skip ahead for speed.
+            for pos in 0..start {
+                mf.skip_match(input, pos, self.max_search_depth, self.nice_match_length);
+            }
+        }
 
         while in_idx < input.len() {
-            let processed = if self.compression_level >= 10 {
-                self.compress_near_optimal_block(
-                    mf,
-                    input,
-                    in_idx,
-                    bs,
-                    flush_mode == FlushMode::Finish,
-                )
-            } else {
-                let lazy_depth = if self.compression_level >= 8 {
-                    2
-                } else if self.compression_level >= 5 {
-                    1
-                } else {
-                    0
-                };
-                self.compress_greedy_block(
-                    mf,
-                    input,
-                    in_idx,
-                    bs,
-                    lazy_depth,
-                    flush_mode == FlushMode::Finish,
-                )
+            let next = self.compress_next(
+                mf,
+                input,
+                in_idx,
+                bs,
+                flush_mode == FlushMode::Finish,
+                &mut cursor,
+            );
+            let Ok(processed) = next else {
+                mf.advance(input.len());
+                return (CompressResult::OutOfMemory, 0, 0);
             };
-
             if processed == 0 {
                 mf.advance(input.len());
                 return (CompressResult::InsufficientSpace, 0, 0);
             }
             in_idx += processed;
+            bs.recycle();
         }
 
-        if in_idx == 0 && flush_mode == FlushMode::Finish {
+        if in_idx == start && flush_mode == FlushMode::Finish {
             let start_out = bs.out_idx;
             let start_bitcount = bs.bitcount;
-            if self.compression_level >= 10 {
-                self.compress_near_optimal_block(mf, input, 0, bs, true);
+            // The initial capacities hold an empty block below level 13.
+            if self.compression_level >= 13 {
+                if self
+                    .compress_exhaustive_segment(mf, input, start, bs, true)
+                    .is_err()
+                {
+                    mf.advance(input.len());
+                    return (CompressResult::OutOfMemory, 0, 0);
+                }
+            } else if self.compression_level >= 10 {
+                self.compress_near_optimal_block(mf, input, start, bs, true);
             } else {
-                self.compress_greedy_block(mf, input, 0, bs, 0, true);
+                self.compress_greedy_block(mf, input, start, bs, 0, true);
             }
             if bs.out_idx == start_out && bs.bitcount == start_bitcount {
                 mf.advance(input.len());
@@ -660,6 +970,8 @@ impl Compressor {
         }
 
         if flush_mode == FlushMode::Sync {
+            // An empty stored block, which ends byte-aligned.
+            let block = self.begin_block(bs);
             if !bs.write_bits(0, 3) {
                 mf.advance(input.len());
                 return (CompressResult::InsufficientSpace, 0, 0);
@@ -678,6 +990,7 @@ impl Compressor {
             bs.output[bs.out_idx + 2].write(0xFF);
             bs.output[bs.out_idx + 3].write(0xFF);
             bs.out_idx += 4;
+            self.end_block(block, BlockType::Stored, bs.bits_written(), bs);
         }
 
         let (res, valid_bits) = bs.flush();
@@ -687,105 +1000,266 @@ impl Compressor {
         }
 
         mf.advance(input.len());
-        (CompressResult::Success, bs.out_idx, valid_bits)
+        (CompressResult::Success, bs.total_out(), valid_bits)
     }
 
-    pub fn compress(
+    /// Compresses `input` as independent chunks on the rayon thread pool. Each task also
+    /// checksums its chunk, and the chunk checksums are combined into the third return value.
+    ///
+    /// The tasks borrow compressors from `chunk_pool`, so repeated calls allocate none.
+    /// When `output` holds the bound of every chunk, each chunk is compressed straight into
+    /// its own slot of `output` and the slots are then moved together.
+    #[cfg(feature = "std")]
+    fn compress_chunks(
         &mut self,
         input: &[u8],
         output: &mut [MaybeUninit<u8>],
         flush_mode: FlushMode,
+        checksum: ChunkChecksum,
     ) -> (CompressResult, usize, u32) {
-        if input.len() > 256 * 1024 {
-            let chunk_size = 256 * 1024;
-            let chunks: Vec<&[u8]> = input.chunks(chunk_size).collect();
-
-            let compressed_chunks_res: Vec<io::Result<Vec<u8>>> = chunks
-                .par_iter()
-                .enumerate()
-                .map_init(
-                    || {
-                        (
-                            Compressor::new(self.compression_level),
-                            Vec::with_capacity(chunk_size + chunk_size / 2),
-                        )
-                    },
-                    |(compressor, buf), (i, chunk)| {
-                        let is_last = i == chunks.len() - 1;
-                        let mode = if is_last { flush_mode } else { FlushMode::Sync };
-
-                        let bound = Self::deflate_compress_bound(chunk.len());
-                        if buf.capacity() < bound {
-                            buf.reserve(bound - buf.len());
-                        }
-                        unsafe {
-                            buf.set_len(bound);
+        let parallelism = self.parallelism.clone();
+        let chunk_size = parallelism.chunk_size();
+        let chunks: Vec<&[u8]> = input.chunks(chunk_size).collect();
+        let num_chunks = chunks.len();
+        let level = self.compression_level;
+        let mode_of = |i: usize| {
+            if i == num_chunks - 1 {
+                flush_mode
+            } else {
+                FlushMode::Sync
+            }
+        };
+
+        // With priming, every chunk after the first sees the 32 KiB before it as history.
+        let history = if self.prime_chunks {
+            DEFLATE_MAX_MATCH_OFFSET
+        } else {
+            0
+        };
+        let probe_blocks = self.probe_blocks;
+        let collect_stats = self.stats.is_some();
+        // Also returns the stats of the chunk, if enabled.
+        let compress_chunk =
+            |compressor: &mut Compressor, i: usize, out: &mut [MaybeUninit<u8>]| {
+                compressor.probe_blocks = probe_blocks;
+                compressor.set_collect_stats(collect_stats);
+                let start = i * chunk_size;
+                let history = history.min(start);
+                let window = &input[start - history..start + chunks[i].len()];
+                let res = compressor.compress_primed(window, history, out, mode_of(i));
+                (res, compressor.stats.take())
+            };
+
+        let mut pool = core::mem::take(&mut self.chunk_pool);
+        pool.retain(|c| c.compression_level == level);
+        let pool = std::sync::Mutex::new(pool);
+        let allocator = self.allocator.clone();
+        let borrow = || PooledCompressor::take(&pool, level, &allocator);
+
+        let bounds: Vec<usize> = chunks
+            .iter()
+            .map(|c| Self::deflate_compress_bound(c.len()))
+            .collect();
+        let result = if bounds.iter().sum::<usize>() <= output.len() {
+            let mut slots = Vec::with_capacity(num_chunks);
+            let mut rest = &mut output[..];
+            for &bound in &bounds {
+                let (slot, tail) = rest.split_at_mut(bound);
+                slots.push(slot);
+                rest = tail;
+            }
+            let sizes: Vec<ChunkOutput<usize>> = parallelism.install(|| {
+                chunks
+                    .par_iter()
+                    .zip(slots)
+                    .enumerate()
+                    .map_init(borrow, |compressor, (i, (chunk, slot))| {
+                        let compressor = compressor.get().ok_or(CompressResult::OutOfMemory)?;
+                        let ((res, size, _), stats) = compress_chunk(compressor, i, slot);
+                        if res != CompressResult::Success {
+                            return Err(res);
                         }
+                        // Checksum the chunk while it is still in this thread's cache.
+                        Ok((size, checksum.of(chunk), stats))
+                    })
+                    .collect()
+            });
 
-                        let buf_uninit = unsafe {
-                            std::slice::from_raw_parts_mut(
-                                buf.as_mut_ptr() as *mut MaybeUninit<u8>,
-                                buf.len(),
-                            )
-                        };
-
-                        let (res, size, _) = compressor.compress(chunk, buf_uninit, mode);
-                        if res == CompressResult::Success {
-                            unsafe {
-                                buf.set_len(size);
-                            }
-                            if size < buf.capacity() / 2 {
-                                Ok(buf.to_vec())
-                            } else {
-                                Ok(std::mem::replace(
-                                    buf,
-                                    Vec::with_capacity(chunk_size + chunk_size / 2),
-                                ))
-                            }
-                        } else {
-                            Err(io::Error::other("Compression failed"))
+            let mut out_idx = 0;
+            let mut slot_start = 0;
+            let mut sum = checksum.initial();
+            let mut failure = None;
+            for ((size, chunk), bound) in sizes.into_iter().zip(&chunks).zip(&bounds) {
+                let (size, chunk_sum, stats) = match size {
+                    Ok(size) => size,
+                    Err(res) => {
+                        failure = Some(res);
+                        break;
+                    }
+                };
+                self.add_stats(stats);
+                output.copy_within(slot_start..slot_start + size, out_idx);
+                out_idx += size;
+                slot_start += bound;
+                sum = checksum.combine(sum, chunk_sum, chunk.len());
+            }
+            failure.map_or(Ok((out_idx, sum)), Err)
+        } else {
+            let compressed: Vec<ChunkOutput<Vec<u8>>> = parallelism.install(|| {
+                chunks
+                    .par_iter()
+                    .zip(&bounds)
+                    .enumerate()
+                    .map_init(borrow, |compressor, (i, (chunk, &bound))| {
+                        let compressor = compressor.get().ok_or(CompressResult::OutOfMemory)?;
+                        let mut buf = Vec::with_capacity(bound);
+                        let ((res, size, _), stats) =
+                            compress_chunk(compressor, i, buf.spare_capacity_mut());
+                        if res != CompressResult::Success {
+                            return Err(res);
                         }
-                    },
-                )
-                .collect();
+                        unsafe {
+                            buf.set_len(size);
+                        }
+                        Ok((buf, checksum.of(chunk), stats))
+                    })
+                    .collect()
+            });
 
             let mut out_idx = 0;
-            for res in compressed_chunks_res {
+            let mut sum = checksum.initial();
+            let mut failure = None;
+            for (res, chunk) in compressed.into_iter().zip(&chunks) {
                 match res {
-                    Ok(data) => {
-                        if out_idx + data.len() > output.len() {
-                            return (CompressResult::InsufficientSpace, 0, 0);
-                        }
+                    Ok((data, chunk_sum, stats)) if out_idx + data.len() <= output.len() => {
                         unsafe {
-                            std::ptr::copy_nonoverlapping(
+                            core::ptr::copy_nonoverlapping(
                                 data.as_ptr(),
                                 output.as_mut_ptr().add(out_idx) as *mut u8,
                                 data.len(),
                             );
                         }
                         out_idx += data.len();
+                        sum = checksum.combine(sum, chunk_sum, chunk.len());
+                        self.add_stats(stats);
+                    }
+                    Ok(_) => {
+                        failure = Some(CompressResult::InsufficientSpace);
+                        break;
+                    }
+                    Err(res) => {
+                        failure = Some(res);
+                        break;
                     }
-                    Err(_) => return (CompressResult::InsufficientSpace, 0, 0),
                 }
             }
-            return (CompressResult::Success, out_idx, 0);
+            failure.map_or(Ok((out_idx, sum)), Err)
+        };
+
+        self.chunk_pool = pool.into_inner().unwrap_or_else(|e| e.into_inner());
+        match result {
+            Ok((size, sum)) => (CompressResult::Success, size, sum),
+            Err(res) => (res, 0, 0),
         }
+    }
 
-        if self.compression_level == 0 {
-            return self.compress_uncompressed(input, output, flush_mode);
+    pub fn compress(
+        &mut self,
+        input: &[u8],
+        output: &mut [MaybeUninit<u8>],
+        flush_mode: FlushMode,
+    ) -> (CompressResult, usize, u32) {
+        self.reset_stats();
+        #[cfg(feature = "std")]
+        if self.parallelism.splits(input.len()) {
+            let (res, size, _) =
+                self.compress_chunks(input, output, flush_mode, ChunkChecksum::None);
+            return (res, size, 0);
+        }
+        self.compress_primed(input, 0, output, flush_mode)
+    }
+
+    /// Like [`Self::compress`], but compresses only `input[start..]`, with `input[..start]`
+    /// as history for matches. Input with history is compressed on the calling thread.
+    #[cfg(feature = "std")]
+    pub(crate) fn compress_after(
+        &mut self,
+        input: &[u8],
+        start: usize,
+        output: &mut [MaybeUninit<u8>],
+        flush_mode: FlushMode,
+    ) -> (CompressResult, usize, u32) {
+        if start == 0 {
+            return self.compress(input, output, flush_mode);
         }
+        self.reset_stats();
+        self.compress_primed(input, start, output, flush_mode)
+    }
 
-        let mut bs = Bitstream::new(output);
+    /// Switches to `level`, reallocating the working memory for it from the same
+    /// allocator. Parallelism, chunk priming and the incompressible probe are kept.
+    pub fn set_level(&mut self, level: usize) {
+        if level == self.compression_level {
+            return;
+        }
+        let mut c = Self::with_allocator_ref(level, self.allocator.clone());
+        c.prime_chunks = self.prime_chunks;
+        c.probe_blocks = self.probe_blocks;
+        c.parallelism = core::mem::take(&mut self.parallelism);
+        c.stats = self.stats.take();
+        *self = c;
+    }
 
-        let mut mf_enum = self.mf.take().unwrap();
+    /// Sets how large inputs are split over threads; see [`Parallelism`].
+    pub fn set_parallelism(&mut self, parallelism: Parallelism) {
+        self.parallelism = parallelism;
+    }
 
-        let res = match &mut mf_enum {
-            MatchFinderEnum::Chain(mf) => self.compress_loop(mf, input, &mut bs, flush_mode),
-            MatchFinderEnum::Table(mf) => self.compress_loop(mf, input, &mut bs, flush_mode),
-            MatchFinderEnum::Bt(mf) => self.compress_loop(mf, input, &mut bs, flush_mode),
-        };
+    pub fn parallelism(&self) -> &Parallelism {
+        &self.parallelism
+    }
 
-        self.mf = Some(mf_enum);
+    /// Sets whether each parallel chunk after the first is primed with the 32 KiB of input
+    /// before it, so that matches may cross chunk boundaries as in serial compression.
+    /// Enabled by default. Without it, a decoder can start at any chunk boundary with an
+    /// empty window, at some cost in ratio.
+    pub fn set_chunk_priming(&mut self, enabled: bool) {
+        self.prime_chunks = enabled;
+    }
+
+    /// Sets whether levels 1 to 12 probe the input ahead of each block and write stretches
+    /// that are not expected to compress, such as already-compressed media, as stored or
+    /// Huffman-only blocks without searching them for matches. Enabled by default from
+    /// level 6 on; at lower levels the probe costs about as much time as it saves.
+    pub fn set_incompressible_probe(&mut self, enabled: bool) {
+        self.probe_blocks = Some(enabled);
+    }
+
+    /// Compresses `input[start..]`, with `input[..start]` as history that matches may refer
+    /// to but that is not itself emitted.
+    fn compress_primed(
+        &mut self,
+        input: &[u8],
+        start: usize,
+        output: &mut [MaybeUninit<u8>],
+        flush_mode: FlushMode,
+    ) -> (CompressResult, usize, u32) {
+        if self.compression_level == 0 {
+            return self.compress_uncompressed(&input[start..], output, flush_mode);
+        }
+
+        let stats = self.stats.clone();
+        let mut bs = Bitstream::new(output);
+        let res = self.compress_bitstream(input, start, &mut bs, flush_mode);
+
+        // Incompressible data can come out larger than the bound; stored blocks always fit it.
+        if res.0 == CompressResult::InsufficientSpace
+            && output.len() >= Self::deflate_compress_bound(input.len() - start)
+        {
+            // The stats describe the stored blocks alone, not the abandoned attempt.
+            self.stats = stats;
+            return self.compress_uncompressed(&input[start..], output, flush_mode);
+        }
         res
     }
 
@@ -797,16 +1271,20 @@ impl Compressor {
     ) -> usize {
         let mut in_idx = 0;
         let mut total_bits = 0;
+        let mut cursor = ProbeCursor::new(0);
         mf.prepare(input.len());
 
         while in_idx < input.len() {
-            let (processed, bits) = if self.compression_level < 2 {
-                self.calculate_block_size_fast(mf, input, in_idx)
-            } else if self.compression_level >= 10 {
-                self.calculate_block_size_near_optimal(mf, input, in_idx)
-            } else {
-                self.calculate_block_size_greedy_lazy(mf, input, in_idx)
-            };
+            let (processed, bits) =
+                if let Some(size) = self.probed_window_size(mf, input, in_idx, &mut cursor) {
+                    size
+                } else if self.compression_level < 2 {
+                    self.calculate_block_size_fast(mf, input, in_idx)
+                } else if self.compression_level >= 10 {
+                    self.calculate_block_size_near_optimal(mf, input, in_idx)
+                } else {
+                    self.calculate_block_size_greedy_lazy(mf, input, in_idx)
+                };
 
             in_idx += processed;
             total_bits += bits;
@@ -973,7 +1451,7 @@ impl Compressor {
                 continue;
             }
 
-            let lit_cost = self.litlen_lens[block_input[pos] as usize] as u32;
+            let lit_cost = self.literal_cost(block_input[pos]);
             if cur_cost + lit_cost < self.dp_costs[pos + 1] {
                 self.dp_costs[pos + 1] = cur_cost + lit_cost;
                 self.dp_path[pos + 1] = 1_u32;
@@ -1070,6 +1548,11 @@ impl Compressor {
         (processed, bits)
     }
 
+    /// An approximation of the raw DEFLATE size in bytes of `input`, ending with a final
+    /// block if `final_block`. It uses this level's match finder, but picks the block
+    /// boundaries and the codes more simply than [`compress`](Self::compress), and writes
+    /// nothing, so the output may differ by a few percent either way. For the exact size,
+    /// see [`estimate_compressed_size`](Self::estimate_compressed_size).
     pub fn compress_to_size(&mut self, input: &[u8], final_block: bool) -> usize {
         if self.compression_level == 0 {
             let num_blocks = input.len() / 65535
@@ -1373,12 +1856,13 @@ impl Compressor {
     }
 
     fn write_dynamic_block_with_sequences(
-        &self,
+        &mut self,
         input: &[u8],
         start_pos: usize,
         bs: &mut Bitstream,
         is_final: bool,
     ) -> bool {
+        let start = self.begin_block(bs);
         if !bs.write_bits(if is_final { 1 } else { 0 }, 1) {
             return false;
         }
@@ -1388,12 +1872,40 @@ impl Compressor {
         if !self.write_dynamic_huffman_header_impl(bs) {
             return false;
         }
+        let header_end = bs.bits_written();
+        if !self.write_sequences_to_bitstream(bs, input, start_pos) {
+            return false;
+        }
+        if !self.write_sym(bs, 256) {
+            return false;
+        }
+        self.end_block(start, BlockType::Dynamic, header_end, bs);
+        true
+    }
+
+    /// Writes `sequences` as a block with the static Huffman codes, which must be loaded.
+    fn write_static_block_with_sequences(
+        &mut self,
+        input: &[u8],
+        start_pos: usize,
+        bs: &mut Bitstream,
+        is_final: bool,
+    ) -> bool {
+        let start = self.begin_block(bs);
+        if !bs.write_bits(if is_final { 1 } else { 0 }, 1) {
+            return false;
+        }
+        if !bs.write_bits(1, 2) {
+            return false;
+        }
+        let header_end = bs.bits_written();
         if !self.write_sequences_to_bitstream(bs, input, start_pos) {
             return false;
         }
         if !self.write_sym(bs, 256) {
             return false;
         }
+        self.end_block(start, BlockType::Static, header_end, bs);
         true
     }
 
@@ -1405,13 +1917,15 @@ impl Compressor {
     ) -> (CompressResult, usize, u32) {
         let mut bs = Bitstream::new(output);
         let mut in_idx = 0;
-        while in_idx < input.len() {
+        // Empty input still needs one (empty) block to form a valid stream.
+        loop {
             let bfinal = if in_idx + 65535 >= input.len() && flush_mode == FlushMode::Finish {
                 1
             } else {
                 0
             };
             let block_len = min(65535, input.len() - in_idx);
+            let start = self.begin_block(&bs);
             if !bs.write_bits(bfinal, 1) || !bs.write_bits(0, 2) {
                 return (CompressResult::InsufficientSpace, 0, 0);
             }
@@ -1425,29 +1939,35 @@ impl Compressor {
             let len = block_len as u16;
             let nlen = !len;
             unsafe {
-                std::ptr::copy_nonoverlapping(
+                core::ptr::copy_nonoverlapping(
                     len.to_le_bytes().as_ptr(),
                     bs.output.as_mut_ptr().add(bs.out_idx) as *mut u8,
                     2,
                 );
-                std::ptr::copy_nonoverlapping(
+                core::ptr::copy_nonoverlapping(
                     nlen.to_le_bytes().as_ptr(),
                     bs.output.as_mut_ptr().add(bs.out_idx + 2) as *mut u8,
                     2,
                 );
             }
             bs.out_idx += 4;
+            let header_end = bs.bits_written();
             unsafe {
-                std::ptr::copy_nonoverlapping(
+                core::ptr::copy_nonoverlapping(
                     input.as_ptr().add(in_idx),
                     bs.output.as_mut_ptr().add(bs.out_idx) as *mut u8,
                     block_len,
                 );
             }
             bs.out_idx += block_len;
+            self.end_block(start, BlockType::Stored, header_end, &bs);
             in_idx += block_len;
+            if in_idx >= input.len() {
+                break;
+            }
         }
         if flush_mode == FlushMode::Sync {
+            let block = self.begin_block(&bs);
             if bs.out_idx + 5 > bs.output.len() {
                 return (CompressResult::InsufficientSpace, 0, 0);
             }
@@ -1458,6 +1978,7 @@ impl Compressor {
             bs.output[bs.out_idx + 2].write(0xFF);
             bs.output[bs.out_idx + 3].write(0xFF);
             bs.out_idx += 4;
+            self.end_block(block, BlockType::Stored, bs.bits_written(), &bs);
         }
 
         (CompressResult::Success, bs.out_idx, 0)
@@ -1567,17 +2088,7 @@ impl Compressor {
 
         let processed = in_idx - start_pos;
         let is_final = (start_pos + processed >= input.len()) && final_block;
-        if !bs.write_bits(if is_final { 1 } else { 0 }, 1) {
-            return 0;
-        }
-        if !bs.write_bits(1, 2) {
-            return 0;
-        }
-
-        if !self.write_sequences_to_bitstream(bs, input, start_pos) {
-            return 0;
-        }
-        if !self.write_sym(bs, 256) {
+        if !self.write_static_block_with_sequences(input, start_pos, bs, is_final) {
             return 0;
         }
         processed
@@ -1590,6 +2101,39 @@ impl Compressor {
         start_pos: usize,
         bs: &mut Bitstream,
         final_block: bool,
+    ) -> usize {
+        let processed = self.decide_near_optimal_sequences(mf, input, start_pos);
+        let is_final = (start_pos + processed >= input.len()) && final_block;
+
+        make_huffman_code(
+            DEFLATE_NUM_LITLEN_SYMS,
+            MAX_LITLEN_CODEWORD_LEN,
+            &self.litlen_freqs,
+            &mut self.litlen_lens,
+            &mut self.litlen_codewords,
+        );
+        make_huffman_code(
+            DEFLATE_NUM_OFFSET_SYMS,
+            MAX_OFFSET_CODEWORD_LEN,
+            &self.offset_freqs,
+            &mut self.offset_lens,
+            &mut self.offset_codewords,
+        );
+        self.update_huffman_tables();
+
+        if !self.write_dynamic_block_with_sequences(input, start_pos, bs, is_final) {
+            return 0;
+        }
+        processed
+    }
+
+    /// Chooses the next block's extent and fills `sequences` and the symbol frequencies
+    /// with its minimum-cost parse. Returns the number of input bytes in the block.
+    fn decide_near_optimal_sequences<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start_pos: usize,
     ) -> usize {
         self.split_stats.reset();
         self.litlen_freqs.fill(0);
@@ -1628,7 +2172,6 @@ impl Compressor {
 
         let processed = in_idx - start_pos;
         let block_input = &input[start_pos..start_pos + processed];
-        let is_final = (start_pos + processed >= input.len()) && final_block;
 
         self.sequences.clear();
         self.litlen_freqs[256] += 1;
@@ -1671,7 +2214,7 @@ impl Compressor {
                 continue;
             }
 
-            let lit_cost = self.litlen_lens[block_input[pos] as usize] as u32;
+            let lit_cost = self.literal_cost(block_input[pos]);
             if cur_cost + lit_cost < self.dp_costs[pos + 1] {
                 self.dp_costs[pos + 1] = cur_cost + lit_cost;
                 self.dp_path[pos + 1] = 1_u32;
@@ -1749,26 +2292,6 @@ impl Compressor {
             }
         }
         self.sequences.push(Sequence::new(litrunlen, 0, 0, 0));
-
-        make_huffman_code(
-            DEFLATE_NUM_LITLEN_SYMS,
-            MAX_LITLEN_CODEWORD_LEN,
-            &self.litlen_freqs,
-            &mut self.litlen_lens,
-            &mut self.litlen_codewords,
-        );
-        make_huffman_code(
-            DEFLATE_NUM_OFFSET_SYMS,
-            MAX_OFFSET_CODEWORD_LEN,
-            &self.offset_freqs,
-            &mut self.offset_lens,
-            &mut self.offset_codewords,
-        );
-        self.update_huffman_tables();
-
-        if !self.write_dynamic_block_with_sequences(input, start_pos, bs, is_final) {
-            return 0;
-        }
         processed
     }
 
@@ -1980,7 +2503,7 @@ impl Compressor {
                             if new_bitcount >= 64 {
                                 let low = bitbuf | (code << bitcount);
                                 let high = code >> (64 - bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u64,
                                     low.to_le(),
                                 );
@@ -1990,7 +2513,7 @@ impl Compressor {
                             } else {
                                 bitbuf |= code << bitcount;
                                 if new_bitcount >= 32 {
-                                    std::ptr::write_unaligned(
+                                    core::ptr::write_unaligned(
                                         out_ptr.add(out_idx) as *mut u32,
                                         (bitbuf as u32).to_le(),
                                     );
@@ -2014,7 +2537,7 @@ impl Compressor {
                             let new_bitcount = bitcount + len;
                             if new_bitcount >= 32 {
                                 let buf = bitbuf | ((code as u64) << bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u32,
                                     (buf as u32).to_le(),
                                 );
@@ -2035,7 +2558,7 @@ impl Compressor {
                             let new_bitcount = bitcount + len;
                             if new_bitcount >= 32 {
                                 let buf = bitbuf | ((code as u64) << bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u32,
                                     (buf as u32).to_le(),
                                 );
@@ -2090,7 +2613,7 @@ impl Compressor {
                             let new_bitcount = bitcount + combined_len;
                             if new_bitcount >= 32 {
                                 let buf = bitbuf | ((code as u64) << bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u32,
                                     (buf as u32).to_le(),
                                 );
@@ -2105,7 +2628,7 @@ impl Compressor {
                             let new_bitcount = bitcount + len_len;
                             if new_bitcount >= 32 {
                                 let buf = bitbuf | ((len_val as u64) << bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u32,
                                     (buf as u32).to_le(),
                                 );
@@ -2120,7 +2643,7 @@ impl Compressor {
                             let new_bitcount = bitcount + off_len_total;
                             if new_bitcount >= 32 {
                                 let buf = bitbuf | ((off_val as u64) << bitcount);
-                                std::ptr::write_unaligned(
+                                core::ptr::write_unaligned(
                                     out_ptr.add(out_idx) as *mut u32,
                                     (buf as u32).to_le(),
                                 );
@@ -2212,17 +2735,72 @@ impl Compressor {
             let len_slot = (len_info >> 24) as usize;
             let len_extra_bits = (len_info >> 16) & 0xFF;
 
-            let len_cost =
-                unsafe { *self.litlen_lens.get_unchecked(257 + len_slot) } as u32 + len_extra_bits;
+            let len_cost = match unsafe { *self.litlen_lens.get_unchecked(257 + len_slot) } {
+                0 => LENGTH_NOSTAT_BITS,
+                bits => bits as u32,
+            } + len_extra_bits;
             unsafe { *self.length_costs.get_unchecked_mut(len) = len_cost };
         }
         for slot in 0..30 {
             let extra_bits = unsafe { *OFFSET_EXTRA_BITS_TABLE.get_unchecked(slot) } as u32;
-            let off_cost = unsafe { *self.offset_lens.get_unchecked(slot) } as u32 + extra_bits;
+            let off_cost = match unsafe { *self.offset_lens.get_unchecked(slot) } {
+                0 => OFFSET_NOSTAT_BITS,
+                bits => bits as u32,
+            } + extra_bits;
             unsafe { *self.offset_slot_costs.get_unchecked_mut(slot) = off_cost };
         }
     }
 
+    /// Runs `compress_loop` with the match finder of this level, timing it for the stats.
+    fn compress_bitstream(
+        &mut self,
+        input: &[u8],
+        start: usize,
+        bs: &mut Bitstream,
+        flush_mode: FlushMode,
+    ) -> (CompressResult, usize, u32) {
+        let timer = self.begin_match_finding();
+        let mut mf_enum = self.mf.take().unwrap();
+
+        let res = match &mut mf_enum {
+            MatchFinderEnum::Chain(mf) => self.compress_loop(mf, input, start, bs, flush_mode),
+            MatchFinderEnum::Table(mf) => self.compress_loop(mf, input, start, bs, flush_mode),
+            MatchFinderEnum::Bt(mf) => self.compress_loop(mf, input, start, bs, flush_mode),
+        };
+
+        self.mf = Some(mf_enum);
+        self.end_match_finding(timer);
+        res
+    }
+
+    /// Compresses with [`FlushMode::Finish`] and also returns the checksum of `input`. Large
+    /// inputs are checksummed chunk by chunk inside the parallel compression tasks.
+    fn compress_finish_with_checksum(
+        &mut self,
+        input: &[u8],
+        output: &mut [MaybeUninit<u8>],
+        checksum: ChunkChecksum,
+    ) -> (CompressResult, usize, u32) {
+        self.reset_stats();
+        #[cfg(feature = "std")]
+        if self.parallelism.splits(input.len()) {
+            return self.compress_chunks(input, output, FlushMode::Finish, checksum);
+        }
+        let (res, size, _) = self.compress(input, output, FlushMode::Finish);
+        if res != CompressResult::Success {
+            return (res, 0, 0);
+        }
+        (res, size, checksum.of(input))
+    }
+
+    #[inline(always)]
+    fn literal_cost(&self, lit: u8) -> u32 {
+        match self.litlen_lens[lit as usize] {
+            0 => LITERAL_NOSTAT_BITS,
+            bits => bits as u32,
+        }
+    }
+
     #[inline(always)]
     fn get_match_cost(&self, len: usize, offset: usize) -> u32 {
         unsafe {
@@ -2234,7 +2812,20 @@ impl Compressor {
     }
 
     pub fn deflate_compress_bound(size: usize) -> usize {
-        size.saturating_add((size / 65535 + 1) * 5 + 10)
+        // Large inputs are compressed as independent chunks, each with its own block overhead.
+        let chunks = size.div_ceil(PARALLEL_CHUNK_SIZE).max(1);
+        size.saturating_add((size / 65535) * 5)
+            .saturating_add(chunks * 15)
+    }
+
+    /// Like [`deflate_compress_bound`](Self::deflate_compress_bound), but also covers the
+    /// per-chunk overhead of a chunk size set through [`set_parallelism`](Self::set_parallelism).
+    pub fn deflate_bound(&self, size: usize) -> usize {
+        let chunks = size.div_ceil(self.parallelism.chunk_size()).max(1);
+        Self::deflate_compress_bound(size).max(
+            size.saturating_add((size / 65535) * 5)
+                .saturating_add(chunks.saturating_mul(15)),
+        )
     }
 
     pub fn zlib_compress_bound(size: usize) -> usize {
@@ -2245,15 +2836,8 @@ impl Compressor {
         GZIP_MIN_OVERHEAD.saturating_add(Self::deflate_compress_bound(size))
     }
 
-    pub fn compress_zlib(
-        &mut self,
-        input: &[u8],
-        output: &mut [MaybeUninit<u8>],
-    ) -> (CompressResult, usize) {
-        if output.len() < ZLIB_MIN_OVERHEAD {
-            return (CompressResult::InsufficientSpace, 0);
-        }
-        let mut out_idx = 0;
+    /// The two-byte zlib header, with the level hint for this compressor's level.
+    pub(crate) fn zlib_header(&self) -> [u8; 2] {
         let mut hdr = (ZLIB_CM_DEFLATE as u16) << 8;
         hdr |= (ZLIB_CINFO_32K_WINDOW as u16) << 12;
         let level_hint = if self.compression_level < 2 {
@@ -2267,27 +2851,62 @@ impl Compressor {
         };
         hdr |= (level_hint as u16) << 6;
         hdr |= 31 - (hdr % 31);
+        hdr.to_be_bytes()
+    }
+
+    /// The fixed ten bytes of a gzip member header; FNAME is set if a name follows.
+    pub(crate) fn gzip_fixed_header(
+        &self,
+        mtime: u32,
+        has_name: bool,
+    ) -> [u8; GZIP_MIN_HEADER_SIZE] {
+        let mut xfl = 0u8;
+        if self.compression_level < 2 {
+            xfl |= GZIP_XFL_FASTEST_COMPRESSION;
+        } else if self.compression_level >= 8 {
+            xfl |= GZIP_XFL_SLOWEST_COMPRESSION;
+        }
+        let mtime = mtime.to_le_bytes();
+        [
+            GZIP_ID1,
+            GZIP_ID2,
+            GZIP_CM_DEFLATE,
+            if has_name { GZIP_FNAME } else { 0 },
+            mtime[0],
+            mtime[1],
+            mtime[2],
+            mtime[3],
+            xfl,
+            GZIP_OS_UNKNOWN,
+        ]
+    }
+
+    pub fn compress_zlib(
+        &mut self,
+        input: &[u8],
+        output: &mut [MaybeUninit<u8>],
+    ) -> (CompressResult, usize) {
+        if output.len() < ZLIB_MIN_OVERHEAD {
+            return (CompressResult::InsufficientSpace, 0);
+        }
+        let mut out_idx = 0;
+        let hdr = self.zlib_header();
         unsafe {
-            std::ptr::copy_nonoverlapping(
-                hdr.to_be_bytes().as_ptr(),
-                output.as_mut_ptr().add(0) as *mut u8,
-                2,
-            );
+            core::ptr::copy_nonoverlapping(hdr.as_ptr(), output.as_mut_ptr() as *mut u8, 2);
         }
         out_idx += 2;
         let out_len = output.len();
-        let (res, deflate_size, _) = self.compress(
+        let (res, deflate_size, adler) = self.compress_finish_with_checksum(
             input,
             &mut output[out_idx..out_len - ZLIB_FOOTER_SIZE],
-            FlushMode::Finish,
+            ChunkChecksum::Adler32,
         );
         if res != CompressResult::Success {
             return (res, 0);
         }
         out_idx += deflate_size;
-        let adler = crate::adler32::adler32(1, input);
         unsafe {
-            std::ptr::copy_nonoverlapping(
+            core::ptr::copy_nonoverlapping(
                 adler.to_be_bytes().as_ptr(),
                 output.as_mut_ptr().add(out_idx) as *mut u8,
                 4,
@@ -2302,43 +2921,58 @@ impl Compressor {
         input: &[u8],
         output: &mut [MaybeUninit<u8>],
     ) -> (CompressResult, usize) {
-        if output.len() < GZIP_MIN_OVERHEAD {
+        self.compress_gzip_with_header(input, output, &GzipHeader::default())
+    }
+
+    /// Like [`compress_gzip`](Self::compress_gzip), but records `header.mtime` and
+    /// `header.filename` in the gzip header. The output needs
+    /// `header.encoded_len() - GZIP_MIN_HEADER_SIZE` bytes more than
+    /// [`gzip_compress_bound`](Self::gzip_compress_bound).
+    pub fn compress_gzip_with_header(
+        &mut self,
+        input: &[u8],
+        output: &mut [MaybeUninit<u8>],
+        header: &GzipHeader,
+    ) -> (CompressResult, usize) {
+        let header_len = header.encoded_len();
+        if output.len() < header_len + GZIP_FOOTER_SIZE {
             return (CompressResult::InsufficientSpace, 0);
         }
+        let filename = header.filename_bytes();
         let mut out_idx = 0;
-        output[0].write(GZIP_ID1);
-        output[1].write(GZIP_ID2);
-        output[2].write(GZIP_CM_DEFLATE);
-        output[3].write(0);
+        let fixed = self.gzip_fixed_header(header.mtime, filename.is_some());
         unsafe {
-            std::ptr::copy_nonoverlapping(
-                GZIP_MTIME_UNAVAILABLE.to_le_bytes().as_ptr(),
-                output.as_mut_ptr().add(4) as *mut u8,
-                4,
+            core::ptr::copy_nonoverlapping(
+                fixed.as_ptr(),
+                output.as_mut_ptr() as *mut u8,
+                GZIP_MIN_HEADER_SIZE,
             );
         }
-        let mut xfl = 0u8;
-        if self.compression_level < 2 {
-            xfl |= GZIP_XFL_FASTEST_COMPRESSION;
-        } else if self.compression_level >= 8 {
-            xfl |= GZIP_XFL_SLOWEST_COMPRESSION;
+        out_idx += GZIP_MIN_HEADER_SIZE;
+        if let Some(name) = filename {
+            unsafe {
+                core::ptr::copy_nonoverlapping(
+                    name.as_ptr(),
+                    output.as_mut_ptr().add(out_idx) as *mut u8,
+                    name.len(),
+                );
+            }
+            out_idx += name.len();
+            output[out_idx].write(0);
+            out_idx += 1;
         }
-        output[8].write(xfl);
-        output[9].write(GZIP_OS_UNKNOWN);
-        out_idx += 10;
         let out_len = output.len();
-        let (res, deflate_size, _) = self.compress(
+        let (res, deflate_size, crc) = self.compress_finish_with_checksum(
             input,
             &mut output[out_idx..out_len - GZIP_FOOTER_SIZE],
-            FlushMode::Finish,
+            ChunkChecksum::Crc32,
         );
         if res != CompressResult::Success {
             return (res, 0);
         }
         out_idx += deflate_size;
-        let crc = crate::crc32::crc32(0, input);
         unsafe {
-            std::ptr::copy_nonoverlapping(
+            core::ptr::copy_nonoverlapping(
                 crc.to_le_bytes().as_ptr(),
                 output.as_mut_ptr().add(out_idx) as *mut u8,
                 4,
@@ -2346,7 +2980,7 @@ impl Compressor {
         }
         out_idx += 4;
         unsafe {
-            std::ptr::copy_nonoverlapping(
+            core::ptr::copy_nonoverlapping(
                 (input.len() as u32).to_le_bytes().as_ptr(),
                 output.as_mut_ptr().add(out_idx) as *mut u8,
                 4,
@@ -0,0 +1,590 @@
+use super::bitstream::Bitstream;
+use super::huffman_comp::make_huffman_code;
+use super::matchfinder::{MATCHFINDER_WINDOW_SIZE, MatchFinderTrait};
+use super::tokens::Token;
+use super::{
+    Compressor, LENGTH_EXTRA_BITS_TABLE, MATCHES_CAPACITY, MAX_LITLEN_CODEWORD_LEN,
+    MAX_OFFSET_CODEWORD_LEN, OFFSET_EXTRA_BITS_TABLE,
+};
+use crate::allocator::{AllocError, AllocVec};
+use crate::common::*;
+use core::cmp::min;
+use core::mem;
+
+/// Longest stretch of input that levels 13 and up parse and split as a whole.
+const EXHAUSTIVE_SEGMENT_LEN: usize = 1 << 18;
+/// Blocks are never split into pieces shorter than this many bytes.
+const MIN_SPLIT_BLOCK_LEN: usize = 1024;
+/// Capacity of `match_cache_index`: an entry per byte of a segment, and one for its end.
+pub(super) const MATCH_CACHE_INDEX_CAPACITY: usize = EXHAUSTIVE_SEGMENT_LEN + 1;
+/// Capacity of `scratch_path` and `optimal_path`: every token covers at least one byte.
+pub(super) const PATH_CAPACITY: usize = EXHAUSTIVE_SEGMENT_LEN;
+/// Capacity of `optimal_blocks`: every block of a segment but the last is at least
+/// [`MIN_SPLIT_BLOCK_LEN`] bytes long.
+pub(super) const PLANNED_BLOCKS_CAPACITY: usize = EXHAUSTIVE_SEGMENT_LEN / MIN_SPLIT_BLOCK_LEN + 1;
+/// Most entries `match_cache` holds: as many matches as `find_matches` can report at every
+/// position of a segment.
+const MATCH_CACHE_MAX: usize = EXHAUSTIVE_SEGMENT_LEN * MATCHES_CAPACITY;
+/// The most levels 13 and up allocate while compressing, beyond their initial capacities:
+/// `match_cache`, which grows with the matches of the data, up to [`MATCH_CACHE_MAX`] with
+/// the old buffer still held while it moves, and the scratch of `iterate_parse` and
+/// `find_block_splits`.
+pub(super) const SEGMENT_SCRATCH_BOUND: usize =
+    2 * AllocVec::<(u16, u16)>::footprint(MATCH_CACHE_MAX)
+        + AllocVec::<Token>::footprint(EXHAUSTIVE_SEGMENT_LEN)
+        + AllocVec::<usize>::footprint(EXHAUSTIVE_SEGMENT_LEN + 1)
+        + 2 * AllocVec::<usize>::footprint(PLANNED_BLOCKS_CAPACITY);
+/// Evenly spaced split points evaluated in each round of the boundary search.
+const SPLIT_CANDIDATES: usize = 16;
+/// Parse costs are kept in 1/16 bit units.
+const COST_SHIFT: u32 = 4;
+const INFINITE_COST: u32 = 0x3FFFFFFF;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum BlockType {
+    Stored,
+    Static,
+    Dynamic,
+}
+
+/// One block chosen by the exhaustive search: `tokens_end` indexes `optimal_path` and
+/// `len` is the number of input bytes it covers.
+#[derive(Clone, Copy, Debug)]
+pub(super) struct PlannedBlock {
+    tokens_end: usize,
+    len: usize,
+    block_type: BlockType,
+}
+
+fn entropy_cost(count: u32, total: u32) -> u32 {
+    ((log2(total.max(1)) - log2(count.max(1))).max(1.0) * (1 << COST_SHIFT) as f64) as u32
+}
+
+#[cfg(feature = "std")]
+fn log2(x: u32) -> f64 {
+    (x as f64).log2()
+}
+
+/// `f64::log2` needs `std`; with `x = 2^k * m`, `log2(m)` comes from the series
+/// `ln(m) = 2 * atanh((m - 1) / (m + 1))`, which for `m` in `[1, 2)` converges to well
+/// below the precision the costs keep.
+#[cfg(not(feature = "std"))]
+fn log2(x: u32) -> f64 {
+    let k = 31 - x.leading_zeros();
+    let m = x as f64 / (1u64 << k) as f64;
+    let z = (m - 1.0) / (m + 1.0);
+    let z2 = z * z;
+    let mut term = z;
+    let mut sum = 0.0;
+    for i in 0..16 {
+        sum += term / (2 * i + 1) as f64;
+        term *= z2;
+    }
+    k as f64 + 2.0 * sum * core::f64::consts::LOG2_E
+}
+
+/// Size in bits of `len` bytes sent as stored blocks, assuming the worst-case padding.
+pub(super) fn stored_size(len: usize) -> usize {
+    let blocks = len.div_ceil(65535).max(1);
+    blocks * (3 + 7 + 32) + len * 8
+}
+
+impl Compressor {
+    /// How many times levels 13 and up re-parse a stretch of input with the costs of the
+    /// previous parse, and how many passes in a row may fail to shrink the encoded size
+    /// before the cost model counts as converged.
+    fn optimization_passes(&self) -> (usize, usize) {
+        match self.compression_level {
+            0..=13 => (8, 0),
+            14 => (16, 2),
+            15 => (32, 4),
+            _ => (64, 8),
+        }
+    }
+
+    /// Compresses up to [`EXHAUSTIVE_SEGMENT_LEN`] bytes at `start_pos` as one or more
+    /// blocks and returns the number of bytes consumed, or 0 if the output is full.
+    pub(super) fn compress_exhaustive_segment<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start_pos: usize,
+        bs: &mut Bitstream,
+        final_block: bool,
+    ) -> Result<usize, AllocError> {
+        let processed = self.decide_exhaustive_blocks(mf, input, start_pos)?;
+        let is_last_segment = start_pos + processed >= input.len() && final_block;
+
+        let path = mem::take(&mut self.optimal_path);
+        let blocks = mem::take(&mut self.optimal_blocks);
+        let mut ok = true;
+        let mut block_start = start_pos;
+        let mut tokens_start = 0;
+        for (i, block) in blocks.iter().enumerate() {
+            let is_final = is_last_segment && i + 1 == blocks.len();
+            let tokens = &path[tokens_start..block.tokens_end];
+            ok = match block.block_type {
+                BlockType::Stored => self.write_stored_blocks(
+                    bs,
+                    &input[block_start..block_start + block.len],
+                    is_final,
+                ),
+                BlockType::Static => {
+                    self.load_token_block(tokens);
+                    self.load_static_huffman_codes();
+                    self.write_static_block_with_sequences(input, block_start, bs, is_final)
+                }
+                BlockType::Dynamic => {
+                    self.load_token_block(tokens);
+                    self.make_block_codes();
+                    self.update_huffman_tables();
+                    self.write_dynamic_block_with_sequences(input, block_start, bs, is_final)
+                }
+            };
+            if !ok {
+                break;
+            }
+            block_start += block.len;
+            tokens_start = block.tokens_end;
+        }
+        self.optimal_path = path;
+        self.optimal_blocks = blocks;
+        Ok(if ok { processed } else { 0 })
+    }
+
+    /// Parses the next segment of input with an iterated cost model, searches it for the
+    /// block boundaries that minimize the encoded size, re-optimizes each block on its own
+    /// and picks its block type. The result is left in `optimal_path` and `optimal_blocks`.
+    pub(super) fn decide_exhaustive_blocks<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start_pos: usize,
+    ) -> Result<usize, AllocError> {
+        let end = min(input.len(), start_pos + EXHAUSTIVE_SEGMENT_LEN);
+        self.cache_matches(mf, input, start_pos, end)?;
+
+        self.load_static_huffman_codes();
+        self.update_costs_from_lens();
+        let mut segment_path = mem::take(&mut self.scratch_path);
+        segment_path.clear();
+        let parsed = self.iterate_parse(input, start_pos, 0, end - start_pos, &mut segment_path);
+        let splits = parsed.and_then(|()| self.find_block_splits(&segment_path));
+        let splits = match splits {
+            Ok(splits) => splits,
+            Err(e) => {
+                self.scratch_path = segment_path;
+                return Err(e);
+            }
+        };
+
+        let mut path = mem::take(&mut self.optimal_path);
+        path.clear();
+        self.optimal_blocks.clear();
+        let mut tokens_start = 0;
+        let mut block_start = 0;
+        for tokens_end in splits.iter().copied().chain(Some(segment_path.len())) {
+            let block_tokens = &segment_path[tokens_start..tokens_end];
+            let block_len: usize = block_tokens.iter().map(Token::uncompressed_len).sum();
+
+            self.load_token_block(block_tokens);
+            self.update_entropy_costs();
+            let path_start = path.len();
+            let parsed = self.iterate_parse(
+                input,
+                start_pos,
+                block_start,
+                block_start + block_len,
+                &mut path,
+            );
+            if let Err(e) = parsed {
+                self.optimal_path = path;
+                self.scratch_path = segment_path;
+                return Err(e);
+            }
+            let (_, block_type) = self.block_cost(&path[path_start..], block_len);
+            self.optimal_blocks.push(PlannedBlock {
+                tokens_end: path.len(),
+                len: block_len,
+                block_type,
+            });
+
+            tokens_start = tokens_end;
+            block_start += block_len;
+        }
+        self.optimal_path = path;
+        self.scratch_path = segment_path;
+        Ok(end - start_pos)
+    }
+
+    /// Records every match the binary tree finds in `input[start..end]`, with the preceding
+    /// window inserted first so that matches can reach back before `start`.
+    fn cache_matches<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start: usize,
+        end: usize,
+    ) -> Result<(), AllocError> {
+        let context_start = start.saturating_sub(MATCHFINDER_WINDOW_SIZE);
+        let data = &input[context_start..end];
+        let context_len = start - context_start;
+
+        mf.reset();
+        mf.skip_positions(
+            data,
+            0,
+            context_len,
+            self.max_search_depth,
+            self.nice_match_length,
+        );
+
+        self.match_cache.clear();
+        self.match_cache_index.clear();
+        let mut pos = context_len;
+        while pos < data.len() {
+            self.match_cache_index.push(self.match_cache.len() as u32);
+            mf.find_matches(
+                data,
+                pos,
+                self.max_search_depth,
+                self.nice_match_length,
+                &mut self.matches,
+            );
+            let needed = self.match_cache.len() + self.matches.len();
+            if needed > self.match_cache.capacity() {
+                let capacity = (self.match_cache.capacity() * 2).clamp(needed, MATCH_CACHE_MAX);
+                self.match_cache.try_reserve_total(capacity)?;
+            }
+            self.match_cache.extend_from_slice(&self.matches);
+            let best_len = self.matches.last().map_or(0, |&(len, _)| len as usize);
+            if best_len >= self.nice_match_length {
+                // Long runs would make the tree search quadratic; only the first position
+                // of the run gets its matches.
+                let skip = min(best_len, data.len() - pos);
+                mf.skip_positions(
+                    data,
+                    pos + 1,
+                    skip - 1,
+                    self.max_search_depth,
+                    self.nice_match_length,
+                );
+                for _ in 1..skip {
+                    self.match_cache_index.push(self.match_cache.len() as u32);
+                }
+                pos += skip;
+            } else {
+                pos += 1;
+            }
+        }
+        self.match_cache_index.push(self.match_cache.len() as u32);
+        Ok(())
+    }
+
+    /// Re-parses `[start, end)` of the segment, updating the cost model from each parse, until
+    /// the encoded size stops shrinking. Appends the smallest parse found to `out`.
+    fn iterate_parse(
+        &mut self,
+        input: &[u8],
+        segment_start: usize,
+        start: usize,
+        end: usize,
+        out: &mut AllocVec<Token>,
+    ) -> Result<(), AllocError> {
+        let (passes, patience) = self.optimization_passes();
+        let out_start = out.len();
+        let mut best_bits = usize::MAX;
+        let mut best_litlen_freqs = [0; DEFLATE_NUM_LITLEN_SYMS];
+        let mut best_offset_freqs = [0; DEFLATE_NUM_OFFSET_SYMS];
+        let mut misses = 0;
+        let mut candidate = AllocVec::try_with_capacity_in(end - start, &self.allocator)?;
+        for _ in 0..passes {
+            candidate.clear();
+            self.parse_with_costs(input, segment_start, start, end, &mut candidate);
+            // `block_cost` leaves the candidate's symbol frequencies loaded.
+            let (bits, _) = self.block_cost(&candidate, end - start);
+            if bits < best_bits {
+                best_bits = bits;
+                best_litlen_freqs = self.litlen_freqs;
+                best_offset_freqs = self.offset_freqs;
+                out.truncate(out_start);
+                out.extend_from_slice(&candidate);
+                misses = 0;
+            } else {
+                misses += 1;
+                if misses > patience {
+                    break;
+                }
+                // Feeding a worse parse's statistics straight back tends to cycle, so
+                // average them with the best ones to land on a new cost model.
+                for (f, &b) in self.litlen_freqs.iter_mut().zip(&best_litlen_freqs) {
+                    *f = (*f + b).div_ceil(2);
+                }
+                for (f, &b) in self.offset_freqs.iter_mut().zip(&best_offset_freqs) {
+                    *f = (*f + b).div_ceil(2);
+                }
+            }
+            self.update_entropy_costs();
+        }
+        Ok(())
+    }
+
+    /// Finds the minimum-cost parse of `[start, end)` of the segment under the current
+    /// `literal_costs`, `length_costs` and `offset_slot_costs`. Every length up to each
+    /// cached match is considered, not only the longest.
+    fn parse_with_costs(
+        &mut self,
+        input: &[u8],
+        segment_start: usize,
+        start: usize,
+        end: usize,
+        out: &mut AllocVec<Token>,
+    ) {
+        let n = end - start;
+        self.dp_costs.clear();
+        self.dp_costs.resize(n + 1, INFINITE_COST);
+        self.dp_costs[0] = 0;
+        self.dp_path.clear();
+        self.dp_path.resize(n + 1, 0);
+
+        for i in 0..n {
+            let cur_cost = self.dp_costs[i];
+            let pos = start + i;
+            let lit = input[segment_start + pos];
+            let lit_cost = cur_cost + self.literal_costs[lit as usize];
+            if lit_cost < self.dp_costs[i + 1] {
+                self.dp_costs[i + 1] = lit_cost;
+                self.dp_path[i + 1] = 1;
+            }
+
+            let matches =
+                self.match_cache_index[pos] as usize..self.match_cache_index[pos + 1] as usize;
+            let mut prev_len = DEFLATE_MIN_MATCH_LEN - 1;
+            for &(len, offset) in &self.match_cache[matches] {
+                let len = min(len as usize, n - i);
+                if len <= prev_len {
+                    break;
+                }
+                let off_cost = self.offset_slot_costs[self.get_offset_slot(offset as usize)];
+                for l in prev_len + 1..=len {
+                    let cost = cur_cost + self.length_costs[l] + off_cost;
+                    if cost < self.dp_costs[i + l] {
+                        self.dp_costs[i + l] = cost;
+                        self.dp_path[i + l] = (l as u32) | ((offset as u32) << 16);
+                    }
+                }
+                prev_len = len;
+            }
+        }
+
+        let out_start = out.len();
+        let mut i = n;
+        while i > 0 {
+            let packed = self.dp_path[i];
+            let len = (packed & 0xFFFF) as usize;
+            if len == 1 {
+                out.push(Token::Literal(input[segment_start + start + i - 1]));
+            } else {
+                out.push(Token::Match {
+                    length: len as u16,
+                    distance: (packed >> 16) as u16,
+                });
+            }
+            i -= len;
+        }
+        out[out_start..].reverse();
+    }
+
+    /// Recursively splits the segment's tokens wherever two blocks with their own codes
+    /// encode smaller than one. Returns the token indices that start a new block.
+    fn find_block_splits(&mut self, tokens: &[Token]) -> Result<AllocVec<usize>, AllocError> {
+        let mut offsets = AllocVec::try_with_capacity_in(tokens.len() + 1, &self.allocator)?;
+        let mut pos = 0;
+        offsets.push(0);
+        for token in tokens {
+            pos += token.uncompressed_len();
+            offsets.push(pos);
+        }
+
+        // Both sides of a split get MIN_SPLIT_BLOCK_LEN bytes, which bounds the splits and
+        // the ranges waiting to be split.
+        let max_splits = pos / MIN_SPLIT_BLOCK_LEN + 1;
+        let mut splits = AllocVec::try_with_capacity_in(max_splits, &self.allocator)?;
+        let mut pending = AllocVec::try_with_capacity_in(max_splits, &self.allocator)?;
+        pending.push((0, tokens.len()));
+        while let Some((lo, hi)) = pending.pop() {
+            if offsets[hi] - offsets[lo] < 2 * MIN_SPLIT_BLOCK_LEN {
+                continue;
+            }
+            let (whole, _) = self.block_cost(&tokens[lo..hi], offsets[hi] - offsets[lo]);
+
+            // Only split points that leave MIN_SPLIT_BLOCK_LEN bytes on both sides.
+            let first = offsets[lo..hi].partition_point(|&o| o < offsets[lo] + MIN_SPLIT_BLOCK_LEN);
+            let last = offsets[lo..hi].partition_point(|&o| o <= offsets[hi] - MIN_SPLIT_BLOCK_LEN);
+            let (mut left, mut right) = (lo + first, lo + last);
+            if left >= right {
+                continue;
+            }
+
+            // Narrow the window around the best of evenly spaced candidates until every
+            // token index in it has been tried.
+            let mut best = (usize::MAX, left);
+            loop {
+                let step = ((right - left) / SPLIT_CANDIDATES).max(1);
+                let mut split = left;
+                while split < right {
+                    let cost = self
+                        .block_cost(&tokens[lo..split], offsets[split] - offsets[lo])
+                        .0
+                        + self
+                            .block_cost(&tokens[split..hi], offsets[hi] - offsets[split])
+                            .0;
+                    if cost < best.0 {
+                        best = (cost, split);
+                    }
+                    split += step;
+                }
+                if step == 1 {
+                    break;
+                }
+                left = best.1.saturating_sub(step).max(left);
+                right = min(best.1 + step, right);
+            }
+
+            if best.0 < whole {
+                splits.push(best.1);
+                pending.push((lo, best.1));
+                pending.push((best.1, hi));
+            }
+        }
+        splits.sort_unstable();
+        Ok(splits)
+    }
+
+    /// Size in bits of `tokens` (decoding to `len` bytes) as the cheapest of a stored, a
+    /// static Huffman and a dynamic Huffman block, and which type that is.
+    fn block_cost(&mut self, tokens: &[Token], len: usize) -> (usize, BlockType) {
+        self.load_token_block(tokens);
+        self.make_block_codes();
+        let dynamic = 3 + self.calculate_dynamic_header_size() + self.calculate_block_data_size();
+        self.load_static_huffman_codes();
+        let fixed = 3 + self.calculate_block_data_size();
+        let stored = stored_size(len);
+
+        if stored < fixed && stored < dynamic {
+            (stored, BlockType::Stored)
+        } else if fixed <= dynamic {
+            (fixed, BlockType::Static)
+        } else {
+            (dynamic, BlockType::Dynamic)
+        }
+    }
+
+    pub(super) fn make_block_codes(&mut self) {
+        make_huffman_code(
+            DEFLATE_NUM_LITLEN_SYMS,
+            MAX_LITLEN_CODEWORD_LEN,
+            &self.litlen_freqs,
+            &mut self.litlen_lens,
+            &mut self.litlen_codewords,
+        );
+        make_huffman_code(
+            DEFLATE_NUM_OFFSET_SYMS,
+            MAX_OFFSET_CODEWORD_LEN,
+            &self.offset_freqs,
+            &mut self.offset_lens,
+            &mut self.offset_codewords,
+        );
+    }
+
+    /// Sets the parse costs to the current codeword lengths.
+    fn update_costs_from_lens(&mut self) {
+        for lit in 0..256 {
+            self.literal_costs[lit] = (self.litlen_lens[lit] as u32) << COST_SHIFT;
+        }
+        self.fill_match_costs(
+            |c, sym| (c.litlen_lens[sym] as u32) << COST_SHIFT,
+            |c, slot| (c.offset_lens[slot] as u32) << COST_SHIFT,
+        );
+    }
+
+    /// Sets the parse costs to the entropy of the current symbol frequencies, so that symbols
+    /// the last parse did not use still get a finite cost.
+    fn update_entropy_costs(&mut self) {
+        let litlen_total: u32 = self.litlen_freqs.iter().sum();
+        let offset_total: u32 = self.offset_freqs.iter().sum();
+        for lit in 0..256 {
+            self.literal_costs[lit] = entropy_cost(self.litlen_freqs[lit], litlen_total);
+        }
+        self.fill_match_costs(
+            |c, sym| entropy_cost(c.litlen_freqs[sym], litlen_total),
+            |c, slot| {
+                if offset_total == 0 {
+                    5 << COST_SHIFT
+                } else {
+                    entropy_cost(c.offset_freqs[slot], offset_total)
+                }
+            },
+        );
+    }
+
+    fn fill_match_costs(
+        &mut self,
+        litlen_cost: impl Fn(&Self, usize) -> u32,
+        offset_cost: impl Fn(&Self, usize) -> u32,
+    ) {
+        for len in DEFLATE_MIN_MATCH_LEN..=DEFLATE_MAX_MATCH_LEN {
+            let slot = self.get_length_slot(len);
+            let extra = (LENGTH_EXTRA_BITS_TABLE[slot] as u32) << COST_SHIFT;
+            let cost = litlen_cost(self, 257 + slot) + extra;
+            self.length_costs[len] = cost;
+        }
+        for (slot, &extra) in OFFSET_EXTRA_BITS_TABLE.iter().enumerate() {
+            let cost = offset_cost(self, slot) + ((extra as u32) << COST_SHIFT);
+            self.offset_slot_costs[slot] = cost;
+        }
+    }
+
+    /// Writes `data` as stored blocks of at most 65535 bytes each.
+    pub(super) fn write_stored_blocks(
+        &mut self,
+        bs: &mut Bitstream,
+        data: &[u8],
+        is_final: bool,
+    ) -> bool {
+        if data.is_empty() {
+            return self.write_stored_block(bs, data, is_final);
+        }
+        let mut chunks = data.chunks(65535).peekable();
+        while let Some(chunk) = chunks.next() {
+            let last = chunks.peek().is_none();
+            if !self.write_stored_block(bs, chunk, is_final && last) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn write_stored_block(&mut self, bs: &mut Bitstream, data: &[u8], is_final: bool) -> bool {
+        let start = self.begin_block(bs);
+        if !bs.write_bits(is_final as u32, 1) || !bs.write_bits(0, 2) {
+            return false;
+        }
+        let (res, _) = bs.flush();
+        if !res || bs.out_idx + 4 + data.len() > bs.output.len() {
+            return false;
+        }
+        let header_end = bs.bits_written() + 32;
+        let len = data.len() as u16;
+        let header = [len.to_le_bytes(), (!len).to_le_bytes()].concat();
+        for (dst, &src) in bs.output[bs.out_idx..]
+            .iter_mut()
+            .zip(header.iter().chain(data))
+        {
+            dst.write(src);
+        }
+        bs.out_idx += 4 + data.len();
+        self.end_block(start, BlockType::Stored, header_end, bs);
+        true
+    }
+}
@@ -0,0 +1,95 @@
+use super::PARALLEL_CHUNK_SIZE;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use rayon::ThreadPool;
+
+/// How [`Compressor::compress`](super::Compressor::compress),
+/// [`DeflateEncoder`](crate::stream::DeflateEncoder) and
+/// [`BatchCompressor`](crate::batch::BatchCompressor) spread work over threads.
+///
+/// The default splits inputs over [`PARALLEL_CHUNK_SIZE`] into chunks of that size on
+/// rayon's global pool. Without the `std` feature there are no threads, and inputs are
+/// never split.
+#[derive(Clone, Debug)]
+pub struct Parallelism {
+    #[cfg(feature = "std")]
+    thread_pool: Option<Arc<ThreadPool>>,
+    chunk_size: usize,
+    threshold: usize,
+    single_threaded: bool,
+}
+
+impl Default for Parallelism {
+    fn default() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            thread_pool: None,
+            chunk_size: PARALLEL_CHUNK_SIZE,
+            threshold: PARALLEL_CHUNK_SIZE,
+            single_threaded: false,
+        }
+    }
+}
+
+impl Parallelism {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Never splits inputs or spawns tasks: everything runs on the calling thread, and the
+    /// output is that of compressing each input in one piece.
+    pub fn single_threaded() -> Self {
+        Self {
+            single_threaded: true,
+            ..Self::default()
+        }
+    }
+
+    /// Runs parallel work on `pool` instead of rayon's global pool.
+    #[cfg(feature = "std")]
+    pub fn with_thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    /// Sets the size of the chunks that large inputs are split into (at least 1 byte).
+    /// The output depends on it, so fixing it makes the output independent of the pool.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// Sets the input size above which inputs are split into chunks.
+    pub fn with_threshold(mut self, threshold: usize) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+
+    pub fn is_single_threaded(&self) -> bool {
+        self.single_threaded
+    }
+
+    /// Whether an input of `len` bytes is split into chunks.
+    #[cfg(feature = "std")]
+    pub(crate) fn splits(&self, len: usize) -> bool {
+        !self.single_threaded && len > self.threshold
+    }
+
+    /// Runs `f` inside the configured pool, so that rayon calls in it use that pool.
+    #[cfg(feature = "std")]
+    pub(crate) fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
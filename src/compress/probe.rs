@@ -0,0 +1,255 @@
+//! A cheap look at the input ahead of each block, so that levels 1 to 12 do not spend match
+//! finding on data that will not compress, such as JPEG, video or zip members. Levels 13
+//! and up already weigh stored blocks against the others for every block.
+
+use super::bitstream::Bitstream;
+use super::huffman_comp::make_huffman_code;
+use super::matchfinder::MatchFinderTrait;
+use super::optimal::stored_size;
+use super::{Compressor, MAX_LITLEN_CODEWORD_LEN, Sequence};
+use crate::common::*;
+
+/// Input looked at by one probe, and covered by its decision.
+const PROBE_WINDOW: usize = 32 * 1024;
+/// Shorter windows are always compressed normally.
+const MIN_PROBE_LEN: usize = 4096;
+const PROBE_HASH_BITS: u32 = 14;
+/// Entries in the table of last positions that [`probe`] takes.
+pub(super) const PROBE_TABLE_SIZE: usize = 1 << PROBE_HASH_BITS;
+/// With fewer positions per 1024 whose next 4 bytes occurred in the 32 KiB before them,
+/// matches are not expected to pay for themselves.
+const MAX_REPEATS_PER_KIB: usize = 16;
+/// Huffman-only coding must save at least 1/32 of the bytes; otherwise the window is
+/// stored.
+const MIN_HUFFMAN_GAIN: usize = 32;
+/// Below this level the probe is off unless enabled: levels 1 to 5 search so little that
+/// it costs about as much as it saves.
+pub(super) const MIN_DEFAULT_PROBE_LEVEL: usize = 6;
+
+/// Where the probe stands in the input of one compression call.
+pub(super) struct ProbeCursor {
+    /// Blocks starting before this are not probed: the windows up to it were.
+    next: usize,
+    /// The end and strategy of the window at `next`, if it was probed already.
+    pending: Option<(usize, BlockStrategy)>,
+}
+
+impl ProbeCursor {
+    pub(super) fn new(start: usize) -> Self {
+        Self {
+            next: start,
+            pending: None,
+        }
+    }
+}
+
+/// How the probe says a window should be coded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum BlockStrategy {
+    /// Compress as the level normally would.
+    Matches,
+    /// One dynamic Huffman block of literals, without match finding.
+    HuffmanOnly,
+    /// Stored blocks.
+    Stored,
+}
+
+/// Predicts the best [`BlockStrategy`] for `input[start..end]` from how often its 4-byte
+/// strings occurred within the 32 KiB before them, and from the size of its bytes under a
+/// Huffman code of their own. `table` holds the last positions of the strings hashed so
+/// far, [`PROBE_TABLE_SIZE`] entries.
+pub(super) fn probe(table: &mut [u32], input: &[u8], start: usize, end: usize) -> BlockStrategy {
+    let data = &input[start..end];
+    if data.len() < MIN_PROBE_LEN {
+        return BlockStrategy::Matches;
+    }
+
+    let mut freqs = [0u32; 256];
+    for &b in data {
+        freqs[b as usize] += 1;
+    }
+
+    // Positions + 1 of the last occurrence of each hashed 4-byte string. Only the window
+    // is hashed; entries left by the windows probed before it stand in for the history, and
+    // those out of reach, or from other inputs, fail the checks below. So the table is
+    // never cleared.
+    let mut repeats = 0;
+    for pos in start..end.saturating_sub(3) {
+        let s = &input[pos..pos + 4];
+        let v = u32::from_le_bytes([s[0], s[1], s[2], s[3]]);
+        let h = (v.wrapping_mul(0x1E35A7BD) >> (32 - PROBE_HASH_BITS)) as usize;
+        let prev = table[h] as usize;
+        if prev != 0
+            && prev - 1 < pos
+            && pos - (prev - 1) <= DEFLATE_MAX_MATCH_OFFSET
+            && input[prev - 1..prev + 3] == *s
+        {
+            repeats += 1;
+        }
+        table[h] = (pos + 1) as u32;
+    }
+    if repeats * 1024 > MAX_REPEATS_PER_KIB * data.len() {
+        return BlockStrategy::Matches;
+    }
+
+    let mut lens = [0u8; 256];
+    let mut codewords = [0u32; 256];
+    make_huffman_code(
+        256,
+        MAX_LITLEN_CODEWORD_LEN,
+        &freqs,
+        &mut lens,
+        &mut codewords,
+    );
+    let huffman_bytes = freqs
+        .iter()
+        .zip(&lens)
+        .map(|(&freq, &len)| freq as usize * len as usize)
+        .sum::<usize>()
+        / 8;
+    if data.len() - huffman_bytes.min(data.len()) < data.len() / MIN_HUFFMAN_GAIN {
+        BlockStrategy::Stored
+    } else {
+        BlockStrategy::HuffmanOnly
+    }
+}
+
+/// The end of the probe window at `start`; a remainder too short to probe joins it.
+fn window_end(input: &[u8], start: usize) -> usize {
+    let end = start + PROBE_WINDOW;
+    if input.len().saturating_sub(end) < MIN_PROBE_LEN {
+        input.len()
+    } else {
+        end
+    }
+}
+
+impl Compressor {
+    /// Probes the window at `start_pos` and, unless it should be compressed normally, writes
+    /// it as stored or Huffman-only blocks. Returns the number of input bytes written, or
+    /// `None` to compress normally; `Some(0)` means the output is full.
+    ///
+    /// Consecutive windows coded alike are written together. They are still entered into
+    /// `mf`, so that later blocks may match against them.
+    pub(super) fn compress_probed_window<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start_pos: usize,
+        bs: &mut Bitstream,
+        final_block: bool,
+        cursor: &mut ProbeCursor,
+    ) -> Option<usize> {
+        let (end, strategy) = self.probe_next_window(input, start_pos, cursor)?;
+        let window = &input[start_pos..end];
+        let is_final = final_block && end == input.len();
+        mf.skip_positions(
+            input,
+            start_pos,
+            window.len(),
+            self.max_search_depth,
+            self.nice_match_length,
+        );
+        let ok = match strategy {
+            BlockStrategy::Stored => self.write_stored_blocks(bs, window, is_final),
+            _ => {
+                self.load_literal_block(window);
+                self.make_block_codes();
+                self.update_huffman_tables();
+                self.write_dynamic_block_with_sequences(input, start_pos, bs, is_final)
+            }
+        };
+        Some(if ok { window.len() } else { 0 })
+    }
+
+    /// The [`compress_to_size`](Self::compress_to_size) counterpart of
+    /// [`compress_probed_window`](Self::compress_probed_window): the number of input bytes
+    /// and output bits of the window at `start_pos`, or `None` to compress normally.
+    pub(super) fn probed_window_size<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        start_pos: usize,
+        cursor: &mut ProbeCursor,
+    ) -> Option<(usize, usize)> {
+        let (end, strategy) = self.probe_next_window(input, start_pos, cursor)?;
+        let len = end - start_pos;
+        mf.skip_positions(
+            input,
+            start_pos,
+            len,
+            self.max_search_depth,
+            self.nice_match_length,
+        );
+        match strategy {
+            BlockStrategy::Stored => Some((len, stored_size(len))),
+            _ => {
+                self.load_literal_block(&input[start_pos..end]);
+                self.make_block_codes();
+                let bits =
+                    3 + self.calculate_dynamic_header_size() + self.calculate_block_data_size();
+                Some((len, bits))
+            }
+        }
+    }
+
+    /// Probes the window at `start_pos` if it is due, and the windows after it while they
+    /// should be coded the same way, up to a block's worth. Returns where they end and how
+    /// to code them, or `None` to compress normally.
+    fn probe_next_window(
+        &mut self,
+        input: &[u8],
+        start_pos: usize,
+        cursor: &mut ProbeCursor,
+    ) -> Option<(usize, BlockStrategy)> {
+        if !self.probes_blocks() || self.probe_table.is_empty() || start_pos < cursor.next {
+            return None;
+        }
+        let (mut end, strategy) = match cursor.pending.take() {
+            Some(pending) if start_pos == cursor.next => pending,
+            _ => {
+                let end = window_end(input, start_pos);
+                (end, probe(&mut self.probe_table, input, start_pos, end))
+            }
+        };
+        cursor.next = end;
+        if strategy == BlockStrategy::Matches {
+            return None;
+        }
+        while end < input.len() {
+            let next_end = window_end(input, end);
+            if next_end - start_pos > SOFT_MAX_BLOCK_LENGTH {
+                break;
+            }
+            let next = probe(&mut self.probe_table, input, end, next_end);
+            if next != strategy {
+                cursor.pending = Some((next_end, next));
+                break;
+            }
+            end = next_end;
+            cursor.next = end;
+        }
+        Some((end, strategy))
+    }
+
+    /// Whether blocks are probed: as set by
+    /// [`set_incompressible_probe`](Self::set_incompressible_probe), or else from level
+    /// [`MIN_DEFAULT_PROBE_LEVEL`] on.
+    pub(super) fn probes_blocks(&self) -> bool {
+        self.probe_blocks
+            .unwrap_or(self.compression_level >= MIN_DEFAULT_PROBE_LEVEL)
+    }
+
+    /// Sets up `sequences` and the symbol frequencies for a block of the literals `data`.
+    fn load_literal_block(&mut self, data: &[u8]) {
+        self.litlen_freqs.fill(0);
+        self.offset_freqs.fill(0);
+        for &b in data {
+            self.litlen_freqs[b as usize] += 1;
+        }
+        self.litlen_freqs[DEFLATE_END_OF_BLOCK] += 1;
+        self.sequences.clear();
+        self.sequences
+            .push(Sequence::new(data.len() as u32, 0, 0, 0));
+    }
+}
@@ -0,0 +1,158 @@
+//! Exact and approximate compressed sizes, for callers that need to know how large the
+//! output will be before, or instead of, producing it.
+
+use super::bitstream::Bitstream;
+use super::{CompressResult, Compressor, FlushMode};
+use crate::allocator::AllocVec;
+use crate::common::*;
+use core::mem::MaybeUninit;
+#[cfg(feature = "std")]
+use {super::PooledCompressor, core::cmp::min, rayon::prelude::*};
+
+/// Room for what one step of `compress_loop` writes: a block of up to
+/// [`SOFT_MAX_BLOCK_LENGTH`] bytes plus the [`MIN_BLOCK_LENGTH`] tail it may take in and a
+/// match running past that, twice over.
+const SIZE_SCRATCH_LEN: usize =
+    2 * (SOFT_MAX_BLOCK_LENGTH + MIN_BLOCK_LENGTH + DEFLATE_MAX_MATCH_LEN);
+
+/// The container around a DEFLATE stream.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    /// Raw DEFLATE (RFC 1951).
+    Deflate,
+    /// zlib (RFC 1950).
+    Zlib,
+    /// gzip (RFC 1952) with the minimal header of [`Compressor::compress_gzip`].
+    Gzip,
+}
+
+impl Format {
+    /// Bytes of header and trailer around the DEFLATE stream.
+    pub fn overhead(self) -> usize {
+        match self {
+            Format::Deflate => 0,
+            Format::Zlib => ZLIB_MIN_OVERHEAD,
+            Format::Gzip => GZIP_MIN_OVERHEAD,
+        }
+    }
+}
+
+impl Compressor {
+    /// The exact size in bytes of `input` compressed into `format` at this level, as
+    /// [`compress`](Self::compress) with [`FlushMode::Finish`],
+    /// [`compress_zlib`](Self::compress_zlib) or [`compress_gzip`](Self::compress_gzip)
+    /// write it given [`deflate_bound`](Self::deflate_bound) bytes for the DEFLATE stream.
+    ///
+    /// The input goes through the same match finding and block decisions, including the
+    /// chunking of [`Parallelism`](super::Parallelism), so this costs about as much as
+    /// compressing. The blocks are written into a scratch buffer of a few hundred KiB
+    /// and dropped, so no room for the output is needed.
+    pub fn estimate_compressed_size(&mut self, input: &[u8], format: Format) -> usize {
+        format.overhead() + self.deflate_size(input)
+    }
+
+    /// A cheaper estimate of [`estimate_compressed_size`](Self::estimate_compressed_size)
+    /// from [`compress_to_size`](Self::compress_to_size), typically within a few percent.
+    pub fn approximate_compressed_size(&mut self, input: &[u8], format: Format) -> usize {
+        format.overhead() + self.compress_to_size(input, true)
+    }
+
+    fn deflate_size(&mut self, input: &[u8]) -> usize {
+        #[cfg(feature = "std")]
+        if self.parallelism.splits(input.len()) {
+            return self.chunked_deflate_size(input);
+        }
+        let mut scratch =
+            AllocVec::from_elem_in(MaybeUninit::uninit(), SIZE_SCRATCH_LEN, &self.allocator);
+        let limit = self.deflate_bound(input.len());
+        self.primed_size(input, 0, FlushMode::Finish, limit, &mut scratch)
+    }
+
+    /// The counterpart of `compress_chunks`: the sizes of the chunks, measured in parallel
+    /// with compressors from `chunk_pool`, added up.
+    #[cfg(feature = "std")]
+    fn chunked_deflate_size(&mut self, input: &[u8]) -> usize {
+        let parallelism = self.parallelism.clone();
+        let chunk_size = parallelism.chunk_size();
+        let num_chunks = input.len().div_ceil(chunk_size);
+        let level = self.compression_level;
+        let history = if self.prime_chunks {
+            DEFLATE_MAX_MATCH_OFFSET
+        } else {
+            0
+        };
+        let probe_blocks = self.probe_blocks;
+
+        let mut pool = core::mem::take(&mut self.chunk_pool);
+        pool.retain(|c| c.compression_level == level);
+        let pool = std::sync::Mutex::new(pool);
+        let allocator = self.allocator.clone();
+        let init = || {
+            (
+                PooledCompressor::take(&pool, level, &allocator),
+                AllocVec::from_elem_in(MaybeUninit::uninit(), SIZE_SCRATCH_LEN, &allocator),
+            )
+        };
+
+        let size = parallelism.install(|| {
+            (0..num_chunks)
+                .into_par_iter()
+                .map_init(init, |(compressor, scratch), i| {
+                    let start = i * chunk_size;
+                    let end = min(start + chunk_size, input.len());
+                    let history = history.min(start);
+                    let mode = if i == num_chunks - 1 {
+                        FlushMode::Finish
+                    } else {
+                        FlushMode::Sync
+                    };
+                    let Some(compressor) = compressor.get() else {
+                        return stored_stream_size(end - start, mode);
+                    };
+                    compressor.probe_blocks = probe_blocks;
+                    compressor.primed_size(
+                        &input[start - history..end],
+                        history,
+                        mode,
+                        Self::deflate_compress_bound(end - start),
+                        scratch,
+                    )
+                })
+                .sum()
+        });
+
+        self.chunk_pool = pool.into_inner().unwrap_or_else(|e| e.into_inner());
+        size
+    }
+
+    /// The size of what `compress_primed` writes into `limit` bytes of output, which must
+    /// be at least [`deflate_compress_bound`](Self::deflate_compress_bound) of the input
+    /// after `start`.
+    fn primed_size(
+        &mut self,
+        input: &[u8],
+        start: usize,
+        flush_mode: FlushMode,
+        limit: usize,
+        scratch: &mut [MaybeUninit<u8>],
+    ) -> usize {
+        if self.compression_level > 0 {
+            // Measuring is not compressing: leave the stats of the last call alone.
+            let stats = self.stats.take();
+            let mut bs = Bitstream::counting(scratch, limit);
+            let (res, size, _) = self.compress_bitstream(input, start, &mut bs, flush_mode);
+            self.stats = stats;
+            if res == CompressResult::Success {
+                return size;
+            }
+        }
+        stored_stream_size(input.len() - start, flush_mode)
+    }
+}
+
+/// The size of `len` bytes as stored blocks of up to 65535 bytes, at least one, and an
+/// empty one to sync: what `compress_primed` falls back to.
+fn stored_stream_size(len: usize, flush_mode: FlushMode) -> usize {
+    let sync = if flush_mode == FlushMode::Sync { 5 } else { 0 };
+    len + len.div_ceil(65535).max(1) * 5 + sync
+}
@@ -0,0 +1,181 @@
+//! What a compression call did, for logging and tuning.
+
+use super::Compressor;
+use super::bitstream::Bitstream;
+use super::optimal::BlockType;
+use core::time::Duration;
+
+/// Counts gathered while compressing, when enabled with
+/// [`Compressor::set_collect_stats`]. Each compression call starts from zero.
+///
+/// Inputs split into parallel chunks add up the counts of every chunk, and the times
+/// of every thread. Without the `std` feature the times stay zero.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CompressionStats {
+    /// Stored blocks, including the empty ones that end [`FlushMode::Sync`] flushes and
+    /// parallel chunks.
+    ///
+    /// [`FlushMode::Sync`]: super::FlushMode::Sync
+    pub stored_blocks: u64,
+    pub static_blocks: u64,
+    pub dynamic_blocks: u64,
+    /// Literals coded in static and dynamic blocks. The bytes of stored blocks are not
+    /// counted.
+    pub literals: u64,
+    pub matches: u64,
+    /// Input bytes covered by matches.
+    pub match_bytes: u64,
+    /// Bits of block headers: the block type, the Huffman code description of dynamic
+    /// blocks, and the padding and lengths of stored blocks.
+    pub header_bits: u64,
+    /// Bits of block contents: the codes of literals, matches and end-of-block, and the
+    /// bytes of stored blocks.
+    pub payload_bits: u64,
+    /// Time spent finding matches and choosing blocks and their codes.
+    pub match_finding_time: Duration,
+    /// Time spent writing blocks.
+    pub encoding_time: Duration,
+}
+
+impl CompressionStats {
+    pub fn blocks(&self) -> u64 {
+        self.stored_blocks + self.static_blocks + self.dynamic_blocks
+    }
+
+    /// Mean length of the matches, or 0.0 without any.
+    pub fn average_match_length(&self) -> f64 {
+        if self.matches == 0 {
+            0.0
+        } else {
+            self.match_bytes as f64 / self.matches as f64
+        }
+    }
+
+    /// Adds the counts and times of `other` to these.
+    pub fn merge(&mut self, other: &Self) {
+        self.stored_blocks += other.stored_blocks;
+        self.static_blocks += other.static_blocks;
+        self.dynamic_blocks += other.dynamic_blocks;
+        self.literals += other.literals;
+        self.matches += other.matches;
+        self.match_bytes += other.match_bytes;
+        self.header_bits += other.header_bits;
+        self.payload_bits += other.payload_bits;
+        self.match_finding_time += other.match_finding_time;
+        self.encoding_time += other.encoding_time;
+    }
+}
+
+/// A point in time to measure from; without `std` no time passes.
+#[derive(Clone, Copy)]
+pub(super) struct Timer {
+    #[cfg(feature = "std")]
+    start: std::time::Instant,
+}
+
+impl Timer {
+    pub(super) fn start() -> Self {
+        Self {
+            #[cfg(feature = "std")]
+            start: std::time::Instant::now(),
+        }
+    }
+
+    pub(super) fn elapsed(&self) -> Duration {
+        #[cfg(feature = "std")]
+        return self.start.elapsed();
+        #[cfg(not(feature = "std"))]
+        Duration::ZERO
+    }
+}
+
+/// Where a block began in the output, and when; see [`Compressor::begin_block`].
+pub(super) struct BlockStart {
+    timer: Timer,
+    bits: usize,
+}
+
+impl Compressor {
+    /// Sets whether compression calls gather [`CompressionStats`], available from
+    /// [`stats`](Self::stats) afterwards. Off by default, when it costs nothing beyond
+    /// a check per block.
+    pub fn set_collect_stats(&mut self, enabled: bool) {
+        self.stats = enabled.then(CompressionStats::default);
+    }
+
+    /// The stats of the last compression call, if enabled.
+    pub fn stats(&self) -> Option<&CompressionStats> {
+        self.stats.as_ref()
+    }
+
+    /// Zeroes the stats, if enabled, at the start of a compression call.
+    pub(super) fn reset_stats(&mut self) {
+        if let Some(stats) = &mut self.stats {
+            *stats = CompressionStats::default();
+        }
+    }
+
+    /// Adds the stats of a chunk compressed by another compressor.
+    #[cfg(feature = "std")]
+    pub(super) fn add_stats(&mut self, chunk: Option<CompressionStats>) {
+        if let (Some(stats), Some(chunk)) = (&mut self.stats, chunk) {
+            stats.merge(&chunk);
+        }
+    }
+
+    /// Marks the start of a block in `bs`, if stats are enabled.
+    #[inline]
+    pub(super) fn begin_block(&self, bs: &Bitstream) -> Option<BlockStart> {
+        self.stats.as_ref().map(|_| BlockStart {
+            timer: Timer::start(),
+            bits: bs.bits_written(),
+        })
+    }
+
+    /// Records a block that began at `start`, whose header ended `header_end` bits into
+    /// the output and whose contents end where `bs` is now. The literals and matches of
+    /// static and dynamic blocks are those in `sequences`.
+    pub(super) fn end_block(
+        &mut self,
+        start: Option<BlockStart>,
+        block_type: BlockType,
+        header_end: usize,
+        bs: &Bitstream,
+    ) {
+        let (Some(start), Some(stats)) = (start, &mut self.stats) else {
+            return;
+        };
+        match block_type {
+            BlockType::Stored => stats.stored_blocks += 1,
+            BlockType::Static => stats.static_blocks += 1,
+            BlockType::Dynamic => stats.dynamic_blocks += 1,
+        }
+        if block_type != BlockType::Stored {
+            for seq in self.sequences.iter() {
+                stats.literals += seq.litrunlen as u64;
+                if seq.len() > 0 {
+                    stats.matches += 1;
+                    stats.match_bytes += seq.len() as u64;
+                }
+            }
+        }
+        stats.header_bits += (header_end - start.bits) as u64;
+        stats.payload_bits += (bs.bits_written() - header_end) as u64;
+        stats.encoding_time += start.timer.elapsed();
+    }
+
+    /// Starts timing a compression call for [`end_match_finding`](Self::end_match_finding).
+    pub(super) fn begin_match_finding(&self) -> Option<(Timer, Duration)> {
+        self.stats
+            .as_ref()
+            .map(|stats| (Timer::start(), stats.encoding_time))
+    }
+
+    /// Takes the time since `start` that was not spent writing blocks as match finding.
+    pub(super) fn end_match_finding(&mut self, start: Option<(Timer, Duration)>) {
+        if let (Some((timer, encoding_before)), Some(stats)) = (start, &mut self.stats) {
+            let encoding = stats.encoding_time - encoding_before;
+            stats.match_finding_time += timer.elapsed().saturating_sub(encoding);
+        }
+    }
+}
@@ -0,0 +1,227 @@
+use super::bitstream::Bitstream;
+use super::huffman_comp::make_huffman_code;
+use super::matchfinder::MatchFinderTrait;
+use super::{
+    Compressor, MAX_LITLEN_CODEWORD_LEN, MAX_OFFSET_CODEWORD_LEN, MatchFinderEnum, Sequence,
+};
+use crate::common::*;
+use crate::error::{Result, insufficient_space, invalid_input};
+use alloc::alloc::handle_alloc_error;
+use alloc::vec::Vec;
+use core::mem::MaybeUninit;
+
+/// One LZ77 token of a deflate parse.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Token {
+    /// A byte that is output as is.
+    Literal(u8),
+    /// Repeats `length` bytes (3..=258) from `distance` bytes back (1..=32768).
+    Match { length: u16, distance: u16 },
+}
+
+impl Token {
+    /// Number of uncompressed bytes the token stands for.
+    pub fn uncompressed_len(&self) -> usize {
+        match *self {
+            Token::Literal(_) => 1,
+            Token::Match { length, .. } => length as usize,
+        }
+    }
+}
+
+/// Parses `input` into tokens the way level 6 would, without encoding them.
+pub fn parse(input: &[u8]) -> Vec<Token> {
+    Compressor::new(6).parse(input)
+}
+
+/// Encodes `tokens` as a complete deflate stream into `output` and returns its size.
+///
+/// A new block starts at each token index in `block_boundaries`, which must be strictly
+/// increasing and lie in `1..tokens.len()`. Every block gets its own Huffman codes.
+pub fn encode_tokens(
+    tokens: &[Token],
+    output: &mut [u8],
+    block_boundaries: &[usize],
+) -> Result<usize> {
+    let out_uninit = unsafe {
+        core::slice::from_raw_parts_mut(output.as_mut_ptr() as *mut MaybeUninit<u8>, output.len())
+    };
+    Compressor::new(0).encode_tokens(tokens, out_uninit, block_boundaries)
+}
+
+/// Replays `tokens` into the data they encode, checking that every match is legal.
+fn expand_tokens(tokens: &[Token]) -> Result<Vec<u8>> {
+    let total = tokens.iter().map(Token::uncompressed_len).sum();
+    let mut data = Vec::with_capacity(total);
+    for token in tokens {
+        match *token {
+            Token::Literal(b) => data.push(b),
+            Token::Match { length, distance } => {
+                let (length, distance) = (length as usize, distance as usize);
+                if !(DEFLATE_MIN_MATCH_LEN..=DEFLATE_MAX_MATCH_LEN).contains(&length) {
+                    return Err(invalid_input("match length out of range"));
+                }
+                if distance == 0 || distance > DEFLATE_MAX_MATCH_OFFSET || distance > data.len() {
+                    return Err(invalid_input("match distance out of range"));
+                }
+                let start = data.len() - distance;
+                for i in 0..length {
+                    data.push(data[start + i]);
+                }
+            }
+        }
+    }
+    Ok(data)
+}
+
+impl Compressor {
+    /// Runs this compressor's match finding and block splitting over `input` and returns
+    /// the chosen tokens instead of encoding them.
+    pub fn parse(&mut self, input: &[u8]) -> Vec<Token> {
+        if self.compression_level == 0 {
+            return input.iter().map(|&b| Token::Literal(b)).collect();
+        }
+        let mut tokens = Vec::with_capacity(input.len() / 2);
+        let mut mf_enum = self.mf.take().unwrap();
+        match &mut mf_enum {
+            MatchFinderEnum::Chain(mf) => self.parse_loop(mf, input, &mut tokens),
+            MatchFinderEnum::Table(mf) => self.parse_loop(mf, input, &mut tokens),
+            MatchFinderEnum::Bt(mf) => self.parse_loop(mf, input, &mut tokens),
+        }
+        self.mf = Some(mf_enum);
+        tokens
+    }
+
+    fn parse_loop<T: MatchFinderTrait>(
+        &mut self,
+        mf: &mut T,
+        input: &[u8],
+        tokens: &mut Vec<Token>,
+    ) {
+        mf.prepare(input.len());
+        let lazy_depth = self.lazy_depth();
+        let mut in_idx = 0;
+        while in_idx < input.len() {
+            if self.compression_level >= 13 {
+                let processed = self
+                    .decide_exhaustive_blocks(mf, input, in_idx)
+                    .unwrap_or_else(|e| handle_alloc_error(e.layout()));
+                tokens.extend_from_slice(&self.optimal_path);
+                in_idx += processed;
+                continue;
+            }
+            let processed = if self.compression_level >= 10 {
+                self.decide_near_optimal_sequences(mf, input, in_idx)
+            } else {
+                self.decide_greedy_sequences(mf, input, in_idx, lazy_depth)
+            };
+            // Unlike the block writers, the deciders cannot run out of space: they always
+            // take at least one byte.
+            debug_assert!(processed > 0);
+            let mut pos = in_idx;
+            for seq in &self.sequences {
+                for &b in &input[pos..pos + seq.litrunlen as usize] {
+                    tokens.push(Token::Literal(b));
+                }
+                pos += seq.litrunlen as usize;
+                let length = seq.len();
+                if length >= 3 {
+                    tokens.push(Token::Match {
+                        length,
+                        distance: seq.offset,
+                    });
+                    pos += length as usize;
+                }
+            }
+            in_idx += processed;
+        }
+        mf.advance(input.len());
+    }
+
+    /// Like [`encode_tokens`], but reuses this compressor's buffers.
+    pub fn encode_tokens(
+        &mut self,
+        tokens: &[Token],
+        output: &mut [MaybeUninit<u8>],
+        block_boundaries: &[usize],
+    ) -> Result<usize> {
+        let mut prev = 0;
+        for &b in block_boundaries {
+            if b <= prev || b >= tokens.len() {
+                return Err(invalid_input(
+                    "block boundaries must be increasing token indices",
+                ));
+            }
+            prev = b;
+        }
+        let data = expand_tokens(tokens)?;
+
+        let mut bs = Bitstream::new(output);
+        let mut data_pos = 0;
+        let mut block_start = 0;
+        let ends = block_boundaries.iter().copied().chain(Some(tokens.len()));
+        for (i, block_end) in ends.enumerate() {
+            let is_final = i == block_boundaries.len();
+            let block_data_len = self.load_token_block(&tokens[block_start..block_end]);
+
+            make_huffman_code(
+                DEFLATE_NUM_LITLEN_SYMS,
+                MAX_LITLEN_CODEWORD_LEN,
+                &self.litlen_freqs,
+                &mut self.litlen_lens,
+                &mut self.litlen_codewords,
+            );
+            make_huffman_code(
+                DEFLATE_NUM_OFFSET_SYMS,
+                MAX_OFFSET_CODEWORD_LEN,
+                &self.offset_freqs,
+                &mut self.offset_lens,
+                &mut self.offset_codewords,
+            );
+            self.update_huffman_tables();
+
+            if !self.write_dynamic_block_with_sequences(&data, data_pos, &mut bs, is_final) {
+                return Err(insufficient_space());
+            }
+            data_pos += block_data_len;
+            block_start = block_end;
+        }
+
+        let (res, _) = bs.flush();
+        if !res {
+            return Err(insufficient_space());
+        }
+        Ok(bs.out_idx)
+    }
+
+    /// Fills `sequences` and the symbol frequencies from one block of tokens and returns
+    /// the number of bytes the block decodes to.
+    pub(super) fn load_token_block(&mut self, tokens: &[Token]) -> usize {
+        self.sequences.clear();
+        self.litlen_freqs.fill(0);
+        self.offset_freqs.fill(0);
+        let mut litrunlen = 0;
+        let mut len = 0;
+        for token in tokens {
+            match *token {
+                Token::Literal(b) => {
+                    self.litlen_freqs[b as usize] += 1;
+                    litrunlen += 1;
+                    len += 1;
+                }
+                Token::Match { length, distance } => {
+                    let off_slot = self.get_offset_slot(distance as usize);
+                    self.sequences
+                        .push(Sequence::new(litrunlen, length, distance, off_slot as u8));
+                    self.litlen_freqs[257 + self.get_length_slot(length as usize)] += 1;
+                    self.offset_freqs[off_slot] += 1;
+                    litrunlen = 0;
+                    len += length as usize;
+                }
+            }
+        }
+        self.sequences.push(Sequence::new(litrunlen, 0, 0, 0));
+        self.litlen_freqs[256] += 1;
+        len
+    }
+}
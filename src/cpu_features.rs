@@ -0,0 +1,190 @@
+use crate::sync::OnceLock;
+use core::fmt;
+
+/// Environment variable read on first use to cap the instruction set, e.g.
+/// `LIBDEFLATE_MAX_ISA=scalar` or `LIBDEFLATE_MAX_ISA=avx2`. Only read with the `std`
+/// feature.
+pub const MAX_ISA_ENV: &str = "LIBDEFLATE_MAX_ISA";
+
+/// Instruction-set tiers that runtime dispatch can be capped at.
+///
+/// The x86 tiers follow the x86-64 microarchitecture levels: `Sse2` is v1,
+/// `Sse4` is v2 plus PCLMULQDQ, `Avx2` is v3 (AVX2, BMI2, VPCLMULQDQ, AVX-VNNI)
+/// and `Avx512` is v4. On AArch64 every tier above `Scalar` allows NEON and
+/// the CRC and dot-product extensions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IsaLevel {
+    Scalar,
+    Sse2,
+    Sse4,
+    Avx2,
+    Avx512,
+}
+
+impl IsaLevel {
+    pub fn name(self) -> &'static str {
+        match self {
+            IsaLevel::Scalar => "scalar",
+            IsaLevel::Sse2 => "sse2",
+            IsaLevel::Sse4 => "sse4",
+            IsaLevel::Avx2 => "avx2",
+            IsaLevel::Avx512 => "avx512",
+        }
+    }
+
+    /// Parses a tier name as accepted by [`MAX_ISA_ENV`]. Matching is
+    /// case-insensitive, and the x86-64 level names `v1`..`v4` are accepted too.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "scalar" | "generic" | "none" => Some(IsaLevel::Scalar),
+            "sse2" | "v1" => Some(IsaLevel::Sse2),
+            "sse4" | "sse4.1" | "sse4.2" | "v2" => Some(IsaLevel::Sse4),
+            "avx2" | "v3" => Some(IsaLevel::Avx2),
+            "avx512" | "v4" | "native" | "max" => Some(IsaLevel::Avx512),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for IsaLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+static MAX_ISA_LEVEL: OnceLock<IsaLevel> = OnceLock::new();
+
+#[cfg(feature = "std")]
+fn level_from_env() -> IsaLevel {
+    std::env::var(MAX_ISA_ENV)
+        .ok()
+        .and_then(|s| IsaLevel::parse(&s))
+        .unwrap_or(IsaLevel::Avx512)
+}
+
+#[cfg(not(feature = "std"))]
+fn level_from_env() -> IsaLevel {
+    IsaLevel::Avx512
+}
+
+/// Caps the instruction set used by every dispatched code path.
+///
+/// Implementations are selected once and then cached, so the cap must be set
+/// before the first checksum, compression or decompression call. Returns
+/// `false` if the level was already fixed, either by an earlier call or
+/// because dispatch has already happened; [`cpu_features`] reports the level
+/// that is in effect.
+pub fn set_max_isa_level(level: IsaLevel) -> bool {
+    MAX_ISA_LEVEL.set(level).is_ok()
+}
+
+/// The instruction-set cap in effect, from [`set_max_isa_level`] or
+/// [`MAX_ISA_ENV`], or `Avx512` (no cap) by default.
+pub fn max_isa_level() -> IsaLevel {
+    *MAX_ISA_LEVEL.get_or_init(level_from_env)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) fn x86_feature_level(feature: &str) -> IsaLevel {
+    match feature {
+        "sse2" => IsaLevel::Sse2,
+        "ssse3" | "sse4.1" | "sse4.2" | "pclmulqdq" | "popcnt" => IsaLevel::Sse4,
+        "avx" | "avx2" | "bmi1" | "bmi2" | "lzcnt" | "fma" | "avxvnni" | "vpclmulqdq" => {
+            IsaLevel::Avx2
+        }
+        _ => IsaLevel::Avx512,
+    }
+}
+
+/// `is_x86_feature_detected!` that also honors the instruction-set cap.
+#[cfg(all(feature = "std", any(target_arch = "x86", target_arch = "x86_64")))]
+macro_rules! x86_feature_enabled {
+    ($feature:tt) => {
+        $crate::cpu_features::max_isa_level() >= $crate::cpu_features::x86_feature_level($feature)
+            && std::arch::is_x86_feature_detected!($feature)
+    };
+}
+
+/// Without `std` there is no runtime detection, so only the features the crate is
+/// compiled for (`-C target-feature`) are used.
+#[cfg(all(not(feature = "std"), any(target_arch = "x86", target_arch = "x86_64")))]
+macro_rules! x86_feature_enabled {
+    ($feature:tt) => {
+        $crate::cpu_features::compiled_feature_enabled(
+            $crate::cpu_features::x86_feature_level($feature),
+            cfg!(target_feature = $feature),
+        )
+    };
+}
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) use x86_feature_enabled;
+
+/// `is_aarch64_feature_detected!` that also honors the instruction-set cap.
+#[cfg(all(feature = "std", target_arch = "aarch64"))]
+macro_rules! aarch64_feature_enabled {
+    ($feature:tt) => {
+        $crate::cpu_features::max_isa_level() > $crate::cpu_features::IsaLevel::Scalar
+            && std::arch::is_aarch64_feature_detected!($feature)
+    };
+}
+
+#[cfg(all(not(feature = "std"), target_arch = "aarch64"))]
+macro_rules! aarch64_feature_enabled {
+    ($feature:tt) => {
+        $crate::cpu_features::compiled_feature_enabled(
+            $crate::cpu_features::IsaLevel::Sse2,
+            cfg!(target_feature = $feature),
+        )
+    };
+}
+#[cfg(target_arch = "aarch64")]
+pub(crate) use aarch64_feature_enabled;
+
+/// Whether a feature of tier `level` that the crate was (`compiled`) or was not compiled
+/// with may be used, for the `no_std` forms of the detection macros.
+#[cfg(all(
+    not(feature = "std"),
+    any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")
+))]
+pub(crate) fn compiled_feature_enabled(level: IsaLevel, compiled: bool) -> bool {
+    compiled && max_isa_level() >= level
+}
+
+/// The implementation chosen by each runtime-dispatched subsystem.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CpuFeatures {
+    pub max_isa_level: IsaLevel,
+    pub crc32: &'static str,
+    pub crc32c: &'static str,
+    pub crc64: &'static str,
+    pub adler32: &'static str,
+    pub match_len: &'static str,
+    pub decompress: &'static str,
+}
+
+impl fmt::Display for CpuFeatures {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "max_isa_level: {}", self.max_isa_level)?;
+        writeln!(f, "crc32: {}", self.crc32)?;
+        writeln!(f, "crc32c: {}", self.crc32c)?;
+        writeln!(f, "crc64: {}", self.crc64)?;
+        writeln!(f, "adler32: {}", self.adler32)?;
+        writeln!(f, "match_len: {}", self.match_len)?;
+        write!(f, "decompress: {}", self.decompress)
+    }
+}
+
+/// Reports the implementation each subsystem uses on this CPU.
+///
+/// Calling this fixes the instruction-set cap, as any dispatch would.
+pub fn cpu_features() -> CpuFeatures {
+    CpuFeatures {
+        max_isa_level: max_isa_level(),
+        crc32: crate::crc32::implementation_name(),
+        crc32c: crate::crc32::crc32c_implementation_name(),
+        crc64: crate::crc64::implementation_name(),
+        adler32: crate::adler32::implementation_name(),
+        match_len: crate::compress::match_len_implementation_name(),
+        decompress: crate::decompress::implementation_name(),
+    }
+}
@@ -1,5 +1,11 @@
-use crate::crc32_tables::{CRC32_SLICE1_TABLE, CRC32_SLICE8_TABLE};
-use std::sync::OnceLock;
+#[cfg(target_arch = "aarch64")]
+use crate::cpu_features::aarch64_feature_enabled;
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::cpu_features::x86_feature_enabled;
+use crate::crc32_tables::*;
+use crate::sync::OnceLock;
+#[cfg(feature = "std")]
+use rayon::prelude::*;
 
 pub fn crc32_slice1(mut crc: u32, p: &[u8]) -> u32 {
     for &b in p {
@@ -9,7 +15,17 @@ pub fn crc32_slice1(mut crc: u32, p: &[u8]) -> u32 {
 }
 
 #[inline]
-pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
+pub fn crc32_slice8(crc: u32, p: &[u8]) -> u32 {
+    crc32_slice8_with_table(crc, p, &CRC32_SLICE8_TABLE)
+}
+
+#[inline]
+pub fn crc32c_slice8(crc: u32, p: &[u8]) -> u32 {
+    crc32_slice8_with_table(crc, p, &CRC32C_SLICE8_TABLE)
+}
+
+#[inline(always)]
+fn crc32_slice8_with_table(mut crc: u32, p: &[u8], table: &[u32; 2048]) -> u32 {
     let mut len = p.len();
     let mut ptr = p.as_ptr();
 
@@ -18,16 +34,16 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
     // to be interleaved with the dependency chain of the low 4 bytes (which depend on CRC).
     while len >= 64 {
         // First 32 bytes
-        let va = u64::from_le(unsafe { std::ptr::read_unaligned(ptr as *const u64) });
-        let vb = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(8) as *const u64) });
-        let vc = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(16) as *const u64) });
-        let vd = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(24) as *const u64) });
+        let va = u64::from_le(unsafe { core::ptr::read_unaligned(ptr as *const u64) });
+        let vb = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(8) as *const u64) });
+        let vc = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(16) as *const u64) });
+        let vd = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(24) as *const u64) });
 
         // Second 32 bytes
-        let ve = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(32) as *const u64) });
-        let vf = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(40) as *const u64) });
-        let vg = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(48) as *const u64) });
-        let vh = u64::from_le(unsafe { std::ptr::read_unaligned(ptr.add(56) as *const u64) });
+        let ve = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(32) as *const u64) });
+        let vf = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(40) as *const u64) });
+        let vg = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(48) as *const u64) });
+        let vh = u64::from_le(unsafe { core::ptr::read_unaligned(ptr.add(56) as *const u64) });
 
         let va1 = va as u32;
         let va2 = (va >> 32) as u32;
@@ -70,25 +86,25 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx31 = ((vd2 >> 24) as u8) as usize;
 
         // Start independent fetches for first 32 bytes
-        let t4 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx4) };
-        let t5 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx5) };
-        let t6 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx6) };
-        let t7 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx7) };
-
-        let t12 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx12) };
-        let t13 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx13) };
-        let t14 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx14) };
-        let t15 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx15) };
-
-        let t20 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx20) };
-        let t21 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx21) };
-        let t22 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx22) };
-        let t23 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx23) };
-
-        let t28 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx28) };
-        let t29 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx29) };
-        let t30 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx30) };
-        let t31 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx31) };
+        let t4 = unsafe { *table.get_unchecked(0x300 + idx4) };
+        let t5 = unsafe { *table.get_unchecked(0x200 + idx5) };
+        let t6 = unsafe { *table.get_unchecked(0x100 + idx6) };
+        let t7 = unsafe { *table.get_unchecked(idx7) };
+
+        let t12 = unsafe { *table.get_unchecked(0x300 + idx12) };
+        let t13 = unsafe { *table.get_unchecked(0x200 + idx13) };
+        let t14 = unsafe { *table.get_unchecked(0x100 + idx14) };
+        let t15 = unsafe { *table.get_unchecked(idx15) };
+
+        let t20 = unsafe { *table.get_unchecked(0x300 + idx20) };
+        let t21 = unsafe { *table.get_unchecked(0x200 + idx21) };
+        let t22 = unsafe { *table.get_unchecked(0x100 + idx22) };
+        let t23 = unsafe { *table.get_unchecked(idx23) };
+
+        let t28 = unsafe { *table.get_unchecked(0x300 + idx28) };
+        let t29 = unsafe { *table.get_unchecked(0x200 + idx29) };
+        let t30 = unsafe { *table.get_unchecked(0x100 + idx30) };
+        let t31 = unsafe { *table.get_unchecked(idx31) };
 
         // Dependent chain for first 32 bytes
         // Chunk A
@@ -96,10 +112,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx1 = (((crc ^ va1) >> 8) as u8) as usize;
         let idx2 = (((crc ^ va1) >> 16) as u8) as usize;
         let idx3 = (((crc ^ va1) >> 24) as u8) as usize;
-        let t0 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx0) };
-        let t1 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx1) };
-        let t2 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx2) };
-        let t3 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx3) };
+        let t0 = unsafe { *table.get_unchecked(0x700 + idx0) };
+        let t1 = unsafe { *table.get_unchecked(0x600 + idx1) };
+        let t2 = unsafe { *table.get_unchecked(0x500 + idx2) };
+        let t3 = unsafe { *table.get_unchecked(0x400 + idx3) };
         crc = ((t0 ^ t1) ^ (t2 ^ t3)) ^ ((t4 ^ t5) ^ (t6 ^ t7));
 
         // Chunk B
@@ -107,10 +123,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx9 = (((crc ^ vb1) >> 8) as u8) as usize;
         let idx10 = (((crc ^ vb1) >> 16) as u8) as usize;
         let idx11 = (((crc ^ vb1) >> 24) as u8) as usize;
-        let t8 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx8) };
-        let t9 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx9) };
-        let t10 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx10) };
-        let t11 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx11) };
+        let t8 = unsafe { *table.get_unchecked(0x700 + idx8) };
+        let t9 = unsafe { *table.get_unchecked(0x600 + idx9) };
+        let t10 = unsafe { *table.get_unchecked(0x500 + idx10) };
+        let t11 = unsafe { *table.get_unchecked(0x400 + idx11) };
         crc = ((t8 ^ t9) ^ (t10 ^ t11)) ^ ((t12 ^ t13) ^ (t14 ^ t15));
 
         // Chunk C
@@ -118,10 +134,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx17 = (((crc ^ vc1) >> 8) as u8) as usize;
         let idx18 = (((crc ^ vc1) >> 16) as u8) as usize;
         let idx19 = (((crc ^ vc1) >> 24) as u8) as usize;
-        let t16 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx16) };
-        let t17 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx17) };
-        let t18 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx18) };
-        let t19 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx19) };
+        let t16 = unsafe { *table.get_unchecked(0x700 + idx16) };
+        let t17 = unsafe { *table.get_unchecked(0x600 + idx17) };
+        let t18 = unsafe { *table.get_unchecked(0x500 + idx18) };
+        let t19 = unsafe { *table.get_unchecked(0x400 + idx19) };
         crc = ((t16 ^ t17) ^ (t18 ^ t19)) ^ ((t20 ^ t21) ^ (t22 ^ t23));
 
         // Chunk D
@@ -129,10 +145,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx25 = (((crc ^ vd1) >> 8) as u8) as usize;
         let idx26 = (((crc ^ vd1) >> 16) as u8) as usize;
         let idx27 = (((crc ^ vd1) >> 24) as u8) as usize;
-        let t24 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx24) };
-        let t25 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx25) };
-        let t26 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx26) };
-        let t27 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx27) };
+        let t24 = unsafe { *table.get_unchecked(0x700 + idx24) };
+        let t25 = unsafe { *table.get_unchecked(0x600 + idx25) };
+        let t26 = unsafe { *table.get_unchecked(0x500 + idx26) };
+        let t27 = unsafe { *table.get_unchecked(0x400 + idx27) };
         crc = ((t24 ^ t25) ^ (t26 ^ t27)) ^ ((t28 ^ t29) ^ (t30 ^ t31));
 
         // Now process second 32 bytes (chunks E, F, G, H)
@@ -158,25 +174,25 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx63 = ((vh2 >> 24) as u8) as usize;
 
         // Independent fetches for second 32 bytes
-        let t36 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx36) };
-        let t37 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx37) };
-        let t38 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx38) };
-        let t39 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx39) };
-
-        let t44 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx44) };
-        let t45 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx45) };
-        let t46 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx46) };
-        let t47 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx47) };
-
-        let t52 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx52) };
-        let t53 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx53) };
-        let t54 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx54) };
-        let t55 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx55) };
-
-        let t60 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx60) };
-        let t61 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx61) };
-        let t62 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx62) };
-        let t63 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx63) };
+        let t36 = unsafe { *table.get_unchecked(0x300 + idx36) };
+        let t37 = unsafe { *table.get_unchecked(0x200 + idx37) };
+        let t38 = unsafe { *table.get_unchecked(0x100 + idx38) };
+        let t39 = unsafe { *table.get_unchecked(idx39) };
+
+        let t44 = unsafe { *table.get_unchecked(0x300 + idx44) };
+        let t45 = unsafe { *table.get_unchecked(0x200 + idx45) };
+        let t46 = unsafe { *table.get_unchecked(0x100 + idx46) };
+        let t47 = unsafe { *table.get_unchecked(idx47) };
+
+        let t52 = unsafe { *table.get_unchecked(0x300 + idx52) };
+        let t53 = unsafe { *table.get_unchecked(0x200 + idx53) };
+        let t54 = unsafe { *table.get_unchecked(0x100 + idx54) };
+        let t55 = unsafe { *table.get_unchecked(idx55) };
+
+        let t60 = unsafe { *table.get_unchecked(0x300 + idx60) };
+        let t61 = unsafe { *table.get_unchecked(0x200 + idx61) };
+        let t62 = unsafe { *table.get_unchecked(0x100 + idx62) };
+        let t63 = unsafe { *table.get_unchecked(idx63) };
 
         // Dependent chain for second 32 bytes
         // Chunk E
@@ -184,10 +200,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx33 = (((crc ^ ve1) >> 8) as u8) as usize;
         let idx34 = (((crc ^ ve1) >> 16) as u8) as usize;
         let idx35 = (((crc ^ ve1) >> 24) as u8) as usize;
-        let t32 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx32) };
-        let t33 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx33) };
-        let t34 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx34) };
-        let t35 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx35) };
+        let t32 = unsafe { *table.get_unchecked(0x700 + idx32) };
+        let t33 = unsafe { *table.get_unchecked(0x600 + idx33) };
+        let t34 = unsafe { *table.get_unchecked(0x500 + idx34) };
+        let t35 = unsafe { *table.get_unchecked(0x400 + idx35) };
         crc = ((t32 ^ t33) ^ (t34 ^ t35)) ^ ((t36 ^ t37) ^ (t38 ^ t39));
 
         // Chunk F
@@ -195,10 +211,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx41 = (((crc ^ vf1) >> 8) as u8) as usize;
         let idx42 = (((crc ^ vf1) >> 16) as u8) as usize;
         let idx43 = (((crc ^ vf1) >> 24) as u8) as usize;
-        let t40 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx40) };
-        let t41 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx41) };
-        let t42 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx42) };
-        let t43 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx43) };
+        let t40 = unsafe { *table.get_unchecked(0x700 + idx40) };
+        let t41 = unsafe { *table.get_unchecked(0x600 + idx41) };
+        let t42 = unsafe { *table.get_unchecked(0x500 + idx42) };
+        let t43 = unsafe { *table.get_unchecked(0x400 + idx43) };
         crc = ((t40 ^ t41) ^ (t42 ^ t43)) ^ ((t44 ^ t45) ^ (t46 ^ t47));
 
         // Chunk G
@@ -206,10 +222,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx49 = (((crc ^ vg1) >> 8) as u8) as usize;
         let idx50 = (((crc ^ vg1) >> 16) as u8) as usize;
         let idx51 = (((crc ^ vg1) >> 24) as u8) as usize;
-        let t48 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx48) };
-        let t49 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx49) };
-        let t50 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx50) };
-        let t51 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx51) };
+        let t48 = unsafe { *table.get_unchecked(0x700 + idx48) };
+        let t49 = unsafe { *table.get_unchecked(0x600 + idx49) };
+        let t50 = unsafe { *table.get_unchecked(0x500 + idx50) };
+        let t51 = unsafe { *table.get_unchecked(0x400 + idx51) };
         crc = ((t48 ^ t49) ^ (t50 ^ t51)) ^ ((t52 ^ t53) ^ (t54 ^ t55));
 
         // Chunk H
@@ -217,10 +233,10 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx57 = (((crc ^ vh1) >> 8) as u8) as usize;
         let idx58 = (((crc ^ vh1) >> 16) as u8) as usize;
         let idx59 = (((crc ^ vh1) >> 24) as u8) as usize;
-        let t56 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx56) };
-        let t57 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx57) };
-        let t58 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx58) };
-        let t59 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx59) };
+        let t56 = unsafe { *table.get_unchecked(0x700 + idx56) };
+        let t57 = unsafe { *table.get_unchecked(0x600 + idx57) };
+        let t58 = unsafe { *table.get_unchecked(0x500 + idx58) };
+        let t59 = unsafe { *table.get_unchecked(0x400 + idx59) };
         crc = ((t56 ^ t57) ^ (t58 ^ t59)) ^ ((t60 ^ t61) ^ (t62 ^ t63));
 
         unsafe {
@@ -231,7 +247,7 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
 
     // Fallback for remaining chunks < 64 bytes
     while len >= 8 {
-        let v = u64::from_le(unsafe { std::ptr::read_unaligned(ptr as *const u64) });
+        let v = u64::from_le(unsafe { core::ptr::read_unaligned(ptr as *const u64) });
         let v1 = v as u32;
         let v2 = (v >> 32) as u32;
 
@@ -244,14 +260,14 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         let idx6 = ((v2 >> 16) as u8) as usize;
         let idx7 = ((v2 >> 24) as u8) as usize;
 
-        let t0 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x700 + idx0) };
-        let t1 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x600 + idx1) };
-        let t2 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x500 + idx2) };
-        let t3 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x400 + idx3) };
-        let t4 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x300 + idx4) };
-        let t5 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx5) };
-        let t6 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx6) };
-        let t7 = unsafe { *CRC32_SLICE8_TABLE.get_unchecked(idx7) };
+        let t0 = unsafe { *table.get_unchecked(0x700 + idx0) };
+        let t1 = unsafe { *table.get_unchecked(0x600 + idx1) };
+        let t2 = unsafe { *table.get_unchecked(0x500 + idx2) };
+        let t3 = unsafe { *table.get_unchecked(0x400 + idx3) };
+        let t4 = unsafe { *table.get_unchecked(0x300 + idx4) };
+        let t5 = unsafe { *table.get_unchecked(0x200 + idx5) };
+        let t6 = unsafe { *table.get_unchecked(0x100 + idx6) };
+        let t7 = unsafe { *table.get_unchecked(idx7) };
 
         // Optimization: Use tree-based XOR reduction to break dependency chains and increase ILP.
         crc = ((t0 ^ t1) ^ (t2 ^ t3)) ^ ((t4 ^ t5) ^ (t6 ^ t7));
@@ -262,13 +278,13 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
         len -= 8;
     }
     if len >= 4 {
-        let v = u32::from_le(unsafe { std::ptr::read_unaligned(ptr as *const u32) });
+        let v = u32::from_le(unsafe { core::ptr::read_unaligned(ptr as *const u32) });
         crc ^= v;
         crc = unsafe {
-            *CRC32_SLICE8_TABLE.get_unchecked(0x300 + (crc as u8) as usize)
-                ^ *CRC32_SLICE8_TABLE.get_unchecked(0x200 + ((crc >> 8) as u8) as usize)
-                ^ *CRC32_SLICE8_TABLE.get_unchecked(0x100 + ((crc >> 16) as u8) as usize)
-                ^ *CRC32_SLICE8_TABLE.get_unchecked(((crc >> 24) as u8) as usize)
+            *table.get_unchecked(0x300 + (crc as u8) as usize)
+                ^ *table.get_unchecked(0x200 + ((crc >> 8) as u8) as usize)
+                ^ *table.get_unchecked(0x100 + ((crc >> 16) as u8) as usize)
+                ^ *table.get_unchecked(((crc >> 24) as u8) as usize)
         };
         unsafe {
             ptr = ptr.add(4);
@@ -289,9 +305,9 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
 
                 crc = unsafe {
                     (crc >> 24)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(0x200 + idx0 as usize)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx1 as usize)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(idx2 as usize)
+                        ^ *table.get_unchecked(0x200 + idx0 as usize)
+                        ^ *table.get_unchecked(0x100 + idx1 as usize)
+                        ^ *table.get_unchecked(idx2 as usize)
                 };
             }
             2 => {
@@ -304,18 +320,17 @@ pub fn crc32_slice8(mut crc: u32, p: &[u8]) -> u32 {
 
                 crc = unsafe {
                     (crc >> 16)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(0x100 + idx0 as usize)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(idx1 as usize)
+                        ^ *table.get_unchecked(0x100 + idx0 as usize)
+                        ^ *table.get_unchecked(idx1 as usize)
                 };
             }
             1 => {
                 let b0 = unsafe { *ptr } as u32;
                 crc = unsafe {
-                    (crc >> 8)
-                        ^ *CRC32_SLICE8_TABLE.get_unchecked(((crc as u8 as u32) ^ b0) as usize)
+                    (crc >> 8) ^ *table.get_unchecked(((crc as u8 as u32) ^ b0) as usize)
                 };
             }
-            _ => unsafe { std::hint::unreachable_unchecked() },
+            _ => unsafe { core::hint::unreachable_unchecked() },
         }
     }
     crc
@@ -328,38 +343,333 @@ mod x86;
 
 type Crc32Fn = unsafe fn(u32, &[u8]) -> u32;
 
+/// Bit-reflected generator polynomial of the gzip/zlib CRC-32.
+pub const CRC32_POLY: u32 = 0xedb88320;
+/// Bit-reflected generator polynomial of CRC-32C (Castagnoli).
+pub const CRC32C_POLY: u32 = 0x82f63b78;
+
+static CRC32C_SLICE8_TABLE: [u32; 2048] = crc32_slice8_table(CRC32C_POLY);
+
+/// Folding constants for one reflected CRC-32 polynomial, shared by all of the
+/// carry-less multiplication kernels. `xN` is x^N mod G, bit-reflected.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) struct Crc32Consts {
+    pub(crate) x95: u64,
+    pub(crate) x159: u64,
+    pub(crate) x223: u64,
+    pub(crate) x287: u64,
+    pub(crate) x479: u64,
+    pub(crate) x543: u64,
+    pub(crate) x991: u64,
+    pub(crate) x1055: u64,
+    pub(crate) x2015: u64,
+    pub(crate) x2079: u64,
+    pub(crate) x4063: u64,
+    pub(crate) x4127: u64,
+    pub(crate) barrett_1: u64,
+    pub(crate) barrett_2: u64,
+    /// Handles inputs too short to fold and the bytes left over after folding.
+    pub(crate) fallback: Crc32Fn,
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+impl Crc32Consts {
+    pub(crate) const fn new(poly: u32, fallback: Crc32Fn) -> Self {
+        Self {
+            x95: xn_modg(poly, 95),
+            x159: xn_modg(poly, 159),
+            x223: xn_modg(poly, 223),
+            x287: xn_modg(poly, 287),
+            x479: xn_modg(poly, 479),
+            x543: xn_modg(poly, 543),
+            x991: xn_modg(poly, 991),
+            x1055: xn_modg(poly, 1055),
+            x2015: xn_modg(poly, 2015),
+            x2079: xn_modg(poly, 2079),
+            x4063: xn_modg(poly, 4063),
+            x4127: xn_modg(poly, 4127),
+            barrett_1: x64_div_g(poly),
+            barrett_2: ((poly as u64) << 1) | 1,
+            fallback,
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const fn xn_modg(poly: u32, n: usize) -> u64 {
+    let mut rem = 1u32 << 31;
+    let mut i = 0;
+    while i < n {
+        rem = (rem >> 1) ^ if rem & 1 != 0 { poly } else { 0 };
+        i += 1;
+    }
+    rem as u64
+}
+
+/// Bit-reflected quotient used as the first constant of the final Barrett reduction.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const fn x64_div_g(poly: u32) -> u64 {
+    let full = ((poly as u64) << 1) | 1;
+    let mut quotient = 0u64;
+    let mut dividend = 1u64;
+    let mut i = 0;
+    while i < 64 {
+        if (dividend >> i) & 1 != 0 {
+            quotient |= 1 << i;
+            dividend ^= full << i;
+        }
+        i += 1;
+    }
+    quotient
+}
+
+const fn crc32_slice8_table(poly: u32) -> [u32; 2048] {
+    let mut table = [0u32; 2048];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { poly } else { 0 };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    while i < 2048 {
+        let prev = table[i - 256];
+        table[i] = (prev >> 8) ^ table[(prev & 0xFF) as usize];
+        i += 1;
+    }
+    table
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) const CRC32_CONSTS: Crc32Consts = Crc32Consts {
+    x95: CRC32_X95_MODG,
+    x159: CRC32_X159_MODG,
+    x223: CRC32_X223_MODG,
+    x287: CRC32_X287_MODG,
+    x479: CRC32_X479_MODG,
+    x543: CRC32_X543_MODG,
+    x991: CRC32_X991_MODG,
+    x1055: CRC32_X1055_MODG,
+    x2015: CRC32_X2015_MODG,
+    x2079: CRC32_X2079_MODG,
+    x4063: CRC32_X4063_MODG,
+    x4127: CRC32_X4127_MODG,
+    barrett_1: CRC32_BARRETT_CONSTANT_1,
+    barrett_2: CRC32_BARRETT_CONSTANT_2,
+    fallback: crc32_slice8,
+};
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) const CRC32C_CONSTS: Crc32Consts = Crc32Consts::new(CRC32C_POLY, crc32c_slice8);
+
+// The generators must reproduce the precomputed gzip CRC-32 constants.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const _: () = {
+    let generated = Crc32Consts::new(CRC32_POLY, crc32_slice8);
+    assert!(generated.x95 == CRC32_CONSTS.x95 && generated.x159 == CRC32_CONSTS.x159);
+    assert!(generated.x223 == CRC32_CONSTS.x223 && generated.x287 == CRC32_CONSTS.x287);
+    assert!(generated.x479 == CRC32_CONSTS.x479 && generated.x543 == CRC32_CONSTS.x543);
+    assert!(generated.x991 == CRC32_CONSTS.x991 && generated.x1055 == CRC32_CONSTS.x1055);
+    assert!(generated.x2015 == CRC32_CONSTS.x2015 && generated.x2079 == CRC32_CONSTS.x2079);
+    assert!(generated.x4063 == CRC32_CONSTS.x4063 && generated.x4127 == CRC32_CONSTS.x4127);
+    assert!(generated.barrett_1 == CRC32_CONSTS.barrett_1);
+    assert!(generated.barrett_2 == CRC32_CONSTS.barrett_2);
+};
+
+// And the precomputed table.
+const _: () = {
+    let table = crc32_slice8_table(CRC32_POLY);
+    let mut i = 0;
+    while i < 2048 {
+        assert!(table[i] == CRC32_SLICE8_TABLE[i]);
+        i += 1;
+    }
+};
+
+static IMPL: OnceLock<(Crc32Fn, &'static str)> = OnceLock::new();
+
+fn select_impl() -> (Crc32Fn, &'static str) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx512f")
+            && x86_feature_enabled!("avx512bw")
+            && x86_feature_enabled!("avx512vl")
+            && x86_feature_enabled!("vpclmulqdq")
+        {
+            return (x86::crc32_x86_vpclmulqdq_avx512_vl512, "vpclmulqdq_avx512");
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx2") && x86_feature_enabled!("vpclmulqdq") {
+            return (x86::crc32_x86_vpclmulqdq_avx2, "vpclmulqdq_avx2");
+        }
+
+        if x86_feature_enabled!("pclmulqdq") && x86_feature_enabled!("sse4.1") {
+            return (x86::crc32_x86_pclmulqdq, "pclmulqdq");
+        }
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if aarch64_feature_enabled!("crc") {
+            return (arm::crc32_arm, "arm_crc");
+        }
+    }
+    (crc32_slice8, "slice8")
+}
+
+/// Name of the CRC-32 implementation selected for this CPU.
+pub fn implementation_name() -> &'static str {
+    IMPL.get_or_init(select_impl).1
+}
+
 #[inline]
 pub fn crc32(crc: u32, slice: &[u8]) -> u32 {
-    static IMPL: OnceLock<Crc32Fn> = OnceLock::new();
-    let func = IMPL.get_or_init(|| {
-        #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    let func = IMPL.get_or_init(select_impl).0;
+    unsafe { !func(!crc, slice) }
+}
+
+static CRC32C_IMPL: OnceLock<(Crc32Fn, &'static str)> = OnceLock::new();
+
+fn select_crc32c_impl() -> (Crc32Fn, &'static str) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if x86_feature_enabled!("sse4.2") {
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx512f")
+            && x86_feature_enabled!("avx512bw")
+            && x86_feature_enabled!("avx512vl")
+            && x86_feature_enabled!("vpclmulqdq")
         {
-            #[cfg(target_arch = "x86_64")]
-            if is_x86_feature_detected!("avx512f")
-                && is_x86_feature_detected!("avx512bw")
-                && is_x86_feature_detected!("avx512vl")
-                && is_x86_feature_detected!("vpclmulqdq")
-            {
-                return x86::crc32_x86_vpclmulqdq_avx512_vl512;
-            }
+            return (x86::crc32c_x86_vpclmulqdq_avx512_vl512, "vpclmulqdq_avx512");
+        }
 
-            #[cfg(target_arch = "x86_64")]
-            if is_x86_feature_detected!("avx2") && is_x86_feature_detected!("vpclmulqdq") {
-                return x86::crc32_x86_vpclmulqdq_avx2;
-            }
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx2") && x86_feature_enabled!("vpclmulqdq") {
+            return (x86::crc32c_x86_vpclmulqdq_avx2, "vpclmulqdq_avx2");
+        }
 
-            if is_x86_feature_detected!("pclmulqdq") && is_x86_feature_detected!("sse4.1") {
-                return x86::crc32_x86_pclmulqdq;
-            }
+        if x86_feature_enabled!("pclmulqdq") {
+            return (x86::crc32c_x86_pclmulqdq, "pclmulqdq");
         }
-        #[cfg(target_arch = "aarch64")]
-        {
-            if std::arch::is_aarch64_feature_detected!("crc") {
-                return arm::crc32_arm;
-            }
+
+        return (x86::crc32c_x86_sse42, "sse4.2");
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        if aarch64_feature_enabled!("crc") {
+            return (arm::crc32c_arm, "arm_crc");
         }
-        crc32_slice8
-    });
+    }
+    (crc32c_slice8, "slice8")
+}
+
+/// Name of the CRC-32C implementation selected for this CPU.
+pub fn crc32c_implementation_name() -> &'static str {
+    CRC32C_IMPL.get_or_init(select_crc32c_impl).1
+}
 
+/// CRC-32C (Castagnoli), as used by iSCSI, ext4 and SSE4.2's `crc32` instruction.
+///
+/// Like [`crc32`], `crc` is the value returned for the preceding data, or 0 to start.
+#[inline]
+pub fn crc32c(crc: u32, slice: &[u8]) -> u32 {
+    let func = CRC32C_IMPL.get_or_init(select_crc32c_impl).0;
     unsafe { !func(!crc, slice) }
 }
+
+/// Multiplies a bit-reflected polynomial by x^32 modulo G.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+#[inline]
+fn crc32_mul_x32(v: u32) -> u32 {
+    CRC32_SLICE8_TABLE[0x300 + (v as u8) as usize]
+        ^ CRC32_SLICE8_TABLE[0x200 + ((v >> 8) as u8) as usize]
+        ^ CRC32_SLICE8_TABLE[0x100 + ((v >> 16) as u8) as usize]
+        ^ CRC32_SLICE8_TABLE[((v >> 24) as u8) as usize]
+}
+
+fn crc32_multmodp_generic(a: u32, mut b: u32) -> u32 {
+    let mut m = 1u32 << 31;
+    let mut p = 0;
+    loop {
+        if a & m != 0 {
+            p ^= b;
+            if a & (m - 1) == 0 {
+                break;
+            }
+        }
+        m >>= 1;
+        b = if b & 1 != 0 {
+            (b >> 1) ^ CRC32_POLY
+        } else {
+            b >> 1
+        };
+    }
+    p
+}
+
+type MultModPFn = unsafe fn(u32, u32) -> u32;
+
+static MULTMODP_IMPL: OnceLock<MultModPFn> = OnceLock::new();
+
+fn select_multmodp_impl() -> MultModPFn {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    if x86_feature_enabled!("pclmulqdq") && x86_feature_enabled!("sse2") {
+        return x86::crc32_multmodp_pclmulqdq;
+    }
+    crc32_multmodp_generic
+}
+
+/// Returns x^(8 * len) mod G, i.e. the factor that shifts a CRC over `len` zero bytes.
+fn crc32_x8nmodp(mut len: usize) -> u32 {
+    let multmodp = MULTMODP_IMPL.get_or_init(select_multmodp_impl);
+    let mut p = 1u32 << 31;
+    // x^(2^k) mod G repeats with period 32, so k only needs to be tracked mod 32.
+    let mut k = 3;
+    while len != 0 {
+        if len & 1 != 0 {
+            p = unsafe { multmodp(CRC32_X2N_TABLE[k & 31], p) };
+        }
+        len >>= 1;
+        k += 1;
+    }
+    p
+}
+
+/// Extends `crc` as if `n` zero bytes had been appended to the data it covers.
+///
+/// Runs in O(log n) time, so it is cheap even for very large `n`.
+pub fn crc32_zeros(crc: u32, n: usize) -> u32 {
+    let multmodp = MULTMODP_IMPL.get_or_init(select_multmodp_impl);
+    !unsafe { multmodp(crc32_x8nmodp(n), !crc) }
+}
+
+/// Combines `crc1 = crc32(0, a)` and `crc2 = crc32(0, b)` into `crc32(0, a ++ b)`,
+/// where `len2` is the length of `b`.
+pub fn crc32_combine(crc1: u32, crc2: u32, len2: usize) -> u32 {
+    let multmodp = MULTMODP_IMPL.get_or_init(select_multmodp_impl);
+    unsafe { multmodp(crc32_x8nmodp(len2), crc1) ^ crc2 }
+}
+
+/// Inputs larger than this are checksummed by [`crc32_parallel`] in chunks of this size.
+pub const PARALLEL_CHECKSUM_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Same result as [`crc32`], but splits large inputs across the rayon thread pool
+/// and merges the per-chunk CRCs with [`crc32_combine`].
+#[cfg(feature = "std")]
+pub fn crc32_parallel(crc: u32, slice: &[u8]) -> u32 {
+    if slice.len() <= PARALLEL_CHECKSUM_CHUNK_SIZE {
+        return crc32(crc, slice);
+    }
+    let crcs: Vec<u32> = slice
+        .par_chunks(PARALLEL_CHECKSUM_CHUNK_SIZE)
+        .map(|chunk| crc32(0, chunk))
+        .collect();
+    slice
+        .chunks(PARALLEL_CHECKSUM_CHUNK_SIZE)
+        .zip(crcs)
+        .fold(crc, |acc, (chunk, c)| crc32_combine(acc, c, chunk.len()))
+}
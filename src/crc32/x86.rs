@@ -3,28 +3,88 @@ use core::arch::x86::*;
 #[cfg(target_arch = "x86_64")]
 use core::arch::x86_64::*;
 
-use crate::crc32_tables::*;
+use super::{CRC32_CONSTS, CRC32C_CONSTS, Crc32Consts};
+
+// The CRC-32C kernels are only selected when SSE4.2 is available, so they can
+// hand short inputs and tails to the `crc32` instruction instead of the tables.
+const CRC32C_SSE42_CONSTS: Crc32Consts = Crc32Consts {
+    fallback: crc32c_x86_sse42,
+    ..CRC32C_CONSTS
+};
+
+#[target_feature(enable = "pclmulqdq", enable = "sse4.1")]
+pub unsafe fn crc32_x86_pclmulqdq(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_pclmulqdq_impl(crc, p, &CRC32_CONSTS)
+}
+
+#[target_feature(enable = "pclmulqdq", enable = "sse4.2")]
+pub unsafe fn crc32c_x86_pclmulqdq(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_pclmulqdq_impl(crc, p, &CRC32C_SSE42_CONSTS)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw,avx512vl,vpclmulqdq")]
+pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_vpclmulqdq_avx512_vl512_impl(crc, p, &CRC32_CONSTS)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512bw,avx512vl,vpclmulqdq,sse4.2")]
+pub unsafe fn crc32c_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_vpclmulqdq_avx512_vl512_impl(crc, p, &CRC32C_SSE42_CONSTS)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,vpclmulqdq")]
+pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_vpclmulqdq_avx2_impl(crc, p, &CRC32_CONSTS)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,vpclmulqdq,sse4.2")]
+pub unsafe fn crc32c_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
+    crc32_x86_vpclmulqdq_avx2_impl(crc, p, &CRC32C_SSE42_CONSTS)
+}
+
+#[target_feature(enable = "sse4.2")]
+pub unsafe fn crc32c_x86_sse42(mut crc: u32, p: &[u8]) -> u32 {
+    let mut chunks = p.chunks_exact(8);
+    for chunk in &mut chunks {
+        let v = u64::from_le_bytes(chunk.try_into().unwrap());
+        #[cfg(target_arch = "x86_64")]
+        {
+            crc = _mm_crc32_u64(crc as u64, v) as u32;
+        }
+        #[cfg(target_arch = "x86")]
+        {
+            crc = _mm_crc32_u32(crc, v as u32);
+            crc = _mm_crc32_u32(crc, (v >> 32) as u32);
+        }
+    }
+    for &b in chunks.remainder() {
+        crc = _mm_crc32_u8(crc, b);
+    }
+    crc
+}
 
 #[target_feature(enable = "pclmulqdq", enable = "sse4.1")]
-pub unsafe fn crc32_x86_pclmulqdq(mut crc: u32, p: &[u8]) -> u32 {
+#[inline]
+unsafe fn crc32_x86_pclmulqdq_impl(mut crc: u32, p: &[u8], k: &Crc32Consts) -> u32 {
     let mut len = p.len();
     let mut data = p;
 
     if len < 16 {
-        return crate::crc32::crc32_slice8(crc, data);
+        return (k.fallback)(crc, data);
     }
 
-    let mults_128b = _mm_set_epi64x(CRC32_X95_MODG as i64, CRC32_X159_MODG as i64);
-    let mults_256b = _mm_set_epi64x(CRC32_X223_MODG as i64, CRC32_X287_MODG as i64);
-    let barrett_reduction_constants = _mm_set_epi64x(
-        CRC32_BARRETT_CONSTANT_2 as i64,
-        CRC32_BARRETT_CONSTANT_1 as i64,
-    );
+    let mults_128b = _mm_set_epi64x(k.x95 as i64, k.x159 as i64);
+    let mults_256b = _mm_set_epi64x(k.x223 as i64, k.x287 as i64);
+    let barrett_reduction_constants = _mm_set_epi64x(k.barrett_2 as i64, k.barrett_1 as i64);
 
     let mut x0 = _mm_cvtsi32_si128(crc as i32);
 
     if len >= 64 {
-        let mults_512b = _mm_set_epi64x(CRC32_X479_MODG as i64, CRC32_X543_MODG as i64);
+        let mults_512b = _mm_set_epi64x(k.x479 as i64, k.x543 as i64);
         let v0 = _mm_loadu_si128(data.as_ptr() as *const __m128i);
         let v1 = _mm_loadu_si128(data.as_ptr().add(16) as *const __m128i);
         let v2 = _mm_loadu_si128(data.as_ptr().add(32) as *const __m128i);
@@ -48,7 +108,7 @@ pub unsafe fn crc32_x86_pclmulqdq(mut crc: u32, p: &[u8]) -> u32 {
             len -= 64;
 
             if len >= 128 {
-                let mults_1024b = _mm_set_epi64x(CRC32_X991_MODG as i64, CRC32_X1055_MODG as i64);
+                let mults_1024b = _mm_set_epi64x(k.x991 as i64, k.x1055 as i64);
                 while len >= 128 {
                     let v0 = _mm_loadu_si128(data.as_ptr() as *const __m128i);
                     let v1 = _mm_loadu_si128(data.as_ptr().add(16) as *const __m128i);
@@ -206,7 +266,7 @@ pub unsafe fn crc32_x86_pclmulqdq(mut crc: u32, p: &[u8]) -> u32 {
     crc = _mm_extract_epi32(x0, 2) as u32;
 
     if len > 0 {
-        crc = crate::crc32::crc32_slice8(crc, data);
+        crc = (k.fallback)(crc, data);
     }
 
     crc
@@ -214,22 +274,20 @@ pub unsafe fn crc32_x86_pclmulqdq(mut crc: u32, p: &[u8]) -> u32 {
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx512f,avx512bw,avx512vl,vpclmulqdq")]
-pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
+#[inline]
+unsafe fn crc32_x86_vpclmulqdq_avx512_vl512_impl(crc: u32, p: &[u8], k: &Crc32Consts) -> u32 {
     let mut len = p.len();
     let mut data = p;
 
-    let mults_128b = _mm_set_epi64x(CRC32_X95_MODG as i64, CRC32_X159_MODG as i64);
-    let barrett_reduction_constants = _mm_set_epi64x(
-        CRC32_BARRETT_CONSTANT_2 as i64,
-        CRC32_BARRETT_CONSTANT_1 as i64,
-    );
+    let mults_128b = _mm_set_epi64x(k.x95 as i64, k.x159 as i64);
+    let barrett_reduction_constants = _mm_set_epi64x(k.barrett_2 as i64, k.barrett_1 as i64);
 
     let mut x0 = _mm_cvtsi32_si128(crc as i32);
 
     if len < 512 {
         if len < 64 {
             if len < 16 {
-                return crate::crc32::crc32_slice8(crc, data);
+                return (k.fallback)(crc, data);
             } else {
                 x0 = _mm_xor_si128(_mm_loadu_si128(data.as_ptr() as *const __m128i), x0);
                 if len >= 32 {
@@ -257,14 +315,14 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
             if len >= 128 {
                 let mut v1 = _mm512_loadu_si512(data.as_ptr().add(64) as *const _);
                 let mults_1v = _mm512_set_epi64(
-                    CRC32_X479_MODG as i64,
-                    CRC32_X543_MODG as i64,
-                    CRC32_X479_MODG as i64,
-                    CRC32_X543_MODG as i64,
-                    CRC32_X479_MODG as i64,
-                    CRC32_X543_MODG as i64,
-                    CRC32_X479_MODG as i64,
-                    CRC32_X543_MODG as i64,
+                    k.x479 as i64,
+                    k.x543 as i64,
+                    k.x479 as i64,
+                    k.x543 as i64,
+                    k.x479 as i64,
+                    k.x543 as i64,
+                    k.x479 as i64,
+                    k.x543 as i64,
                 );
 
                 if len >= 256 {
@@ -274,34 +332,32 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
                     data = &data[256..];
                     len -= 256;
 
+                    let mults_2v = _mm512_set_epi64(
+                        k.x991 as i64,
+                        k.x1055 as i64,
+                        k.x991 as i64,
+                        k.x1055 as i64,
+                        k.x991 as i64,
+                        k.x1055 as i64,
+                        k.x991 as i64,
+                        k.x1055 as i64,
+                    );
+                    v0 = fold_vec512(v0, v2, mults_2v);
+                    v1 = fold_vec512(v1, v3, mults_2v);
+
                     if len >= 128 {
-                        let mults_2v = _mm512_set_epi64(
-                            CRC32_X991_MODG as i64,
-                            CRC32_X1055_MODG as i64,
-                            CRC32_X991_MODG as i64,
-                            CRC32_X1055_MODG as i64,
-                            CRC32_X991_MODG as i64,
-                            CRC32_X1055_MODG as i64,
-                            CRC32_X991_MODG as i64,
-                            CRC32_X1055_MODG as i64,
+                        v0 = fold_vec512(
+                            v0,
+                            _mm512_loadu_si512(data.as_ptr() as *const _),
+                            mults_2v,
+                        );
+                        v1 = fold_vec512(
+                            v1,
+                            _mm512_loadu_si512(data.as_ptr().add(64) as *const _),
+                            mults_2v,
                         );
-                        v0 = fold_vec512(v0, v2, mults_2v);
-                        v1 = fold_vec512(v1, v3, mults_2v);
-
-                        if len >= 64 {
-                            v0 = fold_vec512(
-                                v0,
-                                _mm512_loadu_si512(data.as_ptr() as *const _),
-                                mults_2v,
-                            );
-                            v1 = fold_vec512(
-                                v1,
-                                _mm512_loadu_si512(data.as_ptr().add(64) as *const _),
-                                mults_2v,
-                            );
-                            data = &data[128..];
-                            len -= 128;
-                        }
+                        data = &data[128..];
+                        len -= 128;
                     }
 
                     v0 = fold_vec512(v0, v1, mults_1v);
@@ -333,12 +389,8 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
                 len -= 64;
             }
 
-            let mults_256b = _mm256_set_epi64x(
-                CRC32_X223_MODG as i64,
-                CRC32_X287_MODG as i64,
-                CRC32_X223_MODG as i64,
-                CRC32_X287_MODG as i64,
-            );
+            let mults_256b =
+                _mm256_set_epi64x(k.x223 as i64, k.x287 as i64, k.x223 as i64, k.x287 as i64);
             let mut y0 = fold_vec256_avx512(
                 _mm512_extracti64x4_epi64(v0, 0),
                 _mm512_extracti64x4_epi64(v0, 1),
@@ -372,7 +424,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
             let align = 64 - align_offset;
 
             let mut current_crc = _mm_cvtsi128_si32(x0) as u32;
-            current_crc = crate::crc32::crc32_slice8(current_crc, &data[..align]);
+            current_crc = (k.fallback)(current_crc, &data[..align]);
             data = &data[align..];
             len -= align;
 
@@ -399,14 +451,14 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
         len -= 512;
 
         let mults_8v = _mm512_set_epi64(
-            CRC32_X4063_MODG as i64,
-            CRC32_X4127_MODG as i64,
-            CRC32_X4063_MODG as i64,
-            CRC32_X4127_MODG as i64,
-            CRC32_X4063_MODG as i64,
-            CRC32_X4127_MODG as i64,
-            CRC32_X4063_MODG as i64,
-            CRC32_X4127_MODG as i64,
+            k.x4063 as i64,
+            k.x4127 as i64,
+            k.x4063 as i64,
+            k.x4127 as i64,
+            k.x4063 as i64,
+            k.x4127 as i64,
+            k.x4063 as i64,
+            k.x4127 as i64,
         );
 
         while len >= 512 {
@@ -451,14 +503,14 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
         }
 
         let mults_4v = _mm512_set_epi64(
-            CRC32_X2015_MODG as i64,
-            CRC32_X2079_MODG as i64,
-            CRC32_X2015_MODG as i64,
-            CRC32_X2079_MODG as i64,
-            CRC32_X2015_MODG as i64,
-            CRC32_X2079_MODG as i64,
-            CRC32_X2015_MODG as i64,
-            CRC32_X2079_MODG as i64,
+            k.x2015 as i64,
+            k.x2079 as i64,
+            k.x2015 as i64,
+            k.x2079 as i64,
+            k.x2015 as i64,
+            k.x2079 as i64,
+            k.x2015 as i64,
+            k.x2079 as i64,
         );
 
         v0 = fold_vec512(v0, v4, mults_4v);
@@ -488,14 +540,14 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
         }
 
         let mults_2v = _mm512_set_epi64(
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
+            k.x991 as i64,
+            k.x1055 as i64,
+            k.x991 as i64,
+            k.x1055 as i64,
+            k.x991 as i64,
+            k.x1055 as i64,
+            k.x991 as i64,
+            k.x1055 as i64,
         );
 
         v0 = fold_vec512(v0, v2, mults_2v);
@@ -513,14 +565,14 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
         }
 
         let mults_1v = _mm512_set_epi64(
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
+            k.x479 as i64,
+            k.x543 as i64,
+            k.x479 as i64,
+            k.x543 as i64,
+            k.x479 as i64,
+            k.x543 as i64,
+            k.x479 as i64,
+            k.x543 as i64,
         );
 
         v0 = fold_vec512(v0, v1, mults_1v);
@@ -531,12 +583,8 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
             len -= 64;
         }
 
-        let mults_256b = _mm256_set_epi64x(
-            CRC32_X223_MODG as i64,
-            CRC32_X287_MODG as i64,
-            CRC32_X223_MODG as i64,
-            CRC32_X287_MODG as i64,
-        );
+        let mults_256b =
+            _mm256_set_epi64x(k.x223 as i64, k.x287 as i64, k.x223 as i64, k.x287 as i64);
         let mut y0 = fold_vec256_avx512(
             _mm512_extracti64x4_epi64(v0, 0),
             _mm512_extracti64x4_epi64(v0, 1),
@@ -574,7 +622,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
     let mut res = _mm_extract_epi32(x0, 2) as u32;
 
     if len > 0 {
-        res = crate::crc32::crc32_slice8(res, data);
+        res = (k.fallback)(res, data);
     }
 
     res
@@ -582,15 +630,13 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx512_vl512(crc: u32, p: &[u8]) -> u32 {
 
 #[cfg(target_arch = "x86_64")]
 #[target_feature(enable = "avx2,vpclmulqdq")]
-pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
+#[inline]
+unsafe fn crc32_x86_vpclmulqdq_avx2_impl(crc: u32, p: &[u8], k: &Crc32Consts) -> u32 {
     let mut len = p.len();
     let mut data = p;
 
-    let mults_128b = _mm_set_epi64x(CRC32_X95_MODG as i64, CRC32_X159_MODG as i64);
-    let barrett_reduction_constants = _mm_set_epi64x(
-        CRC32_BARRETT_CONSTANT_2 as i64,
-        CRC32_BARRETT_CONSTANT_1 as i64,
-    );
+    let mults_128b = _mm_set_epi64x(k.x95 as i64, k.x159 as i64);
+    let barrett_reduction_constants = _mm_set_epi64x(k.barrett_2 as i64, k.barrett_1 as i64);
 
     let mut x0 = _mm_cvtsi32_si128(crc as i32);
 
@@ -600,12 +646,8 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
         let mut v2;
         let mut v3;
 
-        let mults_4v = _mm256_set_epi64x(
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
-            CRC32_X991_MODG as i64,
-            CRC32_X1055_MODG as i64,
-        );
+        let mults_4v =
+            _mm256_set_epi64x(k.x991 as i64, k.x1055 as i64, k.x991 as i64, k.x1055 as i64);
 
         if len >= 256 {
             v0 = _mm256_loadu_si256(data.as_ptr() as *const _);
@@ -624,10 +666,10 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
             len -= 256;
 
             let mults_8v = _mm256_set_epi64x(
-                CRC32_X2015_MODG as i64,
-                CRC32_X2079_MODG as i64,
-                CRC32_X2015_MODG as i64,
-                CRC32_X2079_MODG as i64,
+                k.x2015 as i64,
+                k.x2079 as i64,
+                k.x2015 as i64,
+                k.x2079 as i64,
             );
 
             while len >= 256 {
@@ -709,21 +751,13 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
             len -= 128;
         }
 
-        let mults_2v = _mm256_set_epi64x(
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
-            CRC32_X479_MODG as i64,
-            CRC32_X543_MODG as i64,
-        );
+        let mults_2v =
+            _mm256_set_epi64x(k.x479 as i64, k.x543 as i64, k.x479 as i64, k.x543 as i64);
         v0 = fold_vec256(v0, v2, mults_2v);
         v1 = fold_vec256(v1, v3, mults_2v);
 
-        let mults_1v = _mm256_set_epi64x(
-            CRC32_X223_MODG as i64,
-            CRC32_X287_MODG as i64,
-            CRC32_X223_MODG as i64,
-            CRC32_X287_MODG as i64,
-        );
+        let mults_1v =
+            _mm256_set_epi64x(k.x223 as i64, k.x287 as i64, k.x223 as i64, k.x287 as i64);
         v0 = fold_vec256(v0, v1, mults_1v);
 
         x0 = fold_vec128(
@@ -733,7 +767,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
         );
     } else {
         if len < 16 {
-            return crate::crc32::crc32_slice8(crc, data);
+            return (k.fallback)(crc, data);
         }
 
         if len >= 64 {
@@ -744,7 +778,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
 
             let t1 = fold_vec128(_mm_xor_si128(v0, x0), v1, mults_128b);
             let t2 = fold_vec128(v2, v3, mults_128b);
-            let mults_256b = _mm_set_epi64x(CRC32_X223_MODG as i64, CRC32_X287_MODG as i64);
+            let mults_256b = _mm_set_epi64x(k.x223 as i64, k.x287 as i64);
             x0 = fold_vec128(t1, t2, mults_256b);
 
             data = &data[64..];
@@ -773,10 +807,10 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
 
         let t1 = fold_vec128(v0, v1, mults_128b);
         let t2 = fold_vec128(v2, v3, mults_128b);
-        let mults_256b = _mm_set_epi64x(CRC32_X223_MODG as i64, CRC32_X287_MODG as i64);
+        let mults_256b = _mm_set_epi64x(k.x223 as i64, k.x287 as i64);
         let t3 = fold_vec128(t1, t2, mults_256b);
 
-        let mults_512b = _mm_set_epi64x(CRC32_X479_MODG as i64, CRC32_X543_MODG as i64);
+        let mults_512b = _mm_set_epi64x(k.x479 as i64, k.x543 as i64);
         x0 = fold_vec128(x0, t3, mults_512b);
 
         data = &data[64..];
@@ -788,7 +822,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
         let v1 = _mm_loadu_si128(data.as_ptr().add(16) as *const __m128i);
 
         let t1 = fold_vec128(v0, v1, mults_128b);
-        let mults_256b = _mm_set_epi64x(CRC32_X223_MODG as i64, CRC32_X287_MODG as i64);
+        let mults_256b = _mm_set_epi64x(k.x223 as i64, k.x287 as i64);
         x0 = fold_vec128(x0, t1, mults_256b);
 
         data = &data[32..];
@@ -816,7 +850,7 @@ pub unsafe fn crc32_x86_vpclmulqdq_avx2(crc: u32, p: &[u8]) -> u32 {
     let mut res = _mm_extract_epi32(x0, 2) as u32;
 
     if len > 0 {
-        res = crate::crc32::crc32_slice8(res, data);
+        res = (k.fallback)(res, data);
     }
 
     res
@@ -881,3 +915,20 @@ unsafe fn fold_vec256_avx512(dst: __m256i, src: __m256i, mults: __m256i) -> __m2
         0x96,
     )
 }
+
+/// Multiplies two bit-reflected polynomials modulo G using a single carry-less
+/// multiply, then folds the upper 32 bits of the product back in with the
+/// slice-by-8 tables.
+#[target_feature(enable = "pclmulqdq", enable = "sse2")]
+pub unsafe fn crc32_multmodp_pclmulqdq(a: u32, b: u32) -> u32 {
+    let prod = _mm_clmulepi64_si128(
+        _mm_cvtsi32_si128(a as i32),
+        _mm_cvtsi32_si128(b as i32),
+        0x00,
+    );
+    let lo = _mm_cvtsi128_si32(prod) as u32 as u64;
+    let hi = _mm_cvtsi128_si32(_mm_srli_si128(prod, 4)) as u32 as u64;
+    // The reflected 32x32 product occupies bits 0..63 with x^0 at bit 62.
+    let v = ((hi << 32) | lo) << 1;
+    ((v >> 32) as u32) ^ crate::crc32::crc32_mul_x32(v as u32)
+}
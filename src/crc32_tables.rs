@@ -47,6 +47,12 @@ pub const CRC32_X4127_MODG: u64 = 0x1072db28;
 pub const CRC32_X4063_MODG: u64 = 0x0c30f51d;
 pub const CRC32_BARRETT_CONSTANT_1: u64 = 0xb4e5b025f7011641;
 pub const CRC32_BARRETT_CONSTANT_2: u64 = 0x00000001db710641;
+pub const CRC32_X2N_TABLE: [u32; 32] = [
+    0x40000000, 0x20000000, 0x08000000, 0x00800000, 0x00008000, 0xedb88320, 0xb1e6b092, 0xa06a2517,
+    0xed627dae, 0x88d14467, 0xd7bbfe6a, 0xec447f11, 0x8e7ea170, 0x6427800e, 0x4d47bae0, 0x09fe548f,
+    0x83852d0f, 0x30362f1a, 0x7b5a9cc3, 0x31fec169, 0x9fec022a, 0x6c8dedc4, 0x15d6874d, 0x5fde7a4e,
+    0xbad90e37, 0x2e4e5eef, 0x4eaba214, 0xa8a472c0, 0x429a969e, 0x148d302a, 0xc40ba6d0, 0xc4e22c3c,
+];
 pub const CRC32_SLICE8_TABLE: [u32; 2048] = [
     0x00000000, 0x77073096, 0xee0e612c, 0x990951ba, 0x076dc419, 0x706af48f, 0xe963a535, 0x9e6495a3,
     0x0edb8832, 0x79dcb8a4, 0xe0d5e91e, 0x97d2d988, 0x09b64c2b, 0x7eb17cbd, 0xe7b82d07, 0x90bf1d91,
@@ -0,0 +1,195 @@
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+use crate::cpu_features::x86_feature_enabled;
+use crate::sync::OnceLock;
+
+/// Bit-reflected generator polynomial of CRC-64/XZ (ECMA-182), as used by `.xz` and `.7z`.
+pub const CRC64_XZ_POLY: u64 = 0xc96c5795d7870f42;
+/// Bit-reflected generator polynomial of CRC-64/NVME, used for NVMe end-to-end protection.
+pub const CRC64_NVME_POLY: u64 = 0x9a6c9329ac4bc9b5;
+
+/// Lookup tables and folding constants for one reflected CRC-64 polynomial.
+pub(crate) struct Crc64Consts {
+    /// Only the carry-less multiplication kernels fold.
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    pub(crate) fold: Crc64FoldConsts,
+    pub(crate) table: [u64; 2048],
+}
+
+/// `xN` is x^N mod G, bit-reflected.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) struct Crc64FoldConsts {
+    pub(crate) x127: u64,
+    pub(crate) x191: u64,
+    pub(crate) x255: u64,
+    pub(crate) x319: u64,
+    pub(crate) x511: u64,
+    pub(crate) x575: u64,
+    pub(crate) x1023: u64,
+    pub(crate) x1087: u64,
+    pub(crate) x2047: u64,
+    pub(crate) x2111: u64,
+    /// floor(x^128 / G) without its x^64 term, bit-reflected.
+    pub(crate) barrett_mu: u64,
+    pub(crate) poly: u64,
+}
+
+impl Crc64Consts {
+    const fn new(poly: u64) -> Self {
+        Self {
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            fold: Crc64FoldConsts {
+                x127: xn_modg(poly, 127),
+                x191: xn_modg(poly, 191),
+                x255: xn_modg(poly, 255),
+                x319: xn_modg(poly, 319),
+                x511: xn_modg(poly, 511),
+                x575: xn_modg(poly, 575),
+                x1023: xn_modg(poly, 1023),
+                x1087: xn_modg(poly, 1087),
+                x2047: xn_modg(poly, 2047),
+                x2111: xn_modg(poly, 2111),
+                barrett_mu: x128_div_g(poly),
+                poly,
+            },
+            table: slice8_table(poly),
+        }
+    }
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const fn xn_modg(poly: u64, n: usize) -> u64 {
+    let mut rem = 1u64 << 63;
+    let mut i = 0;
+    while i < n {
+        rem = (rem >> 1) ^ if rem & 1 != 0 { poly } else { 0 };
+        i += 1;
+    }
+    rem
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+const fn x128_div_g(poly: u64) -> u64 {
+    // Divide in the normal (unreflected) domain, where bit i holds x^i. The
+    // leading quotient term x^64 cancels x^128 right away and is left implicit.
+    let g = (1u128 << 64) | poly.reverse_bits() as u128;
+    let mut rem = (poly.reverse_bits() as u128) << 64;
+    let mut quotient = 0u64;
+    let mut d = 127;
+    while d >= 64 {
+        if (rem >> d) & 1 != 0 {
+            quotient |= 1 << (d - 64);
+            rem ^= g << (d - 64);
+        }
+        d -= 1;
+    }
+    quotient.reverse_bits()
+}
+
+const fn slice8_table(poly: u64) -> [u64; 2048] {
+    let mut table = [0u64; 2048];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut j = 0;
+        while j < 8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { poly } else { 0 };
+            j += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    while i < 2048 {
+        let prev = table[i - 256];
+        table[i] = (prev >> 8) ^ table[(prev & 0xFF) as usize];
+        i += 1;
+    }
+    table
+}
+
+pub(crate) static CRC64_XZ_CONSTS: Crc64Consts = Crc64Consts::new(CRC64_XZ_POLY);
+pub(crate) static CRC64_NVME_CONSTS: Crc64Consts = Crc64Consts::new(CRC64_NVME_POLY);
+
+#[inline]
+pub(crate) fn crc64_slice8_with_table(mut crc: u64, p: &[u8], table: &[u64; 2048]) -> u64 {
+    let mut chunks = p.chunks_exact(8);
+    for chunk in &mut chunks {
+        crc ^= u64::from_le_bytes(chunk.try_into().unwrap());
+        crc = table[0x700 + (crc & 0xFF) as usize]
+            ^ table[0x600 + ((crc >> 8) & 0xFF) as usize]
+            ^ table[0x500 + ((crc >> 16) & 0xFF) as usize]
+            ^ table[0x400 + ((crc >> 24) & 0xFF) as usize]
+            ^ table[0x300 + ((crc >> 32) & 0xFF) as usize]
+            ^ table[0x200 + ((crc >> 40) & 0xFF) as usize]
+            ^ table[0x100 + ((crc >> 48) & 0xFF) as usize]
+            ^ table[(crc >> 56) as usize];
+    }
+    for &b in chunks.remainder() {
+        crc = (crc >> 8) ^ table[((crc as u8) ^ b) as usize];
+    }
+    crc
+}
+
+fn crc64_slice8(crc: u64, p: &[u8], k: &Crc64Consts) -> u64 {
+    crc64_slice8_with_table(crc, p, &k.table)
+}
+
+pub fn crc64_xz_slice8(crc: u64, p: &[u8]) -> u64 {
+    crc64_slice8_with_table(crc, p, &CRC64_XZ_CONSTS.table)
+}
+
+pub fn crc64_nvme_slice8(crc: u64, p: &[u8]) -> u64 {
+    crc64_slice8_with_table(crc, p, &CRC64_NVME_CONSTS.table)
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86;
+
+type Crc64Fn = unsafe fn(u64, &[u8], &Crc64Consts) -> u64;
+
+static IMPL: OnceLock<(Crc64Fn, &'static str)> = OnceLock::new();
+
+fn select_impl() -> (Crc64Fn, &'static str) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx512f")
+            && x86_feature_enabled!("avx512vl")
+            && x86_feature_enabled!("vpclmulqdq")
+        {
+            return (x86::crc64_x86_vpclmulqdq_avx512, "vpclmulqdq_avx512");
+        }
+
+        #[cfg(target_arch = "x86_64")]
+        if x86_feature_enabled!("avx2") && x86_feature_enabled!("vpclmulqdq") {
+            return (x86::crc64_x86_vpclmulqdq_avx2, "vpclmulqdq_avx2");
+        }
+
+        if x86_feature_enabled!("pclmulqdq") && x86_feature_enabled!("sse2") {
+            return (x86::crc64_x86_pclmulqdq, "pclmulqdq");
+        }
+    }
+    (crc64_slice8, "slice8")
+}
+
+/// Name of the CRC-64 implementation selected for this CPU.
+pub fn implementation_name() -> &'static str {
+    IMPL.get_or_init(select_impl).1
+}
+
+/// CRC-64/XZ (ECMA-182 polynomial, reflected, initial value and final XOR all ones).
+///
+/// Pass 0 to start a new checksum, or the previous result to continue one.
+#[inline]
+pub fn crc64_xz(crc: u64, slice: &[u8]) -> u64 {
+    let func = IMPL.get_or_init(select_impl).0;
+    unsafe { !func(!crc, slice, &CRC64_XZ_CONSTS) }
+}
+
+/// CRC-64/NVME (reflected, initial value and final XOR all ones).
+///
+/// Pass 0 to start a new checksum, or the previous result to continue one.
+#[inline]
+pub fn crc64_nvme(crc: u64, slice: &[u8]) -> u64 {
+    let func = IMPL.get_or_init(select_impl).0;
+    unsafe { !func(!crc, slice, &CRC64_NVME_CONSTS) }
+}
@@ -0,0 +1,323 @@
+#[cfg(target_arch = "x86")]
+use core::arch::x86::*;
+#[cfg(target_arch = "x86_64")]
+use core::arch::x86_64::*;
+
+use super::{Crc64Consts, crc64_slice8_with_table};
+
+// Data is folded forward in 128-bit lanes. Each lane holds 128 message bits
+// with the earliest bit in bit 0, so its low half is the high-order half of
+// the polynomial. Folding a lane forward by D bits multiplies the low half by
+// x^(D+63) mod G and the high half by x^(D-1) mod G; the extra -1 accounts for
+// the product of two reflected 64-bit values landing one bit short.
+
+#[target_feature(enable = "pclmulqdq", enable = "sse2")]
+#[inline]
+unsafe fn fold_vec128(dst: __m128i, src: __m128i, mults: __m128i) -> __m128i {
+    _mm_xor_si128(
+        src,
+        _mm_xor_si128(
+            _mm_clmulepi64_si128(dst, mults, 0x00),
+            _mm_clmulepi64_si128(dst, mults, 0x11),
+        ),
+    )
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,vpclmulqdq")]
+#[inline]
+unsafe fn fold_vec256(dst: __m256i, src: __m256i, mults: __m256i) -> __m256i {
+    _mm256_xor_si256(
+        src,
+        _mm256_xor_si256(
+            _mm256_clmulepi64_epi128(dst, mults, 0x00),
+            _mm256_clmulepi64_epi128(dst, mults, 0x11),
+        ),
+    )
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vl,vpclmulqdq")]
+#[inline]
+unsafe fn fold_vec512(dst: __m512i, src: __m512i, mults: __m512i) -> __m512i {
+    _mm512_ternarylogic_epi32(
+        _mm512_clmulepi64_epi128(dst, mults, 0x00),
+        _mm512_clmulepi64_epi128(dst, mults, 0x11),
+        src,
+        0x96,
+    )
+}
+
+#[target_feature(enable = "pclmulqdq", enable = "sse2")]
+#[inline]
+unsafe fn clmul64(a: u64, b: u64) -> [u64; 2] {
+    let prod = _mm_clmulepi64_si128(
+        _mm_set_epi64x(0, a as i64),
+        _mm_set_epi64x(0, b as i64),
+        0x00,
+    );
+    let mut lanes = [0u64; 2];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, prod);
+    lanes
+}
+
+/// Reduces a folded 128-bit lane to the CRC register value.
+#[target_feature(enable = "pclmulqdq", enable = "sse2")]
+#[inline]
+unsafe fn reduce128(x: __m128i, k: &Crc64Consts) -> u64 {
+    // Multiply the lane by x^64, folding its high-order half into the low-order one.
+    let x = _mm_xor_si128(
+        _mm_clmulepi64_si128(x, _mm_set_epi64x(0, k.fold.x127 as i64), 0x00),
+        _mm_srli_si128(x, 8),
+    );
+    let mut lanes = [0u64; 2];
+    _mm_storeu_si128(lanes.as_mut_ptr() as *mut __m128i, x);
+    let [hi, lo] = lanes;
+
+    // Barrett reduction: q = floor(hi * x^64 / G), then subtract q * G.
+    let q = hi ^ (clmul64(hi, k.fold.barrett_mu)[0] << 1);
+    let [r0, r1] = clmul64(q, k.fold.poly);
+    lo ^ ((r0 >> 63) | (r1 << 1))
+}
+
+#[target_feature(enable = "pclmulqdq", enable = "sse2")]
+pub unsafe fn crc64_x86_pclmulqdq(crc: u64, p: &[u8], k: &Crc64Consts) -> u64 {
+    let mut data = p;
+    let mut len = data.len();
+
+    if len < 16 {
+        return crc64_slice8_with_table(crc, data, &k.table);
+    }
+
+    let mults_128b = _mm_set_epi64x(k.fold.x127 as i64, k.fold.x191 as i64);
+    let mut x0 = _mm_xor_si128(
+        _mm_loadu_si128(data.as_ptr() as *const __m128i),
+        _mm_set_epi64x(0, crc as i64),
+    );
+    data = &data[16..];
+    len -= 16;
+
+    if len >= 112 {
+        let mut x1 = _mm_loadu_si128(data.as_ptr() as *const __m128i);
+        let mut x2 = _mm_loadu_si128(data.as_ptr().add(16) as *const __m128i);
+        let mut x3 = _mm_loadu_si128(data.as_ptr().add(32) as *const __m128i);
+        let mut x4 = _mm_loadu_si128(data.as_ptr().add(48) as *const __m128i);
+        let mut x5 = _mm_loadu_si128(data.as_ptr().add(64) as *const __m128i);
+        let mut x6 = _mm_loadu_si128(data.as_ptr().add(80) as *const __m128i);
+        let mut x7 = _mm_loadu_si128(data.as_ptr().add(96) as *const __m128i);
+        data = &data[112..];
+        len -= 112;
+
+        let mults_1024b = _mm_set_epi64x(k.fold.x1023 as i64, k.fold.x1087 as i64);
+        while len >= 128 {
+            let ptr = data.as_ptr() as *const __m128i;
+            x0 = fold_vec128(x0, _mm_loadu_si128(ptr), mults_1024b);
+            x1 = fold_vec128(x1, _mm_loadu_si128(ptr.add(1)), mults_1024b);
+            x2 = fold_vec128(x2, _mm_loadu_si128(ptr.add(2)), mults_1024b);
+            x3 = fold_vec128(x3, _mm_loadu_si128(ptr.add(3)), mults_1024b);
+            x4 = fold_vec128(x4, _mm_loadu_si128(ptr.add(4)), mults_1024b);
+            x5 = fold_vec128(x5, _mm_loadu_si128(ptr.add(5)), mults_1024b);
+            x6 = fold_vec128(x6, _mm_loadu_si128(ptr.add(6)), mults_1024b);
+            x7 = fold_vec128(x7, _mm_loadu_si128(ptr.add(7)), mults_1024b);
+            data = &data[128..];
+            len -= 128;
+        }
+
+        x0 = fold_vec128(x0, x1, mults_128b);
+        x0 = fold_vec128(x0, x2, mults_128b);
+        x0 = fold_vec128(x0, x3, mults_128b);
+        x0 = fold_vec128(x0, x4, mults_128b);
+        x0 = fold_vec128(x0, x5, mults_128b);
+        x0 = fold_vec128(x0, x6, mults_128b);
+        x0 = fold_vec128(x0, x7, mults_128b);
+    }
+
+    while len >= 16 {
+        x0 = fold_vec128(
+            x0,
+            _mm_loadu_si128(data.as_ptr() as *const __m128i),
+            mults_128b,
+        );
+        data = &data[16..];
+        len -= 16;
+    }
+
+    crc64_slice8_with_table(reduce128(x0, k), data, &k.table)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx2,vpclmulqdq,pclmulqdq")]
+pub unsafe fn crc64_x86_vpclmulqdq_avx2(crc: u64, p: &[u8], k: &Crc64Consts) -> u64 {
+    let mut data = p;
+    let mut len = data.len();
+
+    if len < 256 {
+        return crc64_x86_pclmulqdq(crc, data, k);
+    }
+
+    let ptr = data.as_ptr() as *const __m256i;
+    let mut y0 = _mm256_xor_si256(
+        _mm256_loadu_si256(ptr),
+        _mm256_zextsi128_si256(_mm_set_epi64x(0, crc as i64)),
+    );
+    let mut y1 = _mm256_loadu_si256(ptr.add(1));
+    let mut y2 = _mm256_loadu_si256(ptr.add(2));
+    let mut y3 = _mm256_loadu_si256(ptr.add(3));
+    data = &data[128..];
+    len -= 128;
+
+    let mults_1024b = _mm256_set_epi64x(
+        k.fold.x1023 as i64,
+        k.fold.x1087 as i64,
+        k.fold.x1023 as i64,
+        k.fold.x1087 as i64,
+    );
+    while len >= 128 {
+        let ptr = data.as_ptr() as *const __m256i;
+        y0 = fold_vec256(y0, _mm256_loadu_si256(ptr), mults_1024b);
+        y1 = fold_vec256(y1, _mm256_loadu_si256(ptr.add(1)), mults_1024b);
+        y2 = fold_vec256(y2, _mm256_loadu_si256(ptr.add(2)), mults_1024b);
+        y3 = fold_vec256(y3, _mm256_loadu_si256(ptr.add(3)), mults_1024b);
+        data = &data[128..];
+        len -= 128;
+    }
+
+    let mults_256b = _mm256_set_epi64x(
+        k.fold.x255 as i64,
+        k.fold.x319 as i64,
+        k.fold.x255 as i64,
+        k.fold.x319 as i64,
+    );
+    y0 = fold_vec256(y0, y1, mults_256b);
+    y0 = fold_vec256(y0, y2, mults_256b);
+    y0 = fold_vec256(y0, y3, mults_256b);
+    while len >= 32 {
+        y0 = fold_vec256(
+            y0,
+            _mm256_loadu_si256(data.as_ptr() as *const __m256i),
+            mults_256b,
+        );
+        data = &data[32..];
+        len -= 32;
+    }
+
+    let mults_128b = _mm_set_epi64x(k.fold.x127 as i64, k.fold.x191 as i64);
+    let mut x0 = fold_vec128(
+        _mm256_extracti128_si256(y0, 0),
+        _mm256_extracti128_si256(y0, 1),
+        mults_128b,
+    );
+    if len >= 16 {
+        x0 = fold_vec128(
+            x0,
+            _mm_loadu_si128(data.as_ptr() as *const __m128i),
+            mults_128b,
+        );
+        data = &data[16..];
+    }
+
+    crc64_slice8_with_table(reduce128(x0, k), data, &k.table)
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vl,vpclmulqdq,avx2,pclmulqdq")]
+pub unsafe fn crc64_x86_vpclmulqdq_avx512(crc: u64, p: &[u8], k: &Crc64Consts) -> u64 {
+    let mut data = p;
+    let mut len = data.len();
+
+    if len < 512 {
+        return crc64_x86_pclmulqdq(crc, data, k);
+    }
+
+    let ptr = data.as_ptr() as *const __m512i;
+    let mut z0 = _mm512_xor_si512(
+        _mm512_loadu_si512(ptr),
+        _mm512_zextsi128_si512(_mm_set_epi64x(0, crc as i64)),
+    );
+    let mut z1 = _mm512_loadu_si512(ptr.add(1));
+    let mut z2 = _mm512_loadu_si512(ptr.add(2));
+    let mut z3 = _mm512_loadu_si512(ptr.add(3));
+    data = &data[256..];
+    len -= 256;
+
+    let mults_2048b = _mm512_set_epi64(
+        k.fold.x2047 as i64,
+        k.fold.x2111 as i64,
+        k.fold.x2047 as i64,
+        k.fold.x2111 as i64,
+        k.fold.x2047 as i64,
+        k.fold.x2111 as i64,
+        k.fold.x2047 as i64,
+        k.fold.x2111 as i64,
+    );
+    while len >= 256 {
+        let ptr = data.as_ptr() as *const __m512i;
+        z0 = fold_vec512(z0, _mm512_loadu_si512(ptr), mults_2048b);
+        z1 = fold_vec512(z1, _mm512_loadu_si512(ptr.add(1)), mults_2048b);
+        z2 = fold_vec512(z2, _mm512_loadu_si512(ptr.add(2)), mults_2048b);
+        z3 = fold_vec512(z3, _mm512_loadu_si512(ptr.add(3)), mults_2048b);
+        data = &data[256..];
+        len -= 256;
+    }
+
+    let mults_512b = _mm512_set_epi64(
+        k.fold.x511 as i64,
+        k.fold.x575 as i64,
+        k.fold.x511 as i64,
+        k.fold.x575 as i64,
+        k.fold.x511 as i64,
+        k.fold.x575 as i64,
+        k.fold.x511 as i64,
+        k.fold.x575 as i64,
+    );
+    z0 = fold_vec512(z0, z1, mults_512b);
+    z0 = fold_vec512(z0, z2, mults_512b);
+    z0 = fold_vec512(z0, z3, mults_512b);
+    while len >= 64 {
+        z0 = fold_vec512(
+            z0,
+            _mm512_loadu_si512(data.as_ptr() as *const __m512i),
+            mults_512b,
+        );
+        data = &data[64..];
+        len -= 64;
+    }
+
+    let mults_256b = _mm256_set_epi64x(
+        k.fold.x255 as i64,
+        k.fold.x319 as i64,
+        k.fold.x255 as i64,
+        k.fold.x319 as i64,
+    );
+    let mut y0 = fold_vec256(
+        _mm512_extracti64x4_epi64(z0, 0),
+        _mm512_extracti64x4_epi64(z0, 1),
+        mults_256b,
+    );
+    if len >= 32 {
+        y0 = fold_vec256(
+            y0,
+            _mm256_loadu_si256(data.as_ptr() as *const __m256i),
+            mults_256b,
+        );
+        data = &data[32..];
+        len -= 32;
+    }
+
+    let mults_128b = _mm_set_epi64x(k.fold.x127 as i64, k.fold.x191 as i64);
+    let mut x0 = fold_vec128(
+        _mm256_extracti128_si256(y0, 0),
+        _mm256_extracti128_si256(y0, 1),
+        mults_128b,
+    );
+    if len >= 16 {
+        x0 = fold_vec128(
+            x0,
+            _mm_loadu_si128(data.as_ptr() as *const __m128i),
+            mults_128b,
+        );
+        data = &data[16..];
+    }
+
+    crc64_slice8_with_table(reduce128(x0, k), data, &k.table)
+}
@@ -3,7 +3,10 @@ mod tables;
 
 use self::tables::*;
 use crate::common::*;
-use std::cmp::min;
+#[cfg(target_arch = "x86_64")]
+use crate::cpu_features::x86_feature_enabled;
+use crate::sync::OnceLock;
+use core::cmp::min;
 
 #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
 mod x86;
@@ -13,6 +16,27 @@ pub use self::tables::{
     PRECODE_TABLEBITS,
 };
 
+#[cfg(target_arch = "x86_64")]
+static USE_BMI2: OnceLock<bool> = OnceLock::new();
+
+#[cfg(target_arch = "x86_64")]
+fn use_bmi2() -> bool {
+    *USE_BMI2.get_or_init(|| {
+        x86_feature_enabled!("bmi2")
+            && x86_feature_enabled!("ssse3")
+            && x86_feature_enabled!("sse4.1")
+    })
+}
+
+/// Name of the decompression implementation selected for this CPU.
+pub fn implementation_name() -> &'static str {
+    #[cfg(target_arch = "x86_64")]
+    if use_bmi2() {
+        return "bmi2";
+    }
+    "generic"
+}
+
 macro_rules! refill_bits {
     ($input:expr, $in_idx:expr, $bitbuf:expr, $bitsleft:expr) => {
         if $bitsleft < 32 {
@@ -46,6 +70,59 @@ pub enum DecompressorState {
     Done,
 }
 
+/// Checksum that a [`Decompressor`] can compute over its output while decoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputChecksum {
+    #[default]
+    None,
+    /// gzip CRC-32, starting from 0.
+    Crc32,
+    /// zlib Adler-32, starting from 1.
+    Adler32,
+}
+
+impl OutputChecksum {
+    fn initial(self) -> u32 {
+        match self {
+            OutputChecksum::Adler32 => 1,
+            _ => 0,
+        }
+    }
+}
+
+/// The flavour of deflate a [`Decompressor`] decodes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DeflateVariant {
+    /// Standard deflate (RFC 1951) with a 32 KiB window.
+    #[default]
+    Deflate,
+    /// Deflate64 ("Enhanced Deflate", zip method 9): a 64 KiB window, length symbol 285
+    /// carrying 16 extra bits, and distance symbols 30 and 31.
+    Deflate64,
+}
+
+impl DeflateVariant {
+    /// How far back a match may reach.
+    pub fn window_size(self) -> usize {
+        match self {
+            DeflateVariant::Deflate => 32 * 1024,
+            DeflateVariant::Deflate64 => 64 * 1024,
+        }
+    }
+}
+
+/// How much of a gzip or zlib trailer the decompressor verifies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ChecksumVerification {
+    /// Check the CRC-32 or Adler-32, and the gzip ISIZE.
+    #[default]
+    Full,
+    /// Check only the gzip ISIZE; zlib trailers go unchecked. No checksum is computed.
+    SizeOnly,
+    /// Check nothing in the trailer.
+    None,
+}
+
 #[derive(Clone)]
 pub struct Decompressor {
     pub precode_decode_table: [u32; PRECODE_ENOUGH],
@@ -63,6 +140,13 @@ pub struct Decompressor {
     pub bitsleft: u32,
     pub state: DecompressorState,
     pub is_final_block: bool,
+
+    variant: DeflateVariant,
+    verification: ChecksumVerification,
+    checksum_kind: OutputChecksum,
+    checksum: u32,
+    /// Output index up to which `checksum` is current, within the current call.
+    checksum_pos: usize,
 }
 
 struct StaticHuffmanData {
@@ -72,7 +156,8 @@ struct StaticHuffmanData {
     litlen_tablebits: usize,
 }
 
-static STATIC_HUFFMAN_DATA: std::sync::OnceLock<StaticHuffmanData> = std::sync::OnceLock::new();
+static STATIC_HUFFMAN_DATA: OnceLock<StaticHuffmanData> = OnceLock::new();
+static STATIC_HUFFMAN_DATA_64: OnceLock<StaticHuffmanData> = OnceLock::new();
 
 #[derive(Debug, PartialEq, Eq)]
 #[must_use = "Decompression result must be checked for errors"]
@@ -91,6 +176,12 @@ impl Default for Decompressor {
 }
 
 impl Decompressor {
+    /// The bytes a decompressor takes. Its tables are all inline and it allocates
+    /// nothing, so this is all the memory it needs wherever the caller places it.
+    pub const fn memory_footprint() -> usize {
+        size_of::<Self>()
+    }
+
     pub fn new() -> Self {
         Self {
             precode_decode_table: [0; PRECODE_ENOUGH],
@@ -105,9 +196,82 @@ impl Decompressor {
             bitsleft: 0,
             state: DecompressorState::Start,
             is_final_block: false,
+            variant: DeflateVariant::Deflate,
+            verification: ChecksumVerification::Full,
+            checksum_kind: OutputChecksum::None,
+            checksum: 0,
+            checksum_pos: 0,
         }
     }
 
+    /// Selects standard deflate or Deflate64 for the following calls, including the
+    /// deflate data inside zlib and gzip wrappers. Defaults to [`DeflateVariant::Deflate`].
+    pub fn set_variant(&mut self, variant: DeflateVariant) {
+        if variant != self.variant {
+            self.variant = variant;
+            self.static_codes_loaded = false;
+        }
+    }
+
+    pub fn variant(&self) -> DeflateVariant {
+        self.variant
+    }
+
+    /// Sets how much of gzip and zlib trailers to verify. Defaults to
+    /// [`ChecksumVerification::Full`].
+    ///
+    /// Anything weaker is unsafe for untrusted input: corrupted data is returned as if
+    /// it were good. Only relax this for data the caller itself just wrote, such as a
+    /// cache re-read in the same process.
+    pub fn set_checksum_verification(&mut self, verification: ChecksumVerification) {
+        self.verification = verification;
+    }
+
+    pub fn checksum_verification(&self) -> ChecksumVerification {
+        self.verification
+    }
+
+    /// Makes the decoder checksum its output as it goes, one finished block at a time
+    /// while the block is still in cache, and restarts the running checksum.
+    ///
+    /// The checksum spans every byte produced by later calls, so a stream decoded
+    /// with several [`decompress_streaming`](Self::decompress_streaming) calls gets
+    /// one checksum. One-shot calls restart it.
+    pub fn set_output_checksum(&mut self, kind: OutputChecksum) {
+        self.checksum_kind = kind;
+        self.checksum = kind.initial();
+    }
+
+    /// The checksum selected with [`set_output_checksum`](Self::set_output_checksum)
+    /// of all output produced since it was last restarted.
+    pub fn output_checksum(&self) -> u32 {
+        self.checksum
+    }
+
+    /// Folds `out_ptr[checksum_pos..out_idx]` into the running output checksum.
+    ///
+    /// # Safety
+    ///
+    /// `out_ptr[..out_idx]` must be initialized output of the current call.
+    #[inline]
+    pub(crate) unsafe fn checksum_output(&mut self, out_ptr: *const u8, out_idx: usize) {
+        if self.checksum_kind != OutputChecksum::None && out_idx > self.checksum_pos {
+            // SAFETY: `checksum_pos` only ever trails the caller's `out_idx`.
+            let data = unsafe {
+                core::slice::from_raw_parts(
+                    out_ptr.add(self.checksum_pos),
+                    out_idx - self.checksum_pos,
+                )
+            };
+            self.checksum = match self.checksum_kind {
+                OutputChecksum::Crc32 => crate::crc32::crc32(self.checksum, data),
+                OutputChecksum::Adler32 => crate::adler32::adler32(self.checksum, data),
+                OutputChecksum::None => self.checksum,
+            };
+        }
+        self.checksum_pos = out_idx;
+    }
+
     fn build_precode_decode_table(&mut self) -> bool {
         build_decode_table(
             &mut self.precode_decode_table,
@@ -126,7 +290,10 @@ impl Decompressor {
             &mut self.litlen_decode_table,
             &self.lens[..num_litlen_syms],
             num_litlen_syms,
-            &LITLEN_DECODE_RESULTS,
+            match self.variant {
+                DeflateVariant::Deflate => &LITLEN_DECODE_RESULTS,
+                DeflateVariant::Deflate64 => &LITLEN_DECODE_RESULTS_64,
+            },
             LITLEN_TABLEBITS,
             DEFLATE_MAX_LITLEN_CODEWORD_LEN,
             &mut self.sorted_syms,
@@ -143,7 +310,10 @@ impl Decompressor {
             &mut self.offset_decode_table,
             &self.lens[num_litlen_syms..num_litlen_syms + num_offset_syms],
             num_offset_syms,
-            &OFFSET_DECODE_RESULTS,
+            match self.variant {
+                DeflateVariant::Deflate => &OFFSET_DECODE_RESULTS,
+                DeflateVariant::Deflate64 => &OFFSET_DECODE_RESULTS_64,
+            },
             OFFSET_TABLEBITS,
             DEFLATE_MAX_OFFSET_CODEWORD_LEN,
             &mut self.sorted_syms,
@@ -154,7 +324,7 @@ impl Decompressor {
     pub unsafe fn decompress_uninit(
         &mut self,
         input: &[u8],
-        output: &mut [std::mem::MaybeUninit<u8>],
+        output: &mut [core::mem::MaybeUninit<u8>],
     ) -> (DecompressResult, usize, usize) {
         let out_ptr = output.as_mut_ptr() as *mut u8;
         let out_len = output.len();
@@ -175,12 +345,12 @@ impl Decompressor {
         out_ptr: *mut u8,
         out_len: usize,
     ) -> (DecompressResult, usize, usize) {
+        self.checksum = self.checksum_kind.initial();
+        self.checksum_pos = 0;
+
         #[cfg(target_arch = "x86_64")]
         {
-            if is_x86_feature_detected!("bmi2")
-                && is_x86_feature_detected!("ssse3")
-                && is_x86_feature_detected!("sse4.1")
-            {
+            if use_bmi2() {
                 let res = unsafe { x86::decompress_bmi2_ptr(self, input, out_ptr, out_len) };
                 // Security: Reset state because x86 implementation clobbers internal tables.
                 // This prevents state corruption if the Decompressor is reused for streaming.
@@ -198,7 +368,20 @@ impl Decompressor {
         self.is_final_block = false;
 
         let mut out_idx = 0;
-        unsafe { self.decompress_streaming_ptr(input, out_ptr, out_len, &mut out_idx) }
+        let (res, in_consumed, out_produced) =
+            unsafe { self.decompress_streaming_ptr(input, out_ptr, out_len, &mut out_idx) };
+        // Report only the bytes that belong to the deflate stream, so that callers can find
+        // whatever follows it (a gzip/zlib trailer or another gzip member).
+        let unused_bytes = (self.bitsleft / 8) as usize;
+        // Leave the decompressor ready for reuse, as the x86 path does.
+        self.state = DecompressorState::Start;
+        self.is_final_block = false;
+        self.bitbuf = 0;
+        self.bitsleft = 0;
+        if res == DecompressResult::Success {
+            return (res, in_consumed - unused_bytes, out_produced);
+        }
+        (res, in_consumed, out_produced)
     }
 
     pub fn decompress_streaming(
@@ -216,6 +399,20 @@ impl Decompressor {
         out_ptr: *mut u8,
         out_len: usize,
         out_idx: &mut usize,
+    ) -> (DecompressResult, usize, usize) {
+        self.checksum_pos = *out_idx;
+        let res = unsafe { self.decompress_blocks_ptr(input, out_ptr, out_len, out_idx) };
+        // Whatever a partial block left behind.
+        unsafe { self.checksum_output(out_ptr, *out_idx) };
+        res
+    }
+
+    unsafe fn decompress_blocks_ptr(
+        &mut self,
+        input: &[u8],
+        out_ptr: *mut u8,
+        out_len: usize,
+        out_idx: &mut usize,
     ) -> (DecompressResult, usize, usize) {
         let mut in_idx = 0;
         let start_out_idx = *out_idx;
@@ -251,10 +448,15 @@ impl Decompressor {
                     }
                 }
                 DecompressorState::DynamicHeader => {
+                    let header_start = (in_idx, self.bitbuf, self.bitsleft);
                     let res = self.read_dynamic_huffman_header(input, &mut in_idx);
                     if res == DecompressResult::Success {
                         self.state = DecompressorState::BlockBody;
                     } else {
+                        if res == DecompressResult::ShortInput {
+                            // The header is read again from the start once more input arrives.
+                            self.rewind(&mut in_idx, header_start);
+                        }
                         return (res, in_idx, *out_idx - start_out_idx);
                     }
                 }
@@ -269,6 +471,7 @@ impl Decompressor {
                         )
                     };
                     if res == DecompressResult::Success {
+                        unsafe { self.checksum_output(out_ptr, *out_idx) };
                         if self.is_final_block {
                             self.state = DecompressorState::Done;
                             return (DecompressResult::Success, in_idx, *out_idx - start_out_idx);
@@ -309,7 +512,7 @@ impl Decompressor {
                     let copy_len = min(remaining, min(available_in, available_out));
 
                     unsafe {
-                        std::ptr::copy_nonoverlapping(
+                        core::ptr::copy_nonoverlapping(
                             input.as_ptr().add(in_idx),
                             out_ptr.add(*out_idx),
                             copy_len,
@@ -320,6 +523,7 @@ impl Decompressor {
                     let new_len = remaining - copy_len;
 
                     if new_len == 0 {
+                        unsafe { self.checksum_output(out_ptr, *out_idx) };
                         if self.is_final_block {
                             self.state = DecompressorState::Done;
                             return (DecompressResult::Success, in_idx, *out_idx - start_out_idx);
@@ -357,8 +561,13 @@ impl Decompressor {
             return;
         }
 
-        let data = STATIC_HUFFMAN_DATA.get_or_init(|| {
+        let cache = match self.variant {
+            DeflateVariant::Deflate => &STATIC_HUFFMAN_DATA,
+            DeflateVariant::Deflate64 => &STATIC_HUFFMAN_DATA_64,
+        };
+        let data = cache.get_or_init(|| {
             let mut d = Decompressor::new();
+            d.variant = self.variant;
             let mut i = 0;
             while i < 144 {
                 d.lens[i] = 8;
@@ -562,10 +771,10 @@ impl Decompressor {
             unsafe {
                 let out_ptr = out_ptr_start;
                 if offset >= length {
-                    std::ptr::copy_nonoverlapping(out_ptr.add(src), out_ptr.add(dest), length);
+                    core::ptr::copy_nonoverlapping(out_ptr.add(src), out_ptr.add(dest), length);
                 } else if offset == 1 {
                     let b = *out_ptr.add(src);
-                    std::ptr::write_bytes(out_ptr.add(dest), b, length);
+                    core::ptr::write_bytes(out_ptr.add(dest), b, length);
                 } else if offset < 8 {
                     let src_ptr = out_ptr.add(src);
                     let dest_ptr = out_ptr.add(dest);
@@ -573,14 +782,14 @@ impl Decompressor {
                         let pattern = prepare_pattern(offset, src_ptr);
                         let mut i = 0;
                         while i + 32 <= length {
-                            std::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
-                            std::ptr::write_unaligned(dest_ptr.add(i + 8) as *mut u64, pattern);
-                            std::ptr::write_unaligned(dest_ptr.add(i + 16) as *mut u64, pattern);
-                            std::ptr::write_unaligned(dest_ptr.add(i + 24) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i + 8) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i + 16) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i + 24) as *mut u64, pattern);
                             i += 32;
                         }
                         while i + 8 <= length {
-                            std::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
                             i += 8;
                         }
                         while i < length {
@@ -591,7 +800,7 @@ impl Decompressor {
                         let pattern = prepare_pattern(offset, src_ptr);
                         let mut i = 0;
                         while i + 8 <= length {
-                            std::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
+                            core::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
                             i += offset;
                         }
                         while i < length {
@@ -602,8 +811,8 @@ impl Decompressor {
                 } else {
                     let mut copied = 0;
                     while copied < length {
-                        let copy_len = std::cmp::min(offset, length - copied);
-                        std::ptr::copy_nonoverlapping(
+                        let copy_len = core::cmp::min(offset, length - copied);
+                        core::ptr::copy_nonoverlapping(
                             out_ptr.add(src + copied),
                             out_ptr.add(dest + copied),
                             copy_len,
@@ -627,6 +836,7 @@ impl Decompressor {
 
         unsafe {
             while in_next.add(15) <= in_ptr_end && out_next.add(258) <= out_ptr_end {
+                let sym_start = (in_next, bitbuf, bitsleft);
                 if bitsleft < 32 {
                     let word = (in_next as *const u64).read_unaligned();
                     let word = u64::from_le(word);
@@ -711,6 +921,12 @@ impl Decompressor {
                     if offset > current_out_idx {
                         break;
                     }
+                    // Only Deflate64 lengths outgrow the 258 bytes of room this loop checks for;
+                    // leave such a match to the slow loop, which can report the lack of space.
+                    if length > DEFLATE_MAX_MATCH_LEN && out_next.add(length) > out_ptr_end {
+                        (in_next, bitbuf, bitsleft) = sym_start;
+                        break;
+                    }
 
                     let src = out_next.sub(offset);
                     if offset < 8 {
@@ -745,7 +961,7 @@ impl Decompressor {
                             }
                         }
                     } else if offset >= length {
-                        std::ptr::copy_nonoverlapping(src, out_next, length);
+                        core::ptr::copy_nonoverlapping(src, out_next, length);
                     } else {
                         // Optimization: Use u64 copy loop for overlapping case with offset >= 8.
                         // This avoids function call overhead of copy_nonoverlapping for small chunks.
@@ -776,6 +992,7 @@ impl Decompressor {
 
         loop {
             while *in_idx + 15 < input.len() && *out_idx + 258 < out_len {
+                let sym_start = (*in_idx, self.bitbuf, self.bitsleft);
                 if self.bitsleft < 32 {
                     let word =
                         unsafe { (input.as_ptr().add(*in_idx) as *const u64).read_unaligned() };
@@ -862,6 +1079,9 @@ impl Decompressor {
 
                     if offset > *out_idx {
                         return DecompressResult::BadData;
+                    } else if length > DEFLATE_MAX_MATCH_LEN && *out_idx + length > out_len {
+                        self.rewind(in_idx, sym_start);
+                        break;
                     } else {
                         let src = *out_idx - offset;
                         let dest = *out_idx;
@@ -875,26 +1095,26 @@ impl Decompressor {
                                     let pattern = prepare_pattern(offset, src_ptr);
                                     let mut i = 0;
                                     while i + 32 <= length {
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i) as *mut u64,
                                             pattern,
                                         );
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i + 8) as *mut u64,
                                             pattern,
                                         );
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i + 16) as *mut u64,
                                             pattern,
                                         );
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i + 24) as *mut u64,
                                             pattern,
                                         );
                                         i += 32;
                                     }
                                     while i + 8 <= length {
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i) as *mut u64,
                                             pattern,
                                         );
@@ -908,7 +1128,7 @@ impl Decompressor {
                                     let pattern = prepare_pattern(offset, src_ptr);
                                     let mut i = 0;
                                     while i + 8 <= length {
-                                        std::ptr::write_unaligned(
+                                        core::ptr::write_unaligned(
                                             dest_ptr.add(i) as *mut u64,
                                             pattern,
                                         );
@@ -923,7 +1143,7 @@ impl Decompressor {
                                 let mut copied = 0;
                                 while copied < length {
                                     let copy_len = min(offset, length - copied);
-                                    std::ptr::copy_nonoverlapping(
+                                    core::ptr::copy_nonoverlapping(
                                         out_ptr.add(src + copied),
                                         out_ptr.add(dest + copied),
                                         copy_len,
@@ -938,9 +1158,15 @@ impl Decompressor {
             }
 
             refill_bits!(input, *in_idx, self.bitbuf, self.bitsleft);
+            // Running out of input or output part-way through a symbol rewinds to here, so
+            // that a later call with more of either resumes cleanly.
+            let sym_start = (*in_idx, self.bitbuf, self.bitsleft);
             let mut entry = self.litlen_decode_table[(self.bitbuf as usize) & litlen_tablemask];
             if entry & HUFFDEC_EXCEPTIONAL != 0 {
                 if entry & HUFFDEC_END_OF_BLOCK != 0 {
+                    if self.bitsleft < entry & 0xFF {
+                        return DecompressResult::ShortInput;
+                    }
                     self.bitbuf >>= entry as u8;
                     self.bitsleft -= entry & 0xFF;
                     return DecompressResult::Success;
@@ -956,17 +1182,28 @@ impl Decompressor {
                     let subtable_bits = (entry >> 8) & 0x3F;
                     entry = self.litlen_decode_table
                         [subtable_idx + ((self.bitbuf as usize) & ((1 << subtable_bits) - 1))];
+                    if entry & HUFFDEC_END_OF_BLOCK != 0 {
+                        if self.bitsleft < entry & 0xFF {
+                            self.rewind(in_idx, sym_start);
+                            return DecompressResult::ShortInput;
+                        }
+                        self.bitbuf >>= entry as u8;
+                        self.bitsleft -= entry & 0xFF;
+                        return DecompressResult::Success;
+                    }
                 }
             }
             let saved_bitbuf = self.bitbuf;
             let total_bits = entry & 0xFF;
             if self.bitsleft < total_bits {
+                self.rewind(in_idx, sym_start);
                 return DecompressResult::ShortInput;
             }
             self.bitbuf >>= total_bits;
             self.bitsleft -= total_bits;
             if entry & HUFFDEC_LITERAL != 0 {
                 if *out_idx >= out_len {
+                    self.rewind(in_idx, sym_start);
                     return DecompressResult::InsufficientSpace;
                 }
                 unsafe {
@@ -986,10 +1223,7 @@ impl Decompressor {
                 if entry & HUFFDEC_SUBTABLE_POINTER != 0 {
                     let main_bits = entry & 0xFF;
                     if self.bitsleft < main_bits {
-                        self.state = DecompressorState::BlockBodyOffset {
-                            length,
-                            extra_bits: 0,
-                        };
+                        self.rewind(in_idx, sym_start);
                         return DecompressResult::ShortInput;
                     }
                     self.bitbuf >>= main_bits;
@@ -1002,10 +1236,7 @@ impl Decompressor {
                 let saved_bitbuf = self.bitbuf;
                 let total_bits = entry & 0xFF;
                 if self.bitsleft < total_bits {
-                    self.state = DecompressorState::BlockBodyOffset {
-                        length,
-                        extra_bits: 0,
-                    };
+                    self.rewind(in_idx, sym_start);
                     return DecompressResult::ShortInput;
                 }
                 self.bitbuf >>= total_bits;
@@ -1022,16 +1253,17 @@ impl Decompressor {
                 let dest = *out_idx;
                 let src = dest - offset;
                 if dest + length > out_len {
+                    self.rewind(in_idx, sym_start);
                     return DecompressResult::InsufficientSpace;
                 }
 
                 unsafe {
                     let out_ptr = out_ptr_start;
                     if offset >= length {
-                        std::ptr::copy_nonoverlapping(out_ptr.add(src), out_ptr.add(dest), length);
+                        core::ptr::copy_nonoverlapping(out_ptr.add(src), out_ptr.add(dest), length);
                     } else if offset == 1 {
                         let b = *out_ptr.add(src);
-                        std::ptr::write_bytes(out_ptr.add(dest), b, length);
+                        core::ptr::write_bytes(out_ptr.add(dest), b, length);
                     } else if offset < 8 {
                         let src_ptr = out_ptr.add(src);
                         let dest_ptr = out_ptr.add(dest);
@@ -1039,7 +1271,7 @@ impl Decompressor {
                             let pattern = prepare_pattern(offset, src_ptr);
                             let mut i = 0;
                             while i + 8 <= length {
-                                std::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
+                                core::ptr::write_unaligned(dest_ptr.add(i) as *mut u64, pattern);
                                 i += 8;
                             }
                             while i < length {
@@ -1056,8 +1288,8 @@ impl Decompressor {
                     } else {
                         let mut copied = 0;
                         while copied < length {
-                            let copy_len = std::cmp::min(offset, length - copied);
-                            std::ptr::copy_nonoverlapping(
+                            let copy_len = core::cmp::min(offset, length - copied);
+                            core::ptr::copy_nonoverlapping(
                                 out_ptr.add(src + copied),
                                 out_ptr.add(dest + copied),
                                 copy_len,
@@ -1071,10 +1303,17 @@ impl Decompressor {
         }
     }
 
+    /// Puts back the bits of a partly decoded symbol; `start` is `(in_idx, bitbuf, bitsleft)`
+    /// from before it.
+    #[inline(always)]
+    fn rewind(&mut self, in_idx: &mut usize, start: (usize, u64, u32)) {
+        (*in_idx, self.bitbuf, self.bitsleft) = start;
+    }
+
     pub unsafe fn decompress_zlib_uninit(
         &mut self,
         input: &[u8],
-        output: &mut [std::mem::MaybeUninit<u8>],
+        output: &mut [core::mem::MaybeUninit<u8>],
     ) -> (DecompressResult, usize, usize) {
         if input.len() < ZLIB_MIN_OVERHEAD {
             return (DecompressResult::ShortInput, 0, 0);
@@ -1094,16 +1333,22 @@ impl Decompressor {
             return (DecompressResult::BadData, 0, 0);
         }
 
+        let verify = self.verification == ChecksumVerification::Full;
+        let prev_checksum = self.checksum_kind;
+        self.checksum_kind = if verify {
+            OutputChecksum::Adler32
+        } else {
+            OutputChecksum::None
+        };
         let (res, in_consumed, out_produced) =
             unsafe { self.decompress_uninit(&input[2..input.len() - ZLIB_FOOTER_SIZE], output) };
+        self.checksum_kind = prev_checksum;
 
         if res != DecompressResult::Success {
             return (res, in_consumed + 2, out_produced);
         }
 
-        let out_slice =
-            unsafe { std::slice::from_raw_parts(output.as_ptr() as *const u8, out_produced) };
-        let actual_adler = crate::adler32::adler32(1, out_slice);
+        let actual_adler = self.checksum;
         let expected_adler = u32::from_be_bytes([
             input[2 + in_consumed],
             input[2 + in_consumed + 1],
@@ -1111,7 +1356,7 @@ impl Decompressor {
             input[2 + in_consumed + 3],
         ]);
 
-        if actual_adler != expected_adler {
+        if verify && actual_adler != expected_adler {
             return (
                 DecompressResult::BadData,
                 in_consumed + 2 + ZLIB_FOOTER_SIZE,
@@ -1133,8 +1378,8 @@ impl Decompressor {
     ) -> (DecompressResult, usize, usize) {
         // Safe because output is initialized
         let output_uninit = unsafe {
-            std::slice::from_raw_parts_mut(
-                output.as_mut_ptr() as *mut std::mem::MaybeUninit<u8>,
+            core::slice::from_raw_parts_mut(
+                output.as_mut_ptr() as *mut core::mem::MaybeUninit<u8>,
                 output.len(),
             )
         };
@@ -1144,64 +1389,38 @@ impl Decompressor {
     pub unsafe fn decompress_gzip_uninit(
         &mut self,
         input: &[u8],
-        output: &mut [std::mem::MaybeUninit<u8>],
+        output: &mut [core::mem::MaybeUninit<u8>],
     ) -> (DecompressResult, usize, usize) {
         if input.len() < GZIP_MIN_OVERHEAD {
             return (DecompressResult::ShortInput, 0, 0);
         }
 
-        if input[0] != GZIP_ID1 || input[1] != GZIP_ID2 || input[2] != GZIP_CM_DEFLATE {
-            return (DecompressResult::BadData, 0, 0);
-        }
-
-        let flg = input[3];
-        if flg & GZIP_FRESERVED != 0 {
-            return (DecompressResult::BadData, 0, 0);
-        }
-
-        let mut in_idx = 10;
-
-        if flg & GZIP_FEXTRA != 0 {
-            if in_idx + 2 > input.len() {
-                return (DecompressResult::ShortInput, 0, 0);
-            }
-            let xlen = u16::from_le_bytes([input[in_idx], input[in_idx + 1]]) as usize;
-            in_idx += 2 + xlen;
-        }
-
-        if flg & GZIP_FNAME != 0 {
-            while in_idx < input.len() && input[in_idx] != 0 {
-                in_idx += 1;
-            }
-            in_idx += 1;
-        }
-
-        if flg & GZIP_FCOMMENT != 0 {
-            while in_idx < input.len() && input[in_idx] != 0 {
-                in_idx += 1;
-            }
-            in_idx += 1;
-        }
-
-        if flg & GZIP_FHCRC != 0 {
-            in_idx += 2;
-        }
+        let in_idx = match parse_gzip_header(input) {
+            Ok((_, header_len)) => header_len,
+            Err(res) => return (res, 0, 0),
+        };
 
         if in_idx + GZIP_FOOTER_SIZE > input.len() {
             return (DecompressResult::ShortInput, 0, 0);
         }
 
+        let verify = self.verification == ChecksumVerification::Full;
+        let prev_checksum = self.checksum_kind;
+        self.checksum_kind = if verify {
+            OutputChecksum::Crc32
+        } else {
+            OutputChecksum::None
+        };
         let (res, in_consumed, out_produced) = unsafe {
             self.decompress_uninit(&input[in_idx..input.len() - GZIP_FOOTER_SIZE], output)
         };
+        self.checksum_kind = prev_checksum;
 
         if res != DecompressResult::Success {
             return (res, in_idx + in_consumed, out_produced);
         }
 
-        let out_slice =
-            unsafe { std::slice::from_raw_parts(output.as_ptr() as *const u8, out_produced) };
-        let actual_crc = crate::crc32::crc32(0, out_slice);
+        let actual_crc = self.checksum;
         let expected_crc = u32::from_le_bytes([
             input[in_idx + in_consumed],
             input[in_idx + in_consumed + 1],
@@ -1209,7 +1428,7 @@ impl Decompressor {
             input[in_idx + in_consumed + 3],
         ]);
 
-        if actual_crc != expected_crc {
+        if verify && actual_crc != expected_crc {
             return (
                 DecompressResult::BadData,
                 in_idx + in_consumed + GZIP_FOOTER_SIZE,
@@ -1224,7 +1443,9 @@ impl Decompressor {
             input[in_idx + in_consumed + 7],
         ]);
 
-        if (out_produced as u32) != expected_isize {
+        if self.verification != ChecksumVerification::None
+            && (out_produced as u32) != expected_isize
+        {
             return (
                 DecompressResult::BadData,
                 in_idx + in_consumed + GZIP_FOOTER_SIZE,
@@ -1246,8 +1467,8 @@ impl Decompressor {
     ) -> (DecompressResult, usize, usize) {
         // Safe because output is initialized
         let output_uninit = unsafe {
-            std::slice::from_raw_parts_mut(
-                output.as_mut_ptr() as *mut std::mem::MaybeUninit<u8>,
+            core::slice::from_raw_parts_mut(
+                output.as_mut_ptr() as *mut core::mem::MaybeUninit<u8>,
                 output.len(),
             )
         };
@@ -1255,6 +1476,89 @@ impl Decompressor {
     }
 }
 
+/// Parses the header of a gzip member at the start of `input`.
+///
+/// Returns the header fields the compressor can write together with the header length, i.e. the
+/// offset of the deflate stream.
+pub fn parse_gzip_header(input: &[u8]) -> Result<(GzipHeader, usize), DecompressResult> {
+    if input.len() < GZIP_MIN_HEADER_SIZE {
+        return Err(DecompressResult::ShortInput);
+    }
+
+    if input[0] != GZIP_ID1 || input[1] != GZIP_ID2 || input[2] != GZIP_CM_DEFLATE {
+        return Err(DecompressResult::BadData);
+    }
+
+    let flg = input[3];
+    if flg & GZIP_FRESERVED != 0 {
+        return Err(DecompressResult::BadData);
+    }
+
+    let mut header = GzipHeader {
+        mtime: u32::from_le_bytes([input[4], input[5], input[6], input[7]]),
+        filename: None,
+    };
+    let mut in_idx = GZIP_MIN_HEADER_SIZE;
+
+    if flg & GZIP_FEXTRA != 0 {
+        if in_idx + 2 > input.len() {
+            return Err(DecompressResult::ShortInput);
+        }
+        let xlen = u16::from_le_bytes([input[in_idx], input[in_idx + 1]]) as usize;
+        in_idx += 2 + xlen;
+    }
+
+    if flg & GZIP_FNAME != 0 {
+        let start = min(in_idx, input.len());
+        let name_len = match input[start..].iter().position(|&b| b == 0) {
+            Some(len) => len,
+            None => return Err(DecompressResult::ShortInput),
+        };
+        header.filename = Some(input[start..start + name_len].to_vec());
+        in_idx = start + name_len + 1;
+    }
+
+    if flg & GZIP_FCOMMENT != 0 {
+        while in_idx < input.len() && input[in_idx] != 0 {
+            in_idx += 1;
+        }
+        in_idx += 1;
+    }
+
+    if flg & GZIP_FHCRC != 0 {
+        in_idx += 2;
+    }
+
+    if in_idx > input.len() {
+        return Err(DecompressResult::ShortInput);
+    }
+
+    Ok((header, in_idx))
+}
+
+/// Copies a match of any length, `offset` bytes at a time so overlapping sources repeat.
+///
+/// # Safety
+///
+/// `src` must be `offset` bytes before `out_next`, and `out_next[..length]` must be
+/// writable.
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+pub(crate) unsafe fn copy_long_match(
+    out_next: *mut u8,
+    src: *const u8,
+    offset: usize,
+    length: usize,
+) {
+    let mut copied = 0;
+    while copied < length {
+        let n = min(offset, length - copied);
+        // SAFETY: `src + copied` is `offset` bytes behind `out_next + copied`, so a chunk of
+        // at most `offset` bytes never overlaps its destination.
+        unsafe { core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), n) };
+        copied += n;
+    }
+}
+
 #[inline(always)]
 pub(crate) unsafe fn prepare_pattern(offset: usize, src_ptr: *const u8) -> u64 {
     unsafe {
@@ -1264,7 +1568,7 @@ pub(crate) unsafe fn prepare_pattern(offset: usize, src_ptr: *const u8) -> u64 {
                 b.wrapping_mul(0x0101010101010101)
             }
             2 => {
-                let w = std::ptr::read_unaligned(src_ptr as *const u16) as u64;
+                let w = core::ptr::read_unaligned(src_ptr as *const u16) as u64;
                 w | (w << 16) | (w << 32) | (w << 48)
             }
             3 => {
@@ -1277,7 +1581,7 @@ pub(crate) unsafe fn prepare_pattern(offset: usize, src_ptr: *const u8) -> u64 {
                 u64::from_le(p_le)
             }
             4 => {
-                let w = std::ptr::read_unaligned(src_ptr as *const u32) as u64;
+                let w = core::ptr::read_unaligned(src_ptr as *const u32) as u64;
                 w | (w << 32)
             }
             5 => {
@@ -1436,7 +1740,12 @@ fn build_decode_table(
                 }
                 cur_table_end <<= 1;
             }
-            if len > table_bits || len_counts[len] != 0 {
+            // Keep going past `table_bits`: the subtable pass below must start at a length
+            // that actually has codewords.
+            if len > DEFLATE_MAX_CODEWORD_LEN {
+                return false;
+            }
+            if len_counts[len] != 0 {
                 break;
             }
         }
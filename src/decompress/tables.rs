@@ -377,3 +377,18 @@ pub const OFFSET_DECODE_RESULTS: [u32; DEFLATE_NUM_OFFSET_SYMS] = [
     entry_dist!(24577, 13),
     entry_dist!(24577, 13),
 ];
+
+/// Deflate64 changes only length symbol 285, which becomes base 3 with 16 extra bits.
+pub const LITLEN_DECODE_RESULTS_64: [u32; DEFLATE_NUM_LITLEN_SYMS] = {
+    let mut results = LITLEN_DECODE_RESULTS;
+    results[285] = entry_len!(3, 16);
+    results
+};
+
+/// Deflate64 gives meaning to distance symbols 30 and 31, reaching back 64 KiB.
+pub const OFFSET_DECODE_RESULTS_64: [u32; DEFLATE_NUM_OFFSET_SYMS] = {
+    let mut results = OFFSET_DECODE_RESULTS;
+    results[30] = entry_dist!(32769, 14);
+    results[31] = entry_dist!(49153, 14);
+    results
+};
@@ -1,15 +1,16 @@
 #![allow(unsafe_op_in_unsafe_fn)]
+use crate::common::DEFLATE_MAX_MATCH_LEN;
 use crate::decompress::tables::{
     HUFFDEC_END_OF_BLOCK, HUFFDEC_EXCEPTIONAL, HUFFDEC_LITERAL, HUFFDEC_SUBTABLE_POINTER,
     OFFSET_TABLEBITS,
 };
 use crate::decompress::{
     DEFLATE_BLOCKTYPE_DYNAMIC_HUFFMAN, DEFLATE_BLOCKTYPE_STATIC_HUFFMAN,
-    DEFLATE_BLOCKTYPE_UNCOMPRESSED, DecompressResult, Decompressor,
+    DEFLATE_BLOCKTYPE_UNCOMPRESSED, DecompressResult, Decompressor, copy_long_match,
 };
 
 #[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::*;
+use core::arch::x86_64::*;
 
 macro_rules! refill_bits {
     ($input:expr, $in_idx:expr, $bitbuf:expr, $bitsleft:expr) => {
@@ -85,7 +86,7 @@ macro_rules! decompress_offset_rotated {
             if $copied < $length {
                 let mut tmp = [0u8; 16];
                 _mm_storeu_si128(tmp.as_mut_ptr() as *mut __m128i, $v_pat);
-                std::ptr::copy_nonoverlapping(tmp.as_ptr(), $out_next.add($copied), $length - $copied);
+                core::ptr::copy_nonoverlapping(tmp.as_ptr(), $out_next.add($copied), $length - $copied);
             }
         }
     }
@@ -124,7 +125,7 @@ macro_rules! decompress_offset_simple {
             if $copied < $length {
                 let mut tmp = [0u8; 16];
                 _mm_storeu_si128(tmp.as_mut_ptr() as *mut __m128i, $v_pat);
-                std::ptr::copy_nonoverlapping(tmp.as_ptr(), $out_next.add($copied), $length - $copied);
+                core::ptr::copy_nonoverlapping(tmp.as_ptr(), $out_next.add($copied), $length - $copied);
             }
         }
     }
@@ -204,7 +205,7 @@ unsafe fn decompress_offset_18(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -224,7 +225,7 @@ unsafe fn decompress_fill_pattern_16(out_next: *mut u8, v: __m128i, src: *const
         i += 16;
     }
     if i < length {
-        std::ptr::copy_nonoverlapping(src.add(i), out_next.add(i), length - i);
+        core::ptr::copy_nonoverlapping(src.add(i), out_next.add(i), length - i);
     }
 }
 
@@ -250,7 +251,7 @@ unsafe fn decompress_fill_pattern(out_next: *mut u8, v_pattern: __m128i, length:
     }
     let pattern = _mm_cvtsi128_si64(v_pattern) as u64;
     while i + 8 <= length {
-        std::ptr::write_unaligned(out_next.add(i) as *mut u64, pattern);
+        core::ptr::write_unaligned(out_next.add(i) as *mut u64, pattern);
         i += 8;
     }
     // Optimization: If the remaining length is small (tail), use a single overlapping 8-byte write
@@ -270,7 +271,7 @@ unsafe fn decompress_fill_pattern(out_next: *mut u8, v_pattern: __m128i, length:
     // by subsequent operations anyway) without exceeding the buffer bounds.
     if i < length {
         if length <= 250 {
-            std::ptr::write_unaligned(out_next.add(i) as *mut u64, pattern);
+            core::ptr::write_unaligned(out_next.add(i) as *mut u64, pattern);
         } else {
             while i < length {
                 *out_next.add(i) = (pattern >> ((i & 7) * 8)) as u8;
@@ -493,7 +494,7 @@ unsafe fn decompress_offset_alignr_cycle<const SHIFT: i32>(
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -564,7 +565,7 @@ unsafe fn decompress_offset_cycle3<const SHIFT: i32>(
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -639,7 +640,7 @@ unsafe fn decompress_offset_cycle4<const SHIFT: i32>(
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1075,7 +1076,7 @@ unsafe fn decompress_offset_17(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1123,7 +1124,7 @@ unsafe fn decompress_offset_20(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1168,7 +1169,7 @@ unsafe fn decompress_offset_24(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1278,7 +1279,7 @@ unsafe fn decompress_offset_30(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1307,7 +1308,7 @@ unsafe fn decompress_offset_32(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1368,7 +1369,7 @@ unsafe fn decompress_offset_28(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1440,7 +1441,7 @@ unsafe fn decompress_offset_36(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1486,7 +1487,7 @@ unsafe fn decompress_offset_40(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1552,7 +1553,7 @@ unsafe fn decompress_offset_42(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1649,7 +1650,7 @@ unsafe fn decompress_offset_44(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1696,7 +1697,7 @@ unsafe fn decompress_offset_48(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1796,7 +1797,7 @@ unsafe fn decompress_offset_52(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1856,7 +1857,7 @@ unsafe fn decompress_offset_56(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -1967,7 +1968,7 @@ unsafe fn decompress_offset_60(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -2021,7 +2022,7 @@ unsafe fn decompress_offset_64(out_next: *mut u8, src: *const u8, v: __m128i, le
     }
 
     if copied < length {
-        std::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
+        core::ptr::copy_nonoverlapping(src.add(copied), out_next.add(copied), length - copied);
     }
 }
 
@@ -2033,7 +2034,7 @@ unsafe fn copy_match_bmi2(out_next: *mut u8, src: *const u8, offset: usize, leng
         _mm_storeu_si128(out_next as *mut __m128i, v);
         if length > 16 {
             if offset >= length {
-                std::ptr::copy_nonoverlapping(src.add(16), out_next.add(16), length - 16);
+                core::ptr::copy_nonoverlapping(src.add(16), out_next.add(16), length - 16);
             } else {
                 match offset {
                     34 => decompress_offset_cycle3::<14>(out_next, src, v, length),
@@ -2106,13 +2107,13 @@ unsafe fn copy_match_bmi2(out_next: *mut u8, src: *const u8, offset: usize, leng
                     32 => decompress_offset_32(out_next, src, v, length),
                     40 => decompress_offset_40(out_next, src, v, length),
                     _ => {
-                        let init = std::cmp::min(offset, length);
-                        std::ptr::copy_nonoverlapping(src, out_next, init);
+                        let init = core::cmp::min(offset, length);
+                        core::ptr::copy_nonoverlapping(src, out_next, init);
 
                         let mut copied = init;
                         while copied < length {
-                            let to_copy = std::cmp::min(length - copied, copied);
-                            std::ptr::copy_nonoverlapping(out_next, out_next.add(copied), to_copy);
+                            let to_copy = core::cmp::min(length - copied, copied);
+                            core::ptr::copy_nonoverlapping(out_next, out_next.add(copied), to_copy);
                             copied += to_copy;
                         }
                     }
@@ -2120,18 +2121,18 @@ unsafe fn copy_match_bmi2(out_next: *mut u8, src: *const u8, offset: usize, leng
             }
         }
     } else if offset >= length {
-        std::ptr::copy_nonoverlapping(src, out_next, length);
+        core::ptr::copy_nonoverlapping(src, out_next, length);
     } else {
         match offset {
             1 => {
                 let b = *src;
-                std::ptr::write_bytes(out_next, b, length);
+                core::ptr::write_bytes(out_next, b, length);
             }
             2 | 4 => {
                 let v_pattern = match offset {
-                    2 => _mm_set1_epi16(std::ptr::read_unaligned(src as *const u16) as i16),
-                    4 => _mm_set1_epi32(std::ptr::read_unaligned(src as *const u32) as i32),
-                    _ => std::hint::unreachable_unchecked(),
+                    2 => _mm_set1_epi16(core::ptr::read_unaligned(src as *const u16) as i16),
+                    4 => _mm_set1_epi32(core::ptr::read_unaligned(src as *const u32) as i32),
+                    _ => core::hint::unreachable_unchecked(),
                 };
                 decompress_fill_pattern(out_next, v_pattern, length);
             }
@@ -2148,7 +2149,7 @@ unsafe fn copy_match_bmi2(out_next: *mut u8, src: *const u8, offset: usize, leng
                 decompress_offset_7(out_next, src, length);
             }
             8 => {
-                let val = std::ptr::read_unaligned(src as *const u64);
+                let val = core::ptr::read_unaligned(src as *const u64);
                 let v_pattern = _mm_set1_epi64x(val as i64);
                 decompress_fill_pattern(out_next, v_pattern, length);
             }
@@ -2176,8 +2177,8 @@ unsafe fn copy_match_bmi2(out_next: *mut u8, src: *const u8, offset: usize, leng
             _ => {
                 let mut copied = 0;
                 while copied + 8 <= length {
-                    let val = std::ptr::read_unaligned(src.add(copied) as *const u64);
-                    std::ptr::write_unaligned(out_next.add(copied) as *mut u64, val);
+                    let val = core::ptr::read_unaligned(src.add(copied) as *const u64);
+                    core::ptr::write_unaligned(out_next.add(copied) as *mut u64, val);
                     copied += 8;
                 }
                 while copied < length {
@@ -2236,7 +2237,7 @@ pub unsafe fn decompress_bmi2_ptr(
                 if in_idx + len > in_len {
                     return (DecompressResult::BadData, 0, 0);
                 }
-                std::ptr::copy_nonoverlapping(
+                core::ptr::copy_nonoverlapping(
                     input.as_ptr().add(in_idx),
                     out_ptr.add(out_idx),
                     len,
@@ -2305,6 +2306,7 @@ pub unsafe fn decompress_bmi2_ptr(
                                         if entry & HUFFDEC_END_OF_BLOCK != 0 {
                                             bitbuf >>= entry as u8;
                                             bitsleft -= entry & 0xFF;
+                                            eob_found = true;
                                             break;
                                         }
                                         bitbuf = saved_bitbuf;
@@ -2382,7 +2384,16 @@ pub unsafe fn decompress_bmi2_ptr(
                                 }
 
                                 let src = out_next.sub(offset);
-                                copy_match_bmi2(out_next, src, offset, length);
+                                if length <= DEFLATE_MAX_MATCH_LEN {
+                                    copy_match_bmi2(out_next, src, offset, length);
+                                } else {
+                                    // Deflate64 length symbol 285: the SIMD copies assume
+                                    // at most 258 bytes, so copy in offset-sized steps.
+                                    if out_next.add(length) > out_ptr_end {
+                                        return (DecompressResult::InsufficientSpace, 0, 0);
+                                    }
+                                    copy_long_match(out_next, src, offset, length);
+                                }
                                 out_next = out_next.add(length);
                             }
                         }
@@ -2419,6 +2430,9 @@ pub unsafe fn decompress_bmi2_ptr(
             }
             _ => return (DecompressResult::BadData, 0, 0),
         }
+        unsafe { d.checksum_output(out_ptr, out_idx) };
     }
-    (DecompressResult::Success, in_idx, out_idx)
+    // Whole bytes still sitting in the bit buffer were never consumed by the stream.
+    let unused_bytes = (bitsleft / 8) as usize;
+    (DecompressResult::Success, in_idx - unused_bytes, out_idx)
 }
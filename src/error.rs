@@ -0,0 +1,51 @@
+//! The error type of the fallible APIs that `no_std` builds keep.
+//!
+//! With `std` it is [`std::io::Error`], as everywhere else in the crate; without it, a
+//! small enum with the same two cases.
+
+#[cfg(feature = "std")]
+pub type Error = std::io::Error;
+
+#[cfg(not(feature = "std"))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The arguments were invalid; the message says how.
+    InvalidInput(&'static str),
+    /// The output buffer was too small.
+    InsufficientSpace,
+}
+
+#[cfg(not(feature = "std"))]
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidInput(msg) => f.write_str(msg),
+            Error::InsufficientSpace => f.write_str("Insufficient space"),
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+impl core::error::Error for Error {}
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[cfg(feature = "std")]
+pub(crate) fn invalid_input(msg: &'static str) -> Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn invalid_input(msg: &'static str) -> Error {
+    Error::InvalidInput(msg)
+}
+
+#[cfg(feature = "std")]
+pub(crate) fn insufficient_space() -> Error {
+    std::io::Error::other("Insufficient space")
+}
+
+#[cfg(not(feature = "std"))]
+pub(crate) fn insufficient_space() -> Error {
+    Error::InsufficientSpace
+}
@@ -1,17 +1,42 @@
 #![cfg_attr(target_arch = "aarch64", feature(stdarch_neon_dotprod))]
+#![cfg_attr(not(feature = "std"), no_std)]
 #![allow(unsafe_op_in_unsafe_fn)]
+
+extern crate alloc;
+
 pub mod adler32;
+pub mod allocator;
+#[cfg(feature = "std")]
 pub mod api;
+#[cfg(feature = "std")]
 pub mod batch;
 #[cfg(feature = "cuda")]
 pub mod batch_cuda;
+#[cfg(feature = "std")]
+pub mod capi;
+#[cfg(feature = "std")]
+pub mod checksum;
 pub mod common;
 pub mod compress;
+pub mod cpu_features;
 pub mod crc32;
 pub mod crc32_tables;
+pub mod crc64;
 pub mod decompress;
+pub mod error;
+#[cfg(feature = "std")]
 pub mod stream;
+mod sync;
+#[cfg(feature = "std")]
+pub mod zip;
+#[cfg(feature = "std")]
+pub mod zlib_compat;
 
 pub use adler32::adler32;
+#[cfg(feature = "std")]
 pub use api::{Compressor, Decompressor};
-pub use crc32::crc32;
+#[cfg(feature = "std")]
+pub use checksum::{Adler32Hasher, ChecksumReader, ChecksumWriter, Crc32Hasher};
+pub use common::GzipHeader;
+pub use cpu_features::{IsaLevel, cpu_features};
+pub use crc32::{crc32, crc32c};
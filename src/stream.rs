@@ -1,5 +1,8 @@
-use crate::compress::{CompressResult, Compressor};
-use crate::decompress::{DecompressResult, Decompressor, DecompressorState};
+use crate::common::{DEFLATE_MAX_MATCH_LEN, DEFLATE64_MAX_MATCH_LEN};
+use crate::compress::{
+    AutoLevel, AutoLevelState, CompressResult, CompressionStats, Compressor, Parallelism,
+};
+use crate::decompress::{DecompressResult, Decompressor, DecompressorState, DeflateVariant};
 use rayon::prelude::*;
 use std::cmp::min;
 use std::io::{self, Read, Write};
@@ -19,6 +22,9 @@ pub struct DeflateEncoder<W: Write + Send> {
     level: usize,
     compressors: Vec<Compressor>,
     output_buffers: Vec<Vec<u8>>,
+    parallelism: Parallelism,
+    auto_level: Option<AutoLevelState>,
+    stats: Option<CompressionStats>,
 }
 
 impl<W: Write + Send> DeflateEncoder<W> {
@@ -30,6 +36,9 @@ impl<W: Write + Send> DeflateEncoder<W> {
             level,
             compressors: Vec::new(),
             output_buffers: Vec::new(),
+            parallelism: Parallelism::default(),
+            auto_level: None,
+            stats: None,
         }
     }
 
@@ -39,20 +48,89 @@ impl<W: Write + Send> DeflateEncoder<W> {
         self
     }
 
+    /// Sets the chunk size and thread pool used to compress each filled buffer; see
+    /// [`Parallelism`].
+    pub fn with_parallelism(mut self, parallelism: Parallelism) -> Self {
+        self.parallelism = parallelism;
+        self
+    }
+
+    /// Chooses the level with `auto` from the first filled buffer, and again from the
+    /// buffer at hand once `auto.recheck_interval()` bytes have been compressed since the
+    /// last choice; see [`AutoLevel`].
+    pub fn with_auto_level(mut self, auto: AutoLevel) -> Self {
+        self.auto_level = Some(AutoLevelState::new(auto));
+        self
+    }
+
+    /// The level that buffered input is compressed at, unless an [`AutoLevel`] chooses
+    /// another when it is flushed.
+    pub fn level(&self) -> usize {
+        self.level
+    }
+
+    /// Sets whether the encoder gathers [`CompressionStats`] over everything it
+    /// compresses, available from [`stats`](Self::stats) and
+    /// [`finish_with_stats`](Self::finish_with_stats). Off by default.
+    pub fn with_collect_stats(mut self, enabled: bool) -> Self {
+        self.stats = enabled.then(CompressionStats::default);
+        for compressor in &mut self.compressors {
+            compressor.set_collect_stats(enabled);
+        }
+        self
+    }
+
+    /// The stats of the buffers compressed so far, if enabled.
+    pub fn stats(&self) -> Option<&CompressionStats> {
+        self.stats.as_ref()
+    }
+
+    /// Each compressor handles one chunk, or the whole buffer, on its own.
+    fn new_compressor(level: usize, collect_stats: bool) -> Compressor {
+        let mut compressor = Compressor::new(level);
+        compressor.set_parallelism(Parallelism::single_threaded());
+        compressor.set_collect_stats(collect_stats);
+        compressor
+    }
+
+    /// Adds the stats of the last call of the first `n` compressors to the totals.
+    fn add_stats(&mut self, n: usize) {
+        if let Some(stats) = &mut self.stats {
+            for compressor in &self.compressors[..n] {
+                if let Some(call) = compressor.stats() {
+                    stats.merge(call);
+                }
+            }
+        }
+    }
+
     fn flush_buffer(&mut self, final_block: bool) -> io::Result<()> {
         if self.buffer.is_empty() && !final_block {
             return Ok(());
         }
 
-        let chunk_size = 256 * 1024;
+        if let Some(level) = self
+            .auto_level
+            .as_mut()
+            .and_then(|a| a.level_for(&self.buffer))
+            && level != self.level
+        {
+            self.level = level;
+            for compressor in &mut self.compressors {
+                compressor.set_level(level);
+            }
+        }
+
+        let chunk_size = self.parallelism.chunk_size();
         let buffer_len = self.buffer.len();
 
-        if buffer_len > chunk_size {
+        if self.parallelism.splits(buffer_len) && buffer_len > chunk_size {
             let chunks: Vec<&[u8]> = self.buffer.chunks(chunk_size).collect();
             let num_chunks = chunks.len();
 
             while self.compressors.len() < num_chunks {
-                self.compressors.push(Compressor::new(self.level));
+                self.compressors
+                    .push(Self::new_compressor(self.level, self.stats.is_some()));
             }
             while self.output_buffers.len() < num_chunks {
                 self.output_buffers.push(Vec::new());
@@ -96,47 +174,52 @@ impl<W: Write + Send> DeflateEncoder<W> {
                 } else {
                     return Err(io::Error::other("Compression failed"));
                 }
+                self.add_stats(1);
             } else {
-                let compressed_chunks: Vec<io::Result<usize>> = chunks
-                    .par_iter()
-                    .zip(self.compressors.par_iter_mut())
-                    .zip(self.output_buffers.par_iter_mut())
-                    .enumerate()
-                    .map(|(i, ((&chunk, compressor), output))| {
-                        let mut bound = Compressor::deflate_compress_bound(chunk.len());
-                        if !(final_block && i == num_chunks - 1) {
-                            bound += 5;
-                        }
-                        if output.len() < bound {
-                            output
-                                .try_reserve(bound - output.len())
-                                .map_err(io::Error::other)?;
-                            // SAFETY: We just reserved sufficient capacity. The compressor writes to
-                            // the buffer using `MaybeUninit` pointers, so uninitialized memory is fine.
-                            unsafe {
-                                output.set_len(bound);
+                let compressors = &mut self.compressors;
+                let output_buffers = &mut self.output_buffers;
+                let compressed_chunks: Vec<io::Result<usize>> = self.parallelism.install(|| {
+                    chunks
+                        .par_iter()
+                        .zip(compressors.par_iter_mut())
+                        .zip(output_buffers.par_iter_mut())
+                        .enumerate()
+                        .map(|(i, ((&chunk, compressor), output))| {
+                            let mut bound = Compressor::deflate_compress_bound(chunk.len());
+                            if !(final_block && i == num_chunks - 1) {
+                                bound += 5;
+                            }
+                            if output.len() < bound {
+                                output
+                                    .try_reserve(bound - output.len())
+                                    .map_err(io::Error::other)?;
+                                // SAFETY: We just reserved sufficient capacity. The compressor writes to
+                                // the buffer using `MaybeUninit` pointers, so uninitialized memory is fine.
+                                unsafe {
+                                    output.set_len(bound);
+                                }
                             }
-                        }
-
-                        let mode = if final_block && i == num_chunks - 1 {
-                            crate::compress::FlushMode::Finish
-                        } else {
-                            crate::compress::FlushMode::Sync
-                        };
-                        let out_uninit = unsafe {
-                            std::slice::from_raw_parts_mut(
-                                output.as_mut_ptr() as *mut MaybeUninit<u8>,
-                                output.len(),
-                            )
-                        };
-                        let (res, size, _) = compressor.compress(chunk, out_uninit, mode);
-                        if res == CompressResult::Success {
-                            Ok(size)
-                        } else {
-                            Err(io::Error::other("Compression failed"))
-                        }
-                    })
-                    .collect();
+
+                            let mode = if final_block && i == num_chunks - 1 {
+                                crate::compress::FlushMode::Finish
+                            } else {
+                                crate::compress::FlushMode::Sync
+                            };
+                            let out_uninit = unsafe {
+                                std::slice::from_raw_parts_mut(
+                                    output.as_mut_ptr() as *mut MaybeUninit<u8>,
+                                    output.len(),
+                                )
+                            };
+                            let (res, size, _) = compressor.compress(chunk, out_uninit, mode);
+                            if res == CompressResult::Success {
+                                Ok(size)
+                            } else {
+                                Err(io::Error::other("Compression failed"))
+                            }
+                        })
+                        .collect()
+                });
 
                 if let Some(writer) = &mut self.writer {
                     for (i, size_res) in compressed_chunks.into_iter().enumerate() {
@@ -144,10 +227,12 @@ impl<W: Write + Send> DeflateEncoder<W> {
                         writer.write_all(&self.output_buffers[i][..size])?;
                     }
                 }
+                self.add_stats(num_chunks);
             }
         } else {
             if self.compressors.is_empty() {
-                self.compressors.push(Compressor::new(self.level));
+                self.compressors
+                    .push(Self::new_compressor(self.level, self.stats.is_some()));
             }
             if self.output_buffers.is_empty() {
                 self.output_buffers.push(Vec::new());
@@ -189,6 +274,7 @@ impl<W: Write + Send> DeflateEncoder<W> {
             } else {
                 return Err(io::Error::other("Compression failed"));
             }
+            self.add_stats(1);
         }
 
         self.buffer.clear();
@@ -204,6 +290,13 @@ impl<W: Write + Send> DeflateEncoder<W> {
         self.flush_buffer(true)?;
         Ok(self.writer.take().unwrap())
     }
+
+    /// Like [`finish`](Self::finish), also returning the stats of the whole stream if
+    /// enabled with [`with_collect_stats`](Self::with_collect_stats).
+    pub fn finish_with_stats(mut self) -> io::Result<(W, Option<CompressionStats>)> {
+        self.flush_buffer(true)?;
+        Ok((self.writer.take().unwrap(), self.stats.take()))
+    }
 }
 
 impl<W: Write + Send> Write for DeflateEncoder<W> {
@@ -239,6 +332,8 @@ pub struct DeflateDecoder<R: Read> {
     input_pos: usize,
     input_cap: usize,
     window: Vec<u8>,
+    history: usize,
+    max_match: usize,
     read_pos: usize,
     write_pos: usize,
     done: bool,
@@ -246,13 +341,31 @@ pub struct DeflateDecoder<R: Read> {
 
 impl<R: Read> DeflateDecoder<R> {
     pub fn new(inner: R) -> Self {
+        Self::with_variant(inner, DeflateVariant::Deflate)
+    }
+
+    /// Creates a decoder for a Deflate64 ("enhanced deflate") stream.
+    pub fn new_deflate64(inner: R) -> Self {
+        Self::with_variant(inner, DeflateVariant::Deflate64)
+    }
+
+    fn with_variant(inner: R, variant: DeflateVariant) -> Self {
+        let mut decompressor = Decompressor::new();
+        decompressor.set_variant(variant);
+        let history = variant.window_size();
+        let max_match = match variant {
+            DeflateVariant::Deflate => DEFLATE_MAX_MATCH_LEN,
+            DeflateVariant::Deflate64 => DEFLATE64_MAX_MATCH_LEN,
+        };
         Self {
             inner,
-            decompressor: Decompressor::new(),
+            decompressor,
             input_buffer: vec![0; 32 * 1024],
             input_pos: 0,
             input_cap: 0,
-            window: vec![0; 64 * 1024],
+            window: vec![0; 2 * history + max_match],
+            history,
+            max_match,
             read_pos: 0,
             write_pos: 0,
             done: false,
@@ -274,16 +387,11 @@ impl<R: Read> Read for DeflateDecoder<R> {
         }
 
         loop {
-            if self.write_pos >= 64 * 1024 && self.read_pos >= 32 * 1024 {
-                self.window.copy_within(
-                    self.read_pos - 32 * 1024..self.write_pos,
-                    32 * 1024 - (self.read_pos - 32 * 1024),
-                );
-
-                let amount_to_keep = 32 * 1024;
-                let shift = self.write_pos - amount_to_keep;
+            if self.write_pos + self.max_match > self.window.len() {
+                // Slide the window down, keeping the match history and any unread output.
+                let shift = min(self.read_pos, self.write_pos - self.history);
                 self.window.copy_within(shift..self.write_pos, 0);
-                self.write_pos = amount_to_keep;
+                self.write_pos -= shift;
                 self.read_pos -= shift;
             }
 
@@ -0,0 +1,81 @@
+//! `OnceLock` for the lazily initialized dispatch and table statics: the `std` one, or
+//! without `std` a minimal spinning version built on atomics.
+
+#[cfg(feature = "std")]
+pub(crate) use std::sync::OnceLock;
+
+#[cfg(not(feature = "std"))]
+pub(crate) use self::spin::OnceLock;
+
+#[cfg(not(feature = "std"))]
+mod spin {
+    use core::cell::UnsafeCell;
+    use core::mem::MaybeUninit;
+    use core::sync::atomic::{AtomicU8, Ordering};
+
+    const EMPTY: u8 = 0;
+    const RUNNING: u8 = 1;
+    const DONE: u8 = 2;
+
+    pub(crate) struct OnceLock<T> {
+        state: AtomicU8,
+        value: UnsafeCell<MaybeUninit<T>>,
+    }
+
+    // SAFETY: the value is written once, before `DONE` is published, and only read after.
+    unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+    impl<T> OnceLock<T> {
+        pub(crate) const fn new() -> Self {
+            Self {
+                state: AtomicU8::new(EMPTY),
+                value: UnsafeCell::new(MaybeUninit::uninit()),
+            }
+        }
+
+        /// Stores `value` unless the lock is already set or being set; `Err` hands it back.
+        pub(crate) fn set(&self, value: T) -> Result<(), T> {
+            if self
+                .state
+                .compare_exchange(EMPTY, RUNNING, Ordering::Acquire, Ordering::Acquire)
+                .is_err()
+            {
+                return Err(value);
+            }
+            unsafe { (*self.value.get()).write(value) };
+            self.state.store(DONE, Ordering::Release);
+            Ok(())
+        }
+
+        pub(crate) fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+            loop {
+                match self.state.compare_exchange(
+                    EMPTY,
+                    RUNNING,
+                    Ordering::Acquire,
+                    Ordering::Acquire,
+                ) {
+                    Ok(_) => {
+                        unsafe { (*self.value.get()).write(f()) };
+                        self.state.store(DONE, Ordering::Release);
+                    }
+                    Err(DONE) => {}
+                    // Another caller is initializing.
+                    Err(_) => {
+                        core::hint::spin_loop();
+                        continue;
+                    }
+                }
+                return unsafe { (*self.value.get()).assume_init_ref() };
+            }
+        }
+    }
+
+    impl<T> Drop for OnceLock<T> {
+        fn drop(&mut self) {
+            if *self.state.get_mut() == DONE {
+                unsafe { self.value.get_mut().assume_init_drop() };
+            }
+        }
+    }
+}
@@ -0,0 +1,699 @@
+//! Reading and writing ZIP archives.
+//!
+//! [`ZipArchive`] reads the central directory, ZIP64 included, and extracts stored, deflate
+//! and Deflate64 entries with CRC-32 checks. [`ZipWriter`] writes stored and deflate
+//! entries, either from whole buffers (optionally compressed in parallel) or streamed
+//! through [`ZipFileWriter`] with a data descriptor after the data.
+
+use crate::api::{Compressor, Decompressor};
+use crate::compress::{CompressResult, Compressor as InternalCompressor, FlushMode};
+use crate::crc32::crc32;
+use crate::decompress::DeflateVariant;
+use rayon::prelude::*;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::mem::MaybeUninit;
+
+pub const ZIP_METHOD_STORED: u16 = 0;
+pub const ZIP_METHOD_DEFLATE: u16 = 8;
+pub const ZIP_METHOD_DEFLATE64: u16 = 9;
+
+/// General purpose flag: the entry is encrypted.
+pub const ZIP_FLAG_ENCRYPTED: u16 = 1 << 0;
+/// General purpose flag: CRC and sizes follow the data in a data descriptor.
+pub const ZIP_FLAG_DATA_DESCRIPTOR: u16 = 1 << 3;
+/// General purpose flag: the name is UTF-8.
+pub const ZIP_FLAG_UTF8: u16 = 1 << 11;
+
+const LOCAL_HEADER_SIG: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIG: u32 = 0x02014b50;
+const DATA_DESCRIPTOR_SIG: u32 = 0x08074b50;
+const EOCD_SIG: u32 = 0x06054b50;
+const ZIP64_EOCD_SIG: u32 = 0x06064b50;
+const ZIP64_LOCATOR_SIG: u32 = 0x07064b50;
+const ZIP64_EXTRA_ID: u16 = 0x0001;
+
+const LOCAL_HEADER_LEN: usize = 30;
+const CENTRAL_HEADER_LEN: usize = 46;
+const EOCD_LEN: usize = 22;
+const ZIP64_EOCD_LEN: usize = 56;
+const ZIP64_LOCATOR_LEN: usize = 20;
+
+const VERSION_DEFAULT: u16 = 20;
+const VERSION_ZIP64: u16 = 45;
+/// 1980-01-01 00:00, the earliest MS-DOS timestamp.
+const DOS_DATE_EPOCH: u16 = (1 << 5) | 1;
+
+/// Input buffered per deflate block of a streamed entry.
+const STREAM_BUFFER_SIZE: usize = 1024 * 1024;
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn le16(b: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([b[at], b[at + 1]])
+}
+
+fn le32(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([b[at], b[at + 1], b[at + 2], b[at + 3]])
+}
+
+fn le64(b: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+}
+
+/// One file in a ZIP archive, as described by the central directory.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ZipEntry {
+    /// Entry name; bytes that are not valid UTF-8 are replaced.
+    pub name: String,
+    /// Compression method, e.g. [`ZIP_METHOD_DEFLATE`].
+    pub method: u16,
+    /// General purpose bit flags.
+    pub flags: u16,
+    pub crc32: u32,
+    pub compressed_size: u64,
+    pub uncompressed_size: u64,
+    /// Offset of the entry's local header from the start of the archive.
+    pub header_offset: u64,
+}
+
+/// A ZIP archive opened for reading.
+pub struct ZipArchive<R: Read + Seek> {
+    reader: R,
+    entries: Vec<ZipEntry>,
+    comment: Vec<u8>,
+    decompressor: Decompressor,
+}
+
+impl<R: Read + Seek> ZipArchive<R> {
+    /// Reads the central directory of the archive in `reader`.
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let file_len = reader.seek(SeekFrom::End(0))?;
+        let tail_len = file_len.min((EOCD_LEN + u16::MAX as usize) as u64);
+        let tail_start = file_len - tail_len;
+        let mut tail = vec![0u8; tail_len as usize];
+        reader.seek(SeekFrom::Start(tail_start))?;
+        reader.read_exact(&mut tail)?;
+        if tail.len() < EOCD_LEN {
+            return Err(invalid_data("end of central directory not found"));
+        }
+
+        // The end of central directory record sits right before a comment of up to 64 KiB.
+        let eocd = (0..=tail.len() - EOCD_LEN)
+            .rev()
+            .find(|&i| {
+                le32(&tail, i) == EOCD_SIG
+                    && i + EOCD_LEN + le16(&tail, i + 20) as usize <= tail.len()
+            })
+            .ok_or_else(|| invalid_data("end of central directory not found"))?;
+        let comment_len = le16(&tail, eocd + 20) as usize;
+        let comment = tail[eocd + EOCD_LEN..eocd + EOCD_LEN + comment_len].to_vec();
+        let mut num_entries = le16(&tail, eocd + 10) as u64;
+        let mut cd_size = le32(&tail, eocd + 12) as u64;
+        let mut cd_offset = le32(&tail, eocd + 16) as u64;
+
+        if eocd >= ZIP64_LOCATOR_LEN && le32(&tail, eocd - ZIP64_LOCATOR_LEN) == ZIP64_LOCATOR_SIG {
+            let zip64_offset = le64(&tail, eocd - ZIP64_LOCATOR_LEN + 8);
+            let mut record = [0u8; ZIP64_EOCD_LEN];
+            reader.seek(SeekFrom::Start(zip64_offset))?;
+            reader.read_exact(&mut record)?;
+            if le32(&record, 0) != ZIP64_EOCD_SIG {
+                return Err(invalid_data("bad ZIP64 end of central directory"));
+            }
+            num_entries = le64(&record, 32);
+            cd_size = le64(&record, 40);
+            cd_offset = le64(&record, 48);
+        }
+
+        if cd_offset
+            .checked_add(cd_size)
+            .is_none_or(|end| end > file_len)
+        {
+            return Err(invalid_data("central directory out of bounds"));
+        }
+        let mut cd = vec![0u8; cd_size as usize];
+        reader.seek(SeekFrom::Start(cd_offset))?;
+        reader.read_exact(&mut cd)?;
+
+        let capacity = num_entries.min((cd.len() / CENTRAL_HEADER_LEN) as u64) as usize;
+        let mut entries = Vec::with_capacity(capacity);
+        let mut pos = 0;
+        for _ in 0..num_entries {
+            let (entry, len) = parse_central_header(&cd[pos..])?;
+            entries.push(entry);
+            pos += len;
+        }
+
+        Ok(Self {
+            reader,
+            entries,
+            comment,
+            decompressor: Decompressor::new(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn entries(&self) -> &[ZipEntry] {
+        &self.entries
+    }
+
+    /// The archive comment.
+    pub fn comment(&self) -> &[u8] {
+        &self.comment
+    }
+
+    /// Index of the first entry called `name`.
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.name == name)
+    }
+
+    /// Caps the size of a single extracted entry; see
+    /// [`Decompressor::set_max_memory_limit`].
+    pub fn set_max_memory_limit(&mut self, limit: usize) {
+        self.decompressor.set_max_memory_limit(limit);
+    }
+
+    /// Extracts entry `index` and checks its CRC-32.
+    pub fn read(&mut self, index: usize) -> io::Result<Vec<u8>> {
+        let entry = self.entries.get(index).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "entry index out of range")
+        })?;
+        if entry.flags & ZIP_FLAG_ENCRYPTED != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "encrypted entries are not supported",
+            ));
+        }
+        let variant = match entry.method {
+            ZIP_METHOD_STORED => None,
+            ZIP_METHOD_DEFLATE => Some(DeflateVariant::Deflate),
+            ZIP_METHOD_DEFLATE64 => Some(DeflateVariant::Deflate64),
+            method => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("unsupported compression method {}", method),
+                ));
+            }
+        };
+        let uncompressed_size = usize::try_from(entry.uncompressed_size)
+            .map_err(|_| invalid_data("entry too large for this platform"))?;
+
+        let mut header = [0u8; LOCAL_HEADER_LEN];
+        self.reader.seek(SeekFrom::Start(entry.header_offset))?;
+        self.reader.read_exact(&mut header)?;
+        if le32(&header, 0) != LOCAL_HEADER_SIG {
+            return Err(invalid_data("bad local file header"));
+        }
+        let skip = le16(&header, 26) as i64 + le16(&header, 28) as i64;
+        self.reader.seek(SeekFrom::Current(skip))?;
+
+        let mut compressed = Vec::new();
+        (&mut self.reader)
+            .take(entry.compressed_size)
+            .read_to_end(&mut compressed)?;
+        if (compressed.len() as u64) < entry.compressed_size {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated entry data",
+            ));
+        }
+
+        let data = match variant {
+            None => compressed,
+            Some(variant) => {
+                self.decompressor.set_variant(variant);
+                self.decompressor
+                    .decompress_deflate(&compressed, uncompressed_size)?
+            }
+        };
+        if data.len() != uncompressed_size {
+            return Err(invalid_data("entry size mismatch"));
+        }
+        if crc32(0, &data) != entry.crc32 {
+            return Err(invalid_data("entry CRC-32 mismatch"));
+        }
+        Ok(data)
+    }
+
+    /// Extracts the first entry called `name`.
+    pub fn read_by_name(&mut self, name: &str) -> io::Result<Vec<u8>> {
+        let index = self
+            .index_of(name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such entry"))?;
+        self.read(index)
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Parses one central directory header, returning it and its length in bytes.
+fn parse_central_header(b: &[u8]) -> io::Result<(ZipEntry, usize)> {
+    if b.len() < CENTRAL_HEADER_LEN || le32(b, 0) != CENTRAL_HEADER_SIG {
+        return Err(invalid_data("bad central directory header"));
+    }
+    let name_len = le16(b, 28) as usize;
+    let extra_len = le16(b, 30) as usize;
+    let comment_len = le16(b, 32) as usize;
+    let len = CENTRAL_HEADER_LEN + name_len + extra_len + comment_len;
+    if b.len() < len {
+        return Err(invalid_data("truncated central directory"));
+    }
+    let name = &b[CENTRAL_HEADER_LEN..CENTRAL_HEADER_LEN + name_len];
+    let mut entry = ZipEntry {
+        name: String::from_utf8_lossy(name).into_owned(),
+        method: le16(b, 10),
+        flags: le16(b, 8),
+        crc32: le32(b, 16),
+        compressed_size: le32(b, 20) as u64,
+        uncompressed_size: le32(b, 24) as u64,
+        header_offset: le32(b, 42) as u64,
+    };
+
+    // The ZIP64 extra field holds, in this order, the 64-bit versions of just those fields
+    // that are saturated in the header.
+    let mut extra = &b[CENTRAL_HEADER_LEN + name_len..CENTRAL_HEADER_LEN + name_len + extra_len];
+    while extra.len() >= 4 {
+        let id = le16(extra, 0);
+        let size = (le16(extra, 2) as usize).min(extra.len() - 4);
+        let mut field = &extra[4..4 + size];
+        if id == ZIP64_EXTRA_ID {
+            for value in [
+                &mut entry.uncompressed_size,
+                &mut entry.compressed_size,
+                &mut entry.header_offset,
+            ] {
+                if *value == u32::MAX as u64 {
+                    if field.len() < 8 {
+                        return Err(invalid_data("truncated ZIP64 extra field"));
+                    }
+                    *value = le64(field, 0);
+                    field = &field[8..];
+                }
+            }
+        }
+        extra = &extra[4 + size..];
+    }
+    Ok((entry, len))
+}
+
+/// Saturates `value` for a 32-bit header field, which then points at the ZIP64 extra field.
+fn header_u32(value: u64) -> u32 {
+    value.min(u32::MAX as u64) as u32
+}
+
+/// What the central directory needs to know about a written entry.
+struct CentralRecord {
+    name: String,
+    method: u16,
+    flags: u16,
+    crc32: u32,
+    compressed_size: u64,
+    uncompressed_size: u64,
+    header_offset: u64,
+}
+
+impl CentralRecord {
+    fn needs_zip64(&self) -> bool {
+        self.compressed_size >= u32::MAX as u64
+            || self.uncompressed_size >= u32::MAX as u64
+            || self.header_offset >= u32::MAX as u64
+    }
+
+    fn write_to(&self, out: &mut Vec<u8>) {
+        let mut zip64 = Vec::new();
+        for value in [
+            self.uncompressed_size,
+            self.compressed_size,
+            self.header_offset,
+        ] {
+            if value >= u32::MAX as u64 {
+                zip64.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        let version = if zip64.is_empty() {
+            VERSION_DEFAULT
+        } else {
+            VERSION_ZIP64
+        };
+        let extra_len = if zip64.is_empty() { 0 } else { 4 + zip64.len() };
+
+        out.extend_from_slice(&CENTRAL_HEADER_SIG.to_le_bytes());
+        out.extend_from_slice(&version.to_le_bytes()); // version made by
+        out.extend_from_slice(&version.to_le_bytes()); // version needed
+        out.extend_from_slice(&self.flags.to_le_bytes());
+        out.extend_from_slice(&self.method.to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // time
+        out.extend_from_slice(&DOS_DATE_EPOCH.to_le_bytes());
+        out.extend_from_slice(&self.crc32.to_le_bytes());
+        out.extend_from_slice(&header_u32(self.compressed_size).to_le_bytes());
+        out.extend_from_slice(&header_u32(self.uncompressed_size).to_le_bytes());
+        out.extend_from_slice(&(self.name.len() as u16).to_le_bytes());
+        out.extend_from_slice(&(extra_len as u16).to_le_bytes());
+        out.extend_from_slice(&0u16.to_le_bytes()); // comment length
+        out.extend_from_slice(&0u16.to_le_bytes()); // disk number
+        out.extend_from_slice(&0u16.to_le_bytes()); // internal attributes
+        out.extend_from_slice(&0u32.to_le_bytes()); // external attributes
+        out.extend_from_slice(&header_u32(self.header_offset).to_le_bytes());
+        out.extend_from_slice(self.name.as_bytes());
+        if !zip64.is_empty() {
+            out.extend_from_slice(&ZIP64_EXTRA_ID.to_le_bytes());
+            out.extend_from_slice(&(zip64.len() as u16).to_le_bytes());
+            out.extend_from_slice(&zip64);
+        }
+    }
+}
+
+/// An entry compressed ahead of writing.
+struct PreparedEntry {
+    method: u16,
+    crc32: u32,
+    data: Vec<u8>,
+    uncompressed_size: u64,
+}
+
+/// Deflates `data`, falling back to storing it when that is no larger.
+fn prepare_entry(compressor: Option<&mut Compressor>, data: &[u8]) -> io::Result<PreparedEntry> {
+    let crc32 = crc32(0, data);
+    let deflated = match compressor {
+        Some(compressor) => Some(compressor.compress_deflate(data)?),
+        None => None,
+    };
+    let (method, stored) = match deflated {
+        Some(deflated) if deflated.len() < data.len() => (ZIP_METHOD_DEFLATE, deflated),
+        _ => (ZIP_METHOD_STORED, data.to_vec()),
+    };
+    Ok(PreparedEntry {
+        method,
+        crc32,
+        data: stored,
+        uncompressed_size: data.len() as u64,
+    })
+}
+
+fn check_name(name: &str) -> io::Result<()> {
+    if name.len() > u16::MAX as usize {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "entry name longer than 65535 bytes",
+        ));
+    }
+    Ok(())
+}
+
+fn name_flags(name: &str) -> u16 {
+    if name.is_ascii() { 0 } else { ZIP_FLAG_UTF8 }
+}
+
+/// Writes a ZIP archive.
+///
+/// Call [`finish()`](Self::finish) to write the central directory; an archive dropped
+/// without it is incomplete.
+pub struct ZipWriter<W: Write> {
+    writer: W,
+    level: i32,
+    compressor: Option<Compressor>,
+    stream_compressor: Option<InternalCompressor>,
+    offset: u64,
+    records: Vec<CentralRecord>,
+    comment: Vec<u8>,
+}
+
+impl<W: Write> ZipWriter<W> {
+    /// Creates a writer whose entries are deflated at `level`; level 0 stores them.
+    pub fn new(writer: W, level: i32) -> io::Result<Self> {
+        let compressor = Compressor::new(level)?;
+        Ok(Self {
+            writer,
+            level,
+            compressor: (level > 0).then_some(compressor),
+            stream_compressor: None,
+            offset: 0,
+            records: Vec::new(),
+            comment: Vec::new(),
+        })
+    }
+
+    /// Sets the archive comment, truncated to 65535 bytes.
+    pub fn set_comment(&mut self, comment: &[u8]) {
+        self.comment = comment[..comment.len().min(u16::MAX as usize)].to_vec();
+    }
+
+    /// Compresses `data` and adds it as entry `name`.
+    pub fn add_file(&mut self, name: &str, data: &[u8]) -> io::Result<()> {
+        check_name(name)?;
+        let entry = prepare_entry(self.compressor.as_mut(), data)?;
+        self.write_prepared(name, entry)
+    }
+
+    /// Adds several entries, in order, compressing them in parallel.
+    pub fn add_files(&mut self, files: &[(&str, &[u8])]) -> io::Result<()> {
+        for (name, _) in files {
+            check_name(name)?;
+        }
+        let level = self.level;
+        let prepared: Vec<io::Result<PreparedEntry>> = files
+            .par_iter()
+            .map_init(
+                || (level > 0).then(|| Compressor::new(level).expect("level already validated")),
+                |compressor, (_, data)| prepare_entry(compressor.as_mut(), data),
+            )
+            .collect();
+        for ((name, _), entry) in files.iter().zip(prepared) {
+            self.write_prepared(name, entry?)?;
+        }
+        Ok(())
+    }
+
+    fn write_prepared(&mut self, name: &str, entry: PreparedEntry) -> io::Result<()> {
+        let record = CentralRecord {
+            name: name.to_string(),
+            method: entry.method,
+            flags: name_flags(name),
+            crc32: entry.crc32,
+            compressed_size: entry.data.len() as u64,
+            uncompressed_size: entry.uncompressed_size,
+            header_offset: self.offset,
+        };
+        self.write_local_header(&record)?;
+        self.write_counted(&entry.data)?;
+        self.records.push(record);
+        Ok(())
+    }
+
+    /// Starts an entry whose data is written through the returned [`ZipFileWriter`]. The
+    /// CRC and sizes follow the data in a data descriptor.
+    pub fn start_file(&mut self, name: &str) -> io::Result<ZipFileWriter<'_, W>> {
+        check_name(name)?;
+        let record = CentralRecord {
+            name: name.to_string(),
+            method: ZIP_METHOD_DEFLATE,
+            flags: name_flags(name) | ZIP_FLAG_DATA_DESCRIPTOR,
+            crc32: 0,
+            compressed_size: 0,
+            uncompressed_size: 0,
+            header_offset: self.offset,
+        };
+        self.write_local_header(&record)?;
+        let level = self.level.max(0) as usize;
+        let compressor = self
+            .stream_compressor
+            .take()
+            .unwrap_or_else(|| InternalCompressor::new(level));
+        Ok(ZipFileWriter {
+            zip: self,
+            record: Some(record),
+            compressor: Some(compressor),
+            buffer: Vec::with_capacity(STREAM_BUFFER_SIZE),
+            output: Vec::new(),
+        })
+    }
+
+    /// Streamed entries always get a ZIP64 extra field, with zero sizes, since their final
+    /// sizes are unknown here; their data descriptors then carry 64-bit sizes.
+    fn write_local_header(&mut self, record: &CentralRecord) -> io::Result<()> {
+        let zip64 = record.flags & ZIP_FLAG_DATA_DESCRIPTOR != 0
+            || record.compressed_size >= u32::MAX as u64
+            || record.uncompressed_size >= u32::MAX as u64;
+        let mut header = Vec::with_capacity(LOCAL_HEADER_LEN + record.name.len() + 20);
+        header.extend_from_slice(&LOCAL_HEADER_SIG.to_le_bytes());
+        let version = if zip64 {
+            VERSION_ZIP64
+        } else {
+            VERSION_DEFAULT
+        };
+        header.extend_from_slice(&version.to_le_bytes());
+        header.extend_from_slice(&record.flags.to_le_bytes());
+        header.extend_from_slice(&record.method.to_le_bytes());
+        header.extend_from_slice(&0u16.to_le_bytes()); // time
+        header.extend_from_slice(&DOS_DATE_EPOCH.to_le_bytes());
+        header.extend_from_slice(&record.crc32.to_le_bytes());
+        if zip64 {
+            header.extend_from_slice(&u32::MAX.to_le_bytes());
+            header.extend_from_slice(&u32::MAX.to_le_bytes());
+        } else {
+            header.extend_from_slice(&(record.compressed_size as u32).to_le_bytes());
+            header.extend_from_slice(&(record.uncompressed_size as u32).to_le_bytes());
+        }
+        header.extend_from_slice(&(record.name.len() as u16).to_le_bytes());
+        header.extend_from_slice(&(if zip64 { 20u16 } else { 0 }).to_le_bytes());
+        header.extend_from_slice(record.name.as_bytes());
+        if zip64 {
+            header.extend_from_slice(&ZIP64_EXTRA_ID.to_le_bytes());
+            header.extend_from_slice(&16u16.to_le_bytes());
+            header.extend_from_slice(&record.uncompressed_size.to_le_bytes());
+            header.extend_from_slice(&record.compressed_size.to_le_bytes());
+        }
+        self.write_counted(&header)
+    }
+
+    fn write_counted(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)?;
+        self.offset += data.len() as u64;
+        Ok(())
+    }
+
+    /// Writes the central directory and returns the underlying writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        let cd_offset = self.offset;
+        let mut cd = Vec::new();
+        for record in &self.records {
+            record.write_to(&mut cd);
+        }
+        self.write_counted(&cd)?;
+        let cd_size = cd.len() as u64;
+        let num_entries = self.records.len() as u64;
+
+        let mut tail = Vec::with_capacity(ZIP64_EOCD_LEN + ZIP64_LOCATOR_LEN + EOCD_LEN);
+        let zip64 = num_entries >= u16::MAX as u64
+            || cd_size >= u32::MAX as u64
+            || cd_offset >= u32::MAX as u64
+            || self.records.iter().any(CentralRecord::needs_zip64);
+        if zip64 {
+            let zip64_offset = self.offset;
+            tail.extend_from_slice(&ZIP64_EOCD_SIG.to_le_bytes());
+            tail.extend_from_slice(&((ZIP64_EOCD_LEN - 12) as u64).to_le_bytes());
+            tail.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            tail.extend_from_slice(&VERSION_ZIP64.to_le_bytes());
+            tail.extend_from_slice(&0u32.to_le_bytes()); // this disk
+            tail.extend_from_slice(&0u32.to_le_bytes()); // central directory disk
+            tail.extend_from_slice(&num_entries.to_le_bytes());
+            tail.extend_from_slice(&num_entries.to_le_bytes());
+            tail.extend_from_slice(&cd_size.to_le_bytes());
+            tail.extend_from_slice(&cd_offset.to_le_bytes());
+
+            tail.extend_from_slice(&ZIP64_LOCATOR_SIG.to_le_bytes());
+            tail.extend_from_slice(&0u32.to_le_bytes());
+            tail.extend_from_slice(&zip64_offset.to_le_bytes());
+            tail.extend_from_slice(&1u32.to_le_bytes()); // total disks
+        }
+        let entries16 = num_entries.min(u16::MAX as u64) as u16;
+        tail.extend_from_slice(&EOCD_SIG.to_le_bytes());
+        tail.extend_from_slice(&0u16.to_le_bytes()); // this disk
+        tail.extend_from_slice(&0u16.to_le_bytes()); // central directory disk
+        tail.extend_from_slice(&entries16.to_le_bytes());
+        tail.extend_from_slice(&entries16.to_le_bytes());
+        tail.extend_from_slice(&header_u32(cd_size).to_le_bytes());
+        tail.extend_from_slice(&header_u32(cd_offset).to_le_bytes());
+        tail.extend_from_slice(&(self.comment.len() as u16).to_le_bytes());
+        tail.extend_from_slice(&self.comment);
+        self.write_counted(&tail)?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Streams the data of one entry into a [`ZipWriter`].
+///
+/// Call [`finish()`](Self::finish) to end the entry. If it is dropped instead, the entry
+/// is still finished, but any I/O errors are silently ignored.
+pub struct ZipFileWriter<'a, W: Write> {
+    zip: &'a mut ZipWriter<W>,
+    record: Option<CentralRecord>,
+    compressor: Option<InternalCompressor>,
+    buffer: Vec<u8>,
+    output: Vec<u8>,
+}
+
+impl<W: Write> ZipFileWriter<'_, W> {
+    fn compress_buffer(&mut self, mode: FlushMode) -> io::Result<()> {
+        let Some(record) = self.record.as_mut() else {
+            return Ok(());
+        };
+        let bound = InternalCompressor::deflate_compress_bound(self.buffer.len()) + 5;
+        self.output.clear();
+        self.output.reserve(bound);
+        let out: &mut [MaybeUninit<u8>] = &mut self.output.spare_capacity_mut()[..bound];
+        let compressor = self.compressor.as_mut().unwrap();
+        let (res, size, _) = compressor.compress(&self.buffer, out, mode);
+        if res != CompressResult::Success {
+            return Err(io::Error::other("Compression failed"));
+        }
+        // SAFETY: the compressor initialized the first `size` bytes.
+        unsafe {
+            self.output.set_len(size);
+        }
+        record.crc32 = crc32(record.crc32, &self.buffer);
+        record.uncompressed_size += self.buffer.len() as u64;
+        record.compressed_size += size as u64;
+        self.buffer.clear();
+        self.zip.write_counted(&self.output)
+    }
+
+    fn finish_entry(&mut self) -> io::Result<()> {
+        if self.record.is_none() {
+            return Ok(());
+        }
+        self.compress_buffer(FlushMode::Finish)?;
+        let record = self.record.take().unwrap();
+
+        // The local header has a ZIP64 extra field, so the sizes are 64-bit.
+        let mut descriptor = Vec::with_capacity(24);
+        descriptor.extend_from_slice(&DATA_DESCRIPTOR_SIG.to_le_bytes());
+        descriptor.extend_from_slice(&record.crc32.to_le_bytes());
+        descriptor.extend_from_slice(&record.compressed_size.to_le_bytes());
+        descriptor.extend_from_slice(&record.uncompressed_size.to_le_bytes());
+        self.zip.write_counted(&descriptor)?;
+        self.zip.records.push(record);
+        Ok(())
+    }
+
+    /// Compresses any buffered data and writes the data descriptor.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.finish_entry()
+    }
+}
+
+impl<W: Write> Write for ZipFileWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(STREAM_BUFFER_SIZE - self.buffer.len());
+        self.buffer.extend_from_slice(&buf[..n]);
+        if self.buffer.len() == STREAM_BUFFER_SIZE {
+            self.compress_buffer(FlushMode::Sync)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            self.compress_buffer(FlushMode::Sync)?;
+        }
+        self.zip.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for ZipFileWriter<'_, W> {
+    fn drop(&mut self) {
+        let _ = self.finish_entry();
+        self.zip.stream_compressor = self.compressor.take();
+    }
+}
@@ -0,0 +1,1043 @@
+//! The zlib C API (`zlib.h`) on top of the crate's compressor and decompressor, for
+//! programs linked against `libz`.
+//!
+//! Like [`crate::capi`], the functions are always compiled but only exported under their C
+//! names with the `zlib-compat` feature. `deflateInit2` and `inflateInit2` are macros in
+//! `zlib.h`; the exported entry points are `deflateInit2_` and `inflateInit2_`.
+//!
+//! [`deflate`] buffers its input and compresses it when the caller flushes or
+//! [`DEFLATE_BUFFER_SIZE`] bytes have accumulated, ending each piece with a sync flush.
+//! Matches reach back into the 32 KiB before each piece, except after `Z_FULL_FLUSH`.
+//! The window is always 32 KiB, whatever `windowBits` asks for. Preset dictionaries,
+//! `deflateParams` and `inflateSync` are not supported.
+
+#![allow(non_snake_case)]
+
+use crate::common::{
+    DEFLATE_MAX_MATCH_LEN, GZIP_CM_DEFLATE, GZIP_FCOMMENT, GZIP_FEXTRA, GZIP_FHCRC, GZIP_FNAME,
+    GZIP_FOOTER_SIZE, GZIP_FRESERVED, GZIP_ID1, GZIP_ID2, GZIP_MIN_HEADER_SIZE, GZIP_MIN_OVERHEAD,
+    ZLIB_CM_DEFLATE, ZLIB_FOOTER_SIZE, ZLIB_MIN_HEADER_SIZE, ZLIB_MIN_OVERHEAD,
+};
+use crate::compress::{CompressResult, Compressor, FlushMode, MAX_COMPRESSION_LEVEL};
+use crate::decompress::{DecompressResult, Decompressor, DecompressorState};
+use std::ffi::{CStr, c_char, c_int, c_uint, c_ulong, c_void};
+use std::mem::{MaybeUninit, align_of, size_of};
+use std::ptr;
+
+pub const Z_NO_FLUSH: c_int = 0;
+pub const Z_PARTIAL_FLUSH: c_int = 1;
+pub const Z_SYNC_FLUSH: c_int = 2;
+pub const Z_FULL_FLUSH: c_int = 3;
+pub const Z_FINISH: c_int = 4;
+pub const Z_BLOCK: c_int = 5;
+pub const Z_TREES: c_int = 6;
+
+pub const Z_OK: c_int = 0;
+pub const Z_STREAM_END: c_int = 1;
+pub const Z_NEED_DICT: c_int = 2;
+pub const Z_ERRNO: c_int = -1;
+pub const Z_STREAM_ERROR: c_int = -2;
+pub const Z_DATA_ERROR: c_int = -3;
+pub const Z_MEM_ERROR: c_int = -4;
+pub const Z_BUF_ERROR: c_int = -5;
+pub const Z_VERSION_ERROR: c_int = -6;
+
+pub const Z_DEFAULT_COMPRESSION: c_int = -1;
+pub const Z_DEFLATED: c_int = 8;
+
+/// The zlib release whose API this module implements.
+pub const ZLIB_VERSION: &CStr = c"1.3.1";
+
+/// Input [`deflate`] collects before compressing it without being asked to flush.
+pub const DEFLATE_BUFFER_SIZE: usize = 1024 * 1024;
+
+const WINDOW_SIZE: usize = 32 * 1024;
+
+pub type AllocFunc = unsafe extern "C" fn(*mut c_void, c_uint, c_uint) -> *mut c_void;
+pub type FreeFunc = unsafe extern "C" fn(*mut c_void, *mut c_void);
+
+/// `z_stream`, laid out as in `zlib.h`.
+#[repr(C)]
+pub struct ZStream {
+    pub next_in: *const u8,
+    pub avail_in: c_uint,
+    pub total_in: c_ulong,
+    pub next_out: *mut u8,
+    pub avail_out: c_uint,
+    pub total_out: c_ulong,
+    pub msg: *const c_char,
+    pub state: *mut c_void,
+    pub zalloc: Option<AllocFunc>,
+    pub zfree: Option<FreeFunc>,
+    pub opaque: *mut c_void,
+    pub data_type: c_int,
+    pub adler: c_ulong,
+    pub reserved: c_ulong,
+}
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+unsafe extern "C" fn default_alloc(
+    _opaque: *mut c_void,
+    items: c_uint,
+    size: c_uint,
+) -> *mut c_void {
+    match (items as usize).checked_mul(size as usize) {
+        Some(bytes) => malloc(bytes),
+        None => ptr::null_mut(),
+    }
+}
+
+unsafe extern "C" fn default_free(_opaque: *mut c_void, address: *mut c_void) {
+    free(address)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Wrapper {
+    Raw,
+    Zlib,
+    Gzip,
+    /// zlib or gzip, whichever the header turns out to be.
+    Auto,
+}
+
+impl Wrapper {
+    /// Splits zlib's `windowBits` convention: 8 to 15 for zlib, negated for raw deflate,
+    /// plus 16 for gzip or plus 32 to detect zlib or gzip.
+    fn from_window_bits(window_bits: c_int, inflate: bool) -> Option<Self> {
+        match window_bits {
+            -15..=-8 => Some(Wrapper::Raw),
+            // 0 takes the window size from the zlib header.
+            0 if inflate => Some(Wrapper::Zlib),
+            8..=15 => Some(Wrapper::Zlib),
+            24..=31 => Some(Wrapper::Gzip),
+            40..=47 if inflate => Some(Wrapper::Auto),
+            _ => None,
+        }
+    }
+
+    fn initial_checksum(self) -> u32 {
+        match self {
+            Wrapper::Zlib | Wrapper::Auto => 1,
+            Wrapper::Raw | Wrapper::Gzip => 0,
+        }
+    }
+
+    fn update_checksum(self, checksum: u32, data: &[u8]) -> u32 {
+        match self {
+            Wrapper::Zlib => crate::adler32(checksum, data),
+            Wrapper::Gzip => crate::crc32(checksum, data),
+            Wrapper::Raw | Wrapper::Auto => checksum,
+        }
+    }
+
+    fn trailer_len(self) -> usize {
+        match self {
+            Wrapper::Zlib => ZLIB_FOOTER_SIZE,
+            Wrapper::Gzip => GZIP_FOOTER_SIZE,
+            Wrapper::Raw | Wrapper::Auto => 0,
+        }
+    }
+}
+
+/// Allocates `value` with the stream's allocator, installing zlib's defaults if unset.
+unsafe fn alloc_state<T>(strm: &mut ZStream, value: T) -> *mut T {
+    const { assert!(align_of::<T>() <= align_of::<u64>()) };
+    let zalloc = *strm.zalloc.get_or_insert(default_alloc);
+    strm.zfree.get_or_insert(default_free);
+    let p = zalloc(strm.opaque, 1, size_of::<T>() as c_uint) as *mut T;
+    if !p.is_null() {
+        p.write(value);
+    }
+    p
+}
+
+unsafe fn free_state<T>(strm: &mut ZStream) {
+    let state = strm.state as *mut T;
+    ptr::drop_in_place(state);
+    if let Some(zfree) = strm.zfree {
+        zfree(strm.opaque, state as *mut c_void);
+    }
+    strm.state = ptr::null_mut();
+}
+
+fn check_version(version: *const c_char, stream_size: c_int) -> bool {
+    !version.is_null()
+        && unsafe { *version } as u8 == b'1'
+        && stream_size as usize == size_of::<ZStream>()
+}
+
+fn level_from_zlib(level: c_int) -> Option<usize> {
+    match level {
+        Z_DEFAULT_COMPRESSION => Some(6),
+        0.. if level as usize <= MAX_COMPRESSION_LEVEL => Some(level as usize),
+        _ => None,
+    }
+}
+
+unsafe fn input_slice<'a>(data: *const u8, len: usize) -> &'a [u8] {
+    if len == 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(data, len)
+    }
+}
+
+/// Hands out the start of `next_in`.
+unsafe fn take_input<'a>(strm: &mut ZStream, len: usize) -> &'a [u8] {
+    let data = input_slice(strm.next_in, len);
+    strm.next_in = strm.next_in.add(len);
+    strm.avail_in -= len as c_uint;
+    strm.total_in = strm.total_in.wrapping_add(len as c_ulong);
+    data
+}
+
+/// Copies as much of `data` as fits into `next_out` and returns how much that was.
+unsafe fn put_output(strm: &mut ZStream, data: &[u8]) -> usize {
+    let len = data.len().min(strm.avail_out as usize);
+    if len > 0 {
+        ptr::copy_nonoverlapping(data.as_ptr(), strm.next_out, len);
+        strm.next_out = strm.next_out.add(len);
+        strm.avail_out -= len as c_uint;
+        strm.total_out = strm.total_out.wrapping_add(len as c_ulong);
+    }
+    len
+}
+
+struct DeflateState {
+    compressor: Compressor,
+    wrapper: Wrapper,
+    /// The last window of compressed input, as history, followed by the input to compress.
+    input: Vec<u8>,
+    /// Length of the history at the start of `input`.
+    history: usize,
+    /// Compressed bytes not yet copied to `next_out`.
+    pending: Vec<u8>,
+    pending_pos: usize,
+    /// Whether `input` has data that no flush has covered yet.
+    unflushed: bool,
+    started: bool,
+    finished: bool,
+    checksum: u32,
+    total_len: u32,
+}
+
+impl DeflateState {
+    fn new(level: usize, wrapper: Wrapper) -> Self {
+        Self {
+            compressor: Compressor::new(level),
+            wrapper,
+            input: Vec::new(),
+            history: 0,
+            pending: Vec::new(),
+            pending_pos: 0,
+            unflushed: false,
+            started: false,
+            finished: false,
+            checksum: wrapper.initial_checksum(),
+            total_len: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.input.clear();
+        self.history = 0;
+        self.pending.clear();
+        self.pending_pos = 0;
+        self.unflushed = false;
+        self.started = false;
+        self.finished = false;
+        self.checksum = self.wrapper.initial_checksum();
+        self.total_len = 0;
+    }
+
+    /// Input buffered but not compressed yet.
+    fn buffered(&self) -> usize {
+        self.input.len() - self.history
+    }
+
+    /// Compresses the buffered input into `pending`, after the header if this is the first
+    /// piece and before the trailer if it is the last. Unless `full`, the window of input
+    /// before the piece stays available to the next piece's matches.
+    fn compress_input(&mut self, finish: bool, full: bool) -> bool {
+        self.pending.drain(..self.pending_pos);
+        self.pending_pos = 0;
+        if !self.started {
+            match self.wrapper {
+                Wrapper::Zlib => {
+                    let header = self.compressor.zlib_header();
+                    self.pending.extend_from_slice(&header);
+                }
+                Wrapper::Gzip => {
+                    let header = self.compressor.gzip_fixed_header(0, false);
+                    self.pending.extend_from_slice(&header);
+                }
+                Wrapper::Raw | Wrapper::Auto => {}
+            }
+            self.started = true;
+        }
+
+        // A sync flush appends an empty stored block.
+        let bound = self.compressor.deflate_bound(self.buffered()) + 5;
+        if self.pending.try_reserve(bound).is_err() {
+            return false;
+        }
+        let start = self.pending.len();
+        let out = &mut self.pending.spare_capacity_mut()[..bound];
+        let mode = if finish {
+            FlushMode::Finish
+        } else {
+            FlushMode::Sync
+        };
+        let (res, size, _) = self
+            .compressor
+            .compress_after(&self.input, self.history, out, mode);
+        if res != CompressResult::Success {
+            return false;
+        }
+        // SAFETY: the compressor initialized `size` bytes after the old length.
+        unsafe { self.pending.set_len(start + size) };
+        let keep = if finish || full {
+            0
+        } else {
+            self.input.len().min(WINDOW_SIZE)
+        };
+        self.input.drain(..self.input.len() - keep);
+        self.history = keep;
+        self.unflushed = false;
+
+        if finish {
+            match self.wrapper {
+                Wrapper::Zlib => self.pending.extend_from_slice(&self.checksum.to_be_bytes()),
+                Wrapper::Gzip => {
+                    self.pending.extend_from_slice(&self.checksum.to_le_bytes());
+                    self.pending
+                        .extend_from_slice(&self.total_len.to_le_bytes());
+                }
+                Wrapper::Raw | Wrapper::Auto => {}
+            }
+            self.finished = true;
+        }
+        true
+    }
+
+    fn bound(&self, source_len: usize) -> usize {
+        let overhead = match self.wrapper {
+            Wrapper::Zlib => ZLIB_MIN_OVERHEAD,
+            Wrapper::Gzip => GZIP_MIN_OVERHEAD,
+            Wrapper::Raw | Wrapper::Auto => 0,
+        };
+        let pieces = source_len / DEFLATE_BUFFER_SIZE + 1;
+        self.compressor
+            .deflate_bound(source_len)
+            .saturating_add(pieces * 5)
+            .saturating_add(overhead)
+    }
+}
+
+unsafe fn deflate_state<'a>(strm: *mut ZStream) -> Option<(&'a mut ZStream, &'a mut DeflateState)> {
+    let strm = strm.as_mut()?;
+    let state = (strm.state as *mut DeflateState).as_mut()?;
+    Some((strm, state))
+}
+
+/// # Safety
+///
+/// `strm` must point to a `z_stream`; `version` must be null or a C string.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflateInit_(
+    strm: *mut ZStream,
+    level: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    deflateInit2_(strm, level, Z_DEFLATED, 15, 8, 0, version, stream_size)
+}
+
+/// Starts a compression stream. `window_bits` selects the wrapper as in zlib; `mem_level`
+/// is checked but otherwise ignored, and so is `strategy`.
+///
+/// # Safety
+///
+/// As for [`deflateInit_`].
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflateInit2_(
+    strm: *mut ZStream,
+    level: c_int,
+    method: c_int,
+    window_bits: c_int,
+    mem_level: c_int,
+    strategy: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    if !check_version(version, stream_size) {
+        return Z_VERSION_ERROR;
+    }
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    strm.msg = ptr::null();
+    let (Some(level), Some(wrapper)) = (
+        level_from_zlib(level),
+        Wrapper::from_window_bits(window_bits, false),
+    ) else {
+        return Z_STREAM_ERROR;
+    };
+    if method != Z_DEFLATED || !(1..=9).contains(&mem_level) || !(0..=4).contains(&strategy) {
+        return Z_STREAM_ERROR;
+    }
+    let state = alloc_state(strm, DeflateState::new(level, wrapper));
+    if state.is_null() {
+        return Z_MEM_ERROR;
+    }
+    strm.state = state as *mut c_void;
+    strm.total_in = 0;
+    strm.total_out = 0;
+    strm.adler = wrapper.initial_checksum() as c_ulong;
+    Z_OK
+}
+
+/// Compresses as much input as possible and writes as much output as fits.
+///
+/// # Safety
+///
+/// `strm` must have been set up by [`deflateInit2_`], with `next_in` and `next_out` valid
+/// for `avail_in` and `avail_out` bytes.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflate(strm: *mut ZStream, flush: c_int) -> c_int {
+    let Some((strm, state)) = deflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    if !(Z_NO_FLUSH..=Z_BLOCK).contains(&flush)
+        || strm.next_out.is_null()
+        || (strm.next_in.is_null() && strm.avail_in != 0)
+        || (state.finished && (strm.avail_in != 0 || flush != Z_FINISH))
+    {
+        return Z_STREAM_ERROR;
+    }
+    let (avail_in, avail_out) = (strm.avail_in, strm.avail_out);
+    let mut flushed = false;
+
+    loop {
+        let copied = put_output(strm, &state.pending[state.pending_pos..]);
+        state.pending_pos += copied;
+        if state.pending_pos < state.pending.len() || state.finished {
+            break;
+        }
+
+        let take = (strm.avail_in as usize).min(DEFLATE_BUFFER_SIZE - state.buffered());
+        if take > 0 {
+            let data = take_input(strm, take);
+            state.checksum = state.wrapper.update_checksum(state.checksum, data);
+            state.total_len = state.total_len.wrapping_add(take as u32);
+            state.input.extend_from_slice(data);
+            state.unflushed = true;
+        }
+
+        // A full flush with nothing new to compress only has to forget the history.
+        if strm.avail_in == 0 && flush == Z_FULL_FLUSH && !state.unflushed {
+            state.input.clear();
+            state.history = 0;
+        }
+
+        let compressed = if state.buffered() == DEFLATE_BUFFER_SIZE {
+            state.compress_input(false, false)
+        } else if strm.avail_in == 0 && flush == Z_FINISH {
+            state.compress_input(true, false)
+        } else if strm.avail_in == 0 && flush != Z_NO_FLUSH && state.unflushed && !flushed {
+            flushed = true;
+            state.compress_input(false, flush == Z_FULL_FLUSH)
+        } else {
+            break;
+        };
+        if !compressed {
+            strm.msg = c"compression failed".as_ptr();
+            return Z_STREAM_ERROR;
+        }
+    }
+
+    strm.adler = state.checksum as c_ulong;
+    if state.finished && state.pending_pos == state.pending.len() {
+        Z_STREAM_END
+    } else if strm.avail_in == avail_in && strm.avail_out == avail_out {
+        Z_BUF_ERROR
+    } else {
+        Z_OK
+    }
+}
+
+/// # Safety
+///
+/// `strm` must be null or have been set up by [`deflateInit2_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflateReset(strm: *mut ZStream) -> c_int {
+    let Some((strm, state)) = deflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    state.reset();
+    strm.total_in = 0;
+    strm.total_out = 0;
+    strm.msg = ptr::null();
+    strm.adler = state.checksum as c_ulong;
+    Z_OK
+}
+
+/// The most bytes that compressing `source_len` bytes in one [`deflate`] call with
+/// [`Z_FINISH`] can produce.
+///
+/// # Safety
+///
+/// `strm` must be null or have been set up by [`deflateInit2_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflateBound(strm: *mut ZStream, source_len: c_ulong) -> c_ulong {
+    match deflate_state(strm) {
+        Some((_, state)) => state.bound(source_len as usize) as c_ulong,
+        None => compressBound(source_len),
+    }
+}
+
+/// Frees the stream. Returns [`Z_DATA_ERROR`] if input or output was still pending.
+///
+/// # Safety
+///
+/// `strm` must be null or have been set up by [`deflateInit2_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn deflateEnd(strm: *mut ZStream) -> c_int {
+    let Some((strm, state)) = deflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    let discarded = !state.input.is_empty() || state.pending_pos < state.pending.len();
+    free_state::<DeflateState>(strm);
+    if discarded { Z_DATA_ERROR } else { Z_OK }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum InflatePhase {
+    Header,
+    Body,
+    Trailer,
+    Done,
+}
+
+struct InflateState {
+    decompressor: Decompressor,
+    wrapper: Wrapper,
+    configured: Wrapper,
+    phase: InflatePhase,
+    /// Header or trailer bytes gathered so far.
+    wrapper_bytes: Vec<u8>,
+    /// Input the decompressor handed back because it ended in the middle of a symbol, or,
+    /// once the stream is done, bytes it read past the end.
+    carry: Vec<u8>,
+    needs_input: bool,
+    window: Vec<u8>,
+    read_pos: usize,
+    write_pos: usize,
+    checksum: u32,
+    total_len: u32,
+}
+
+/// Result of looking at the start of a stream's header.
+enum HeaderStatus {
+    Complete,
+    /// The header is at least this long.
+    NeedMore(usize),
+    Invalid(&'static CStr),
+}
+
+fn parse_zlib_header(bytes: &[u8]) -> HeaderStatus {
+    if bytes.len() < ZLIB_MIN_HEADER_SIZE {
+        return HeaderStatus::NeedMore(ZLIB_MIN_HEADER_SIZE);
+    }
+    let (cmf, flg) = (bytes[0], bytes[1]);
+    if !(cmf as u16 * 256 + flg as u16).is_multiple_of(31) {
+        HeaderStatus::Invalid(c"incorrect header check")
+    } else if cmf & 0x0F != ZLIB_CM_DEFLATE {
+        HeaderStatus::Invalid(c"unknown compression method")
+    } else if cmf >> 4 > 7 {
+        HeaderStatus::Invalid(c"invalid window size")
+    } else if flg & 0x20 != 0 {
+        HeaderStatus::Invalid(c"preset dictionaries are not supported")
+    } else {
+        HeaderStatus::Complete
+    }
+}
+
+fn parse_gzip_header(bytes: &[u8]) -> HeaderStatus {
+    let expected = [GZIP_ID1, GZIP_ID2, GZIP_CM_DEFLATE];
+    if bytes.iter().zip(&expected).any(|(a, b)| a != b) {
+        return HeaderStatus::Invalid(c"incorrect header check");
+    }
+    if bytes.len() < GZIP_MIN_HEADER_SIZE {
+        return HeaderStatus::NeedMore(GZIP_MIN_HEADER_SIZE);
+    }
+    let flags = bytes[3];
+    if flags & GZIP_FRESERVED != 0 {
+        return HeaderStatus::Invalid(c"unknown header flags set");
+    }
+    let mut len = GZIP_MIN_HEADER_SIZE;
+    if flags & GZIP_FEXTRA != 0 {
+        if bytes.len() < len + 2 {
+            return HeaderStatus::NeedMore(len + 2);
+        }
+        len += 2 + u16::from_le_bytes([bytes[len], bytes[len + 1]]) as usize;
+    }
+    for flag in [GZIP_FNAME, GZIP_FCOMMENT] {
+        if flags & flag != 0 {
+            match bytes
+                .get(len..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0))
+            {
+                Some(nul) => len += nul + 1,
+                None => return HeaderStatus::NeedMore(bytes.len().max(len) + 1),
+            }
+        }
+    }
+    if flags & GZIP_FHCRC != 0 {
+        len += 2;
+    }
+    if bytes.len() < len {
+        HeaderStatus::NeedMore(len)
+    } else {
+        HeaderStatus::Complete
+    }
+}
+
+impl InflateState {
+    fn new(wrapper: Wrapper) -> Self {
+        Self {
+            decompressor: Decompressor::new(),
+            wrapper,
+            configured: wrapper,
+            phase: if wrapper == Wrapper::Raw {
+                InflatePhase::Body
+            } else {
+                InflatePhase::Header
+            },
+            wrapper_bytes: Vec::new(),
+            carry: Vec::new(),
+            needs_input: false,
+            window: vec![0; 2 * WINDOW_SIZE + DEFLATE_MAX_MATCH_LEN],
+            read_pos: 0,
+            write_pos: 0,
+            checksum: wrapper.initial_checksum(),
+            total_len: 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        let decompressor = std::mem::take(&mut self.decompressor);
+        let window = std::mem::take(&mut self.window);
+        *self = Self {
+            decompressor,
+            window,
+            ..Self::new(self.configured)
+        };
+        self.decompressor.state = DecompressorState::Start;
+        self.decompressor.is_final_block = false;
+        self.decompressor.bitbuf = 0;
+        self.decompressor.bitsleft = 0;
+    }
+
+    /// Takes one byte of wrapper data, from the carried input first.
+    unsafe fn take_wrapper_byte(&mut self, strm: &mut ZStream) -> bool {
+        if !self.carry.is_empty() {
+            self.wrapper_bytes.push(self.carry.remove(0));
+            true
+        } else if strm.avail_in > 0 {
+            self.wrapper_bytes.push(take_input(strm, 1)[0]);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Gathers and checks the header; `Ok(false)` means more input is needed.
+    unsafe fn read_header(&mut self, strm: &mut ZStream) -> Result<bool, &'static CStr> {
+        loop {
+            if self.wrapper == Wrapper::Auto && !self.wrapper_bytes.is_empty() {
+                self.wrapper = if self.wrapper_bytes[0] == GZIP_ID1 {
+                    Wrapper::Gzip
+                } else {
+                    Wrapper::Zlib
+                };
+                self.checksum = self.wrapper.initial_checksum();
+            }
+            let status = match self.wrapper {
+                Wrapper::Gzip => parse_gzip_header(&self.wrapper_bytes),
+                Wrapper::Zlib => parse_zlib_header(&self.wrapper_bytes),
+                Wrapper::Raw | Wrapper::Auto => HeaderStatus::NeedMore(1),
+            };
+            match status {
+                HeaderStatus::Complete => {
+                    self.wrapper_bytes.clear();
+                    self.phase = InflatePhase::Body;
+                    return Ok(true);
+                }
+                HeaderStatus::Invalid(msg) => return Err(msg),
+                HeaderStatus::NeedMore(len) => {
+                    while self.wrapper_bytes.len() < len {
+                        if !self.take_wrapper_byte(strm) {
+                            return Ok(false);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs the decompressor over the carried input and the start of `next_in`.
+    unsafe fn decode(&mut self, strm: &mut ZStream) -> Result<(), &'static CStr> {
+        if self.write_pos + DEFLATE_MAX_MATCH_LEN > self.window.len() {
+            // Everything has been copied out, so only the match history has to stay.
+            let shift = self.write_pos - WINDOW_SIZE;
+            self.window.copy_within(shift..self.write_pos, 0);
+            self.write_pos -= shift;
+            self.read_pos -= shift;
+        }
+
+        let carried = self.carry.len();
+        let fresh = if carried == 0 {
+            strm.avail_in as usize
+        } else {
+            (strm.avail_in as usize).min(4096)
+        };
+        let next_in = input_slice(strm.next_in, fresh);
+        let combined;
+        let input = if carried == 0 {
+            next_in
+        } else {
+            combined = [&self.carry[..], next_in].concat();
+            &combined[..]
+        };
+
+        let (res, mut consumed, _) =
+            self.decompressor
+                .decompress_streaming(input, &mut self.window, &mut self.write_pos);
+        let done = self.decompressor.state == DecompressorState::Done;
+        if done {
+            // Whole bytes left in the bit buffer belong to whatever follows the stream.
+            consumed -= (self.decompressor.bitsleft / 8) as usize;
+        }
+        let rest = input[consumed..].to_vec();
+        self.carry.clear();
+        let from_next = consumed.saturating_sub(carried);
+        take_input(strm, from_next);
+
+        if done {
+            // Bytes past the end that came from the carry stay with us; the rest stays in
+            // `next_in`.
+            let carried_rest = carried.saturating_sub(consumed);
+            self.carry.extend_from_slice(&rest[..carried_rest]);
+            self.phase = InflatePhase::Trailer;
+            return Ok(());
+        }
+        match res {
+            DecompressResult::BadData => Err(c"invalid deflate data"),
+            DecompressResult::ShortInput => {
+                // Keep the unfinished symbol and wait for more input.
+                self.carry = rest;
+                take_input(strm, fresh - from_next);
+                self.needs_input = true;
+                Ok(())
+            }
+            _ => {
+                self.carry
+                    .extend_from_slice(&rest[..carried.saturating_sub(consumed)]);
+                Ok(())
+            }
+        }
+    }
+
+    /// Reads and checks the trailer; `Ok(false)` means more input is needed.
+    unsafe fn read_trailer(&mut self, strm: &mut ZStream) -> Result<bool, &'static CStr> {
+        let len = self.wrapper.trailer_len();
+        while self.wrapper_bytes.len() < len {
+            if !self.take_wrapper_byte(strm) {
+                return Ok(false);
+            }
+        }
+        let trailer = &self.wrapper_bytes;
+        let valid = match self.wrapper {
+            Wrapper::Zlib => trailer[..4] == self.checksum.to_be_bytes(),
+            Wrapper::Gzip => {
+                if trailer[..4] != self.checksum.to_le_bytes() {
+                    false
+                } else if trailer[4..8] != self.total_len.to_le_bytes() {
+                    return Err(c"incorrect length check");
+                } else {
+                    true
+                }
+            }
+            Wrapper::Raw | Wrapper::Auto => true,
+        };
+        if !valid {
+            return Err(c"incorrect data check");
+        }
+        self.phase = InflatePhase::Done;
+        Ok(true)
+    }
+}
+
+unsafe fn inflate_state<'a>(strm: *mut ZStream) -> Option<(&'a mut ZStream, &'a mut InflateState)> {
+    let strm = strm.as_mut()?;
+    let state = (strm.state as *mut InflateState).as_mut()?;
+    Some((strm, state))
+}
+
+/// # Safety
+///
+/// As for [`deflateInit_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn inflateInit_(
+    strm: *mut ZStream,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    inflateInit2_(strm, 15, version, stream_size)
+}
+
+/// Starts a decompression stream; `window_bits` selects raw, zlib, gzip or automatic
+/// zlib/gzip detection as in zlib.
+///
+/// # Safety
+///
+/// As for [`deflateInit_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn inflateInit2_(
+    strm: *mut ZStream,
+    window_bits: c_int,
+    version: *const c_char,
+    stream_size: c_int,
+) -> c_int {
+    if !check_version(version, stream_size) {
+        return Z_VERSION_ERROR;
+    }
+    let Some(strm) = strm.as_mut() else {
+        return Z_STREAM_ERROR;
+    };
+    strm.msg = ptr::null();
+    let Some(wrapper) = Wrapper::from_window_bits(window_bits, true) else {
+        return Z_STREAM_ERROR;
+    };
+    let state = alloc_state(strm, InflateState::new(wrapper));
+    if state.is_null() {
+        return Z_MEM_ERROR;
+    }
+    strm.state = state as *mut c_void;
+    strm.total_in = 0;
+    strm.total_out = 0;
+    strm.adler = wrapper.initial_checksum() as c_ulong;
+    Z_OK
+}
+
+/// Decompresses as much input as possible and writes as much output as fits. Returns
+/// [`Z_STREAM_END`] once the whole stream, trailer included, has been read and written out;
+/// any input after it is left in `next_in`.
+///
+/// # Safety
+///
+/// `strm` must have been set up by [`inflateInit2_`], with `next_in` and `next_out` valid
+/// for `avail_in` and `avail_out` bytes.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn inflate(strm: *mut ZStream, flush: c_int) -> c_int {
+    let Some((strm, state)) = inflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    if !(Z_NO_FLUSH..=Z_TREES).contains(&flush)
+        || strm.next_out.is_null()
+        || (strm.next_in.is_null() && strm.avail_in != 0)
+    {
+        return Z_STREAM_ERROR;
+    }
+    let (avail_in, avail_out) = (strm.avail_in, strm.avail_out);
+
+    loop {
+        let produced = &state.window[state.read_pos..state.write_pos];
+        let copied = put_output(strm, produced);
+        state.checksum = state
+            .wrapper
+            .update_checksum(state.checksum, &produced[..copied]);
+        state.total_len = state.total_len.wrapping_add(copied as u32);
+        state.read_pos += copied;
+        if state.read_pos < state.write_pos {
+            break;
+        }
+
+        let step = match state.phase {
+            InflatePhase::Header => state.read_header(strm),
+            InflatePhase::Body => {
+                if state.needs_input && strm.avail_in == 0 {
+                    Ok(false)
+                } else {
+                    state.needs_input = false;
+                    state.decode(strm).map(|()| true)
+                }
+            }
+            InflatePhase::Trailer => state.read_trailer(strm),
+            InflatePhase::Done => Ok(false),
+        };
+        match step {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(msg) => {
+                strm.msg = msg.as_ptr();
+                return Z_DATA_ERROR;
+            }
+        }
+    }
+
+    strm.adler = state.checksum as c_ulong;
+    if state.phase == InflatePhase::Done {
+        Z_STREAM_END
+    } else if strm.avail_in == avail_in && strm.avail_out == avail_out {
+        Z_BUF_ERROR
+    } else {
+        Z_OK
+    }
+}
+
+/// # Safety
+///
+/// `strm` must be null or have been set up by [`inflateInit2_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn inflateReset(strm: *mut ZStream) -> c_int {
+    let Some((strm, state)) = inflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    state.reset();
+    strm.total_in = 0;
+    strm.total_out = 0;
+    strm.msg = ptr::null();
+    strm.adler = state.checksum as c_ulong;
+    Z_OK
+}
+
+/// # Safety
+///
+/// `strm` must be null or have been set up by [`inflateInit2_`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn inflateEnd(strm: *mut ZStream) -> c_int {
+    let Some((strm, _)) = inflate_state(strm) else {
+        return Z_STREAM_ERROR;
+    };
+    free_state::<InflateState>(strm);
+    Z_OK
+}
+
+/// # Safety
+///
+/// `dest` must be valid for `*dest_len` bytes and `source` for `source_len` bytes.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn compress(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+) -> c_int {
+    compress2(dest, dest_len, source, source_len, Z_DEFAULT_COMPRESSION)
+}
+
+/// Compresses `source` to a zlib stream in `dest` and stores its size in `*dest_len`.
+/// Returns [`Z_BUF_ERROR`] if it does not fit and [`Z_MEM_ERROR`] if memory runs out.
+///
+/// # Safety
+///
+/// As for [`compress`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn compress2(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+    level: c_int,
+) -> c_int {
+    let Some(level) = level_from_zlib(level) else {
+        return Z_STREAM_ERROR;
+    };
+    let input = input_slice(source, source_len as usize);
+    let output: &mut [MaybeUninit<u8>] = if *dest_len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(dest as *mut MaybeUninit<u8>, *dest_len as usize)
+    };
+    match Compressor::new(level).compress_zlib(input, output) {
+        (CompressResult::Success, size) => {
+            *dest_len = size as c_ulong;
+            Z_OK
+        }
+        (CompressResult::OutOfMemory, _) => Z_MEM_ERROR,
+        (CompressResult::InsufficientSpace, _) => Z_BUF_ERROR,
+    }
+}
+
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub extern "C" fn compressBound(source_len: c_ulong) -> c_ulong {
+    Compressor::zlib_compress_bound(source_len as usize) as c_ulong
+}
+
+/// Decompresses the zlib stream in `source` into `dest` and stores its size in
+/// `*dest_len`. Returns [`Z_BUF_ERROR`] if it does not fit and [`Z_DATA_ERROR`] if the
+/// data is corrupt or incomplete.
+///
+/// # Safety
+///
+/// As for [`compress`].
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn uncompress(
+    dest: *mut u8,
+    dest_len: *mut c_ulong,
+    source: *const u8,
+    source_len: c_ulong,
+) -> c_int {
+    let input = input_slice(source, source_len as usize);
+    let output: &mut [MaybeUninit<u8>] = if *dest_len == 0 {
+        &mut []
+    } else {
+        std::slice::from_raw_parts_mut(dest as *mut MaybeUninit<u8>, *dest_len as usize)
+    };
+    match Decompressor::new().decompress_zlib_uninit(input, output) {
+        (DecompressResult::Success, _, size) => {
+            *dest_len = size as c_ulong;
+            Z_OK
+        }
+        (DecompressResult::InsufficientSpace, _, _) => Z_BUF_ERROR,
+        _ => Z_DATA_ERROR,
+    }
+}
+
+/// Updates a CRC-32; a null `buf` returns the initial value, 0.
+///
+/// # Safety
+///
+/// `buf` must be null or valid for `len` bytes.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn crc32(crc: c_ulong, buf: *const u8, len: c_uint) -> c_ulong {
+    if buf.is_null() {
+        return 0;
+    }
+    crate::crc32(crc as u32, input_slice(buf, len as usize)) as c_ulong
+}
+
+/// Updates an Adler-32 checksum; a null `buf` returns the initial value, 1.
+///
+/// # Safety
+///
+/// `buf` must be null or valid for `len` bytes.
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub unsafe extern "C" fn adler32(adler: c_ulong, buf: *const u8, len: c_uint) -> c_ulong {
+    if buf.is_null() {
+        return 1;
+    }
+    crate::adler32(adler as u32, input_slice(buf, len as usize)) as c_ulong
+}
+
+#[cfg_attr(feature = "zlib-compat", unsafe(no_mangle))]
+pub extern "C" fn zlibVersion() -> *const c_char {
+    ZLIB_VERSION.as_ptr()
+}
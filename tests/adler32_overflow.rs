@@ -1,3 +1,7 @@
+// Checks against libdeflater, which links the C libdeflate; its symbols clash with the
+// `capi` exports.
+#![cfg(not(feature = "capi"))]
+
 use libdeflate;
 use libdeflater;
 
@@ -25,3 +25,20 @@ fn test_adler32_large_chunks() {
         "Failed at large size"
     );
 }
+
+#[test]
+fn test_adler32_simd_tails_vs_generic() {
+    let data: Vec<u8> = (0..10_000u32).map(|i| (i * 7 + (i >> 5)) as u8).collect();
+    for len in (0..300).chain([2048, 2049, 4095, 4096, 4097, 9000]) {
+        for offset in 0..3 {
+            let slice = &data[offset..offset + len];
+            assert_eq!(
+                libdeflate::adler32(1, slice),
+                adler32_generic(1, slice),
+                "len {} offset {}",
+                len,
+                offset
+            );
+        }
+    }
+}
@@ -0,0 +1,237 @@
+use libdeflate::allocator::{Allocator, CallbackAllocator, Global};
+use libdeflate::compress::{CompressResult, Compressor, FlushMode, Parallelism};
+use libdeflate::decompress::{DecompressResult, Decompressor};
+use std::alloc::Layout;
+use std::ffi::c_void;
+use std::io;
+use std::mem::{MaybeUninit, size_of};
+use std::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+/// Counts what passes through to the global allocator.
+#[derive(Default)]
+struct Counting {
+    requested: AtomicUsize,
+    live: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl Allocator for Counting {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        self.requested.fetch_add(layout.size(), Ordering::SeqCst);
+        let live = self.live.fetch_add(layout.size(), Ordering::SeqCst) + layout.size();
+        self.peak.fetch_max(live, Ordering::SeqCst);
+        Global.allocate(layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        self.live.fetch_sub(layout.size(), Ordering::SeqCst);
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+/// Hands out at most `budget` bytes in all, like a fixed arena.
+struct Limited {
+    budget: AtomicUsize,
+}
+
+impl Limited {
+    fn new(budget: usize) -> Self {
+        Self {
+            budget: AtomicUsize::new(budget),
+        }
+    }
+}
+
+impl Allocator for Limited {
+    fn allocate(&self, layout: Layout) -> *mut u8 {
+        let size = layout.size();
+        match self
+            .budget
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |b| b.checked_sub(size))
+        {
+            Ok(_) => Global.allocate(layout),
+            Err(_) => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn deallocate(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { Global.deallocate(ptr, layout) }
+    }
+}
+
+fn try_compress(compressor: &mut Compressor, data: &[u8]) -> (CompressResult, Vec<u8>) {
+    let mut out = vec![MaybeUninit::uninit(); compressor.deflate_bound(data.len())];
+    let (res, size, _) = compressor.compress(data, &mut out, FlushMode::Finish);
+    let out = out[..size]
+        .iter()
+        .map(|b| unsafe { b.assume_init() })
+        .collect();
+    (res, out)
+}
+
+fn compress(compressor: &mut Compressor, data: &[u8]) -> Vec<u8> {
+    let (res, out) = try_compress(compressor, data);
+    assert_eq!(res, CompressResult::Success);
+    out
+}
+
+#[test]
+fn test_compressor_allocator_footprint() {
+    // Long matches, then data that only stored or Huffman-only blocks suit.
+    let mut data = periodic_bytes(700_000);
+    data.extend(random_bytes(400_000, 1));
+    data.extend(random_bytes(400_000, 2).iter().map(|b| b % 64));
+    for level in [0, 1, 2, 6, 9, 10, 12, 13] {
+        let counting = Arc::new(Counting::default());
+        let mut compressor = Compressor::with_allocator(level, counting.clone());
+        compressor.set_parallelism(Parallelism::single_threaded());
+        let footprint = Compressor::memory_footprint(level) - size_of::<Compressor>();
+        assert_eq!(
+            counting.requested.load(Ordering::SeqCst),
+            footprint,
+            "level {level}"
+        );
+
+        let mut plain = Compressor::new(level);
+        plain.set_parallelism(Parallelism::single_threaded());
+        let expected = compress(&mut plain, &data);
+        assert!(
+            compress(&mut compressor, &data) == expected,
+            "level {level}"
+        );
+
+        // Below level 13 the footprint is exact; above, the match cache comes on top.
+        let peak = counting.peak.load(Ordering::SeqCst);
+        if level < 13 {
+            assert_eq!(counting.requested.load(Ordering::SeqCst), footprint);
+        } else {
+            assert!(peak > footprint, "level {level}");
+            assert!(
+                peak <= footprint + Compressor::match_cache_footprint(level),
+                "level {level}: {peak}"
+            );
+        }
+
+        drop(compressor);
+        assert_eq!(counting.live.load(Ordering::SeqCst), 0, "level {level}");
+    }
+
+    // Larger levels need more memory.
+    assert!(Compressor::memory_footprint(1) < Compressor::memory_footprint(6));
+    assert!(Compressor::memory_footprint(6) < Compressor::memory_footprint(12));
+    assert_eq!(Compressor::match_cache_footprint(12), 0);
+    assert_eq!(Decompressor::memory_footprint(), size_of::<Decompressor>());
+}
+
+#[test]
+fn test_compressor_allocation_failure() {
+    let data = periodic_bytes(400_000);
+    for level in [0, 1, 6, 10, 12, 13] {
+        let footprint = Compressor::memory_footprint(level) - size_of::<Compressor>();
+        if footprint > 0 {
+            let short = Arc::new(Limited::new(footprint - 1));
+            let err = Compressor::try_with_allocator(level, short.clone()).err();
+            assert!(err.is_some(), "level {level}");
+            let err = libdeflate::Compressor::with_allocator(level as i32, short).err();
+            assert_eq!(
+                err.map(|e| e.kind()),
+                Some(io::ErrorKind::OutOfMemory),
+                "level {level}"
+            );
+        }
+
+        // The footprint is enough below level 13; above, the match cache does not fit.
+        let exact = Arc::new(Limited::new(footprint));
+        let mut compressor = Compressor::try_with_allocator(level, exact).unwrap();
+        compressor.set_parallelism(Parallelism::single_threaded());
+        let (res, out) = try_compress(&mut compressor, &data);
+        if level < 13 {
+            let mut plain = Compressor::new(level);
+            plain.set_parallelism(Parallelism::single_threaded());
+            assert_eq!(res, CompressResult::Success, "level {level}");
+            assert!(out == compress(&mut plain, &data), "level {level}");
+        } else {
+            assert_eq!(res, CompressResult::OutOfMemory, "level {level}");
+        }
+
+        let exact = Arc::new(Limited::new(footprint));
+        let mut compressor = libdeflate::Compressor::with_allocator(level as i32, exact).unwrap();
+        compressor.set_parallelism(Parallelism::single_threaded());
+        let res = compressor.compress_deflate(&data).map_err(|e| e.kind());
+        if level >= 13 {
+            assert_eq!(res.err(), Some(io::ErrorKind::OutOfMemory), "level {level}");
+        } else {
+            assert!(res.is_ok(), "level {level}");
+        }
+    }
+
+    // With room for the match cache too, level 13 succeeds.
+    let budget = Compressor::memory_footprint(13) - size_of::<Compressor>()
+        + Compressor::match_cache_footprint(13);
+    let mut compressor = Compressor::with_allocator(13, Arc::new(Limited::new(budget)));
+    compressor.set_parallelism(Parallelism::single_threaded());
+    compress(&mut compressor, &data);
+
+    // Chunk compressors that cannot be made fail the call the same way.
+    let footprint = Compressor::memory_footprint(6) - size_of::<Compressor>();
+    let mut compressor = Compressor::with_allocator(6, Arc::new(Limited::new(footprint)));
+    compressor.set_parallelism(Parallelism::default().with_chunk_size(100_000));
+    let (res, _) = try_compress(&mut compressor, &data);
+    assert_eq!(res, CompressResult::OutOfMemory);
+}
+
+#[test]
+fn test_compressor_allocator_parallel_chunks() {
+    let data: Vec<u8> = (0..1_500_000).map(|i: usize| (i % 97) as u8).collect();
+    let counting = Arc::new(Counting::default());
+    let mut compressor = Compressor::with_allocator(6, counting.clone());
+    let initial = counting.requested.load(Ordering::SeqCst);
+    let compressed = compress(&mut compressor, &data);
+    // The chunk compressors come from the same allocator.
+    assert!(counting.requested.load(Ordering::SeqCst) > initial);
+
+    let mut back = vec![0u8; data.len()];
+    let (res, _, size) = Decompressor::new().decompress(&compressed, &mut back);
+    assert_eq!(res, DecompressResult::Success);
+    assert_eq!(size, data.len());
+    assert!(back == data);
+    drop(compressor);
+    assert_eq!(counting.live.load(Ordering::SeqCst), 0);
+}
+
+static MALLOCS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+unsafe extern "C" fn counting_malloc(size: usize) -> *mut c_void {
+    MALLOCS.fetch_add(1, Ordering::SeqCst);
+    unsafe { malloc(size) }
+}
+
+#[test]
+fn test_callback_allocator() {
+    let allocator = unsafe { CallbackAllocator::new(counting_malloc, free) };
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let p = allocator.allocate(layout);
+    assert!(!p.is_null());
+    unsafe { allocator.deallocate(p, layout) };
+    assert!(
+        allocator
+            .allocate(Layout::from_size_align(64, 4096).unwrap())
+            .is_null()
+    );
+    assert_eq!(MALLOCS.load(Ordering::SeqCst), 1);
+
+    // The match-finder tables, the sequence buffer and the probe table.
+    let mut compressor = Compressor::with_allocator(9, Arc::new(allocator));
+    assert_eq!(MALLOCS.load(Ordering::SeqCst), 5);
+    compress(&mut compressor, b"hello hello hello hello");
+}
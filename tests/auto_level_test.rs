@@ -0,0 +1,153 @@
+use libdeflate::compress::{
+    AutoLevel, CompressResult, Compressor as RawCompressor, FlushMode, MAX_COMPRESSION_LEVEL,
+};
+use libdeflate::stream::{DeflateDecoder, DeflateEncoder};
+use libdeflate::{Compressor, Decompressor};
+use std::io::{Read, Write};
+use std::mem::MaybeUninit;
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+fn raw_compress(compressor: &mut RawCompressor, data: &[u8]) -> Vec<u8> {
+    let mut out = vec![MaybeUninit::uninit(); compressor.deflate_bound(data.len())];
+    let (res, size, _) = compressor.compress(data, &mut out, FlushMode::Finish);
+    assert_eq!(res, CompressResult::Success);
+    out[..size]
+        .iter()
+        .map(|b| unsafe { b.assume_init() })
+        .collect()
+}
+
+#[test]
+fn test_evaluate_matches_compress_to_size() {
+    let data = periodic_bytes(100_000);
+    let auto = AutoLevel::new().with_levels(&[9, 1, 6, 99, 6]);
+    assert_eq!(auto.levels(), &[1, 6, 9, MAX_COMPRESSION_LEVEL]);
+
+    let trials = auto.evaluate(&data);
+    assert_eq!(trials.len(), 4);
+    for (trial, &level) in trials.iter().zip(auto.levels()) {
+        assert_eq!(trial.level, level);
+        assert_eq!(trial.input_size, data.len());
+        let expected = RawCompressor::new(level).compress_to_size(&data, true);
+        assert_eq!(trial.compressed_size, expected, "level {}", level);
+    }
+
+    // Larger inputs are sampled, not compressed whole.
+    let big = periodic_bytes(4 * 1024 * 1024);
+    let trials = AutoLevel::new().with_sample_size(64 * 1024).evaluate(&big);
+    assert!(trials.iter().all(|t| t.input_size == 64 * 1024));
+}
+
+#[test]
+fn test_choose_by_goal() {
+    let text = periodic_bytes(200_000);
+    let random = random_bytes(200_000, 7);
+
+    // Without goals, the strongest candidate.
+    assert_eq!(AutoLevel::new().choose(&text), 12);
+
+    // The cheapest level reaching the ratio goal; all of them on text, none on random data.
+    let ratio = AutoLevel::new().with_target_ratio(0.5);
+    assert_eq!(ratio.choose(&text), 1);
+    assert_eq!(ratio.choose(&random), 12);
+
+    // An unreachable throughput target falls back to the fastest candidate, a trivial one
+    // allows the strongest.
+    assert_eq!(AutoLevel::new().with_min_throughput(1e15).choose(&text), 1);
+    assert_eq!(AutoLevel::new().with_min_throughput(1.0).choose(&text), 12);
+    assert_eq!(
+        AutoLevel::new()
+            .with_min_throughput(1e15)
+            .with_target_ratio(0.5)
+            .choose(&text),
+        1
+    );
+
+    assert_eq!(AutoLevel::new().with_levels(&[3, 7]).choose(&[]), 7);
+}
+
+#[test]
+fn test_set_level_matches_new_compressor() {
+    let data = periodic_bytes(300_000);
+    let mut compressor = RawCompressor::new(1);
+    raw_compress(&mut compressor, &data);
+    for level in [6, 12, 0, 3] {
+        compressor.set_level(level);
+        assert_eq!(compressor.compression_level, level);
+        assert_eq!(
+            raw_compress(&mut compressor, &data),
+            raw_compress(&mut RawCompressor::new(level), &data),
+            "level {}",
+            level
+        );
+    }
+}
+
+#[test]
+fn test_api_auto_level() {
+    let text = periodic_bytes(300_000);
+    let random = random_bytes(300_000, 3);
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor.set_auto_level(
+        AutoLevel::new()
+            .with_target_ratio(0.5)
+            .with_recheck_interval(500_000),
+    );
+    let mut decompressor = Decompressor::new();
+
+    let compressed = compressor.compress_zlib(&text).unwrap();
+    assert_eq!(compressor.level(), 1);
+    assert_eq!(
+        decompressor
+            .decompress_zlib(&compressed, text.len())
+            .unwrap(),
+        text
+    );
+
+    // Not due yet: the level stays.
+    let compressed = compressor.compress_gzip(&random).unwrap();
+    assert_eq!(compressor.level(), 1);
+    assert_eq!(
+        decompressor
+            .decompress_gzip(&compressed, random.len())
+            .unwrap(),
+        random
+    );
+
+    // Due again after 500_000 bytes.
+    let compressed = compressor.compress_deflate(&random).unwrap();
+    assert_eq!(compressor.level(), 12);
+    assert_eq!(
+        decompressor
+            .decompress_deflate(&compressed, random.len())
+            .unwrap(),
+        random
+    );
+}
+
+#[test]
+fn test_stream_rechecks_level() {
+    let mut data = periodic_bytes(256 * 1024);
+    data.extend(random_bytes(512 * 1024, 11));
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), 6)
+        .with_buffer_size(64 * 1024)
+        .with_auto_level(
+            AutoLevel::new()
+                .with_target_ratio(0.5)
+                .with_recheck_interval(128 * 1024),
+        );
+    encoder.write_all(&data[..256 * 1024]).unwrap();
+    assert_eq!(encoder.level(), 1);
+    encoder.write_all(&data[256 * 1024..]).unwrap();
+    assert_eq!(encoder.level(), 12);
+    let compressed = encoder.finish().unwrap();
+
+    let mut decoded = Vec::new();
+    DeflateDecoder::new(&compressed[..])
+        .read_to_end(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, data);
+}
@@ -0,0 +1,89 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const BENCHMARK: &str = env!("CARGO_BIN_EXE_libdeflate-benchmark");
+
+fn sample_file(name: &str) -> (PathBuf, Vec<u8>) {
+    let path = std::env::temp_dir().join(format!(
+        "libdeflate-benchmark-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let data: Vec<u8> = (0..100_000u32)
+        .map(|i| ((i % 97) ^ (i / 512)) as u8)
+        .collect();
+    fs::write(&path, &data).unwrap();
+    (path, data)
+}
+
+// libdeflater links the C libdeflate, whose symbols clash with the `capi` exports.
+#[cfg(not(feature = "capi"))]
+#[test]
+fn test_checksum_mode() {
+    let (path, data) = sample_file("checksum");
+
+    let out = Command::new(BENCHMARK)
+        .args(["checksum", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("{:08x}", libdeflater::crc32(&data))));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains(libdeflate::crc32::implementation_name()));
+
+    let out = Command::new(BENCHMARK)
+        .args(["checksum", "-A", "-s", "1000", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("{:08x}", libdeflater::adler32(&data))));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains(libdeflate::adler32::implementation_name()));
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[cfg(not(feature = "capi"))]
+#[test]
+fn test_checksum_mode_isa_cap() {
+    let (path, data) = sample_file("isa-cap");
+
+    let out = Command::new(BENCHMARK)
+        .env("LIBDEFLATE_MAX_ISA", "scalar")
+        .args(["checksum", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    assert!(stdout.starts_with(&format!("{:08x}", libdeflater::crc32(&data))));
+    let stderr = String::from_utf8(out.stderr).unwrap();
+    assert!(stderr.contains("slice8"), "{}", stderr);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_benchmark_mode() {
+    let (path, _) = sample_file("bench");
+
+    let out = Command::new(BENCHMARK)
+        .args(["-1", "-12", "-f", "all", "-r", "1", path.to_str().unwrap()])
+        .output()
+        .unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let rows = stdout
+        .lines()
+        .filter(|line| line.starts_with("libdeflate-rs"))
+        .count();
+    assert_eq!(rows, 6);
+
+    fs::remove_file(&path).unwrap();
+}
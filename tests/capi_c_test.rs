@@ -0,0 +1,127 @@
+// Builds a C program against include/libdeflate.h and the cdylib, as a C consumer would.
+#![cfg(all(feature = "capi", unix))]
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include "libdeflate.h"
+
+#define CHECK(cond) \
+	do { \
+		if (!(cond)) { \
+			fprintf(stderr, "line %d: %s\n", __LINE__, #cond); \
+			return 1; \
+		} \
+	} while (0)
+
+static size_t mallocs;
+
+static void *counting_malloc(size_t size)
+{
+	mallocs++;
+	return malloc(size);
+}
+
+int main(void)
+{
+	static unsigned char in[300000], out[400000], back[300000];
+	struct libdeflate_options options;
+	struct libdeflate_compressor *c;
+	struct libdeflate_decompressor *d;
+	enum libdeflate_result res;
+	size_t i, n, actual_in, actual_out;
+
+	for (i = 0; i < sizeof(in); i++)
+		in[i] = (unsigned char)((i * 7 + i / 1000) % 251);
+
+	CHECK(LIBDEFLATE_VERSION_MAJOR == 1);
+	CHECK(LIBDEFLATE_SUCCESS == 0 && LIBDEFLATE_BAD_DATA == 1);
+	CHECK(LIBDEFLATE_SHORT_OUTPUT == 2 && LIBDEFLATE_INSUFFICIENT_SPACE == 3);
+	CHECK(libdeflate_alloc_compressor(-1) == NULL);
+	CHECK(libdeflate_alloc_compressor(17) == NULL);
+
+	memset(&options, 0, sizeof(options));
+	options.sizeof_options = sizeof(options);
+	options.malloc_func = counting_malloc;
+	options.free_func = free;
+	c = libdeflate_alloc_compressor_ex(12, &options);
+	CHECK(c != NULL && mallocs > 1);
+	d = libdeflate_alloc_decompressor_ex(&options);
+	CHECK(d != NULL);
+
+	n = libdeflate_gzip_compress(c, in, sizeof(in), out,
+				     libdeflate_gzip_compress_bound(c, sizeof(in)));
+	CHECK(n > 0 && n < sizeof(in) / 4);
+	res = libdeflate_gzip_decompress_ex(d, out, n, back, sizeof(back),
+					    &actual_in, &actual_out);
+	CHECK(res == LIBDEFLATE_SUCCESS);
+	CHECK(actual_in == n && actual_out == sizeof(in));
+	CHECK(memcmp(in, back, sizeof(in)) == 0);
+	res = libdeflate_gzip_decompress(d, out, n, back, 1000, NULL);
+	CHECK(res == LIBDEFLATE_INSUFFICIENT_SPACE);
+	CHECK(libdeflate_deflate_compress(c, in, sizeof(in), out, 10) == 0);
+
+	n = libdeflate_zlib_compress(c, in, 1000, out, sizeof(out));
+	CHECK(n > 0);
+	res = libdeflate_zlib_decompress(d, out, n, back, 1000, NULL);
+	CHECK(res == LIBDEFLATE_SUCCESS && memcmp(in, back, 1000) == 0);
+	res = libdeflate_zlib_decompress(d, out, n - 1, back, 1000, NULL);
+	CHECK(res == LIBDEFLATE_BAD_DATA);
+
+	CHECK(libdeflate_crc32(0, "123456789", 9) == 0xCBF43926);
+	CHECK(libdeflate_adler32(1, "Wikipedia", 9) == 0x11E60398);
+
+	libdeflate_free_compressor(c);
+	libdeflate_free_decompressor(d);
+	libdeflate_free_compressor(NULL);
+	puts("ok");
+	return 0;
+}
+"#;
+
+#[test]
+fn test_c_program_against_header_and_cdylib() {
+    // Integration tests run from target/<profile>/deps, next to the cdylib.
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let library = deps.join(format!("{DLL_PREFIX}libdeflate{DLL_SUFFIX}"));
+    assert!(library.exists(), "no cdylib at {}", library.display());
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capi_c_test");
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("main.c");
+    let program = dir.join("main");
+    fs::write(&source, PROGRAM).unwrap();
+
+    let include = Path::new(env!("CARGO_MANIFEST_DIR")).join("include");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let out = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(&program)
+        .arg(&source)
+        .arg("-I")
+        .arg(&include)
+        .arg(&library)
+        .arg(format!("-Wl,-rpath,{}", deps.display()))
+        .output()
+        .unwrap_or_else(|e| panic!("cannot run {cc}: {e}"));
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let out = Command::new(&program).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(out.stdout, b"ok\n");
+}
@@ -0,0 +1,291 @@
+use libdeflate::capi::*;
+use std::ffi::c_void;
+use std::mem::size_of;
+use std::ptr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+mod common;
+use common::periodic_bytes;
+
+/// Held by tests that allocate handles, since one of them swaps the global allocator.
+static ALLOCATOR_LOCK: Mutex<()> = Mutex::new(());
+
+#[test]
+fn test_capi_roundtrip() {
+    let data = periodic_bytes(700_000);
+    let _guard = ALLOCATOR_LOCK.lock().unwrap();
+    unsafe {
+        let c = libdeflate_alloc_compressor(6);
+        let d = libdeflate_alloc_decompressor();
+        assert!(!c.is_null() && !d.is_null());
+        assert!(libdeflate_alloc_compressor(-1).is_null());
+        assert!(libdeflate_alloc_compressor(17).is_null());
+
+        type Compress = unsafe extern "C" fn(
+            *mut LibdeflateCompressor,
+            *const c_void,
+            usize,
+            *mut c_void,
+            usize,
+        ) -> usize;
+        type Bound = unsafe extern "C" fn(*mut LibdeflateCompressor, usize) -> usize;
+        type Decompress = unsafe extern "C" fn(
+            *mut LibdeflateDecompressor,
+            *const c_void,
+            usize,
+            *mut c_void,
+            usize,
+            *mut usize,
+            *mut usize,
+        ) -> LibdeflateResult;
+        let formats: [(Compress, Bound, Decompress); 3] = [
+            (
+                libdeflate_deflate_compress,
+                libdeflate_deflate_compress_bound,
+                libdeflate_deflate_decompress_ex,
+            ),
+            (
+                libdeflate_zlib_compress,
+                libdeflate_zlib_compress_bound,
+                libdeflate_zlib_decompress_ex,
+            ),
+            (
+                libdeflate_gzip_compress,
+                libdeflate_gzip_compress_bound,
+                libdeflate_gzip_decompress_ex,
+            ),
+        ];
+        for (compress, bound, decompress) in formats {
+            let max = bound(c, data.len());
+            assert_eq!(max, bound(ptr::null_mut(), data.len()));
+            let mut out = vec![0u8; max];
+            let size = compress(
+                c,
+                data.as_ptr().cast(),
+                data.len(),
+                out.as_mut_ptr().cast(),
+                max,
+            );
+            assert!(size > 0 && size < data.len() / 10);
+            assert_eq!(
+                compress(
+                    c,
+                    data.as_ptr().cast(),
+                    data.len(),
+                    out.as_mut_ptr().cast(),
+                    10
+                ),
+                0
+            );
+
+            // Trailing bytes after the stream are not consumed.
+            out.truncate(size);
+            out.extend_from_slice(b"trailer");
+            let mut back = vec![0u8; data.len() + 1];
+            let (mut in_n, mut out_n) = (0, 0);
+            let res = decompress(
+                d,
+                out.as_ptr().cast(),
+                out.len(),
+                back.as_mut_ptr().cast(),
+                back.len(),
+                &mut in_n,
+                &mut out_n,
+            );
+            assert_eq!(res, LibdeflateResult::Success);
+            assert_eq!((in_n, out_n), (size, data.len()));
+            assert!(back[..out_n] == data[..]);
+
+            // Without a size to report, the output must be filled exactly.
+            let res = decompress(
+                d,
+                out.as_ptr().cast(),
+                size,
+                back.as_mut_ptr().cast(),
+                back.len(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            assert_eq!(res, LibdeflateResult::ShortOutput);
+            let res = decompress(
+                d,
+                out.as_ptr().cast(),
+                size,
+                back.as_mut_ptr().cast(),
+                data.len() - 1,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            assert_eq!(res, LibdeflateResult::InsufficientSpace);
+            let res = decompress(
+                d,
+                out.as_ptr().cast(),
+                size / 2,
+                back.as_mut_ptr().cast(),
+                data.len(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+            );
+            assert_eq!(res, LibdeflateResult::BadData);
+        }
+
+        let mut out = [0u8; 64];
+        let size = libdeflate_gzip_compress(c, ptr::null(), 0, out.as_mut_ptr().cast(), out.len());
+        assert!(size > 0);
+        assert_eq!(
+            libdeflate_gzip_decompress(
+                d,
+                out.as_ptr().cast(),
+                size,
+                ptr::null_mut(),
+                0,
+                ptr::null_mut()
+            ),
+            LibdeflateResult::Success
+        );
+
+        libdeflate_free_compressor(c);
+        libdeflate_free_decompressor(d);
+        libdeflate_free_compressor(ptr::null_mut());
+        libdeflate_free_decompressor(ptr::null_mut());
+
+        assert_eq!(
+            libdeflate_crc32(0, b"123456789".as_ptr().cast(), 9),
+            0xcbf43926
+        );
+        assert_eq!(
+            libdeflate_adler32(1, b"Wikipedia".as_ptr().cast(), 9),
+            0x11e60398
+        );
+        assert_eq!(libdeflate_crc32(0, ptr::null(), 0), 0);
+    }
+}
+
+static MALLOCS: AtomicUsize = AtomicUsize::new(0);
+static FREES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe extern "C" {
+    fn malloc(size: usize) -> *mut c_void;
+    fn free(ptr: *mut c_void);
+}
+
+unsafe extern "C" fn counting_malloc(size: usize) -> *mut c_void {
+    MALLOCS.fetch_add(1, Ordering::SeqCst);
+    unsafe { malloc(size) }
+}
+
+unsafe extern "C" fn counting_free(ptr: *mut c_void) {
+    FREES.fetch_add(1, Ordering::SeqCst);
+    unsafe { free(ptr) }
+}
+
+#[test]
+fn test_capi_allocator() {
+    let _guard = ALLOCATOR_LOCK.lock().unwrap();
+    unsafe {
+        let options = LibdeflateOptions {
+            sizeof_options: size_of::<LibdeflateOptions>(),
+            malloc_func: Some(counting_malloc),
+            free_func: Some(counting_free),
+        };
+        // A level 1 compressor allocates its handle, hash table, sequence buffer and
+        // incompressible-data probe table.
+        let c = libdeflate_alloc_compressor_ex(1, &options);
+        let d = libdeflate_alloc_decompressor_ex(&options);
+        assert_eq!(MALLOCS.load(Ordering::SeqCst), 5);
+        libdeflate_free_compressor(c);
+        libdeflate_free_decompressor(d);
+        assert_eq!(FREES.load(Ordering::SeqCst), 5);
+
+        let bad = LibdeflateOptions {
+            sizeof_options: 1,
+            ..options
+        };
+        assert!(libdeflate_alloc_decompressor_ex(&bad).is_null());
+        let half = LibdeflateOptions {
+            free_func: None,
+            ..options
+        };
+        assert!(libdeflate_alloc_compressor_ex(6, &half).is_null());
+
+        // Handles keep the allocator they were created with. Level 0 allocates its handle
+        // and two match-finder tables.
+        let before = libdeflate_alloc_decompressor();
+        libdeflate_set_memory_allocator(counting_malloc, counting_free);
+        let after = libdeflate_alloc_compressor(0);
+        assert_eq!(MALLOCS.load(Ordering::SeqCst), 8);
+        libdeflate_free_decompressor(before);
+        libdeflate_free_compressor(after);
+        assert_eq!(FREES.load(Ordering::SeqCst), 8);
+        libdeflate_set_memory_allocator(malloc, free);
+    }
+}
+
+static BUDGET: AtomicUsize = AtomicUsize::new(0);
+static LIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Fails once `BUDGET` allocations have been made.
+unsafe extern "C" fn budgeted_malloc(size: usize) -> *mut c_void {
+    if BUDGET
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_err()
+    {
+        return ptr::null_mut();
+    }
+    LIVE.fetch_add(1, Ordering::SeqCst);
+    unsafe { malloc(size) }
+}
+
+unsafe extern "C" fn budgeted_free(ptr: *mut c_void) {
+    LIVE.fetch_sub(1, Ordering::SeqCst);
+    unsafe { free(ptr) }
+}
+
+#[test]
+fn test_capi_out_of_memory() {
+    let _guard = ALLOCATOR_LOCK.lock().unwrap();
+    let options = LibdeflateOptions {
+        sizeof_options: size_of::<LibdeflateOptions>(),
+        malloc_func: Some(budgeted_malloc),
+        free_func: Some(budgeted_free),
+    };
+    unsafe {
+        // A level 1 compressor takes four allocations; with fewer there is none, and what
+        // was allocated is released.
+        for budget in 0..4 {
+            BUDGET.store(budget, Ordering::SeqCst);
+            assert!(
+                libdeflate_alloc_compressor_ex(1, &options).is_null(),
+                "budget {budget}"
+            );
+            assert_eq!(LIVE.load(Ordering::SeqCst), 0, "budget {budget}");
+        }
+
+        // Below level 13 a compressor needs no more memory, unless a large input is split
+        // into chunks, each compressed by a compressor of its own.
+        BUDGET.store(usize::MAX, Ordering::SeqCst);
+        let data = periodic_bytes(400_000);
+        for level in [6, 13] {
+            let c = libdeflate_alloc_compressor_ex(level, &options);
+            assert!(!c.is_null());
+            BUDGET.store(0, Ordering::SeqCst);
+            let mut out = vec![0u8; libdeflate_deflate_compress_bound(c, data.len())];
+            let compress = |len: usize, out: &mut [u8]| {
+                libdeflate_deflate_compress(
+                    c,
+                    data.as_ptr() as *const c_void,
+                    len,
+                    out.as_mut_ptr() as *mut c_void,
+                    out.len(),
+                )
+            };
+            assert_eq!(compress(data.len(), &mut out), 0, "level {level}");
+            // Level 13 also needs room for its match cache.
+            assert_eq!(compress(10_000, &mut out) != 0, level < 13, "level {level}");
+            libdeflate_free_compressor(c);
+            BUDGET.store(usize::MAX, Ordering::SeqCst);
+        }
+        assert_eq!(LIVE.load(Ordering::SeqCst), 0);
+    }
+}
@@ -0,0 +1,129 @@
+use libdeflate::checksum::Checksum;
+use libdeflate::{
+    Adler32Hasher, ChecksumReader, ChecksumWriter, Compressor, Crc32Hasher, adler32, crc32,
+};
+use std::hash::Hasher;
+use std::io::{Read, Write};
+
+fn sample_data() -> Vec<u8> {
+    (0..100_000u32).map(|i| (i * 31 % 251) as u8).collect()
+}
+
+#[test]
+fn test_crc32_hasher() {
+    let data = sample_data();
+    let mut hasher = Crc32Hasher::new();
+    assert_eq!(hasher.finalize(), 0);
+    for chunk in data.chunks(777) {
+        hasher.update(chunk);
+    }
+    assert_eq!(hasher.finalize(), crc32(0, &data));
+    // libdeflater links the C libdeflate, whose symbols clash with the `capi` exports.
+    #[cfg(not(feature = "capi"))]
+    assert_eq!(hasher.finalize(), libdeflater::crc32(&data));
+
+    hasher.reset();
+    assert_eq!(hasher.finalize(), 0);
+    Hasher::write(&mut hasher, b"123456789");
+    assert_eq!(hasher.finish(), 0xcbf43926);
+
+    let mut resumed = Crc32Hasher::with_initial(crc32(0, b"12345"));
+    resumed.update(b"6789");
+    assert_eq!(resumed.finalize(), 0xcbf43926);
+}
+
+#[test]
+fn test_adler32_hasher() {
+    let data = sample_data();
+    let mut hasher = Adler32Hasher::new();
+    assert_eq!(hasher.finalize(), 1);
+    for chunk in data.chunks(5553) {
+        hasher.update(chunk);
+    }
+    assert_eq!(hasher.finalize(), adler32(1, &data));
+    #[cfg(not(feature = "capi"))]
+    assert_eq!(hasher.finalize(), libdeflater::adler32(&data));
+
+    hasher.reset();
+    Hasher::write(&mut hasher, b"Wikipedia");
+    assert_eq!(hasher.finish(), 0x11e60398);
+}
+
+#[test]
+fn test_checksum_reader() {
+    let data = sample_data();
+
+    let mut reader: ChecksumReader<&[u8]> = ChecksumReader::new(&data[..]);
+    let mut out = Vec::new();
+    let mut buf = [0u8; 1000];
+    loop {
+        let n = reader.read(&mut buf).unwrap();
+        if n == 0 {
+            break;
+        }
+        out.extend_from_slice(&buf[..n]);
+    }
+    assert_eq!(out, data);
+    assert_eq!(reader.checksum(), crc32(0, &data));
+
+    let mut reader = ChecksumReader::<_, Adler32Hasher>::new(&data[..]);
+    let mut out = Vec::new();
+    reader.read_to_end(&mut out).unwrap();
+    assert_eq!(reader.checksum(), adler32(1, &data));
+    let (rest, hasher) = reader.into_parts();
+    assert!(rest.is_empty());
+    assert_eq!(hasher.finalize(), adler32(1, &data));
+}
+
+/// Accepts at most 100 bytes per write, like a short-writing pipe.
+struct ShortWriter(Vec<u8>);
+
+impl Write for ShortWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(100);
+        self.0.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_checksum_writer_short_writes() {
+    let data = sample_data();
+    let mut writer: ChecksumWriter<_> = ChecksumWriter::new(ShortWriter(Vec::new()));
+    writer.write_all(&data).unwrap();
+    writer.flush().unwrap();
+    assert_eq!(writer.checksum(), crc32(0, &data));
+    assert_eq!(writer.into_inner().0, data);
+}
+
+#[test]
+fn test_checksum_writer_gzip_trailer() {
+    // Checksumming the uncompressed side of a pipeline must agree with the
+    // CRC-32 the compressor stores in the gzip trailer.
+    let data = sample_data();
+    let mut writer = ChecksumWriter::<_, Crc32Hasher>::new(Vec::new());
+    writer.write_all(&data).unwrap();
+    let (copy, crc) = writer.into_parts();
+
+    let mut compressor = Compressor::new(6).unwrap();
+    let gz = compressor.compress_gzip(&copy).unwrap();
+    let trailer = u32::from_le_bytes(gz[gz.len() - 8..gz.len() - 4].try_into().unwrap());
+    assert_eq!(trailer, crc.finalize());
+}
+
+fn digest<C: Checksum>(data: &[u8]) -> u32 {
+    let mut checksum = C::default();
+    checksum.update(data);
+    checksum.finalize()
+}
+
+#[test]
+fn test_checksum_trait() {
+    let data = sample_data();
+    assert_eq!(digest::<Crc32Hasher>(&data), crc32(0, &data));
+    assert_eq!(digest::<Adler32Hasher>(&data), adler32(1, &data));
+}
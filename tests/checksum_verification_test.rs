@@ -0,0 +1,76 @@
+use libdeflate::batch::BatchDecompressor;
+use libdeflate::decompress::ChecksumVerification;
+use libdeflate::{Compressor, Decompressor};
+
+fn sample_data() -> Vec<u8> {
+    (0..100_000u32).map(|i| (i * 7 % 251) as u8).collect()
+}
+
+#[test]
+fn test_verification_levels_gzip() {
+    let data = sample_data();
+    let mut gz = Compressor::new(6).unwrap().compress_gzip(&data).unwrap();
+    let n = gz.len();
+    // Corrupt the CRC-32 but leave ISIZE intact.
+    gz[n - 8] ^= 0xff;
+
+    let mut d = Decompressor::new();
+    assert!(d.decompress_gzip(&gz, data.len()).is_err());
+
+    d.set_checksum_verification(ChecksumVerification::SizeOnly);
+    assert_eq!(d.decompress_gzip(&gz, data.len()).unwrap(), data);
+
+    // Now also corrupt ISIZE.
+    gz[n - 1] ^= 0xff;
+    assert!(d.decompress_gzip(&gz, data.len()).is_err());
+
+    d.set_checksum_verification(ChecksumVerification::None);
+    assert_eq!(d.decompress_gzip(&gz, data.len()).unwrap(), data);
+
+    d.set_checksum_verification(ChecksumVerification::Full);
+    assert!(d.decompress_gzip(&gz, data.len()).is_err());
+}
+
+#[test]
+fn test_verification_levels_zlib() {
+    let data = sample_data();
+    let mut zl = Compressor::new(6).unwrap().compress_zlib(&data).unwrap();
+    let n = zl.len();
+    zl[n - 1] ^= 0xff;
+
+    let mut d = Decompressor::new();
+    assert!(d.decompress_zlib(&zl, data.len()).is_err());
+
+    // zlib has no size field, so SizeOnly already skips the whole trailer.
+    d.set_checksum_verification(ChecksumVerification::SizeOnly);
+    assert_eq!(d.decompress_zlib(&zl, data.len()).unwrap(), data);
+
+    d.set_checksum_verification(ChecksumVerification::None);
+    assert_eq!(d.decompress_zlib(&zl, data.len()).unwrap(), data);
+}
+
+#[test]
+fn test_batch_verification() {
+    let data = sample_data();
+    let good = Compressor::new(1).unwrap().compress_gzip(&data).unwrap();
+    let mut bad = good.clone();
+    let n = bad.len();
+    bad[n - 6] ^= 0x01;
+
+    let inputs = [&good[..], &bad[..]];
+    let sizes = [data.len(), data.len()];
+
+    let mut batch = BatchDecompressor::new();
+    let results = batch.decompress_batch_gzip(&inputs, &sizes);
+    assert_eq!(results[0].as_deref(), Some(&data[..]));
+    assert!(results[1].is_none());
+
+    batch.set_checksum_verification(ChecksumVerification::None);
+    let results = batch.decompress_batch_gzip(&inputs, &sizes);
+    assert_eq!(results[0].as_deref(), Some(&data[..]));
+    assert_eq!(results[1].as_deref(), Some(&data[..]));
+
+    let zl = Compressor::new(1).unwrap().compress_zlib(&data).unwrap();
+    let results = BatchDecompressor::new().decompress_batch_zlib(&[&zl[..]], &[data.len()]);
+    assert_eq!(results[0].as_deref(), Some(&data[..]));
+}
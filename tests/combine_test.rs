@@ -0,0 +1,98 @@
+use libdeflate::adler32::{adler32, adler32_combine, adler32_parallel};
+use libdeflate::crc32::{crc32, crc32_combine, crc32_parallel, crc32_zeros};
+
+mod common;
+use common::random_bytes;
+
+const SPLITS: [usize; 10] = [0, 1, 3, 15, 16, 100, 4096, 5552, 65521, 70000];
+
+#[test]
+fn test_crc32_combine() {
+    let data = random_bytes(70000, 1);
+    let whole = crc32(0, &data);
+    for &split in &SPLITS {
+        let (a, b) = data.split_at(split);
+        assert_eq!(
+            crc32_combine(crc32(0, a), crc32(0, b), b.len()),
+            whole,
+            "split {}",
+            split
+        );
+    }
+}
+
+#[test]
+fn test_crc32_combine_many_pieces() {
+    let data = random_bytes(1 << 20, 1);
+    let combined = data.chunks(12345).fold(0, |acc, chunk| {
+        crc32_combine(acc, crc32(0, chunk), chunk.len())
+    });
+    assert_eq!(combined, crc32(0, &data));
+}
+
+#[test]
+fn test_crc32_zeros() {
+    let data = random_bytes(1000, 1);
+    let crc = crc32(0, &data);
+    for n in [0, 1, 7, 64, 1000, 100_000] {
+        let mut padded = data.clone();
+        padded.resize(data.len() + n, 0);
+        assert_eq!(crc32_zeros(crc, n), crc32(0, &padded), "n {}", n);
+    }
+    assert_eq!(crc32_zeros(0, 0), 0);
+    assert_eq!(crc32_zeros(0, 5), crc32(0, &[0; 5]));
+}
+
+#[test]
+fn test_crc32_zeros_huge() {
+    // x^(2^k) mod G has period 32 in k, so shifting by 2^32 bytes twice
+    // must agree with one shift by 2^33 bytes.
+    let crc = crc32(0, b"hello");
+    let once = crc32_zeros(crc, 1 << 33);
+    let twice = crc32_zeros(crc32_zeros(crc, 1 << 32), 1 << 32);
+    assert_eq!(once, twice);
+}
+
+#[test]
+fn test_adler32_combine() {
+    let data = random_bytes(70000, 1);
+    let whole = adler32(1, &data);
+    for &split in &SPLITS {
+        let (a, b) = data.split_at(split);
+        assert_eq!(
+            adler32_combine(adler32(1, a), adler32(1, b), b.len()),
+            whole,
+            "split {}",
+            split
+        );
+    }
+
+    let ones = vec![0xffu8; 200_000];
+    let (a, b) = ones.split_at(123_457);
+    assert_eq!(
+        adler32_combine(adler32(1, a), adler32(1, b), b.len()),
+        adler32(1, &ones)
+    );
+}
+
+#[test]
+fn test_parallel_checksums() {
+    let data = random_bytes(5 * 1024 * 1024 + 777, 1);
+    for &len in &[0, 1000, 1024 * 1024, 1024 * 1024 + 1, data.len()] {
+        let p = &data[..len];
+        assert_eq!(crc32_parallel(0, p), crc32(0, p), "len {}", len);
+        assert_eq!(
+            crc32_parallel(0x1234_5678, p),
+            crc32(0x1234_5678, p),
+            "len {}",
+            len
+        );
+        assert_eq!(adler32_parallel(1, p), adler32(1, p), "len {}", len);
+        assert_eq!(
+            adler32_parallel(0xabcd_0123, p),
+            adler32(0xabcd_0123, p),
+            "len {}",
+            len
+        );
+    }
+}
@@ -0,0 +1,40 @@
+//! Test data shared by the integration tests.
+
+// Each test crate uses only some of these.
+#![allow(dead_code)]
+
+/// `len` bytes of xorshift64 output starting from `seed`, which must not be zero.
+pub fn random_bytes(len: usize, mut seed: u64) -> Vec<u8> {
+    (0..len)
+        .map(|_| {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed as u8
+        })
+        .collect()
+}
+
+/// `len` bytes of a ramp with period 251 whose phase shifts every 1000 bytes: long
+/// matches at a handful of offsets, so it compresses well at every level.
+pub fn periodic_bytes(len: usize) -> Vec<u8> {
+    (0..len).map(|i| ((i * 7 + i / 1000) % 251) as u8).collect()
+}
+
+/// `len` bytes of `words` in pseudo-random order, with a few random bytes mixed in: a
+/// stand-in for text, with short matches at many offsets.
+pub fn word_salad(len: usize, words: &[&[u8]]) -> Vec<u8> {
+    let mut state = 0x2545f491u32;
+    let mut out = Vec::with_capacity(len);
+    while out.len() < len {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        out.extend_from_slice(words[state as usize % words.len()]);
+        if state.is_multiple_of(97) {
+            out.extend_from_slice(&state.to_le_bytes());
+        }
+    }
+    out.truncate(len);
+    out
+}
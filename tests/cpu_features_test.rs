@@ -0,0 +1,51 @@
+use libdeflate::cpu_features::{IsaLevel, max_isa_level, set_max_isa_level};
+use libdeflate::{Compressor, Decompressor, cpu_features};
+
+// The cap is process-wide and must be set before anything dispatches, so this
+// is the only test in this file that checksums or compresses.
+#[test]
+fn test_scalar_cap() {
+    assert!(set_max_isa_level(IsaLevel::Scalar));
+    assert!(!set_max_isa_level(IsaLevel::Avx512));
+    assert_eq!(max_isa_level(), IsaLevel::Scalar);
+
+    let features = cpu_features();
+    assert_eq!(features.max_isa_level, IsaLevel::Scalar);
+    assert_eq!(features.crc32, "slice8");
+    assert_eq!(features.crc32c, "slice8");
+    assert_eq!(features.crc64, "slice8");
+    assert_eq!(features.adler32, "generic");
+    assert_eq!(features.match_len, "scalar");
+    assert_eq!(features.decompress, "generic");
+    assert!(features.to_string().contains("crc32: slice8"));
+
+    let data: Vec<u8> = (0..300_000u32)
+        .map(|i| ((i % 97) ^ (i / 1024)) as u8)
+        .collect();
+    // libdeflater links the C libdeflate, whose symbols clash with the `capi` exports.
+    #[cfg(not(feature = "capi"))]
+    assert_eq!(libdeflate::crc32(0, &data), libdeflater::crc32(&data));
+    #[cfg(not(feature = "capi"))]
+    assert_eq!(libdeflate::adler32(1, &data), libdeflater::adler32(&data));
+
+    for level in [1, 6, 12] {
+        let mut compressor = Compressor::new(level).unwrap();
+        let compressed = compressor.compress_gzip(&data).unwrap();
+        let mut decompressor = Decompressor::new();
+        let decompressed = decompressor
+            .decompress_gzip(&compressed, data.len())
+            .unwrap();
+        assert_eq!(decompressed, data, "level {}", level);
+    }
+}
+
+#[test]
+fn test_parse_isa_level() {
+    assert_eq!(IsaLevel::parse("scalar"), Some(IsaLevel::Scalar));
+    assert_eq!(IsaLevel::parse("SSE2"), Some(IsaLevel::Sse2));
+    assert_eq!(IsaLevel::parse("v2"), Some(IsaLevel::Sse4));
+    assert_eq!(IsaLevel::parse(" avx2 "), Some(IsaLevel::Avx2));
+    assert_eq!(IsaLevel::parse("v4"), Some(IsaLevel::Avx512));
+    assert_eq!(IsaLevel::parse("mmx"), None);
+    assert!(IsaLevel::Sse2 < IsaLevel::Avx2);
+}
@@ -0,0 +1,75 @@
+use libdeflate::crc32::{CRC32C_POLY, crc32c, crc32c_implementation_name, crc32c_slice8};
+
+fn crc32c_bitwise(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &b in data {
+        crc ^= b as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { CRC32C_POLY } else { 0 };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn test_crc32c_known_values() {
+    assert_eq!(crc32c(0, b""), 0);
+    assert_eq!(crc32c(0, b"123456789"), 0xe3069283);
+    // RFC 3720, appendix B.4.
+    assert_eq!(crc32c(0, &[0u8; 32]), 0x8a9136aa);
+    assert_eq!(crc32c(0, &[0xffu8; 32]), 0x62a8ab43);
+    let ascending: Vec<u8> = (0..32).collect();
+    assert_eq!(crc32c(0, &ascending), 0x46dd794e);
+    let descending: Vec<u8> = (0..32).rev().collect();
+    assert_eq!(crc32c(0, &descending), 0x113fdb5c);
+}
+
+#[test]
+fn test_crc32c_matches_reference() {
+    let data: Vec<u8> = (0..20_000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+        .collect();
+    let name = crc32c_implementation_name();
+    assert!(
+        [
+            "vpclmulqdq_avx512",
+            "vpclmulqdq_avx2",
+            "pclmulqdq",
+            "sse4.2",
+            "arm_crc",
+            "slice8"
+        ]
+        .contains(&name),
+        "{name}"
+    );
+    // The table fallback is only for CPUs without the `crc32` instruction.
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("sse4.2") {
+        assert_ne!(name, "slice8");
+    }
+
+    for len in (0..600).chain([1023, 1024, 4095, 4096, 8191, 8192, 10_000, 19_993]) {
+        for offset in [0, 1, 7] {
+            let slice = &data[offset..offset + len];
+            let expected = crc32c_bitwise(slice);
+            assert_eq!(crc32c(0, slice), expected, "len {} offset {}", len, offset);
+            assert_eq!(
+                !crc32c_slice8(!0, slice),
+                expected,
+                "len {} offset {}",
+                len,
+                offset
+            );
+        }
+    }
+}
+
+#[test]
+fn test_crc32c_incremental() {
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 253) as u8).collect();
+    let whole = crc32c(0, &data);
+    for split in [1, 63, 64, 4097, 50_000] {
+        let crc = crc32c(crc32c(0, &data[..split]), &data[split..]);
+        assert_eq!(crc, whole, "split {}", split);
+    }
+}
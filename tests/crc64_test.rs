@@ -0,0 +1,89 @@
+use libdeflate::crc64::{
+    CRC64_NVME_POLY, CRC64_XZ_POLY, crc64_nvme, crc64_nvme_slice8, crc64_xz, crc64_xz_slice8,
+    implementation_name,
+};
+
+fn crc64_bitwise(poly: u64, data: &[u8]) -> u64 {
+    let mut crc = !0u64;
+    for &b in data {
+        crc ^= b as u64;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ if crc & 1 != 0 { poly } else { 0 };
+        }
+    }
+    !crc
+}
+
+#[test]
+fn test_crc64_xz_known_values() {
+    assert_eq!(crc64_xz(0, b""), 0);
+    assert_eq!(crc64_xz(0, b"123456789"), 0x995dc9bbdf1939fa);
+    assert_eq!(crc64_xz(0, b"a"), 0x330284772e652b05);
+    assert_eq!(
+        crc64_xz(0, b"The quick brown fox jumps over the lazy dog"),
+        0x5b5eb8c2e54aa1c4
+    );
+}
+
+#[test]
+fn test_crc64_nvme_known_values() {
+    assert_eq!(crc64_nvme(0, b"123456789"), 0xae8b14860a799888);
+    // NVM Command Set Specification, 64b CRC test cases (4 KiB blocks).
+    assert_eq!(crc64_nvme(0, &[0u8; 4096]), 0x6482d367eb22b64e);
+    assert_eq!(crc64_nvme(0, &[0xffu8; 4096]), 0xc0ddba7302eca3ac);
+    let ascending: Vec<u8> = (0..4096).map(|i| i as u8).collect();
+    assert_eq!(crc64_nvme(0, &ascending), 0x3e729f5f6750449c);
+    let descending: Vec<u8> = (0..4096).map(|i| 255 - i as u8).collect();
+    assert_eq!(crc64_nvme(0, &descending), 0x9a2df64b8e9e517e);
+}
+
+#[test]
+fn test_crc64_matches_reference() {
+    let data: Vec<u8> = (0..20_000u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 11) as u8)
+        .collect();
+    let name = implementation_name();
+    assert!(
+        [
+            "vpclmulqdq_avx512",
+            "vpclmulqdq_avx2",
+            "pclmulqdq",
+            "slice8"
+        ]
+        .contains(&name),
+        "{name}"
+    );
+    // The table fallback is only for CPUs without carryless multiplication.
+    #[cfg(target_arch = "x86_64")]
+    if std::arch::is_x86_feature_detected!("pclmulqdq") {
+        assert_ne!(name, "slice8");
+    }
+
+    for len in (0..1100).chain([2047, 2048, 4095, 4096, 8191, 10_000, 19_990]) {
+        for offset in [0, 3] {
+            let slice = &data[offset..offset + len];
+            let xz = crc64_bitwise(CRC64_XZ_POLY, slice);
+            let nvme = crc64_bitwise(CRC64_NVME_POLY, slice);
+            assert_eq!(crc64_xz(0, slice), xz, "xz len {} offset {}", len, offset);
+            assert_eq!(!crc64_xz_slice8(!0, slice), xz, "xz len {}", len);
+            assert_eq!(
+                crc64_nvme(0, slice),
+                nvme,
+                "nvme len {} offset {}",
+                len,
+                offset
+            );
+            assert_eq!(!crc64_nvme_slice8(!0, slice), nvme, "nvme len {}", len);
+        }
+    }
+}
+
+#[test]
+fn test_crc64_incremental() {
+    let data: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let whole = crc64_xz(0, &data);
+    for split in [1, 15, 16, 255, 512, 4097, 60_000] {
+        let crc = crc64_xz(crc64_xz(0, &data[..split]), &data[split..]);
+        assert_eq!(crc, whole, "split {}", split);
+    }
+}
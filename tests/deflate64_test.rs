@@ -0,0 +1,208 @@
+use libdeflate::Decompressor;
+use libdeflate::decompress::DeflateVariant;
+use libdeflate::stream::DeflateDecoder;
+use std::io::Read;
+
+mod common;
+use common::random_bytes;
+
+/// LSB-first bit writer for hand-built deflate streams.
+#[derive(Default)]
+struct BitWriter {
+    out: Vec<u8>,
+    bitbuf: u64,
+    bitcount: u32,
+}
+
+impl BitWriter {
+    fn bits(&mut self, value: u32, count: u32) {
+        self.bitbuf |= (value as u64) << self.bitcount;
+        self.bitcount += count;
+        while self.bitcount >= 8 {
+            self.out.push(self.bitbuf as u8);
+            self.bitbuf >>= 8;
+            self.bitcount -= 8;
+        }
+    }
+
+    /// Huffman codewords are stored most significant bit first.
+    fn code(&mut self, codeword: u32, len: u32) {
+        self.bits(codeword.reverse_bits() >> (32 - len), len);
+    }
+
+    fn align(&mut self) {
+        if self.bitcount > 0 {
+            self.bits(0, 8 - self.bitcount);
+        }
+    }
+
+    fn stored_block(&mut self, data: &[u8], is_final: bool) {
+        self.bits(is_final as u32, 1);
+        self.bits(0, 2);
+        self.align();
+        self.out
+            .extend_from_slice(&(data.len() as u16).to_le_bytes());
+        self.out
+            .extend_from_slice(&(!(data.len() as u16)).to_le_bytes());
+        self.out.extend_from_slice(data);
+    }
+
+    fn static_header(&mut self, is_final: bool) {
+        self.bits(is_final as u32, 1);
+        self.bits(1, 2);
+    }
+
+    /// Writes a symbol of the static literal/length code.
+    fn litlen(&mut self, sym: u32) {
+        match sym {
+            0..=143 => self.code(0x30 + sym, 8),
+            144..=255 => self.code(0x190 + sym - 144, 9),
+            256..=279 => self.code(sym - 256, 7),
+            _ => self.code(0xC0 + sym - 280, 8),
+        }
+    }
+
+    /// Writes a match with the length symbol and distance symbol given explicitly.
+    fn matched(
+        &mut self,
+        len_sym: u32,
+        len_extra: (u32, u32),
+        dist_sym: u32,
+        dist_extra: (u32, u32),
+    ) {
+        self.litlen(len_sym);
+        self.bits(len_extra.0, len_extra.1);
+        self.code(dist_sym, 5);
+        self.bits(dist_extra.0, dist_extra.1);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        self.align();
+        self.out
+    }
+}
+
+fn copy_match(out: &mut Vec<u8>, length: usize, distance: usize) {
+    let start = out.len() - distance;
+    for i in 0..length {
+        out.push(out[start + i]);
+    }
+}
+
+/// A stream using all three Deflate64 extensions, and the data it decodes to.
+fn deflate64_stream() -> (Vec<u8>, Vec<u8>) {
+    let history = random_bytes(60_000, 1);
+    let mut w = BitWriter::default();
+    w.stored_block(&history, false);
+    w.static_header(true);
+    // Length symbol 285 carries 16 extra bits on top of a base of 3.
+    w.matched(285, (1000, 16), 0, (0, 0));
+    // Distance symbol 30: base 32769, 14 extra bits.
+    w.matched(264, (0, 0), 30, (35_000 - 32_769, 14));
+    // Distance symbol 31: base 49153, 14 extra bits.
+    w.matched(285, (65_535, 16), 31, (50_000 - 49_153, 14));
+    w.litlen(b'z' as u32);
+    w.litlen(256);
+
+    let mut expected = history;
+    copy_match(&mut expected, 1003, 1);
+    copy_match(&mut expected, 10, 35_000);
+    copy_match(&mut expected, 65_538, 50_000);
+    expected.push(b'z');
+    (w.finish(), expected)
+}
+
+/// Hands out at most `step` bytes per read so that symbols straddle reads.
+struct Trickle<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl Read for Trickle<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.step).min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn test_deflate64_one_shot() {
+    let (stream, expected) = deflate64_stream();
+
+    let mut d = Decompressor::new();
+    d.set_variant(DeflateVariant::Deflate64);
+    assert_eq!(
+        d.decompress_deflate(&stream, expected.len()).unwrap(),
+        expected
+    );
+
+    let mut out = vec![0u8; expected.len() - 1];
+    assert!(d.decompress_deflate_into(&stream, &mut out).is_err());
+
+    // Standard deflate reads the same bits differently.
+    let mut d = Decompressor::new();
+    assert!(
+        d.decompress_deflate(&stream, expected.len())
+            .map_or(true, |out| out != expected)
+    );
+}
+
+#[test]
+fn test_deflate64_streaming() {
+    let (stream, expected) = deflate64_stream();
+    for step in [1, 7, 4096, usize::MAX] {
+        let mut out = Vec::new();
+        DeflateDecoder::new_deflate64(Trickle {
+            data: &stream,
+            step,
+        })
+        .read_to_end(&mut out)
+        .unwrap();
+        assert!(out == expected, "step {}", step);
+    }
+
+    // Many maximum-length matches keep sliding the 64 KiB window.
+    let mut w = BitWriter::default();
+    w.stored_block(b"deflate64", false);
+    w.static_header(true);
+    for _ in 0..200 {
+        w.matched(285, (65_535, 16), 0, (0, 0));
+        w.litlen(b'!' as u32);
+    }
+    w.litlen(256);
+    let stream = w.finish();
+    let mut expected = b"deflate64".to_vec();
+    for _ in 0..200 {
+        copy_match(&mut expected, 65_538, 1);
+        expected.push(b'!');
+    }
+    let mut out = Vec::new();
+    DeflateDecoder::new_deflate64(&stream[..])
+        .read_to_end(&mut out)
+        .unwrap();
+    assert!(out == expected);
+}
+
+#[test]
+fn test_deflate_decoder_long_stream() {
+    // Symbols cut off by the end of the input or the window must be decoded again whole.
+    let data: Vec<u8> = (0..3_000_000u32)
+        .map(|i| ((i * 7 + i / 1000) % 251) as u8)
+        .collect();
+    let stream = libdeflate::Compressor::new(6)
+        .unwrap()
+        .compress_deflate(&data)
+        .unwrap();
+    for step in [13, usize::MAX] {
+        let mut out = Vec::new();
+        DeflateDecoder::new(Trickle {
+            data: &stream,
+            step,
+        })
+        .read_to_end(&mut out)
+        .unwrap();
+        assert!(out == data, "step {}", step);
+    }
+}
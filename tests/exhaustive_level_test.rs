@@ -0,0 +1,82 @@
+use libdeflate::compress::{Compressor as RawCompressor, MAX_COMPRESSION_LEVEL, Token};
+use libdeflate::{Compressor, Decompressor};
+
+mod common;
+use common::{random_bytes, word_salad};
+
+const WORDS: [&[u8]; 8] = [
+    b"deflate ",
+    b"huffman ",
+    b"block ",
+    b"split ",
+    b"the ",
+    b"of ",
+    b"window ",
+    b"match ",
+];
+
+#[test]
+fn test_exhaustive_levels_roundtrip() {
+    // Mixed content so the block search has something to split, and long enough to span
+    // more than one segment.
+    let mut data = word_salad(200_000, &WORDS);
+    data.extend_from_slice(&random_bytes(40_000, 0x9e3779b9));
+    data.extend_from_slice(&word_salad(100_000, &WORDS));
+
+    let mut decompressor = Decompressor::new();
+    for level in 13..=MAX_COMPRESSION_LEVEL as i32 {
+        let mut compressor = Compressor::new(level).unwrap();
+        for input in [&data[..], b"", b"a", &random_bytes(5000, 0x9e3779b9)] {
+            let deflate = compressor.compress_deflate(input).unwrap();
+            assert_eq!(
+                decompressor
+                    .decompress_deflate(&deflate, input.len())
+                    .unwrap(),
+                input,
+                "level {}",
+                level
+            );
+        }
+        let gz = compressor.compress_gzip(&data).unwrap();
+        assert_eq!(decompressor.decompress_gzip(&gz, data.len()).unwrap(), data);
+    }
+}
+
+#[test]
+fn test_exhaustive_level_beats_level_12() {
+    let data = word_salad(150_000, &WORDS);
+    let size_12 = Compressor::new(12)
+        .unwrap()
+        .compress_deflate(&data)
+        .unwrap()
+        .len();
+    let size_13 = Compressor::new(13)
+        .unwrap()
+        .compress_deflate(&data)
+        .unwrap()
+        .len();
+    assert!(
+        size_13 < size_12,
+        "level 13: {}, level 12: {}",
+        size_13,
+        size_12
+    );
+
+    // Incompressible input must not grow beyond stored blocks.
+    let data = random_bytes(100_000, 0x9e3779b9);
+    let size = Compressor::new(13)
+        .unwrap()
+        .compress_deflate(&data)
+        .unwrap()
+        .len();
+    assert!(size <= data.len() + 5 * data.len().div_ceil(65535));
+}
+
+#[test]
+fn test_exhaustive_parse_tokens() {
+    let data = word_salad(50_000, &WORDS);
+    let tokens = RawCompressor::new(14).parse(&data);
+    let len: usize = tokens.iter().map(Token::uncompressed_len).sum();
+    assert_eq!(len, data.len());
+    assert!(tokens.len() < data.len() / 3);
+}
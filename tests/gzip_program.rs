@@ -0,0 +1,174 @@
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, UNIX_EPOCH};
+
+const GZIP: &str = env!("CARGO_BIN_EXE_libdeflate-gzip");
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("libdeflate-gzip-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn sample_data() -> Vec<u8> {
+    (0..200_000u32)
+        .map(|i| ((i % 251) ^ (i / 1000)) as u8)
+        .collect()
+}
+
+fn run(args: &[&str], stdin: &[u8]) -> Output {
+    let mut child = Command::new(GZIP)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_file_round_trip() {
+    let dir = temp_dir("round-trip");
+    let path = dir.join("data.bin");
+    let gz_path = dir.join("data.bin.gz");
+    let data = sample_data();
+    fs::write(&path, &data).unwrap();
+
+    let out = run(&[path.to_str().unwrap()], &[]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!path.exists());
+    assert!(gz_path.exists());
+
+    let out = run(&["-d", gz_path.to_str().unwrap()], &[]);
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert!(!gz_path.exists());
+    assert_eq!(fs::read(&path).unwrap(), data);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_stdin_stdout_all_levels() {
+    let data = sample_data();
+    for level in 1..=12 {
+        let flag = format!("-{}", level);
+        let out = run(&[&flag, "-c"], &data);
+        assert!(out.status.success());
+
+        // libdeflater links the C libdeflate, whose symbols clash with the `capi` exports.
+        #[cfg(not(feature = "capi"))]
+        {
+            let mut decompressor = libdeflater::Decompressor::new();
+            let mut decoded = vec![0u8; data.len()];
+            let n = decompressor
+                .gzip_decompress(&out.stdout, &mut decoded)
+                .unwrap();
+            assert_eq!(&decoded[..n], &data[..], "level {}", level);
+        }
+
+        let out = run(&["-dc"], &out.stdout);
+        assert!(out.status.success());
+        assert_eq!(out.stdout, data, "level {}", level);
+    }
+}
+
+#[test]
+fn test_keep_and_force() {
+    let dir = temp_dir("keep-force");
+    let path = dir.join("a.txt");
+    let gz_path = dir.join("a.txt.gz");
+    fs::write(&path, b"hello hello hello").unwrap();
+
+    assert!(run(&["-k", path.to_str().unwrap()], &[]).status.success());
+    assert!(path.exists());
+
+    // The output already exists.
+    assert!(!run(&["-k", path.to_str().unwrap()], &[]).status.success());
+    assert!(run(&["-kf", path.to_str().unwrap()], &[]).status.success());
+
+    // Files that already have the suffix are skipped.
+    assert!(!run(&[gz_path.to_str().unwrap()], &[]).status.success());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_multi_member_and_test_mode() {
+    let a = run(&["-c"], b"first member\n");
+    let b = run(&["-9", "-c"], b"second member\n");
+    let mut both = a.stdout;
+    both.extend_from_slice(&b.stdout);
+
+    let out = run(&["-dc"], &both);
+    assert!(out.status.success());
+    assert_eq!(out.stdout, b"first member\nsecond member\n");
+
+    assert!(run(&["-t"], &both).status.success());
+    let mut corrupt = both.clone();
+    let len = corrupt.len();
+    corrupt[len - 6] ^= 1;
+    assert!(!run(&["-t"], &corrupt).status.success());
+}
+
+#[test]
+fn test_name_and_mtime() {
+    let dir = temp_dir("name-mtime");
+    let path = dir.join("original.txt");
+    let data = sample_data();
+    fs::write(&path, &data).unwrap();
+    let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+
+    assert!(run(&[path.to_str().unwrap()], &[]).status.success());
+    let gz_path = dir.join("original.txt.gz");
+    let gz = fs::read(&gz_path).unwrap();
+    assert_eq!(
+        u32::from_le_bytes(gz[4..8].try_into().unwrap()),
+        1_000_000_000
+    );
+    assert_eq!(&gz[10..23], b"original.txt\0");
+    assert_eq!(fs::metadata(&gz_path).unwrap().modified().unwrap(), mtime);
+
+    let out = run(&["-l", gz_path.to_str().unwrap()], &[]);
+    assert!(out.status.success());
+    let listing = String::from_utf8(out.stdout).unwrap();
+    assert!(listing.contains(&data.len().to_string()), "{}", listing);
+
+    // -N restores the stored name, even after a rename.
+    let renamed = dir.join("renamed.gz");
+    fs::rename(&gz_path, &renamed).unwrap();
+    assert!(
+        run(&["-dN", renamed.to_str().unwrap()], &[])
+            .status
+            .success()
+    );
+    assert_eq!(fs::read(&path).unwrap(), data);
+    assert_eq!(fs::metadata(&path).unwrap().modified().unwrap(), mtime);
+
+    // -n stores neither.
+    let out = run(&["-n", "-S", ".z", path.to_str().unwrap()], &[]);
+    assert!(out.status.success());
+    let gz = fs::read(dir.join("original.txt.z")).unwrap();
+    assert_eq!(gz[3], 0);
+    assert_eq!(&gz[4..8], &[0, 0, 0, 0]);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
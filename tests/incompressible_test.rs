@@ -0,0 +1,149 @@
+use libdeflate::compress::{CompressResult, Compressor as RawCompressor, FlushMode, Parallelism};
+use libdeflate::{Compressor, Decompressor};
+use std::mem::MaybeUninit;
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+fn raw_compress(level: usize, probe: bool, data: &[u8], flush_mode: FlushMode) -> Vec<u8> {
+    let mut compressor = RawCompressor::new(level);
+    compressor.set_parallelism(Parallelism::single_threaded());
+    compressor.set_incompressible_probe(probe);
+    let mut out = vec![MaybeUninit::uninit(); compressor.deflate_bound(data.len())];
+    let (res, size, _) = compressor.compress(data, &mut out, flush_mode);
+    assert_eq!(res, CompressResult::Success);
+    out[..size]
+        .iter()
+        .map(|b| unsafe { b.assume_init() })
+        .collect()
+}
+
+#[test]
+fn test_incompressible_data_is_stored() {
+    let data = random_bytes(300_000, 1);
+    let mut decompressor = Decompressor::new();
+    for level in [1, 6, 9, 12] {
+        let mut compressor = Compressor::new(level).unwrap();
+        let compressed = compressor.compress_deflate(&data).unwrap();
+        // Stored blocks: 5 bytes of overhead per 65535 bytes at most.
+        assert!(
+            compressed.len() <= data.len() + 5 * data.len().div_ceil(32 * 1024),
+            "level {level}: {}",
+            compressed.len()
+        );
+        let back = decompressor
+            .decompress_deflate(&compressed, data.len())
+            .unwrap();
+        assert!(back == data, "level {level}");
+
+        // Stored windows in a row make blocks as long as stored blocks can be, as without
+        // the probe.
+        let probed = raw_compress(level as usize, true, &data[..200_000], FlushMode::Finish);
+        let plain = raw_compress(level as usize, false, &data[..200_000], FlushMode::Finish);
+        assert_eq!(probed.len(), plain.len(), "level {level}");
+    }
+}
+
+#[test]
+fn test_incompressible_probe_default_levels() {
+    let mut data = random_bytes(100_000, 6);
+    data.extend(random_bytes(100_000, 7).iter().map(|b| b % 64));
+    for level in 1..=12 {
+        let mut compressor = RawCompressor::new(level);
+        compressor.set_parallelism(Parallelism::single_threaded());
+        let mut out = vec![MaybeUninit::uninit(); compressor.deflate_bound(data.len())];
+        let (res, size, _) = compressor.compress(&data, &mut out, FlushMode::Finish);
+        assert_eq!(res, CompressResult::Success);
+        let out: Vec<u8> = out[..size]
+            .iter()
+            .map(|b| unsafe { b.assume_init() })
+            .collect();
+        // Only levels 6 and up probe unless asked to.
+        let probed = raw_compress(level, level >= 6, &data, FlushMode::Finish);
+        assert!(out == probed, "level {level}");
+    }
+}
+
+#[test]
+fn test_incompressible_probe_mixed_data() {
+    // Compressible, random, 6-bit random (no matches, but Huffman coding pays), then
+    // compressible again.
+    let mut data = periodic_bytes(100_000);
+    data.extend(random_bytes(200_000, 2));
+    data.extend(random_bytes(100_000, 3).iter().map(|b| b % 64));
+    data.extend(periodic_bytes(100_000));
+
+    let mut decompressor = Decompressor::new();
+    for level in [1, 2, 6, 10, 12] {
+        let probed = raw_compress(level, true, &data, FlushMode::Finish);
+        let plain = raw_compress(level, false, &data, FlushMode::Finish);
+        assert!(probed.len() <= plain.len(), "level {level}");
+        let back = decompressor
+            .decompress_deflate(&probed, data.len())
+            .unwrap();
+        assert!(back == data, "level {level}");
+
+        // The size estimate follows the same decisions.
+        let mut compressor = RawCompressor::new(level);
+        compressor.set_incompressible_probe(true);
+        let estimate = compressor.compress_to_size(&data, true);
+        assert!(estimate.abs_diff(probed.len()) < 64, "level {level}");
+
+        let mut synced = raw_compress(level, true, &data, FlushMode::Sync);
+        synced.extend_from_slice(&[3, 0]);
+        let back = decompressor
+            .decompress_deflate(&synced, data.len())
+            .unwrap();
+        assert!(back == data, "level {level}");
+    }
+
+    // Parallel chunks probe too.
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor.set_parallelism(Parallelism::new().with_chunk_size(64 * 1024));
+    let compressed = compressor.compress_deflate(&data).unwrap();
+    let back = decompressor
+        .decompress_deflate(&compressed, data.len())
+        .unwrap();
+    assert!(back == data);
+}
+
+#[test]
+fn test_incompressible_probe_huffman_only() {
+    let data: Vec<u8> = random_bytes(200_000, 4).iter().map(|b| b % 64).collect();
+    for level in [1, 6, 12] {
+        let compressed = raw_compress(level, true, &data, FlushMode::Finish);
+        // Six bits per byte, plus the block headers.
+        assert!(
+            compressed.len() < data.len() * 6 / 8 + 1024,
+            "level {level}"
+        );
+        let back = Decompressor::new()
+            .decompress_deflate(&compressed, data.len())
+            .unwrap();
+        assert!(back == data, "level {level}");
+    }
+}
+
+#[test]
+fn test_incompressible_probe_keeps_compressible_output() {
+    let data = periodic_bytes(500_000);
+    for level in [1, 6, 9, 12, 13] {
+        assert!(
+            raw_compress(level, true, &data, FlushMode::Finish)
+                == raw_compress(level, false, &data, FlushMode::Finish),
+            "level {level}"
+        );
+    }
+
+    // Random data repeating exactly one probe window later: the first copy is stored, and
+    // the rest still match against it.
+    let data = random_bytes(32 * 1024, 5).repeat(8);
+    for level in [2, 6, 9] {
+        let probed = raw_compress(level, true, &data, FlushMode::Finish);
+        assert!(probed.len() < 40_000, "level {level}: {}", probed.len());
+        let back = Decompressor::new()
+            .decompress_deflate(&probed, data.len())
+            .unwrap();
+        assert!(back == data, "level {level}");
+    }
+}
@@ -0,0 +1,79 @@
+use libdeflate::decompress::{DecompressResult, OutputChecksum};
+use libdeflate::{Compressor, Decompressor, adler32, crc32};
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+/// Runs and noise in turn, so the stream has matches as well as literals.
+fn sample_data(len: usize) -> Vec<u8> {
+    let runs = periodic_bytes(len);
+    let noise = random_bytes(len, 1);
+    (0..len)
+        .map(|i| if i % 1024 < 512 { runs[i] } else { noise[i] })
+        .collect()
+}
+
+#[test]
+fn test_gzip_zlib_trailer_checked() {
+    let data = sample_data(300_000);
+    for level in [0, 1, 6, 12] {
+        let mut compressor = Compressor::new(level).unwrap();
+        let mut decompressor = Decompressor::new();
+
+        let mut gz = compressor.compress_gzip(&data).unwrap();
+        assert_eq!(decompressor.decompress_gzip(&gz, data.len()).unwrap(), data);
+        let n = gz.len();
+        gz[n - 8] ^= 1;
+        assert!(decompressor.decompress_gzip(&gz, data.len()).is_err());
+
+        let mut zl = compressor.compress_zlib(&data).unwrap();
+        assert_eq!(decompressor.decompress_zlib(&zl, data.len()).unwrap(), data);
+        let n = zl.len();
+        zl[n - 1] ^= 1;
+        assert!(decompressor.decompress_zlib(&zl, data.len()).is_err());
+    }
+}
+
+#[test]
+fn test_streaming_output_checksum() {
+    let data = sample_data(200_000);
+    for level in [0, 1, 6, 12] {
+        let mut compressor = Compressor::new(level).unwrap();
+        let compressed = compressor.compress_deflate(&data).unwrap();
+
+        for kind in [OutputChecksum::Crc32, OutputChecksum::Adler32] {
+            let mut decompressor = libdeflate::decompress::Decompressor::new();
+            decompressor.set_output_checksum(kind);
+
+            let mut output = vec![0u8; data.len()];
+            let mut out_idx = 0;
+            let (res, _, _) =
+                decompressor.decompress_streaming(&compressed, &mut output, &mut out_idx);
+            assert_eq!(res, DecompressResult::Success);
+            assert_eq!(&output[..out_idx], &data[..]);
+
+            let expected = match kind {
+                OutputChecksum::Crc32 => crc32(0, &data),
+                _ => adler32(1, &data),
+            };
+            assert_eq!(decompressor.output_checksum(), expected, "level {}", level);
+        }
+    }
+}
+
+#[test]
+fn test_one_shot_output_checksum() {
+    let data = sample_data(100_000);
+    let mut compressor = Compressor::new(6).unwrap();
+    let compressed = compressor.compress_deflate(&data).unwrap();
+
+    let mut decompressor = libdeflate::decompress::Decompressor::new();
+    decompressor.set_output_checksum(OutputChecksum::Crc32);
+    let mut output = vec![0u8; data.len()];
+    for _ in 0..2 {
+        let (res, _, size) = decompressor.decompress(&compressed, &mut output);
+        assert_eq!(res, DecompressResult::Success);
+        assert_eq!(size, data.len());
+        assert_eq!(decompressor.output_checksum(), crc32(0, &data));
+    }
+}
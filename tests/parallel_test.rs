@@ -1,4 +1,12 @@
+use libdeflate::batch::BatchCompressor;
+use libdeflate::compress::Parallelism;
+use libdeflate::stream::DeflateEncoder;
 use libdeflate::{Compressor, Decompressor};
+use std::io::Write;
+use std::sync::Arc;
+
+mod common;
+use common::random_bytes;
 
 #[test]
 fn test_parallel_deflate_1mb() {
@@ -115,3 +123,233 @@ fn test_parallel_insufficient_space() {
             || err.to_string().contains("Compression failed")
     );
 }
+
+#[test]
+fn test_parallel_trailer_checksums() {
+    // Several full chunks plus a partial one, so the per-chunk checksums get combined.
+    let size: usize = 3 * 256 * 1024 + 12345;
+    let data: Vec<u8> = (0..size)
+        .map(|i| (i.wrapping_mul(31) % 253) as u8)
+        .collect();
+
+    let mut compressor = Compressor::new(6).unwrap();
+
+    let gz = compressor.compress_gzip(&data).unwrap();
+    let trailer = &gz[gz.len() - 8..];
+    assert_eq!(
+        u32::from_le_bytes(trailer[..4].try_into().unwrap()),
+        libdeflate::crc32(0, &data)
+    );
+    assert_eq!(
+        u32::from_le_bytes(trailer[4..].try_into().unwrap()),
+        size as u32
+    );
+
+    let zl = compressor.compress_zlib(&data).unwrap();
+    assert_eq!(
+        u32::from_be_bytes(zl[zl.len() - 4..].try_into().unwrap()),
+        libdeflate::adler32::adler32(1, &data)
+    );
+}
+
+#[test]
+fn test_parallel_repeated_calls_and_tight_output() {
+    let size = 5 * 256 * 1024 + 777;
+    let data: Vec<u8> = (0..size)
+        .map(|i| ((i * 7 + i / 1000) % 251) as u8)
+        .collect();
+
+    let mut compressor = Compressor::new(6).unwrap();
+    let mut decompressor = Decompressor::new();
+    let first = compressor.compress_deflate(&data).unwrap();
+    for _ in 0..3 {
+        assert_eq!(compressor.compress_deflate(&data).unwrap(), first);
+    }
+    assert_eq!(decompressor.decompress_deflate(&first, size).unwrap(), data);
+
+    // Too small for every chunk's bound, but large enough for the result.
+    let mut output = vec![0u8; first.len()];
+    let n = compressor
+        .compress_deflate_into(&data, &mut output)
+        .unwrap();
+    assert_eq!(&output[..n], &first[..]);
+}
+
+#[test]
+fn test_parallel_chunk_priming() {
+    // A 20 KB random block repeated: every chunk after the first starts mid-repeat, so
+    // without history its first 20 KB cannot be matched. The odd length leaves a short
+    // last chunk.
+    let data: Vec<u8> = random_bytes(20_000, 1)
+        .iter()
+        .cycle()
+        .take(4 * 256 * 1024 + 12_345)
+        .copied()
+        .collect();
+
+    let mut decompressor = Decompressor::new();
+    for level in [0, 1, 6, 10, 12, 13, 14, 15, 16] {
+        let mut compressor = Compressor::new(level).unwrap();
+        let primed = compressor.compress_deflate(&data).unwrap();
+        let primed_gzip = compressor.compress_gzip(&data).unwrap();
+        compressor.set_chunk_priming(false);
+        let independent = compressor.compress_deflate(&data).unwrap();
+
+        if level == 0 {
+            // Stored blocks take nothing from the history.
+            assert_eq!(primed, independent);
+        } else {
+            assert!(
+                primed.len() + 3 * 15_000 < independent.len(),
+                "level {}: primed {}, independent {}",
+                level,
+                primed.len(),
+                independent.len()
+            );
+        }
+        // Matches reach back across every chunk boundary of the primed streams.
+        for stream in [&primed, &independent] {
+            assert_eq!(
+                decompressor.decompress_deflate(stream, data.len()).unwrap(),
+                data,
+                "level {}",
+                level
+            );
+        }
+        assert_eq!(
+            decompressor
+                .decompress_gzip(&primed_gzip, data.len())
+                .unwrap(),
+            data,
+            "level {}",
+            level
+        );
+    }
+}
+
+#[test]
+fn test_parallelism_controls() {
+    let size = 3 * 256 * 1024 + 123;
+    let data: Vec<u8> = (0..size)
+        .map(|i| ((i * 7 + i / 1000) % 251) as u8)
+        .collect();
+    let mut decompressor = Decompressor::new();
+
+    // Single-threaded compresses the whole input in one piece, like an unreachable threshold.
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor.set_parallelism(Parallelism::new().with_threshold(usize::MAX));
+    let whole = compressor.compress_deflate(&data).unwrap();
+    compressor.set_parallelism(Parallelism::single_threaded());
+    assert_eq!(compressor.compress_deflate(&data).unwrap(), whole);
+
+    // The output depends on the chunk size only, not on the pool.
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap(),
+    );
+    compressor.set_parallelism(Parallelism::new());
+    let global = compressor.compress_deflate(&data).unwrap();
+    compressor.set_parallelism(Parallelism::new().with_thread_pool(pool.clone()));
+    assert_eq!(compressor.compress_deflate(&data).unwrap(), global);
+    assert_ne!(global, whole);
+
+    // Small chunks cost more than the default bound allows for; the instance bound covers them.
+    let small = Parallelism::new()
+        .with_chunk_size(1024)
+        .with_threshold(0)
+        .with_thread_pool(pool);
+    compressor.set_parallelism(small);
+    let bound = compressor.deflate_compress_bound(data.len());
+    let mut output = vec![0u8; bound];
+    let n = compressor
+        .compress_deflate_into(&data, &mut output)
+        .unwrap();
+    assert_eq!(
+        decompressor.decompress_deflate(&output[..n], size).unwrap(),
+        data
+    );
+    let gz = compressor.compress_gzip(&data).unwrap();
+    assert_eq!(decompressor.decompress_gzip(&gz, size).unwrap(), data);
+}
+
+#[test]
+fn test_parallelism_stream_and_batch() {
+    let size = 2 * 1024 * 1024 + 99;
+    let data: Vec<u8> = (0..size)
+        .map(|i| ((i * 13 + i / 777) % 253) as u8)
+        .collect();
+    let mut decompressor = Decompressor::new();
+    let pool = Arc::new(
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(2)
+            .build()
+            .unwrap(),
+    );
+
+    for parallelism in [
+        Parallelism::single_threaded(),
+        Parallelism::new()
+            .with_chunk_size(64 * 1024)
+            .with_thread_pool(pool.clone()),
+    ] {
+        let mut encoder = DeflateEncoder::new(Vec::new(), 6).with_parallelism(parallelism.clone());
+        for chunk in data.chunks(100_000) {
+            encoder.write_all(chunk).unwrap();
+        }
+        let stream = encoder.finish().unwrap();
+        assert_eq!(
+            decompressor.decompress_deflate(&stream, size).unwrap(),
+            data
+        );
+
+        let mut batch = BatchCompressor::new(6);
+        batch.set_parallelism(parallelism);
+        let inputs: Vec<&[u8]> = vec![&data, &data[..1000], b""];
+        let outputs = batch.compress_batch(&inputs);
+        for (input, output) in inputs.iter().zip(&outputs) {
+            assert_eq!(
+                decompressor
+                    .decompress_deflate(output, input.len())
+                    .unwrap(),
+                *input
+            );
+        }
+    }
+}
+
+#[test]
+fn test_near_optimal_levels_with_primed_chunks() {
+    // The first pass over a primed chunk matches from the history at once, leaving most
+    // literals without a codeword; the parse must not treat those as free.
+    let size = 4 * 256 * 1024;
+    let data: Vec<u8> = (0..size)
+        .map(|i| ((i * 7 + i / 1000) % 251) as u8)
+        .collect();
+    let level_9 = Compressor::new(9)
+        .unwrap()
+        .compress_deflate(&data)
+        .unwrap()
+        .len();
+    for level in 10..=12 {
+        let mut compressor = Compressor::new(level).unwrap();
+        let primed = compressor.compress_deflate(&data).unwrap();
+        compressor.set_chunk_priming(false);
+        let independent = compressor.compress_deflate(&data).unwrap().len();
+        assert!(
+            primed.len() <= independent && primed.len() < 2 * level_9,
+            "level {}: primed {}, independent {}, level 9 {}",
+            level,
+            primed.len(),
+            independent,
+            level_9
+        );
+        assert_eq!(
+            Decompressor::new()
+                .decompress_deflate(&primed, size)
+                .unwrap(),
+            data
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use libdeflate::Compressor;
+use libdeflate::compress::{Format, MAX_COMPRESSION_LEVEL, Parallelism};
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+/// Text, random data, 6-bit random data and a repeat of the start, to hit every kind of
+/// block.
+fn mixed(len: usize) -> Vec<u8> {
+    let mut data = periodic_bytes(len / 4);
+    data.extend(random_bytes(len / 4, 5));
+    data.extend(random_bytes(len / 4, 9).iter().map(|b| b & 0x3F));
+    data.extend_from_within(..len / 4);
+    data
+}
+
+fn compressed_len(compressor: &mut Compressor, data: &[u8], format: Format) -> usize {
+    match format {
+        Format::Deflate => compressor.compress_deflate(data),
+        Format::Zlib => compressor.compress_zlib(data),
+        Format::Gzip => compressor.compress_gzip(data),
+    }
+    .unwrap()
+    .len()
+}
+
+#[test]
+fn test_estimate_is_exact_at_every_level() {
+    let data = mixed(800_000);
+    for level in 0..=MAX_COMPRESSION_LEVEL as i32 {
+        let mut compressor = Compressor::new(level).unwrap();
+        for format in [Format::Deflate, Format::Zlib, Format::Gzip] {
+            let estimate = compressor.estimate_compressed_size(&data, format);
+            assert_eq!(
+                estimate,
+                compressed_len(&mut compressor, &data, format),
+                "level {} {:?}",
+                level,
+                format
+            );
+        }
+    }
+}
+
+#[test]
+fn test_estimate_is_exact_for_small_inputs() {
+    let inputs = [
+        Vec::new(),
+        vec![7],
+        periodic_bytes(100),
+        random_bytes(70_000, 3),
+    ];
+    for level in [0, 1, 6, 12, 14] {
+        let mut compressor = Compressor::new(level).unwrap();
+        for data in &inputs {
+            for format in [Format::Deflate, Format::Zlib, Format::Gzip] {
+                assert_eq!(
+                    compressor.estimate_compressed_size(data, format),
+                    compressed_len(&mut compressor, data, format),
+                    "level {} len {} {:?}",
+                    level,
+                    data.len(),
+                    format
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_estimate_is_exact_for_parallel_chunks() {
+    let data = mixed(1_200_000);
+    for priming in [true, false] {
+        for level in [0, 1, 6, 10] {
+            let mut compressor = Compressor::new(level).unwrap();
+            compressor.set_parallelism(
+                Parallelism::new()
+                    .with_chunk_size(200_000)
+                    .with_threshold(200_000),
+            );
+            compressor.set_chunk_priming(priming);
+            for format in [Format::Deflate, Format::Gzip] {
+                assert_eq!(
+                    compressor.estimate_compressed_size(&data, format),
+                    compressed_len(&mut compressor, &data, format),
+                    "level {} priming {} {:?}",
+                    level,
+                    priming,
+                    format
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_approximate_size_is_close() {
+    let data = mixed(800_000);
+    for level in [1, 3, 6, 9, 12] {
+        let mut compressor = Compressor::new(level).unwrap();
+        for format in [Format::Deflate, Format::Zlib] {
+            let exact = compressed_len(&mut compressor, &data, format);
+            let approximate = compressor.approximate_compressed_size(&data, format);
+            assert!(
+                approximate.abs_diff(exact) * 10 < exact,
+                "level {} {:?}: {} vs {}",
+                level,
+                format,
+                approximate,
+                exact
+            );
+        }
+    }
+}
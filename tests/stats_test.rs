@@ -0,0 +1,162 @@
+use libdeflate::compress::{
+    CompressResult, CompressionStats, Compressor as RawCompressor, FlushMode, Format, Parallelism,
+};
+use libdeflate::stream::DeflateEncoder;
+use libdeflate::{Compressor, Decompressor};
+use std::io::Write;
+use std::mem::MaybeUninit;
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+fn compress_with_stats(level: i32, data: &[u8]) -> (Vec<u8>, CompressionStats) {
+    let mut compressor = Compressor::new(level).unwrap();
+    compressor.set_collect_stats(true);
+    let compressed = compressor.compress_deflate(data).unwrap();
+    (compressed, compressor.stats().unwrap().clone())
+}
+
+#[test]
+fn test_stats_disabled_by_default() {
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor
+        .compress_deflate(&periodic_bytes(10_000))
+        .unwrap();
+    assert!(compressor.stats().is_none());
+}
+
+#[test]
+fn test_stats_account_for_every_bit_and_byte() {
+    let data = periodic_bytes(500_000);
+    for level in [1, 2, 6, 9, 10, 12, 13, 16] {
+        let (compressed, stats) = compress_with_stats(level, &data);
+        assert!(stats.blocks() > stats.stored_blocks);
+        if level == 1 {
+            assert_eq!(stats.dynamic_blocks, 0);
+        }
+        assert_eq!(
+            stats.literals + stats.match_bytes,
+            data.len() as u64,
+            "level {}",
+            level
+        );
+        assert!(stats.matches > 0 && stats.average_match_length() >= 3.0);
+        assert_eq!(
+            (stats.header_bits + stats.payload_bits).div_ceil(8),
+            compressed.len() as u64,
+            "level {}",
+            level
+        );
+        assert!(stats.match_finding_time + stats.encoding_time > std::time::Duration::ZERO);
+    }
+}
+
+#[test]
+fn test_stats_of_stored_blocks() {
+    let data = random_bytes(200_000, 3);
+    for level in [0, 6] {
+        let (compressed, stats) = compress_with_stats(level, &data);
+        assert!(stats.stored_blocks >= 4, "level {}", level);
+        assert_eq!(stats.literals + stats.matches, 0);
+        assert_eq!(stats.average_match_length(), 0.0);
+        assert_eq!(stats.payload_bits, data.len() as u64 * 8);
+        assert_eq!(
+            (stats.header_bits + stats.payload_bits).div_ceil(8),
+            compressed.len() as u64
+        );
+    }
+}
+
+#[test]
+fn test_stats_of_stored_fallback() {
+    // A Huffman block for one byte and the empty block ending the flush overrun the bound,
+    // so level 6 falls back to the stored blocks level 0 writes.
+    let data = [0x5a];
+    let mut stats = Vec::new();
+    for level in [0, 6] {
+        let mut compressor = RawCompressor::new(level);
+        compressor.set_collect_stats(true);
+        let bound = RawCompressor::deflate_compress_bound(data.len());
+        let mut out = vec![MaybeUninit::uninit(); bound];
+        let (res, size, _) = compressor.compress(&data, &mut out, FlushMode::Sync);
+        assert_eq!(res, CompressResult::Success);
+        let level_stats = compressor.stats().unwrap().clone();
+        assert_eq!(
+            (level_stats.header_bits + level_stats.payload_bits).div_ceil(8),
+            size as u64,
+            "level {}",
+            level
+        );
+        stats.push(level_stats);
+    }
+    assert_eq!(stats[1].stored_blocks, stats[0].stored_blocks);
+    assert_eq!(stats[1].blocks(), stats[0].blocks());
+}
+
+#[test]
+fn test_stats_are_per_call() {
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor.set_collect_stats(true);
+    let small = periodic_bytes(1000);
+    compressor.compress_zlib(&periodic_bytes(300_000)).unwrap();
+    compressor.compress_gzip(&small).unwrap();
+    let stats = compressor.stats().unwrap().clone();
+    assert_eq!(stats.literals + stats.match_bytes, small.len() as u64);
+
+    // Estimating a size is not a compression call.
+    compressor.estimate_compressed_size(&periodic_bytes(300_000), Format::Zlib);
+    assert_eq!(compressor.stats(), Some(&stats));
+
+    compressor.set_collect_stats(false);
+    compressor.compress_deflate(&small).unwrap();
+    assert!(compressor.stats().is_none());
+}
+
+#[test]
+fn test_stats_of_parallel_chunks() {
+    let data = periodic_bytes(1_000_000);
+    let mut compressor = Compressor::new(6).unwrap();
+    compressor.set_collect_stats(true);
+    compressor.set_parallelism(
+        Parallelism::new()
+            .with_chunk_size(200_000)
+            .with_threshold(200_000),
+    );
+    for _ in 0..2 {
+        let compressed = compressor.compress_gzip(&data).unwrap();
+        let stats = compressor.stats().unwrap();
+        assert!(stats.dynamic_blocks >= 5);
+        assert_eq!(stats.literals + stats.match_bytes, data.len() as u64);
+        assert_eq!(
+            Decompressor::new()
+                .decompress_gzip(&compressed, data.len())
+                .unwrap(),
+            data
+        );
+    }
+}
+
+#[test]
+fn test_stream_stats() {
+    let data = periodic_bytes(700_000);
+    let mut encoder = DeflateEncoder::new(Vec::new(), 6)
+        .with_buffer_size(100_000)
+        .with_collect_stats(true);
+    encoder.write_all(&data[..350_000]).unwrap();
+    let partial = encoder.stats().unwrap().clone();
+    assert!(partial.literals + partial.match_bytes >= 300_000);
+    encoder.write_all(&data[350_000..]).unwrap();
+    let (compressed, stats) = encoder.finish_with_stats().unwrap();
+    let stats = stats.unwrap();
+    assert!(stats.dynamic_blocks >= 2);
+    assert_eq!(stats.literals + stats.match_bytes, data.len() as u64);
+    assert_eq!(
+        (stats.header_bits + stats.payload_bits).div_ceil(8),
+        compressed.len() as u64
+    );
+
+    let (_, stats) = DeflateEncoder::new(Vec::new(), 6)
+        .finish_with_stats()
+        .unwrap();
+    assert!(stats.is_none());
+}
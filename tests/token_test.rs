@@ -0,0 +1,113 @@
+use libdeflate::Decompressor;
+use libdeflate::compress::{Compressor, Token, encode_tokens, parse};
+use std::io::ErrorKind;
+
+mod common;
+use common::{periodic_bytes, random_bytes};
+
+fn sample_data(len: usize) -> Vec<u8> {
+    let runs = periodic_bytes(len);
+    let noise = random_bytes(len, 1);
+    (0..len)
+        .map(|i| if i % 2048 < 1200 { runs[i] } else { noise[i] })
+        .collect()
+}
+
+fn expand(tokens: &[Token]) -> Vec<u8> {
+    let mut out = Vec::new();
+    for token in tokens {
+        match *token {
+            Token::Literal(b) => out.push(b),
+            Token::Match { length, distance } => {
+                let start = out.len() - distance as usize;
+                for i in 0..length as usize {
+                    out.push(out[start + i]);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn inflate(stream: &[u8], len: usize) -> Vec<u8> {
+    Decompressor::new().decompress_deflate(stream, len).unwrap()
+}
+
+#[test]
+fn test_parse_encode_roundtrip() {
+    let data = sample_data(200_000);
+    for level in [0, 1, 6, 9, 12] {
+        let tokens = Compressor::new(level).parse(&data);
+        assert_eq!(expand(&tokens), data, "level {}", level);
+        if level > 0 {
+            assert!(tokens.iter().any(|t| matches!(t, Token::Match { .. })));
+        }
+
+        let mut out = vec![0u8; data.len() + data.len() / 8 + 1024];
+        let n = encode_tokens(&tokens, &mut out, &[]).unwrap();
+        assert_eq!(inflate(&out[..n], data.len()), data, "level {}", level);
+    }
+
+    assert_eq!(parse(&data), Compressor::new(6).parse(&data));
+}
+
+#[test]
+fn test_encode_with_block_boundaries() {
+    let data = sample_data(100_000);
+    let tokens = parse(&data);
+    let boundaries: Vec<usize> = (1..8).map(|i| i * tokens.len() / 8).collect();
+
+    let mut out = vec![0u8; data.len() * 2];
+    let n = encode_tokens(&tokens, &mut out, &boundaries).unwrap();
+    assert_eq!(inflate(&out[..n], data.len()), data);
+
+    // Hand-written tokens with a match that overlaps its own output.
+    let tokens = [
+        Token::Literal(b'a'),
+        Token::Literal(b'b'),
+        Token::Match {
+            length: 10,
+            distance: 2,
+        },
+        Token::Literal(b'c'),
+    ];
+    let n = encode_tokens(&tokens, &mut out, &[3]).unwrap();
+    assert_eq!(inflate(&out[..n], 13), b"ababababababc");
+
+    let n = encode_tokens(&[], &mut out, &[]).unwrap();
+    assert_eq!(inflate(&out[..n], 0), b"");
+}
+
+#[test]
+fn test_encode_rejects_invalid_tokens() {
+    let mut out = vec![0u8; 1024];
+    let bad_distance = [
+        Token::Literal(b'a'),
+        Token::Match {
+            length: 3,
+            distance: 2,
+        },
+    ];
+    let short_match = [
+        Token::Literal(b'a'),
+        Token::Match {
+            length: 2,
+            distance: 1,
+        },
+    ];
+    for tokens in [&bad_distance[..], &short_match[..]] {
+        let err = encode_tokens(tokens, &mut out, &[]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    let tokens = [Token::Literal(b'a'); 4];
+    for boundaries in [&[0][..], &[2, 2], &[4], &[3, 1]] {
+        let err = encode_tokens(&tokens, &mut out, boundaries).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    }
+
+    let data = sample_data(50_000);
+    let tokens = parse(&data);
+    let mut small = vec![0u8; 64];
+    assert!(encode_tokens(&tokens, &mut small, &[]).is_err());
+}
@@ -1,5 +1,8 @@
 use libdeflate::{Compressor, Decompressor, adler32, crc32};
 
+mod common;
+use common::random_bytes;
+
 #[test]
 fn test_adler32_empty() {
     let buf = [];
@@ -200,16 +203,16 @@ fn test_new_compressor_invalid_level() {
     assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     assert_eq!(
         err.to_string(),
-        "Compression level must be between 0 and 12"
+        "Compression level must be between 0 and 16"
     );
 
-    let res = Compressor::new(13);
+    let res = Compressor::new(17);
     assert!(res.is_err());
     let err = res.err().unwrap();
     assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     assert_eq!(
         err.to_string(),
-        "Compression level must be between 0 and 12"
+        "Compression level must be between 0 and 16"
     );
 }
 
@@ -348,6 +351,8 @@ fn test_offset_3_large_match() {
     }
 }
 
+// libdeflater links the C libdeflate, whose symbols clash with the `capi` exports.
+#[cfg(not(feature = "capi"))]
 #[test]
 fn test_crc32_tails_vs_reference() {
     // Verify CRC32 against libdeflater (reference implementation)
@@ -357,6 +362,7 @@ fn test_crc32_tails_vs_reference() {
         20, 28, 31, 32, // Medium with tails
         100, 108, 128, // Larger with tails
         1024, 1036, // Block + tails
+        256, 300, 383, 384, 511, // Between the two- and eight-vector folds
     ];
 
     for &size in &sizes {
@@ -367,6 +373,19 @@ fn test_crc32_tails_vs_reference() {
     }
 }
 
+#[cfg(not(feature = "capi"))]
+#[test]
+fn test_crc32_folds_every_vector_of_short_inputs() {
+    // Inputs of 256 to 383 bytes once left two of the four 512-bit vectors out of the fold.
+    let data: Vec<u8> = (0..1024u32)
+        .map(|i| (i.wrapping_mul(2654435761) >> 11) as u8)
+        .collect();
+    for len in 192..448 {
+        let slice = &data[..len];
+        assert_eq!(crc32(0, slice), libdeflater::crc32(slice), "len {}", len);
+    }
+}
+
 #[test]
 fn test_decompress_dos_attempt() {
     let mut decompressor = Decompressor::new();
@@ -387,3 +406,98 @@ fn test_decompress_dos_attempt() {
     assert_eq!(err.kind(), std::io::ErrorKind::InvalidInput);
     assert!(err.to_string().contains("exceeds safety limit"));
 }
+
+#[test]
+fn test_decompress_end_of_block_in_subtable() {
+    // At level 2 this input gives the end-of-block symbol a codeword longer than the main
+    // litlen table, so it is only reachable through a subtable.
+    let data: Vec<u8> = (0..200_000u32)
+        .map(|i| ((i % 251) ^ (i / 1000)) as u8)
+        .collect();
+    let mut compressor = Compressor::new(2).unwrap();
+    let compressed = compressor.compress_deflate(&data).unwrap();
+
+    let mut decompressor = Decompressor::new();
+    let decompressed = decompressor
+        .decompress_deflate(&compressed, data.len())
+        .unwrap();
+    assert_eq!(decompressed, data);
+
+    let mut decompressor = libdeflate::decompress::Decompressor::new();
+    let mut output = vec![0u8; data.len()];
+    let mut out_idx = 0;
+    let (res, _, _) = decompressor.decompress_streaming(&compressed, &mut output, &mut out_idx);
+    assert_eq!(res, libdeflate::decompress::DecompressResult::Success);
+    assert_eq!(output, data);
+}
+
+#[test]
+fn test_compress_bound_covers_parallel_chunks() {
+    // Stored data split into parallel chunks pays block and sync overhead in every chunk.
+    let size = 1_000_000;
+    let data = random_bytes(size, 1);
+    let mut compressor = Compressor::new(0).unwrap();
+    let compressed = compressor.compress_deflate(&data).unwrap();
+    assert!(compressed.len() <= compressor.deflate_compress_bound(size));
+
+    let mut decompressor = Decompressor::new();
+    let decompressed = decompressor.decompress_deflate(&compressed, size).unwrap();
+    assert_eq!(decompressed, data);
+}
+
+#[test]
+fn test_compress_incompressible_all_levels() {
+    // Covers both the single-call path and the parallel chunked path.
+    for size in [1000, 600_000] {
+        let data = random_bytes(size, 2);
+        for level in 0..=12 {
+            let mut compressor = Compressor::new(level).unwrap();
+            let compressed = compressor.compress_deflate(&data).unwrap();
+            assert!(compressed.len() <= compressor.deflate_compress_bound(size));
+
+            let mut decompressor = Decompressor::new();
+            let decompressed = decompressor.decompress_deflate(&compressed, size).unwrap();
+            assert_eq!(decompressed, data, "level {}", level);
+        }
+    }
+}
+
+#[cfg(not(feature = "capi"))]
+#[test]
+fn test_compress_empty_all_levels() {
+    for level in 0..=12 {
+        let mut compressor = Compressor::new(level).unwrap();
+        let compressed = compressor.compress_deflate(&[]).unwrap();
+
+        let mut decompressor = libdeflater::Decompressor::new();
+        let mut out = [0u8; 1];
+        assert_eq!(
+            decompressor
+                .deflate_decompress(&compressed, &mut out)
+                .unwrap(),
+            0,
+            "level {}",
+            level
+        );
+    }
+}
+
+#[test]
+fn test_compress_base64_text() {
+    // Text over a small alphabet produces hash chains that reach exactly one window back.
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let data: Vec<u8> = random_bytes(100_000, 3)
+        .iter()
+        .map(|&b| ALPHABET[(b & 63) as usize])
+        .collect();
+    for level in 1..=12 {
+        let mut compressor = Compressor::new(level).unwrap();
+        let compressed = compressor.compress_deflate(&data).unwrap();
+
+        let mut decompressor = Decompressor::new();
+        let decompressed = decompressor
+            .decompress_deflate(&compressed, data.len())
+            .unwrap();
+        assert_eq!(decompressed, data, "level {}", level);
+    }
+}
@@ -0,0 +1,206 @@
+use libdeflate::zip::{
+    ZIP_FLAG_DATA_DESCRIPTOR, ZIP_FLAG_UTF8, ZIP_METHOD_DEFLATE, ZIP_METHOD_STORED, ZipArchive,
+    ZipWriter,
+};
+use std::io::{Cursor, ErrorKind, Write};
+
+mod common;
+use common::{random_bytes, word_salad};
+
+const WORDS: [&[u8]; 6] = [
+    b"zip ",
+    b"entry ",
+    b"central ",
+    b"directory ",
+    b"the ",
+    b"of ",
+];
+
+#[test]
+fn test_zip_roundtrip() {
+    let text = word_salad(300_000, &WORDS);
+    let random = random_bytes(10_000, 0x9e3779b9);
+    let big = word_salad(3_000_000, &WORDS);
+
+    let mut zip = ZipWriter::new(Vec::new(), 6).unwrap();
+    zip.set_comment(b"archive comment");
+    zip.add_file("text.txt", &text).unwrap();
+    zip.add_file("random.bin", &random).unwrap();
+    zip.add_file("empty", b"").unwrap();
+    zip.add_files(&[
+        ("dir/a.txt", &text[..1000]),
+        ("dir/b.bin", &random[..]),
+        ("dir/ünïcode.txt", b"hello hello hello hello"),
+    ])
+    .unwrap();
+    let mut file = zip.start_file("streamed.txt").unwrap();
+    for chunk in big.chunks(70_001) {
+        file.write_all(chunk).unwrap();
+    }
+    file.finish().unwrap();
+    // Dropping the entry writer finishes the entry too.
+    zip.start_file("streamed-empty").unwrap();
+    let bytes = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.comment(), b"archive comment");
+    let names: Vec<&str> = archive.entries().iter().map(|e| e.name.as_str()).collect();
+    assert_eq!(
+        names,
+        [
+            "text.txt",
+            "random.bin",
+            "empty",
+            "dir/a.txt",
+            "dir/b.bin",
+            "dir/ünïcode.txt",
+            "streamed.txt",
+            "streamed-empty"
+        ]
+    );
+
+    let entries = archive.entries().to_vec();
+    assert_eq!(entries[0].method, ZIP_METHOD_DEFLATE);
+    assert!(entries[0].compressed_size < text.len() as u64 / 2);
+    assert_eq!(entries[1].method, ZIP_METHOD_STORED);
+    assert_eq!(entries[4].method, ZIP_METHOD_STORED);
+    assert_ne!(entries[5].flags & ZIP_FLAG_UTF8, 0);
+    assert_ne!(entries[6].flags & ZIP_FLAG_DATA_DESCRIPTOR, 0);
+    assert_eq!(entries[6].uncompressed_size, big.len() as u64);
+
+    let expected: [&[u8]; 8] = [
+        &text,
+        &random,
+        b"",
+        &text[..1000],
+        &random,
+        b"hello hello hello hello",
+        &big,
+        b"",
+    ];
+    for (i, data) in expected.iter().enumerate() {
+        assert!(archive.read(i).unwrap() == *data, "entry {}", i);
+    }
+    assert_eq!(archive.read_by_name("dir/a.txt").unwrap(), &text[..1000]);
+    assert_eq!(
+        archive.read_by_name("missing").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+}
+
+#[test]
+fn test_zip_streamed_entry_layout() {
+    let data = word_salad(100_000, &WORDS);
+    let mut zip = ZipWriter::new(Vec::new(), 6).unwrap();
+    let mut file = zip.start_file("s").unwrap();
+    file.write_all(&data).unwrap();
+    file.finish().unwrap();
+    let bytes = zip.finish().unwrap();
+    let le16 = |at: usize| u16::from_le_bytes(bytes[at..at + 2].try_into().unwrap());
+    let le32 = |at: usize| u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap());
+    let le64 = |at: usize| u64::from_le_bytes(bytes[at..at + 8].try_into().unwrap());
+
+    // The local header asks for ZIP64 and leaves the sizes to a ZIP64 extra field of zeros.
+    assert_eq!(le32(0), 0x04034b50);
+    assert_eq!(le16(4), 45);
+    assert_ne!(le16(6) & ZIP_FLAG_DATA_DESCRIPTOR, 0);
+    assert_eq!((le32(18), le32(22)), (u32::MAX, u32::MAX));
+    assert_eq!((le16(26), le16(28)), (1, 20));
+    assert_eq!((le16(31), le16(33)), (1, 16));
+    assert_eq!((le64(35), le64(43)), (0, 0));
+
+    // The data descriptor after the data has 64-bit sizes.
+    let archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    let entry = &archive.entries()[0];
+    let descriptor = 51 + entry.compressed_size as usize;
+    assert_eq!(le32(descriptor), 0x08074b50);
+    assert_eq!(le32(descriptor + 4), libdeflate::crc32(0, &data));
+    assert_eq!(le64(descriptor + 8), entry.compressed_size);
+    assert_eq!(le64(descriptor + 16), data.len() as u64);
+    assert_eq!(le32(descriptor + 24), 0x02014b50);
+}
+
+#[test]
+fn test_zip_stored_level_and_crc_check() {
+    let data = word_salad(5000, &WORDS);
+    let mut zip = ZipWriter::new(Vec::new(), 0).unwrap();
+    zip.add_file("a", &data).unwrap();
+    let mut bytes = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.entries()[0].method, ZIP_METHOD_STORED);
+    assert_eq!(archive.read(0).unwrap(), data);
+
+    // Local header (30 bytes) plus the one-byte name come before the data.
+    bytes[31 + 100] ^= 1;
+    let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.read(0).unwrap_err().kind(), ErrorKind::InvalidData);
+
+    assert!(ZipArchive::new(Cursor::new(&bytes[..10])).is_err());
+    assert!(ZipWriter::new(Vec::new(), 17).is_err());
+}
+
+#[test]
+fn test_zip64_many_entries() {
+    let count = 70_000;
+    let mut zip = ZipWriter::new(Vec::new(), 1).unwrap();
+    for i in 0..count {
+        zip.add_file(&i.to_string(), if i % 1000 == 0 { b"x" } else { b"" })
+            .unwrap();
+    }
+    let bytes = zip.finish().unwrap();
+
+    let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    assert_eq!(archive.len(), count);
+    assert_eq!(archive.entries()[69_999].name, "69999");
+    assert_eq!(archive.read_by_name("69000").unwrap(), b"x");
+}
+
+#[test]
+fn test_zip64_extra_field() {
+    // One stored entry whose sizes and offset live only in the ZIP64 extra field.
+    let data = b"zip64 entry data";
+    let crc = libdeflate::crc32(0, data);
+    let mut bytes = Vec::new();
+    bytes.extend_from_slice(&0x04034b50u32.to_le_bytes());
+    bytes.extend_from_slice(&[45, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&20u16.to_le_bytes());
+    bytes.push(b'z');
+    bytes.extend_from_slice(&[1, 0, 16, 0]);
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(data);
+
+    let cd_offset = bytes.len();
+    bytes.extend_from_slice(&0x02014b50u32.to_le_bytes());
+    bytes.extend_from_slice(&[45, 0, 45, 0, 0, 0, 0, 0, 0, 0, 0x21, 0]);
+    bytes.extend_from_slice(&crc.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&28u16.to_le_bytes());
+    bytes.extend_from_slice(&[0; 10]);
+    bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+    bytes.push(b'z');
+    bytes.extend_from_slice(&[1, 0, 24, 0]);
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&(data.len() as u64).to_le_bytes());
+    bytes.extend_from_slice(&0u64.to_le_bytes());
+    let cd_size = bytes.len() - cd_offset;
+
+    bytes.extend_from_slice(&0x06054b50u32.to_le_bytes());
+    bytes.extend_from_slice(&[0, 0, 0, 0, 1, 0, 1, 0]);
+    bytes.extend_from_slice(&(cd_size as u32).to_le_bytes());
+    bytes.extend_from_slice(&(cd_offset as u32).to_le_bytes());
+    bytes.extend_from_slice(&0u16.to_le_bytes());
+
+    let mut archive = ZipArchive::new(Cursor::new(&bytes)).unwrap();
+    let entry = &archive.entries()[0];
+    assert_eq!(entry.compressed_size, data.len() as u64);
+    assert_eq!(entry.header_offset, 0);
+    assert_eq!(archive.read(0).unwrap(), data);
+}
@@ -0,0 +1,242 @@
+// Builds a program in the style of zlib's example.c against the system zlib.h and links
+// it to the cdylib instead of libz, as a program switching to the shim would.
+#![cfg(all(feature = "zlib-compat", unix))]
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const PROGRAM: &str = r#"
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+#include <zlib.h>
+
+#define CHECK_ERR(err, msg) \
+	do { \
+		if ((err) != Z_OK) { \
+			fprintf(stderr, "line %d: %s error: %d\n", __LINE__, msg, (int)(err)); \
+			exit(1); \
+		} \
+	} while (0)
+
+#define CHECK(cond) \
+	do { \
+		if (!(cond)) { \
+			fprintf(stderr, "line %d: %s\n", __LINE__, #cond); \
+			exit(1); \
+		} \
+	} while (0)
+
+static const char hello[] = "hello, hello!";
+
+static void test_compress(Byte *compr, uLong comprLen, Byte *uncompr, uLong uncomprLen)
+{
+	uLong len = (uLong)strlen(hello) + 1;
+	int err;
+
+	err = compress(compr, &comprLen, (const Bytef *)hello, len);
+	CHECK_ERR(err, "compress");
+	strcpy((char *)uncompr, "garbage");
+	err = uncompress(uncompr, &uncomprLen, compr, comprLen);
+	CHECK_ERR(err, "uncompress");
+	CHECK(uncomprLen == len && strcmp((char *)uncompr, hello) == 0);
+}
+
+/* One byte of input and of output per call, as example.c does. */
+static uLong test_deflate(Byte *compr, uLong comprLen)
+{
+	z_stream c_stream;
+	uLong len = (uLong)strlen(hello) + 1;
+	int err;
+
+	memset(&c_stream, 0, sizeof(c_stream));
+	err = deflateInit(&c_stream, Z_DEFAULT_COMPRESSION);
+	CHECK_ERR(err, "deflateInit");
+
+	c_stream.next_in = (z_const Bytef *)hello;
+	c_stream.next_out = compr;
+	while (c_stream.total_in != len && c_stream.total_out < comprLen) {
+		c_stream.avail_in = c_stream.avail_out = 1;
+		err = deflate(&c_stream, Z_NO_FLUSH);
+		CHECK_ERR(err, "deflate");
+	}
+	for (;;) {
+		c_stream.avail_out = 1;
+		err = deflate(&c_stream, Z_FINISH);
+		if (err == Z_STREAM_END)
+			break;
+		CHECK_ERR(err, "deflate");
+	}
+	CHECK(c_stream.adler == adler32(adler32(0L, Z_NULL, 0), (const Bytef *)hello, len));
+	err = deflateEnd(&c_stream);
+	CHECK_ERR(err, "deflateEnd");
+	return c_stream.total_out;
+}
+
+static void test_inflate(Byte *compr, uLong comprLen, Byte *uncompr, uLong uncomprLen)
+{
+	z_stream d_stream;
+	int err;
+
+	strcpy((char *)uncompr, "garbage");
+	memset(&d_stream, 0, sizeof(d_stream));
+	d_stream.next_in = compr;
+	err = inflateInit(&d_stream);
+	CHECK_ERR(err, "inflateInit");
+
+	d_stream.next_out = uncompr;
+	while (d_stream.total_out < uncomprLen && d_stream.total_in < comprLen) {
+		d_stream.avail_in = d_stream.avail_out = 1;
+		err = inflate(&d_stream, Z_NO_FLUSH);
+		if (err == Z_STREAM_END)
+			break;
+		CHECK_ERR(err, "inflate");
+	}
+	CHECK(err == Z_STREAM_END);
+	err = inflateEnd(&d_stream);
+	CHECK_ERR(err, "inflateEnd");
+	CHECK(strcmp((char *)uncompr, hello) == 0);
+}
+
+/* Large buffers at the best level, with a gzip wrapper read back through
+ * automatic header detection. */
+static void test_large(Byte *compr, uLong comprLen, Byte *uncompr, uLong uncomprLen)
+{
+	z_stream c_stream, d_stream;
+	uLong i;
+	int err;
+
+	for (i = 0; i < uncomprLen; i++)
+		uncompr[i] = (Byte)((i * 7 + i / 1000) % 251);
+
+	memset(&c_stream, 0, sizeof(c_stream));
+	err = deflateInit2(&c_stream, Z_BEST_COMPRESSION, Z_DEFLATED, 15 + 16, 8,
+			   Z_DEFAULT_STRATEGY);
+	CHECK_ERR(err, "deflateInit2");
+	c_stream.next_in = uncompr;
+	c_stream.avail_in = (uInt)uncomprLen;
+	c_stream.next_out = compr;
+	c_stream.avail_out = (uInt)comprLen;
+	err = deflate(&c_stream, Z_NO_FLUSH);
+	CHECK_ERR(err, "deflate");
+	CHECK(c_stream.avail_in == 0);
+	err = deflate(&c_stream, Z_FINISH);
+	CHECK(err == Z_STREAM_END);
+	CHECK(c_stream.total_out < uncomprLen / 4);
+	err = deflateEnd(&c_stream);
+	CHECK_ERR(err, "deflateEnd");
+
+	memset(&d_stream, 0, sizeof(d_stream));
+	d_stream.next_in = compr;
+	d_stream.avail_in = (uInt)c_stream.total_out;
+	err = inflateInit2(&d_stream, 15 + 32);
+	CHECK_ERR(err, "inflateInit2");
+	memset(uncompr, 0, uncomprLen);
+	d_stream.next_out = uncompr;
+	d_stream.avail_out = (uInt)uncomprLen;
+	err = inflate(&d_stream, Z_FINISH);
+	CHECK(err == Z_STREAM_END);
+	CHECK(d_stream.total_out == uncomprLen);
+	for (i = 0; i < uncomprLen; i++)
+		CHECK(uncompr[i] == (Byte)((i * 7 + i / 1000) % 251));
+	err = inflateEnd(&d_stream);
+	CHECK_ERR(err, "inflateEnd");
+}
+
+/* A full flush in the middle, after which the rest decodes on its own. */
+static void test_flush(Byte *compr, uLong *comprLen)
+{
+	z_stream c_stream;
+	uInt len = (uInt)strlen(hello) + 1;
+	int err;
+
+	memset(&c_stream, 0, sizeof(c_stream));
+	err = deflateInit(&c_stream, Z_DEFAULT_COMPRESSION);
+	CHECK_ERR(err, "deflateInit");
+	c_stream.next_in = (z_const Bytef *)hello;
+	c_stream.next_out = compr;
+	c_stream.avail_in = 3;
+	c_stream.avail_out = (uInt)*comprLen;
+	err = deflate(&c_stream, Z_FULL_FLUSH);
+	CHECK_ERR(err, "deflate");
+
+	compr[3]++; /* damage the first block; the checksum catches it */
+	c_stream.avail_in = len - 3;
+	err = deflate(&c_stream, Z_FINISH);
+	CHECK(err == Z_STREAM_END);
+	err = deflateEnd(&c_stream);
+	CHECK_ERR(err, "deflateEnd");
+	*comprLen = c_stream.total_out;
+}
+
+int main(void)
+{
+	static Byte compr[1 << 20], uncompr[1 << 20];
+	uLong comprLen = sizeof(compr), uncomprLen = sizeof(uncompr);
+	Byte small[64];
+	uLong n;
+
+	/* The shim, not libz, must be the one linked. */
+	CHECK(zlibVersion()[0] == ZLIB_VERSION[0]);
+	CHECK(strcmp(zlibVersion(), "1.3.1") == 0);
+
+	test_compress(compr, comprLen, small, sizeof(small));
+	n = test_deflate(compr, comprLen);
+	test_inflate(compr, n, small, sizeof(small));
+	test_large(compr, comprLen, uncompr, uncomprLen / 4);
+
+	n = comprLen;
+	test_flush(compr, &n);
+	{
+		uLong len = sizeof(small);
+		CHECK(uncompress(small, &len, compr, n) == Z_DATA_ERROR);
+	}
+
+	CHECK(crc32(0L, (const Bytef *)"123456789", 9) == 0xCBF43926UL);
+	CHECK(adler32(1L, (const Bytef *)"Wikipedia", 9) == 0x11E60398UL);
+	CHECK(compressBound(1000) >= 1000);
+	puts("ok");
+	return 0;
+}
+"#;
+
+#[test]
+fn test_example_program_against_zlib_h_and_cdylib() {
+    // Integration tests run from target/<profile>/deps, next to the cdylib.
+    let exe = std::env::current_exe().unwrap();
+    let deps = exe.parent().unwrap();
+    let library = deps.join(format!("{DLL_PREFIX}libdeflate{DLL_SUFFIX}"));
+    assert!(library.exists(), "no cdylib at {}", library.display());
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("zlib_compat_c_test");
+    fs::create_dir_all(&dir).unwrap();
+    let source = dir.join("example.c");
+    let program = dir.join("example");
+    fs::write(&source, PROGRAM).unwrap();
+
+    // Only the cdylib is linked, so every zlib symbol must resolve to the shim.
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let out = Command::new(&cc)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-o"])
+        .arg(&program)
+        .arg(&source)
+        .arg(&library)
+        .arg(format!("-Wl,-rpath,{}", deps.display()))
+        .output()
+        .unwrap_or_else(|e| panic!("cannot run {cc}: {e}"));
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+
+    let out = Command::new(&program).output().unwrap();
+    assert!(
+        out.status.success(),
+        "{}",
+        String::from_utf8_lossy(&out.stderr)
+    );
+    assert_eq!(out.stdout, b"ok\n");
+}
@@ -0,0 +1,403 @@
+use libdeflate::zlib_compat::*;
+use libdeflate::{Compressor, Decompressor};
+use std::ffi::{c_int, c_ulong};
+use std::mem::{MaybeUninit, size_of};
+use std::ptr;
+
+mod common;
+use common::periodic_bytes;
+
+fn new_stream() -> ZStream {
+    // SAFETY: an all-zero `z_stream` is how C callers start, with no allocator set.
+    unsafe { MaybeUninit::zeroed().assume_init() }
+}
+
+fn stream_size() -> c_int {
+    size_of::<ZStream>() as c_int
+}
+
+/// Deflates `data` feeding `in_step` bytes and taking `out_step` bytes per call.
+fn deflate_all(
+    data: &[u8],
+    level: c_int,
+    window_bits: c_int,
+    in_step: usize,
+    out_step: usize,
+) -> Vec<u8> {
+    let mut strm = new_stream();
+    let mut out = vec![0u8; data.len() * 2 + 1024];
+    unsafe {
+        let res = deflateInit2_(
+            &mut strm,
+            level,
+            Z_DEFLATED,
+            window_bits,
+            8,
+            0,
+            zlibVersion(),
+            stream_size(),
+        );
+        assert_eq!(res, Z_OK);
+        strm.next_in = data.as_ptr();
+        strm.next_out = out.as_mut_ptr();
+        let mut res = Z_OK;
+        while res != Z_STREAM_END {
+            let in_pos = strm.total_in as usize;
+            strm.avail_in = in_step.min(data.len() - in_pos) as u32;
+            strm.avail_out = out_step as u32;
+            let flush = if strm.avail_in as usize == data.len() - in_pos {
+                Z_FINISH
+            } else {
+                Z_NO_FLUSH
+            };
+            res = deflate(&mut strm, flush);
+            assert!(res == Z_OK || res == Z_STREAM_END, "deflate returned {res}");
+        }
+        out.truncate(strm.total_out as usize);
+        assert_eq!(deflateEnd(&mut strm), Z_OK);
+    }
+    out
+}
+
+/// Inflates `data` feeding `in_step` bytes and taking `out_step` bytes per call, and
+/// returns the output and the number of input bytes the stream took.
+fn inflate_all(
+    data: &[u8],
+    window_bits: c_int,
+    in_step: usize,
+    out_step: usize,
+    expected_len: usize,
+) -> Result<(Vec<u8>, usize), c_int> {
+    let mut strm = new_stream();
+    let mut out = vec![0u8; expected_len + out_step];
+    unsafe {
+        let res = inflateInit2_(&mut strm, window_bits, zlibVersion(), stream_size());
+        assert_eq!(res, Z_OK);
+        strm.next_in = data.as_ptr();
+        strm.next_out = out.as_mut_ptr();
+        loop {
+            let in_pos = strm.total_in as usize;
+            strm.avail_in = in_step.min(data.len() - in_pos) as u32;
+            strm.avail_out = out_step as u32;
+            let res = inflate(&mut strm, Z_NO_FLUSH);
+            match res {
+                Z_STREAM_END => break,
+                Z_OK => {}
+                Z_BUF_ERROR if strm.total_in as usize == data.len() => {
+                    inflateEnd(&mut strm);
+                    return Err(Z_BUF_ERROR);
+                }
+                _ => {
+                    assert!(!strm.msg.is_null());
+                    inflateEnd(&mut strm);
+                    return Err(res);
+                }
+            }
+        }
+        out.truncate(strm.total_out as usize);
+        let consumed = strm.total_in as usize;
+        assert_eq!(inflateEnd(&mut strm), Z_OK);
+        Ok((out, consumed))
+    }
+}
+
+#[test]
+fn test_zlib_compat_compress_uncompress() {
+    let data = periodic_bytes(300_000);
+    unsafe {
+        let mut compressed = vec![0u8; compressBound(data.len() as c_ulong) as usize];
+        let mut len = compressed.len() as c_ulong;
+        let res = compress(
+            compressed.as_mut_ptr(),
+            &mut len,
+            data.as_ptr(),
+            data.len() as c_ulong,
+        );
+        assert_eq!(res, Z_OK);
+        compressed.truncate(len as usize);
+
+        let mut back = vec![0u8; data.len()];
+        let mut back_len = back.len() as c_ulong;
+        let res = uncompress(back.as_mut_ptr(), &mut back_len, compressed.as_ptr(), len);
+        assert_eq!(res, Z_OK);
+        assert_eq!(back_len as usize, data.len());
+        assert!(back == data);
+
+        let mut short_len = back.len() as c_ulong - 1;
+        let res = uncompress(back.as_mut_ptr(), &mut short_len, compressed.as_ptr(), len);
+        assert_eq!(res, Z_BUF_ERROR);
+        let res = uncompress(
+            back.as_mut_ptr(),
+            &mut back_len,
+            compressed.as_ptr(),
+            len / 2,
+        );
+        assert_eq!(res, Z_DATA_ERROR);
+
+        let mut tiny = [0u8; 8];
+        let mut tiny_len = tiny.len() as c_ulong;
+        let res = compress2(
+            tiny.as_mut_ptr(),
+            &mut tiny_len,
+            data.as_ptr(),
+            data.len() as c_ulong,
+            9,
+        );
+        assert_eq!(res, Z_BUF_ERROR);
+        let res = compress2(
+            tiny.as_mut_ptr(),
+            &mut tiny_len,
+            data.as_ptr(),
+            data.len() as c_ulong,
+            17,
+        );
+        assert_eq!(res, Z_STREAM_ERROR);
+
+        assert_eq!(crc32(0, b"123456789".as_ptr(), 9), 0xcbf43926);
+        assert_eq!(adler32(1, b"Wikipedia".as_ptr(), 9), 0x11e60398);
+        assert_eq!(crc32(1234, ptr::null(), 0), 0);
+        assert_eq!(adler32(1234, ptr::null(), 0), 1);
+
+        let mut strm = new_stream();
+        assert_eq!(
+            deflateInit_(&mut strm, 6, c"2.0".as_ptr(), stream_size()),
+            Z_VERSION_ERROR
+        );
+        assert_eq!(
+            inflateInit_(&mut strm, zlibVersion(), stream_size() - 8),
+            Z_VERSION_ERROR
+        );
+    }
+}
+
+#[test]
+fn test_zlib_compat_streams() {
+    let data = periodic_bytes(200_000);
+    let small = &data[..5_000];
+    for window_bits in [15, -15, 31] {
+        // One byte at a time, in and out.
+        let compressed = deflate_all(small, 6, window_bits, 1, 1);
+        let (back, consumed) = inflate_all(&compressed, window_bits, 1, 1, small.len()).unwrap();
+        assert!(back == small);
+        assert_eq!(consumed, compressed.len());
+
+        let compressed = deflate_all(&data, 9, window_bits, 70_000, 4096);
+        assert!(compressed.len() < data.len() / 10);
+        let (back, _) = inflate_all(&compressed, window_bits, 777, 1000, data.len()).unwrap();
+        assert!(back == data);
+    }
+
+    // Automatic detection accepts both wrappers.
+    for window_bits in [15, 31] {
+        let compressed = deflate_all(small, 1, window_bits, 1000, 1000);
+        let (back, _) = inflate_all(&compressed, 47, 100, 100, small.len()).unwrap();
+        assert!(back == small);
+    }
+
+    // Truncated and corrupted streams are reported.
+    let compressed = deflate_all(small, 6, 31, 1000, 1000);
+    let truncated = &compressed[..compressed.len() - 3];
+    assert_eq!(
+        inflate_all(truncated, 31, 64, 64, small.len()),
+        Err(Z_BUF_ERROR)
+    );
+    let mut corrupt = compressed.clone();
+    let last = corrupt.len() - 5;
+    corrupt[last] ^= 1;
+    assert_eq!(
+        inflate_all(&corrupt, 31, 64, 64, small.len()),
+        Err(Z_DATA_ERROR)
+    );
+    assert_eq!(
+        inflate_all(&compressed, 15, 64, 64, small.len()),
+        Err(Z_DATA_ERROR)
+    );
+
+    // Input after the end of the stream is left unread.
+    let mut followed = compressed.clone();
+    followed.extend_from_slice(b"next member");
+    for in_step in [1, 7, followed.len()] {
+        let (back, consumed) = inflate_all(&followed, 31, in_step, 64, small.len()).unwrap();
+        assert!(back == small);
+        assert_eq!(consumed, compressed.len());
+    }
+}
+
+#[test]
+fn test_zlib_compat_flush_and_interop() {
+    let data = periodic_bytes(100_000);
+    let mut strm = new_stream();
+    let mut out = vec![0u8; 200_000];
+    unsafe {
+        assert_eq!(
+            deflateInit_(&mut strm, 6, zlibVersion(), stream_size()),
+            Z_OK
+        );
+        let bound = deflateBound(&mut strm, data.len() as c_ulong) as usize;
+        assert!(bound >= Compressor::new(6).unwrap().zlib_compress_bound(data.len()));
+        strm.next_out = out.as_mut_ptr();
+        strm.avail_out = out.len() as u32;
+
+        // A full flush makes everything so far decodable.
+        strm.next_in = data.as_ptr();
+        strm.avail_in = 50_000;
+        assert_eq!(deflate(&mut strm, Z_FULL_FLUSH), Z_OK);
+        assert_eq!(strm.avail_in, 0);
+        let flushed = strm.total_out as usize;
+        assert_eq!(&out[flushed - 4..flushed], &[0, 0, 0xff, 0xff]);
+        let mut inflater = new_stream();
+        let mut partial = vec![0u8; 60_000];
+        assert_eq!(
+            inflateInit_(&mut inflater, zlibVersion(), stream_size()),
+            Z_OK
+        );
+        inflater.next_in = out.as_ptr();
+        inflater.avail_in = flushed as u32;
+        inflater.next_out = partial.as_mut_ptr();
+        inflater.avail_out = partial.len() as u32;
+        assert_eq!(inflate(&mut inflater, Z_NO_FLUSH), Z_OK);
+        assert_eq!(inflater.total_out, 50_000);
+        assert!(partial[..50_000] == data[..50_000]);
+        assert_eq!(inflateEnd(&mut inflater), Z_OK);
+        // Nothing new to flush, so no progress is possible.
+        assert_eq!(deflate(&mut strm, Z_SYNC_FLUSH), Z_BUF_ERROR);
+
+        strm.avail_in = 50_000;
+        assert_eq!(deflate(&mut strm, Z_FINISH), Z_STREAM_END);
+        assert_eq!(strm.adler as u32, libdeflate::adler32(1, &data));
+        let size = strm.total_out as usize;
+        assert_eq!(deflateEnd(&mut strm), Z_OK);
+
+        let back = Decompressor::new()
+            .decompress_zlib(&out[..size], data.len())
+            .unwrap();
+        assert!(back == data);
+
+        // Streams from the crate's own compressor inflate too.
+        let gzip = Compressor::new(12).unwrap().compress_gzip(&data).unwrap();
+        let (back, _) = inflate_all(&gzip, 31, 4096, 4096, data.len()).unwrap();
+        assert!(back == data);
+
+        // Reset starts a new stream with the same settings.
+        assert_eq!(
+            deflateInit2_(
+                &mut strm,
+                1,
+                Z_DEFLATED,
+                31,
+                8,
+                0,
+                zlibVersion(),
+                stream_size()
+            ),
+            Z_OK
+        );
+        for _ in 0..2 {
+            strm.next_in = data.as_ptr();
+            strm.avail_in = data.len() as u32;
+            strm.next_out = out.as_mut_ptr();
+            strm.avail_out = out.len() as u32;
+            assert_eq!(deflate(&mut strm, Z_FINISH), Z_STREAM_END);
+            let size = strm.total_out as usize;
+            let back = Decompressor::new()
+                .decompress_gzip(&out[..size], data.len())
+                .unwrap();
+            assert!(back == data);
+            assert_eq!(deflateReset(&mut strm), Z_OK);
+            assert_eq!(strm.total_out, 0);
+        }
+        assert_eq!(deflateEnd(&mut strm), Z_OK);
+    }
+}
+
+/// Deflates each message of `messages` with `flush` into a raw stream, then finishes it.
+/// Returns the stream and where each flushed message ends in it.
+fn deflate_messages(messages: &[Vec<u8>], flush: c_int) -> (Vec<u8>, Vec<usize>) {
+    let mut strm = new_stream();
+    let mut out = vec![0u8; 1 << 20];
+    let mut ends = Vec::new();
+    unsafe {
+        let res = deflateInit2_(
+            &mut strm,
+            6,
+            Z_DEFLATED,
+            -15,
+            8,
+            0,
+            zlibVersion(),
+            stream_size(),
+        );
+        assert_eq!(res, Z_OK);
+        strm.next_out = out.as_mut_ptr();
+        strm.avail_out = out.len() as u32;
+        for message in messages {
+            strm.next_in = message.as_ptr();
+            strm.avail_in = message.len() as u32;
+            assert_eq!(deflate(&mut strm, flush), Z_OK);
+            assert_eq!(strm.avail_in, 0);
+            ends.push(strm.total_out as usize);
+        }
+        assert_eq!(deflate(&mut strm, Z_FINISH), Z_STREAM_END);
+        out.truncate(strm.total_out as usize);
+        assert_eq!(deflateEnd(&mut strm), Z_OK);
+    }
+    (out, ends)
+}
+
+#[test]
+fn test_zlib_compat_flushed_pieces_share_history() {
+    let messages: Vec<Vec<u8>> = (0..200)
+        .map(|i| format!("{{\"id\": {i}, \"status\": \"ok\", \"note\": \"nothing to report\"}}\n"))
+        .map(String::into_bytes)
+        .collect();
+    let data = messages.concat();
+
+    // Sync-flushed messages match against the ones before them.
+    let (synced, _) = deflate_messages(&messages, Z_SYNC_FLUSH);
+    let (full, ends) = deflate_messages(&messages, Z_FULL_FLUSH);
+    assert!(
+        synced.len() * 2 < full.len(),
+        "{} bytes synced, {} bytes fully flushed",
+        synced.len(),
+        full.len()
+    );
+    for stream in [&synced, &full] {
+        let (back, _) = inflate_all(stream, -15, 7, 64, data.len()).unwrap();
+        assert!(back == data);
+    }
+
+    // After a full flush, the rest decodes without what came before.
+    let (back, _) = inflate_all(&full[ends[99]..], -15, 4096, 4096, data.len()).unwrap();
+    assert!(back == messages[100..].concat());
+
+    // A full flush with nothing new still cuts the history.
+    let mut strm = new_stream();
+    let mut out = vec![0u8; 1 << 16];
+    unsafe {
+        let res = deflateInit2_(
+            &mut strm,
+            6,
+            Z_DEFLATED,
+            -15,
+            8,
+            0,
+            zlibVersion(),
+            stream_size(),
+        );
+        assert_eq!(res, Z_OK);
+        strm.next_out = out.as_mut_ptr();
+        strm.avail_out = out.len() as u32;
+        strm.next_in = messages[0].as_ptr();
+        strm.avail_in = messages[0].len() as u32;
+        assert_eq!(deflate(&mut strm, Z_SYNC_FLUSH), Z_OK);
+        assert_eq!(deflate(&mut strm, Z_FULL_FLUSH), Z_BUF_ERROR);
+        let cut = strm.total_out as usize;
+        strm.next_in = messages[0].as_ptr();
+        strm.avail_in = messages[0].len() as u32;
+        assert_eq!(deflate(&mut strm, Z_FINISH), Z_STREAM_END);
+        let size = strm.total_out as usize;
+        assert_eq!(deflateEnd(&mut strm), Z_OK);
+        let (back, _) = inflate_all(&out[cut..size], -15, 4096, 4096, 1024).unwrap();
+        assert!(back == messages[0]);
+    }
+}